@@ -0,0 +1,158 @@
+//! Numerical integration and differentiation for `integ` and `deriv`.
+
+use crate::{format_number, CalcError};
+
+/// How close, relative to the size of the integral, adaptive Simpson gets.
+const TOLERANCE: f64 = 1e-10;
+
+/// Most times an interval is halved, and most evaluations of f in all, so
+/// that an integral that does not settle fails rather than hangs.
+const MAX_DEPTH: usize = 50;
+const MAX_EVALUATIONS: usize = 100_000;
+
+/// Richardson extrapolation levels `derivative` tries, each halving h.
+const LEVELS: usize = 10;
+
+/// Counts the evaluations of f and rejects infinite values.
+struct Counted<F> {
+    f: F,
+    evaluations: usize,
+}
+
+impl<F: FnMut(f64) -> Result<f64, CalcError>> Counted<F> {
+    fn at(&mut self, x: f64) -> Result<f64, CalcError> {
+        self.evaluations += 1;
+        if self.evaluations > MAX_EVALUATIONS {
+            return Err(CalcError::InvalidArgument(format!(
+                "The integral did not settle after {} evaluations",
+                MAX_EVALUATIONS
+            )));
+        }
+        let y = (self.f)(x)?;
+        if !y.is_finite() {
+            return Err(CalcError::DomainError(format!(
+                "f is not finite at {}",
+                format_number(x)
+            )));
+        }
+        Ok(y)
+    }
+}
+
+/// One Simpson's rule estimate over `[a, b]`, with the midpoint and f there.
+struct Panel {
+    a: f64,
+    fa: f64,
+    m: f64,
+    fm: f64,
+    b: f64,
+    fb: f64,
+    area: f64,
+}
+
+impl Panel {
+    fn new<F>(f: &mut Counted<F>, a: f64, fa: f64, b: f64, fb: f64) -> Result<Panel, CalcError>
+    where
+        F: FnMut(f64) -> Result<f64, CalcError>,
+    {
+        let m = a + (b - a) / 2.0;
+        let fm = f.at(m)?;
+        let area = (b - a) / 6.0 * (fa + 4.0 * fm + fb);
+        Ok(Panel {
+            a,
+            fa,
+            m,
+            fm,
+            b,
+            fb,
+            area,
+        })
+    }
+}
+
+/// The integral of `f` from `a` to `b` by adaptive Simpson's rule, which
+/// halves each interval until its two halves agree with the whole.
+pub fn integrate(
+    f: impl FnMut(f64) -> Result<f64, CalcError>,
+    a: f64,
+    b: f64,
+) -> Result<f64, CalcError> {
+    let mut f = Counted { f, evaluations: 0 };
+    let (fa, fb) = (f.at(a)?, f.at(b)?);
+    let whole = Panel::new(&mut f, a, fa, b, fb)?;
+    let eps = TOLERANCE * whole.area.abs().max(1.0);
+    adapt(&mut f, whole, eps, MAX_DEPTH)
+}
+
+fn adapt<F>(f: &mut Counted<F>, whole: Panel, eps: f64, depth: usize) -> Result<f64, CalcError>
+where
+    F: FnMut(f64) -> Result<f64, CalcError>,
+{
+    let left = Panel::new(f, whole.a, whole.fa, whole.m, whole.fm)?;
+    let right = Panel::new(f, whole.m, whole.fm, whole.b, whole.fb)?;
+    let delta = left.area + right.area - whole.area;
+    if delta.abs() <= 15.0 * eps {
+        return Ok(left.area + right.area + delta / 15.0);
+    }
+    if depth == 0 {
+        return Err(CalcError::InvalidArgument(format!(
+            "The integral does not settle near {}",
+            format_number(whole.m)
+        )));
+    }
+    Ok(adapt(f, left, eps / 2.0, depth - 1)? + adapt(f, right, eps / 2.0, depth - 1)?)
+}
+
+/// The slope of `f` at `x`, from central differences with ever smaller
+/// steps, extrapolated to a step of zero (Ridders' method). Steps that
+/// leave the domain of f are halved until they fit.
+pub fn derivative(
+    mut f: impl FnMut(f64) -> Result<f64, CalcError>,
+    x: f64,
+) -> Result<f64, CalcError> {
+    let mut h = 0.01 * x.abs().max(1.0);
+    let mut slope = |h: f64| -> Result<f64, CalcError> {
+        Ok((f(x + h)? - f(x - h)?) / (2.0 * h))
+    };
+    let mut first = slope(h);
+    for _ in 0..MAX_DEPTH {
+        if first.is_ok() {
+            break;
+        }
+        h /= 2.0;
+        first = slope(h);
+    }
+    // table[i][j]: the estimate from step h / 2^i after j extrapolations.
+    let mut table = [[0.0; LEVELS]; LEVELS];
+    table[0][0] = first?;
+    let (mut best, mut error) = (table[0][0], f64::INFINITY);
+    for i in 1..LEVELS {
+        h /= 2.0;
+        let Ok(estimate) = slope(h) else {
+            break;
+        };
+        table[i][0] = estimate;
+        let mut factor = 4.0;
+        for j in 1..=i {
+            table[i][j] = (factor * table[i][j - 1] - table[i - 1][j - 1]) / (factor - 1.0);
+            factor *= 4.0;
+            let change = (table[i][j] - table[i][j - 1])
+                .abs()
+                .max((table[i][j] - table[i - 1][j - 1]).abs());
+            if change <= error {
+                (best, error) = (table[i][j], change);
+            }
+        }
+        // Once rounding takes over, smaller steps only make it worse.
+        if (table[i][i] - table[i - 1][i - 1]).abs() >= 2.0 * error {
+            break;
+        }
+    }
+    if !best.is_finite() {
+        return Err(CalcError::DomainError(format!(
+            "f has no finite slope at {}",
+            format_number(x)
+        )));
+    }
+    Ok(best)
+}
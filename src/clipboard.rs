@@ -0,0 +1,45 @@
+//! System clipboard access. Without the `clipboard` feature, or when no
+//! clipboard is reachable (e.g. over SSH), every call returns an error that
+//! callers show as a message.
+
+#[cfg(feature = "clipboard")]
+mod imp {
+    use std::sync::Mutex;
+
+    // Kept alive for the whole process: on X11 the clipboard contents are
+    // served by their owner and vanish when it is dropped.
+    static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+    fn with_clipboard<T>(
+        f: impl FnOnce(&mut arboard::Clipboard) -> Result<T, arboard::Error>,
+    ) -> Result<T, String> {
+        let mut guard = CLIPBOARD.lock().map_err(|e| e.to_string())?;
+        if guard.is_none() {
+            *guard = Some(arboard::Clipboard::new().map_err(|e| e.to_string())?);
+        }
+        f(guard.as_mut().unwrap()).map_err(|e| e.to_string())
+    }
+
+    pub fn get_text() -> Result<String, String> {
+        with_clipboard(|c| c.get_text())
+    }
+
+    pub fn set_text(text: &str) -> Result<(), String> {
+        with_clipboard(|c| c.set_text(text))
+    }
+}
+
+#[cfg(not(feature = "clipboard"))]
+mod imp {
+    const UNSUPPORTED: &str = "built without clipboard support";
+
+    pub fn get_text() -> Result<String, String> {
+        Err(UNSUPPORTED.to_string())
+    }
+
+    pub fn set_text(_text: &str) -> Result<(), String> {
+        Err(UNSUPPORTED.to_string())
+    }
+}
+
+pub use imp::{get_text, set_text};
@@ -0,0 +1,123 @@
+//! Counting functions on whole numbers. Results are exact while they fit in
+//! a `u128` and fall back to floating point beyond that, so large inputs
+//! round or overflow to infinity instead of wrapping.
+
+/// The largest magnitude at which every integer is exactly representable.
+const MAX_EXACT: f64 = 9_007_199_254_740_992.0;
+
+// The u128 loop runs one term ahead, and F(186) is the last Fibonacci number
+// that fits in a u128; F(1476) is the last that fits in an f64.
+const MAX_EXACT_FIB: u32 = 185;
+const MAX_FINITE_FIB: f64 = 1476.0;
+
+fn is_whole(x: f64) -> bool {
+    x >= 0.0 && x.fract() == 0.0
+}
+
+/// Checks the operands of nPr and nCr: whole numbers with r <= n.
+fn counting_args(n: f64, r: f64, message: &'static str) -> Result<(), &'static str> {
+    if is_whole(n) && is_whole(r) && r <= n {
+        Ok(())
+    } else {
+        Err(message)
+    }
+}
+
+/// The starting product for exact counting, if n itself is exact.
+fn exact_start(n: f64) -> Option<u128> {
+    (n <= MAX_EXACT).then_some(1)
+}
+
+/// Ordered selections of r items out of n: n! / (n-r)!.
+pub fn permutations(n: f64, r: f64) -> Result<f64, &'static str> {
+    counting_args(n, r, "nPr needs whole numbers with 0 <= r <= n")?;
+    // r > 170 means at least 171! / 1, which no f64 holds.
+    if r > 170.0 {
+        return Ok(f64::INFINITY);
+    }
+    let mut exact = exact_start(n);
+    let mut approx = 1.0;
+    for k in 0..r as u32 {
+        let factor = n - k as f64;
+        exact = exact.and_then(|p| p.checked_mul(factor as u128));
+        approx *= factor;
+    }
+    Ok(exact.map_or(approx, |p| p as f64))
+}
+
+/// Unordered selections of r items out of n, built up multiplicatively as
+/// C(n-r+k, k) for k = 1..r, so no factorial is ever formed.
+pub fn combinations(n: f64, r: f64) -> Result<f64, &'static str> {
+    counting_args(n, r, "nCr needs whole numbers with 0 <= r <= n")?;
+    let r = r.min(n - r);
+    // C(2r, r) > 4^r / (2 sqrt r) is already past f64::MAX at r = 520.
+    if r > 520.0 {
+        return Ok(f64::INFINITY);
+    }
+    let mut exact = exact_start(n);
+    let mut approx = 1.0;
+    for k in 1..=r as u32 {
+        let factor = n - r + k as f64;
+        // Each partial product is C(n-r+k, k), so the division is exact.
+        exact = exact
+            .and_then(|c| c.checked_mul(factor as u128))
+            .map(|c| c / k as u128);
+        approx = approx * factor / k as f64;
+    }
+    Ok(exact.map_or(approx, |c| c as f64))
+}
+
+/// Both operands as exact integers, for gcd and lcm.
+fn integer_args(a: f64, b: f64, message: &'static str) -> Result<(u64, u64), &'static str> {
+    let exact = |x: f64| x.fract() == 0.0 && x.abs() <= MAX_EXACT;
+    if exact(a) && exact(b) {
+        Ok((a.abs() as u64, b.abs() as u64))
+    } else {
+        Err(message)
+    }
+}
+
+fn euclid(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Greatest common divisor, never negative; gcd(0, 0) is 0.
+pub fn gcd(a: f64, b: f64) -> Result<f64, &'static str> {
+    let (a, b) = integer_args(a, b, "gcd needs integers no larger than 2^53")?;
+    Ok(euclid(a, b) as f64)
+}
+
+/// Least common multiple, never negative; 0 if either operand is 0.
+pub fn lcm(a: f64, b: f64) -> Result<f64, &'static str> {
+    let (a, b) = integer_args(a, b, "lcm needs integers no larger than 2^53")?;
+    if a == 0 || b == 0 {
+        return Ok(0.0);
+    }
+    Ok((a / euclid(a, b)) as f64 * b as f64)
+}
+
+/// The n-th Fibonacci number, with F(0) = 0 and F(1) = 1.
+pub fn fibonacci(n: f64) -> Result<f64, &'static str> {
+    if !is_whole(n) {
+        return Err("fib needs a non-negative integer");
+    }
+    if n > MAX_FINITE_FIB {
+        return Ok(f64::INFINITY);
+    }
+    let n = n as u32;
+    if n <= MAX_EXACT_FIB {
+        let (mut a, mut b) = (0u128, 1u128);
+        for _ in 0..n {
+            (a, b) = (b, a + b);
+        }
+        return Ok(a as f64);
+    }
+    let (mut a, mut b) = (0.0, 1.0);
+    for _ in 0..n {
+        (a, b) = (b, a + b);
+    }
+    Ok(a)
+}
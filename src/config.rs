@@ -0,0 +1,327 @@
+//! User settings, read from a file of `key = value` lines. Blank lines and
+//! lines starting with `#` are ignored.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::history::HISTORY_LIMIT;
+use crate::theme::{self, Colors};
+use crate::solver::SolverSettings;
+use crate::{
+    App, EntryMode, ExportProfile, Quoting, SoftKeys, Theme, Tolerance, DEFAULT_MAX_DEPTH,
+};
+
+/// Most iterations `solve_iterations` may allow, so that a failing `solve`
+/// still answers promptly.
+const MAX_SOLVE_ITERATIONS: usize = 10_000;
+
+/// Largest `history_limit`; the tape keeps everything regardless.
+const MAX_HISTORY_LIMIT: usize = 1_000_000;
+
+/// Largest `max_depth`, which keeps a runaway script to a few hundred
+/// megabytes.
+const MAX_STACK_DEPTH: usize = 10_000_000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// Push NaN and infinities (IEEE semantics) instead of rejecting
+    /// out-of-domain operands.
+    pub allow_nan: bool,
+    /// When `~=` and macro tests count values as equal.
+    pub tolerance: Tolerance,
+    /// When `solve` stops, and how long it tries.
+    pub solver: SolverSettings,
+    /// Most values the stack may hold.
+    pub max_depth: usize,
+    /// Most calculations the history keeps.
+    pub history_limit: usize,
+    /// Draw bars next to the counts in the `freq` table.
+    pub freq_bars: bool,
+    /// Wake up less often and redraw only after something happened.
+    pub low_power: bool,
+    /// Log the lines entered in each session for `--replay`.
+    pub replay_log: bool,
+    /// What Enter and `-` do on an empty input line.
+    pub entry: EntryMode,
+    /// How `export` writes numbers and cells.
+    pub export: ExportProfile,
+    pub theme: Theme,
+    /// Show the labels of the F1-F8 soft keys.
+    pub show_softkeys: bool,
+    pub softkeys: SoftKeys,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            allow_nan: false,
+            tolerance: Tolerance::default(),
+            solver: SolverSettings::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            history_limit: HISTORY_LIMIT,
+            freq_bars: true,
+            low_power: false,
+            replay_log: true,
+            entry: EntryMode::default(),
+            export: ExportProfile::default(),
+            theme: Theme::default(),
+            show_softkeys: true,
+            softkeys: SoftKeys::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads settings from `text`. Lines that cannot be understood are
+    /// skipped and described in the returned warnings.
+    pub fn parse(text: &str) -> (Config, Vec<String>) {
+        let mut config = Config::default();
+        let mut warnings = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                warnings.push(format!("line {}: expected key = value", n + 1));
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            let result = match key {
+                "allow_nan" => parse_bool(value).map(|v| config.allow_nan = v),
+                "freq_bars" => parse_bool(value).map(|v| config.freq_bars = v),
+                "low_power" => parse_bool(value).map(|v| config.low_power = v),
+                "replay_log" => parse_bool(value).map(|v| config.replay_log = v),
+                "entry" => parse_entry(value).map(|v| config.entry = v),
+                "softkeys" => parse_bool(value).map(|v| config.show_softkeys = v),
+                "tolerance" => parse_tolerance(value).map(|v| config.tolerance.relative = v),
+                "abs_tolerance" => parse_tolerance(value).map(|v| config.tolerance.absolute = v),
+                "solve_tolerance" => {
+                    parse_solve_tolerance(value).map(|v| config.solver.tolerance = v)
+                }
+                "solve_iterations" => {
+                    parse_iterations(value).map(|v| config.solver.max_iterations = v)
+                }
+                "max_depth" => parse_max_depth(value).map(|v| config.max_depth = v),
+                "history_limit" => {
+                    parse_history_limit(value).map(|v| config.history_limit = v)
+                }
+                "export_decimal" => parse_decimal(value).map(|v| config.export.decimal_comma = v),
+                "export_delimiter" => {
+                    parse_delimiter(value).map(|v| config.export.delimiter = v)
+                }
+                "export_quote" => parse_quoting(value).map(|v| config.export.quoting = v),
+                "export_width" => parse_width(value).map(|v| config.export.width = v),
+                "theme" => parse_theme(value).map(|v| config.theme = v),
+                _ if key.starts_with("color.") => parse_colors(&mut config.theme, &key[6..], value),
+                _ if key.starts_with("softkey.") => {
+                    parse_softkey(&mut config.softkeys, &key[8..], value)
+                }
+                _ => Err(format!("unknown setting '{}'", key)),
+            };
+            if let Err(e) = result {
+                warnings.push(format!("line {}: {}", n + 1, e));
+            }
+        }
+        (config, warnings)
+    }
+
+    /// Reads the config file at `path`; a missing file gives the defaults.
+    pub fn load(path: &Path) -> io::Result<(Config, Vec<String>)> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Config::parse(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok((Config::default(), Vec::new())),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The settings `app` is running with.
+    pub fn of(app: &App) -> Config {
+        Config {
+            allow_nan: app.allow_nan,
+            tolerance: app.tolerance,
+            solver: app.solver,
+            max_depth: app.max_depth,
+            history_limit: app.history_limit,
+            freq_bars: app.freq_bars,
+            low_power: app.low_power,
+            replay_log: app.replay_log,
+            entry: app.entry,
+            export: app.export,
+            theme: app.theme.clone(),
+            show_softkeys: app.show_softkeys,
+            softkeys: app.softkeys.clone(),
+        }
+    }
+
+    pub fn apply(&self, app: &mut App) {
+        app.allow_nan = self.allow_nan;
+        app.tolerance = self.tolerance;
+        app.solver = self.solver;
+        app.max_depth = self.max_depth;
+        app.history_limit = self.history_limit;
+        app.freq_bars = self.freq_bars;
+        app.low_power = self.low_power;
+        app.replay_log = self.replay_log;
+        app.entry = self.entry;
+        app.export = self.export;
+        app.theme = self.theme.clone();
+        app.show_softkeys = self.show_softkeys;
+        app.softkeys = self.softkeys.clone();
+        app.softkey_page = 0;
+    }
+}
+
+/// Every setting as a config file line, which `Config::parse` reads back.
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "allow_nan = {}", self.allow_nan)?;
+        writeln!(f, "tolerance = {:e}", self.tolerance.relative)?;
+        writeln!(f, "abs_tolerance = {:e}", self.tolerance.absolute)?;
+        writeln!(f, "solve_tolerance = {:e}", self.solver.tolerance)?;
+        writeln!(f, "solve_iterations = {}", self.solver.max_iterations)?;
+        writeln!(f, "max_depth = {}", self.max_depth)?;
+        writeln!(f, "history_limit = {}", self.history_limit)?;
+        writeln!(f, "freq_bars = {}", self.freq_bars)?;
+        writeln!(f, "low_power = {}", self.low_power)?;
+        writeln!(f, "replay_log = {}", self.replay_log)?;
+        writeln!(f, "entry = {}", self.entry.name())?;
+        let decimal = if self.export.decimal_comma { "comma" } else { "point" };
+        writeln!(f, "export_decimal = {}", decimal)?;
+        let delimiter = match self.export.delimiter {
+            Some(',') => "comma",
+            Some(';') => "semicolon",
+            Some('\t') => "tab",
+            _ => "auto",
+        };
+        writeln!(f, "export_delimiter = {}", delimiter)?;
+        writeln!(f, "export_quote = {}", self.export.quoting.name())?;
+        writeln!(f, "export_width = {}", self.export.width)?;
+        writeln!(f, "theme = {}", self.theme.name)?;
+        for (part, colors) in self.theme.changes() {
+            writeln!(f, "color.{} = {}", part, colors)?;
+        }
+        writeln!(f, "softkeys = {}", self.show_softkeys)?;
+        for (number, action) in self.softkeys.changes() {
+            writeln!(f, "softkey.{} = {}", number, action)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "yes" | "on" => Ok(true),
+        "false" | "no" | "off" => Ok(false),
+        _ => Err(format!("expected true or false, not '{}'", value)),
+    }
+}
+
+fn parse_tolerance(value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|t| *t >= 0.0 && t.is_finite())
+        .ok_or_else(|| format!("expected a non-negative number, not '{}'", value))
+}
+
+fn parse_solve_tolerance(value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|t| *t > 0.0 && *t < 1.0)
+        .ok_or_else(|| format!("expected a number between 0 and 1, not '{}'", value))
+}
+
+fn parse_iterations(value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|n| (1..=MAX_SOLVE_ITERATIONS).contains(n))
+        .ok_or_else(|| {
+            format!("expected a whole number from 1 to {}, not '{}'", MAX_SOLVE_ITERATIONS, value)
+        })
+}
+
+fn parse_max_depth(value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|n| (1..=MAX_STACK_DEPTH).contains(n))
+        .ok_or_else(|| {
+            format!("expected a whole number from 1 to {}, not '{}'", MAX_STACK_DEPTH, value)
+        })
+}
+
+fn parse_history_limit(value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|n| (1..=MAX_HISTORY_LIMIT).contains(n))
+        .ok_or_else(|| {
+            format!("expected a whole number from 1 to {}, not '{}'", MAX_HISTORY_LIMIT, value)
+        })
+}
+
+fn parse_decimal(value: &str) -> Result<bool, String> {
+    match value {
+        "point" | "." => Ok(false),
+        "comma" | "," => Ok(true),
+        _ => Err(format!("expected point or comma, not '{}'", value)),
+    }
+}
+
+/// `standard` or `classic`, quoted or not.
+fn parse_entry(value: &str) -> Result<EntryMode, String> {
+    EntryMode::from_name(value.trim_matches('"'))
+        .ok_or_else(|| format!("expected standard or classic, not '{}'", value))
+}
+
+fn parse_delimiter(value: &str) -> Result<Option<char>, String> {
+    match value {
+        "auto" => Ok(None),
+        "comma" | "," => Ok(Some(',')),
+        "semicolon" | ";" => Ok(Some(';')),
+        "tab" => Ok(Some('\t')),
+        _ => Err(format!("expected auto, comma, semicolon or tab, not '{}'", value)),
+    }
+}
+
+fn parse_quoting(value: &str) -> Result<Quoting, String> {
+    Quoting::from_name(value)
+        .ok_or_else(|| format!("expected minimal, all or never, not '{}'", value))
+}
+
+fn parse_width(value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|w| *w <= 200)
+        .ok_or_else(|| format!("expected a width from 0 to 200, not '{}'", value))
+}
+
+fn parse_theme(value: &str) -> Result<Theme, String> {
+    Theme::named(value)
+        .ok_or_else(|| format!("expected one of {}, not '{}'", theme::NAMES.join(", "), value))
+}
+
+/// Sets one part of the theme, e.g. `color.input = yellow on black`.
+fn parse_colors(theme: &mut Theme, part: &str, value: &str) -> Result<(), String> {
+    let colors = Colors::parse(value)
+        .ok_or_else(|| format!("expected <color> or <color> on <color>, not '{}'", value))?;
+    let slot = theme
+        .part_mut(part)
+        .ok_or_else(|| format!("no part of the screen is called '{}'", part))?;
+    *slot = colors;
+    Ok(())
+}
+
+/// Binds one soft key, e.g. `softkey.3 = cube`.
+fn parse_softkey(softkeys: &mut SoftKeys, number: &str, action: &str) -> Result<(), String> {
+    let number = number
+        .parse::<usize>()
+        .map_err(|_| format!("expected a soft key number, not '{}'", number))?;
+    softkeys.bind(number, action)
+}
@@ -0,0 +1,123 @@
+//! Fixed-point decimal numbers for money mode. Amounts keep six decimal
+//! places, so sums of currency amounts are exact and products and quotients
+//! carry four guard digits beyond the cent before being shown rounded.
+
+use std::fmt;
+
+/// Decimal places kept internally.
+pub const PLACES: u32 = 6;
+const SCALE: i128 = 10_i128.pow(PLACES);
+
+const OUT_OF_RANGE: &str = "Money amount out of range";
+
+/// A decimal number stored as a count of millionths.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Decimal(i128);
+
+/// `n / d` rounded half away from zero.
+fn div_round(n: i128, d: i128) -> i128 {
+    let (q, r) = (n / d, n % d);
+    if 2 * r.abs() >= d.abs() {
+        q + n.signum() * d.signum()
+    } else {
+        q
+    }
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal(0);
+
+    /// Reads plain decimal notation such as `-12.50`, rounding digits beyond
+    /// the sixth decimal place. Exponents go through `f64`.
+    pub fn parse(text: &str) -> Option<Decimal> {
+        if text.contains(['e', 'E']) {
+            return Decimal::from_f64(text.parse().ok()?);
+        }
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+        let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if int.is_empty() && frac.is_empty() || !all_digits(int) || !all_digits(frac) {
+            return None;
+        }
+        let mut units: i128 = 0;
+        for b in int.bytes() {
+            units = units.checked_mul(10)?.checked_add((b - b'0') as i128)?;
+        }
+        units = units.checked_mul(SCALE)?;
+        let mut place = SCALE;
+        for b in frac.bytes().take(PLACES as usize) {
+            place /= 10;
+            units += (b - b'0') as i128 * place;
+        }
+        if frac.as_bytes().get(PLACES as usize).is_some_and(|&b| b >= b'5') {
+            units += 1;
+        }
+        Some(Decimal(if negative { -units } else { units }))
+    }
+
+    /// The nearest decimal, or `None` for infinities, NaN and values too
+    /// large to hold.
+    pub fn from_f64(value: f64) -> Option<Decimal> {
+        let units = (value * SCALE as f64).round();
+        if units.is_finite() && units.abs() < 1e36 {
+            Some(Decimal(units as i128))
+        } else {
+            None
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / SCALE as f64
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn checked_add(self, other: Decimal) -> Result<Decimal, &'static str> {
+        self.0.checked_add(other.0).map(Decimal).ok_or(OUT_OF_RANGE)
+    }
+
+    pub fn checked_sub(self, other: Decimal) -> Result<Decimal, &'static str> {
+        self.0.checked_sub(other.0).map(Decimal).ok_or(OUT_OF_RANGE)
+    }
+
+    /// The product, correctly rounded to six places.
+    pub fn checked_mul(self, other: Decimal) -> Result<Decimal, &'static str> {
+        let product = self.0.checked_mul(other.0).ok_or(OUT_OF_RANGE)?;
+        Ok(Decimal(div_round(product, SCALE)))
+    }
+
+    /// The quotient, correctly rounded to six places. `other` must not be
+    /// zero.
+    pub fn checked_div(self, other: Decimal) -> Result<Decimal, &'static str> {
+        let dividend = self.0.checked_mul(SCALE).ok_or(OUT_OF_RANGE)?;
+        Ok(Decimal(div_round(dividend, other.0)))
+    }
+
+    /// Shows exactly `places` decimals, rounding half away from zero.
+    pub fn format(self, places: u32) -> String {
+        let places = places.min(PLACES);
+        let unit = 10_i128.pow(places);
+        let rounded = div_round(self.0, SCALE / unit);
+        let sign = if rounded < 0 { "-" } else { "" };
+        let (int, frac) = (rounded.abs() / unit, rounded.abs() % unit);
+        if places == 0 {
+            format!("{}{}", sign, int)
+        } else {
+            format!("{}{}.{:0w$}", sign, int, frac, w = places as usize)
+        }
+    }
+}
+
+/// Every stored digit, without trailing zeros.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let full = self.format(PLACES);
+        let trimmed = full.trim_end_matches('0').trim_end_matches('.');
+        write!(f, "{}", trimmed)
+    }
+}
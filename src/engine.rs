@@ -0,0 +1,127 @@
+//! A line-driven interface to the calculator, for embedding in other
+//! frontends such as GUIs, web services or chat bots. It wraps the `App`
+//! that the terminal UI also uses, so the UI's state (the input line,
+//! popups, theme and soft keys) comes along; commands such as `help` or
+//! `tape` only set it, and an embedder can ignore it.
+
+use crate::{
+    AngleMode, App, CalcError, Command, CommandRegistry, Config, HistoryEntry, OpResult, Timings,
+    Value,
+};
+
+/// A calculator driven by input lines. It keeps the stack, undo history and
+/// calculation history in memory; nothing is read from or written to disk,
+/// and the clipboard is not touched, unless a command such as `run`,
+/// `import`, `export` or `copy` asks for it.
+///
+/// ```
+/// use rpncalc::{Engine, Value};
+///
+/// let mut engine = Engine::new();
+/// engine.execute("3 4 +").unwrap();
+/// assert_eq!(engine.stack(), [Value::Number(7.0)]);
+/// assert_eq!(engine.history()[0].text, "3 + 4 = 7");
+///
+/// assert!(engine.execute("0 /").is_err());
+/// assert_eq!(engine.stack(), [Value::Number(7.0), Value::Number(0.0)]);
+/// ```
+pub struct Engine {
+    app: App,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    /// An empty stack, every built-in command and default settings.
+    pub fn new() -> Engine {
+        let mut app = App::new();
+        app.message.clear();
+        Engine { app }
+    }
+
+    pub fn with_config(config: &Config) -> Engine {
+        let mut engine = Engine::new();
+        config.apply(&mut engine.app);
+        engine
+    }
+
+    /// Runs a line of input: numbers and commands separated by spaces, each
+    /// command taking its inline arguments from the tokens after it, e.g.
+    /// `2 sqrt` or `seed 42 rand`. Stops at the first error; values pushed
+    /// before it stay, and the failed command leaves the stack as it was.
+    /// Returns what the last token did.
+    pub fn execute(&mut self, input: &str) -> OpResult {
+        self.app.eval_line(input)
+    }
+
+    /// The stack `input` would leave, without changing this engine. Commands
+    /// that touch files or the clipboard, or may take long, are refused.
+    pub fn dry_run(&self, input: &str) -> Result<Vec<Value>, CalcError> {
+        self.app.dry_run(input)
+    }
+
+    /// The stack, bottom first.
+    pub fn stack(&self) -> &[Value] {
+        &self.app.stack
+    }
+
+    /// Pushes a value as if it were typed, as one undo step; fails if the
+    /// stack is full.
+    pub fn push(&mut self, value: impl Into<Value>) -> Result<(), CalcError> {
+        self.app.push_literal(value.into())
+    }
+
+    /// Takes the top of the stack off as one undo step.
+    pub fn pop(&mut self) -> Option<Value> {
+        let top = self.app.stack.last().cloned()?;
+        self.app.lift_disabled = false;
+        self.app.pop_operand();
+        Some(top)
+    }
+
+    /// A fingerprint of the stacks, modes and registers; engines given the
+    /// same input from the same seed have the same checksum.
+    pub fn checksum(&self) -> u64 {
+        self.app.checksum()
+    }
+
+    /// How long recent commands and macros took.
+    pub fn timings(&self) -> &Timings {
+        &self.app.timings
+    }
+
+    /// Completed calculations, oldest first.
+    pub fn history(&self) -> &[HistoryEntry] {
+        &self.app.calc_history
+    }
+
+    pub fn angle_mode(&self) -> AngleMode {
+        self.app.angle_mode
+    }
+
+    pub fn set_angle_mode(&mut self, mode: AngleMode) {
+        self.app.angle_mode = mode;
+    }
+
+    pub fn commands(&self) -> &CommandRegistry {
+        &self.app.registry
+    }
+
+    /// Adds a command, replacing any built-in command of the same name.
+    pub fn register(&mut self, cmd: Command) {
+        self.app.registry.register(cmd);
+    }
+
+    /// The full calculator state, for anything this API does not cover.
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+
+use crate::Value;
+
+/// Why an operation could not be carried out. Operations that fail leave the
+/// stack as it was.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CalcError {
+    /// The stack holds fewer values than `op` needs.
+    StackUnderflow {
+        op: String,
+        needed: usize,
+    },
+    EmptyStack,
+    /// `op` got a number where it needs a matrix, or the other way round.
+    WrongType { op: String, expected: &'static str },
+    DivisionByZero,
+    /// An operand is outside the operation's domain; the text explains which.
+    DomainError(String),
+    UnknownCommand(String),
+    /// A command was missing inline arguments; holds its usage string.
+    Usage(String),
+    InvalidArgument(String),
+    NothingToUndo,
+    /// The stack already holds the most values allowed, given here.
+    StackFull(usize),
+    Clipboard(String),
+    /// A line of a script file failed.
+    Script {
+        file: String,
+        line: usize,
+        error: Box<CalcError>,
+    },
+}
+
+impl CalcError {
+    pub fn underflow(op: &str, needed: usize) -> CalcError {
+        CalcError::StackUnderflow {
+            op: op.to_string(),
+            needed,
+        }
+    }
+
+    pub fn wrong_type(op: &str, expected: &'static str) -> CalcError {
+        CalcError::WrongType {
+            op: op.to_string(),
+            expected,
+        }
+    }
+
+    pub fn domain(msg: &str) -> CalcError {
+        CalcError::DomainError(msg.to_string())
+    }
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::StackUnderflow { op, needed: 1 } => write!(f, "Need 1 number for {}", op),
+            CalcError::StackUnderflow { op, needed } => {
+                write!(f, "Need {} numbers for {}", needed, op)
+            }
+            CalcError::EmptyStack => write!(f, "Stack is empty"),
+            CalcError::WrongType { op, expected } => write!(f, "{} needs {}", op, expected),
+            CalcError::DivisionByZero => write!(f, "Division by zero"),
+            CalcError::DomainError(msg) | CalcError::InvalidArgument(msg) => write!(f, "{}", msg),
+            CalcError::UnknownCommand(token) => {
+                write!(f, "Unknown command '{}' (type 'help' for list)", token)
+            }
+            CalcError::Usage(usage) => write!(f, "Usage: {}", usage),
+            CalcError::NothingToUndo => write!(f, "Nothing to undo"),
+            CalcError::StackFull(max) => {
+                write!(f, "Stack is full at {} values (max_depth in the config)", max)
+            }
+            CalcError::Clipboard(e) => write!(f, "Clipboard unavailable: {}", e),
+            CalcError::Script { file, line, error } => write!(f, "{}:{}: {}", file, line, error),
+        }
+    }
+}
+
+impl std::error::Error for CalcError {}
+
+/// What a successful operation did.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OpOutcome {
+    /// A calculation producing `result`, recorded in the history as `text`.
+    Calc { text: String, result: Value },
+    /// Anything else worth telling the user, e.g. after a stack or mode change.
+    Info(String),
+}
+
+impl OpOutcome {
+    pub fn calc(text: String, result: impl Into<Value>) -> OpOutcome {
+        OpOutcome::Calc {
+            text,
+            result: result.into(),
+        }
+    }
+
+    pub fn info(msg: impl Into<String>) -> OpOutcome {
+        OpOutcome::Info(msg.into())
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            OpOutcome::Calc { text, .. } => text,
+            OpOutcome::Info(msg) => msg,
+        }
+    }
+}
+
+pub type OpResult = Result<OpOutcome, CalcError>;
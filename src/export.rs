@@ -0,0 +1,235 @@
+//! Writing the stack and calculation tape to files: CSV or fixed-width text
+//! for spreadsheets, laid out by an export profile so that regional settings
+//! read them correctly, or JSON for other programs.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{App, HistoryEntry, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Delimited text, quoted as the profile says.
+    Csv,
+    /// Columns padded to line up, for readers that split at fixed offsets.
+    Fixed,
+    /// `{"stack": [...], "history": [{"text": ..., "result": ...}]}`.
+    Json,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "csv" => Some(Format::Csv),
+            "fixed" => Some(Format::Fixed),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// When CSV cells are wrapped in double quotes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quoting {
+    /// Only cells holding the delimiter, a quote or a line break.
+    Minimal,
+    All,
+    Never,
+}
+
+impl Quoting {
+    pub fn from_name(name: &str) -> Option<Quoting> {
+        match name {
+            "minimal" => Some(Quoting::Minimal),
+            "all" => Some(Quoting::All),
+            "never" => Some(Quoting::Never),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Quoting::Minimal => "minimal",
+            Quoting::All => "all",
+            Quoting::Never => "never",
+        }
+    }
+}
+
+/// How exported numbers and cells are written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExportProfile {
+    /// Write `3,5` rather than `3.5`.
+    pub decimal_comma: bool,
+    /// Separates CSV cells; `None` picks `;` with a decimal comma and `,`
+    /// otherwise, as spreadsheets in those locales expect.
+    pub delimiter: Option<char>,
+    pub quoting: Quoting,
+    /// Minimum width of each fixed-width column; columns still grow to fit
+    /// their widest cell.
+    pub width: usize,
+}
+
+impl Default for ExportProfile {
+    fn default() -> ExportProfile {
+        ExportProfile {
+            decimal_comma: false,
+            delimiter: None,
+            quoting: Quoting::Minimal,
+            width: 0,
+        }
+    }
+}
+
+impl ExportProfile {
+    pub fn delimiter(&self) -> char {
+        match self.delimiter {
+            Some(d) => d,
+            None if self.decimal_comma => ';',
+            None => ',',
+        }
+    }
+
+    /// A value at full precision, with the profile's decimal separator.
+    pub fn format_value(&self, value: &Value) -> String {
+        let text = value.to_string();
+        if self.decimal_comma {
+            text.replace('.', ",")
+        } else {
+            text
+        }
+    }
+
+    fn quote(&self, cell: &str) -> String {
+        let needs_quotes = match self.quoting {
+            Quoting::All => true,
+            Quoting::Never => false,
+            Quoting::Minimal => cell.contains([self.delimiter(), '"', '\n', '\r']),
+        };
+        if needs_quotes {
+            format!("\"{}\"", cell.replace('"', "\"\""))
+        } else {
+            cell.to_string()
+        }
+    }
+
+    /// `app`'s stack, bottom first, then its calculation tape, oldest first,
+    /// as rows of section, entry and value under a header row.
+    pub fn table(&self, app: &App) -> Vec<[String; 3]> {
+        let mut rows = vec![["section", "entry", "value"].map(String::from)];
+        for (i, value) in app.stack.iter().enumerate() {
+            rows.push(["stack".to_string(), i.to_string(), self.format_value(value)]);
+        }
+        for entry in &app.calc_history {
+            rows.push([
+                "tape".to_string(),
+                entry.text.clone(),
+                self.format_value(&entry.result),
+            ]);
+        }
+        rows
+    }
+
+    /// `app`'s stack and tape in `format`. JSON ignores the profile.
+    pub fn render(&self, format: Format, app: &App) -> String {
+        match format {
+            Format::Csv => self.csv(&self.table(app)),
+            Format::Fixed => self.fixed(&self.table(app)),
+            Format::Json => json(app),
+        }
+    }
+
+    fn csv(&self, rows: &[[String; 3]]) -> String {
+        let delimiter = self.delimiter().to_string();
+        rows.iter()
+            .map(|row| {
+                let cells: Vec<String> = row.iter().map(|cell| self.quote(cell)).collect();
+                cells.join(&delimiter) + "\n"
+            })
+            .collect()
+    }
+
+    /// Text columns are left-aligned and the value column right-aligned, one
+    /// space apart.
+    fn fixed(&self, rows: &[[String; 3]]) -> String {
+        let widths: Vec<usize> = (0..3)
+            .map(|c| {
+                let widest = rows.iter().map(|row| row[c].chars().count()).max();
+                widest.unwrap_or(0).max(self.width)
+            })
+            .collect();
+        rows.iter()
+            .map(|[section, entry, value]| {
+                format!(
+                    "{:<w0$} {:<w1$} {:>w2$}\n",
+                    section,
+                    entry,
+                    value,
+                    w0 = widths[0],
+                    w1 = widths[1],
+                    w2 = widths[2]
+                )
+            })
+            .collect()
+    }
+}
+
+/// A JSON string literal.
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Finite numbers and money amounts as JSON numbers; anything else,
+/// including NaN and infinities, as its text.
+fn json_value(value: &Value) -> String {
+    match value {
+        Value::Number(n) if n.is_finite() => n.to_string(),
+        Value::Decimal(d) => d.to_string(),
+        other => json_string(&other.to_string()),
+    }
+}
+
+fn json_entry(entry: &HistoryEntry) -> String {
+    format!(
+        "{{\"text\": {}, \"result\": {}}}",
+        json_string(&entry.text),
+        json_value(&entry.result)
+    )
+}
+
+fn json(app: &App) -> String {
+    let stack: Vec<String> = app.stack.iter().map(json_value).collect();
+    let history: Vec<String> = app
+        .calc_history
+        .iter()
+        .map(|e| format!("    {}", json_entry(e)))
+        .collect();
+    let history = if history.is_empty() {
+        "[]".to_string()
+    } else {
+        format!("[\n{}\n  ]", history.join(",\n"))
+    };
+    format!(
+        "{{\n  \"stack\": [{}],\n  \"history\": {}\n}}\n",
+        stack.join(", "),
+        history
+    )
+}
+
+/// Writes `app`'s stack and tape to `path`.
+pub fn save(app: &App, format: Format, path: &Path) -> io::Result<()> {
+    fs::write(path, app.export.render(format, app))
+}
@@ -0,0 +1,131 @@
+//! Time-value-of-money math in the style of the HP-12C. Rates are percent per
+//! period, payments fall at the end of each period, and money paid out is
+//! negative.
+
+use crate::format_number;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TvmRegister {
+    N,
+    I,
+    Pv,
+    Pmt,
+    Fv,
+}
+
+impl TvmRegister {
+    pub fn name(self) -> &'static str {
+        match self {
+            TvmRegister::N => "n",
+            TvmRegister::I => "i",
+            TvmRegister::Pv => "PV",
+            TvmRegister::Pmt => "PMT",
+            TvmRegister::Fv => "FV",
+        }
+    }
+}
+
+/// The five TVM registers.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Tvm {
+    pub n: f64,
+    pub i: f64,
+    pub pv: f64,
+    pub pmt: f64,
+    pub fv: f64,
+}
+
+impl Tvm {
+    pub fn get(&self, reg: TvmRegister) -> f64 {
+        match reg {
+            TvmRegister::N => self.n,
+            TvmRegister::I => self.i,
+            TvmRegister::Pv => self.pv,
+            TvmRegister::Pmt => self.pmt,
+            TvmRegister::Fv => self.fv,
+        }
+    }
+
+    pub fn set(&mut self, reg: TvmRegister, value: f64) {
+        match reg {
+            TvmRegister::N => self.n = value,
+            TvmRegister::I => self.i = value,
+            TvmRegister::Pv => self.pv = value,
+            TvmRegister::Pmt => self.pmt = value,
+            TvmRegister::Fv => self.fv = value,
+        }
+    }
+
+    /// Solves for `reg` from the other registers. Only PV, PMT and FV can be
+    /// solved for.
+    pub fn solve(&self, reg: TvmRegister) -> Result<f64, &'static str> {
+        let rate = self.i / 100.0;
+        let growth = (1.0 + rate).powf(self.n);
+        // Value of a unit payment stream after n periods.
+        let annuity = if rate == 0.0 {
+            self.n
+        } else {
+            (growth - 1.0) / rate
+        };
+        match reg {
+            TvmRegister::Fv => Ok(-(self.pv * growth + self.pmt * annuity)),
+            TvmRegister::Pv => Ok(-(self.fv + self.pmt * annuity) / growth),
+            TvmRegister::Pmt => {
+                if annuity == 0.0 {
+                    Err("PMT needs n > 0")
+                } else {
+                    Ok(-(self.pv * growth + self.fv) / annuity)
+                }
+            }
+            TvmRegister::N | TvmRegister::I => Err("Only PV, PMT and FV can be solved for"),
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "n={} i={}% PV={} PMT={} FV={}",
+            format_number(self.n),
+            format_number(self.i),
+            format_number(self.pv),
+            format_number(self.pmt),
+            format_number(self.fv)
+        )
+    }
+}
+
+/// Net present value of `flows` (the first is at time 0) at `rate` percent.
+pub fn npv(rate: f64, flows: &[f64]) -> f64 {
+    let r = 1.0 + rate / 100.0;
+    flows
+        .iter()
+        .enumerate()
+        .map(|(t, cf)| cf / r.powi(t as i32))
+        .sum()
+}
+
+/// Internal rate of return of `flows` in percent, found by bisection on the
+/// NPV between -99.99% and 10000%.
+pub fn irr(flows: &[f64]) -> Result<f64, &'static str> {
+    if !flows.iter().any(|&cf| cf > 0.0) || !flows.iter().any(|&cf| cf < 0.0) {
+        return Err("IRR needs both positive and negative cash flows");
+    }
+    let (mut lo, mut hi) = (-99.99, 10_000.0);
+    let mut f_lo = npv(lo, flows);
+    if f_lo.signum() == npv(hi, flows).signum() {
+        return Err("IRR not found in -99.99%..10000%");
+    }
+    for _ in 0..200 {
+        let mid = (lo + hi) / 2.0;
+        let f_mid = npv(mid, flows);
+        if f_mid == 0.0 || (hi - lo) < 1e-12 {
+            return Ok(mid);
+        }
+        if f_mid.signum() == f_lo.signum() {
+            lo = mid;
+            f_lo = f_mid;
+        } else {
+            hi = mid;
+        }
+    }
+    Ok((lo + hi) / 2.0)
+}
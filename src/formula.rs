@@ -0,0 +1,76 @@
+//! Placeholders such as `?x`, which stand for a value bound later with
+//! `bind x 5`, and the pending calculations that use them.
+
+use crate::Value;
+
+/// Commands shown between their operands rather than as a function call.
+const INFIX: &[&str] = &["+", "-", "*", "/", "^"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Formula {
+    Placeholder(String),
+    /// A command waiting on a placeholder, with its operands deepest first.
+    Apply {
+        op: String,
+        args: Vec<Value>,
+    },
+}
+
+impl Formula {
+    /// Reads `?name`, where the name is a letter followed by letters, digits
+    /// or underscores.
+    pub fn parse_placeholder(text: &str) -> Option<Formula> {
+        let name = text.strip_prefix('?')?;
+        valid_name(name).then(|| Formula::Placeholder(name.to_string()))
+    }
+
+    /// Shows the formula with `show` for its concrete operands.
+    pub fn describe(&self, show: &dyn Fn(&Value) -> String) -> String {
+        match self {
+            Formula::Placeholder(name) => format!("?{}", name),
+            Formula::Apply { op, args } if args.len() == 2 && is_infix(op) => {
+                // Infix operands that are themselves infix get parentheses.
+                let operand = |v: &Value| match v {
+                    Value::Formula(f @ Formula::Apply { op, .. }) if is_infix(op) => {
+                        format!("({})", f.describe(show))
+                    }
+                    v => describe_value(v, show),
+                };
+                format!("{} {} {}", operand(&args[0]), op, operand(&args[1]))
+            }
+            Formula::Apply { op, args } => {
+                let args: Vec<String> = args.iter().map(|v| describe_value(v, show)).collect();
+                format!("{}({})", op, args.join(", "))
+            }
+        }
+    }
+}
+
+fn is_infix(op: &str) -> bool {
+    INFIX.contains(&op)
+}
+
+fn describe_value(value: &Value, show: &dyn Fn(&Value) -> String) -> String {
+    match value {
+        Value::Formula(f) => f.describe(show),
+        v => show(v),
+    }
+}
+
+pub fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// `value` with every `?name` in it replaced by `bound`.
+pub fn substitute(value: &Value, name: &str, bound: &Value) -> Value {
+    match value {
+        Value::Formula(Formula::Placeholder(n)) if n == name => bound.clone(),
+        Value::Formula(Formula::Apply { op, args }) => Value::Formula(Formula::Apply {
+            op: op.clone(),
+            args: args.iter().map(|a| substitute(a, name, bound)).collect(),
+        }),
+        v => v.clone(),
+    }
+}
@@ -0,0 +1,60 @@
+//! Classifying the tokens of an input line, so the TUI can color them as
+//! they are typed.
+
+use crate::{program, tokenize, App, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A number or other value literal, such as a matrix or date.
+    Number,
+    /// A command, macro or control-flow word.
+    Command,
+    /// An inline argument taken by the command before it.
+    Argument,
+    /// A double-quoted argument.
+    Quoted,
+    /// Anything the calculator would reject as an unknown command.
+    Unknown,
+}
+
+/// Splits `line` into pieces that together make up the whole line, with the
+/// kind of each token; whitespace between tokens has no kind. A line the
+/// tokenizer rejects, such as one with an unclosed `[`, is one piece
+/// without a kind.
+pub fn highlight<'a>(app: &App, line: &'a str) -> Vec<(&'a str, Option<TokenKind>)> {
+    let Ok(tokens) = tokenize(line) else {
+        return vec![(line, None)];
+    };
+    let mut pieces = Vec::new();
+    let mut end = 0;
+    // Inline arguments still owed to the last command.
+    let mut args = 0usize;
+    for token in tokens {
+        let start = token.as_ptr() as usize - line.as_ptr() as usize;
+        if start > end {
+            pieces.push((&line[end..start], None));
+        }
+        end = start + token.len();
+        let kind = if token.starts_with('"') {
+            args = args.saturating_sub(1);
+            TokenKind::Quoted
+        } else if args > 0 {
+            args -= 1;
+            TokenKind::Argument
+        } else if let Some(cmd) = app.registry.lookup(token) {
+            args = cmd.params.len();
+            TokenKind::Command
+        } else if app.macros.get(token).is_some() || program::KEYWORDS.contains(&token) {
+            TokenKind::Command
+        } else if Value::parse(token).is_ok() {
+            TokenKind::Number
+        } else {
+            TokenKind::Unknown
+        };
+        pieces.push((token, Some(kind)));
+    }
+    if end < line.len() {
+        pieces.push((&line[end..], None));
+    }
+    pieces
+}
@@ -0,0 +1,66 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::Value;
+
+/// Calculations kept in memory and loaded from the log, unless the config
+/// file sets `history_limit`.
+pub const HISTORY_LIMIT: usize = 1000;
+
+/// One completed calculation, e.g. `3 + 4 = 7`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub text: String,
+    pub result: Value,
+    /// How long the operation took; `None` for entries loaded from the log.
+    pub elapsed: Option<Duration>,
+}
+
+impl HistoryEntry {
+    pub fn new(text: String, result: Value) -> HistoryEntry {
+        HistoryEntry {
+            text,
+            result,
+            elapsed: None,
+        }
+    }
+
+    /// Rebuilds an entry from a logged line, reading the result after the
+    /// last ` = `.
+    pub fn parse(line: &str) -> Option<HistoryEntry> {
+        let (_, result) = line.rsplit_once(" = ")?;
+        let result = Value::parse(result.trim()).ok()?;
+        Some(HistoryEntry::new(line.to_string(), result))
+    }
+}
+
+/// An in-progress filter over the history pane.
+#[derive(Clone, Debug, Default)]
+pub struct HistorySearch {
+    pub query: String,
+    /// Index into the filtered entries.
+    pub selected: usize,
+}
+
+/// Reads the last `limit` entries of the history log. A missing log is
+/// simply empty.
+pub fn load(path: &Path, limit: usize) -> io::Result<Vec<HistoryEntry>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let entries: Vec<HistoryEntry> = text.lines().filter_map(HistoryEntry::parse).collect();
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.into_iter().skip(skip).collect())
+}
+
+pub fn append(path: &Path, entry: &HistoryEntry) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", entry.text)
+}
@@ -0,0 +1,136 @@
+/// How cells are separated within a line of tabular text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delimiter {
+    Whitespace,
+    Comma,
+    Tab,
+    Semicolon,
+}
+
+impl Delimiter {
+    const ALL: [Delimiter; 4] = [
+        Delimiter::Whitespace,
+        Delimiter::Comma,
+        Delimiter::Tab,
+        Delimiter::Semicolon,
+    ];
+
+    /// Picks the delimiter most likely used by `text`. Semicolons win over
+    /// commas, which may be decimal commas in a semicolon-separated file.
+    pub fn guess(text: &str) -> Delimiter {
+        if text.contains('\t') {
+            Delimiter::Tab
+        } else if text.contains(';') {
+            Delimiter::Semicolon
+        } else if text.contains(',') {
+            Delimiter::Comma
+        } else {
+            Delimiter::Whitespace
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Delimiter::Whitespace => "whitespace",
+            Delimiter::Comma => "comma",
+            Delimiter::Tab => "tab",
+            Delimiter::Semicolon => "semicolon",
+        }
+    }
+
+    pub fn next(self) -> Delimiter {
+        let i = Delimiter::ALL.iter().position(|&d| d == self).unwrap_or(0);
+        Delimiter::ALL[(i + 1) % Delimiter::ALL.len()]
+    }
+
+    pub fn split(self, line: &str) -> Vec<&str> {
+        match self {
+            Delimiter::Whitespace => line.split_whitespace().collect(),
+            Delimiter::Comma => line.split(',').map(str::trim).collect(),
+            Delimiter::Tab => line.split('\t').map(str::trim).collect(),
+            Delimiter::Semicolon => line.split(';').map(str::trim).collect(),
+        }
+    }
+}
+
+/// Pasted text that spans several lines or columns, waiting for the user to
+/// choose which column to push.
+#[derive(Clone, Debug)]
+pub struct TableImport {
+    pub text: String,
+    pub delimiter: Delimiter,
+    pub column: usize,
+}
+
+impl TableImport {
+    pub fn new(text: &str) -> TableImport {
+        TableImport {
+            text: text.to_string(),
+            delimiter: Delimiter::guess(text),
+            column: 0,
+        }
+    }
+
+    /// True when `text` holds more than a single line of tokens, or more than
+    /// one delimited cell on its only line.
+    pub fn is_tabular(text: &str) -> bool {
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        match (lines.next(), lines.next()) {
+            (Some(_), Some(_)) => true,
+            (Some(line), None) => line.contains(['\t', ',', ';']),
+            _ => false,
+        }
+    }
+
+    pub fn rows(&self) -> Vec<Vec<&str>> {
+        self.text
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| self.delimiter.split(l))
+            .collect()
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.rows().iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    pub fn next_column(&mut self) {
+        if self.column + 1 < self.column_count() {
+            self.column += 1;
+        }
+    }
+
+    pub fn prev_column(&mut self) {
+        self.column = self.column.saturating_sub(1);
+    }
+
+    pub fn cycle_delimiter(&mut self) {
+        self.delimiter = self.delimiter.next();
+        self.column = self.column.min(self.column_count().saturating_sub(1));
+    }
+
+    /// Numbers in the selected column, plus the count of non-empty cells that
+    /// did not parse (typically headers).
+    pub fn values(&self) -> (Vec<f64>, usize) {
+        let mut values = Vec::new();
+        let mut skipped = 0;
+        for row in self.rows() {
+            if let Some(cell) = row.get(self.column).filter(|cell| !cell.is_empty()) {
+                match parse_cell(cell) {
+                    Some(v) => values.push(v),
+                    None => skipped += 1,
+                }
+            }
+        }
+        (values, skipped)
+    }
+}
+
+/// A cell as a number, allowing the quotes and decimal comma that
+/// spreadsheets and `export` may write.
+fn parse_cell(cell: &str) -> Option<f64> {
+    let cell = cell.trim_matches('"');
+    cell.parse()
+        .ok()
+        .or_else(|| cell.replacen(',', ".", 1).parse().ok())
+}
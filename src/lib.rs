@@ -1,4 +1,84 @@
-fn format_number(val: f64) -> String {
+pub mod calculus;
+pub mod clipboard;
+pub mod combinatorics;
+pub mod config;
+pub mod date;
+pub mod decimal;
+pub mod engine;
+pub mod error;
+pub mod export;
+pub mod finance;
+pub mod formula;
+pub mod highlight;
+pub mod history;
+pub mod import;
+pub mod macros;
+pub mod paths;
+pub mod plot;
+pub mod prefix;
+pub mod program;
+pub mod random;
+pub mod rates;
+pub mod rational;
+pub mod reference;
+pub mod registry;
+pub mod replay;
+pub mod report;
+pub mod script;
+pub mod selftest;
+#[cfg(unix)]
+pub mod session;
+pub mod softkeys;
+pub mod solver;
+pub mod special;
+pub mod stats;
+pub mod tape;
+pub mod theme;
+pub mod timing;
+pub mod tolerance;
+pub mod uncertain;
+pub mod undo;
+pub mod value;
+pub mod workspace;
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use program::Node;
+use selftest::Expect;
+use solver::SolverSettings;
+
+pub use config::Config;
+pub use date::Date;
+pub use decimal::Decimal;
+pub use engine::Engine;
+pub use error::{CalcError, OpOutcome, OpResult};
+pub use export::{ExportProfile, Quoting};
+pub use finance::{Tvm, TvmRegister};
+pub use formula::Formula;
+pub use history::{HistoryEntry, HistorySearch};
+pub use import::{Delimiter, TableImport};
+pub use macros::{Macro, MacroLibrary, MacroTest};
+pub use plot::{ChartStyle, DataChart, Plot};
+pub use random::Rng;
+pub use rates::RateTable;
+pub use rational::Rational;
+pub use registry::{Command, CommandRegistry, Mode};
+pub use softkeys::SoftKeys;
+pub use stats::{PairStats, Spread};
+pub use tape::{Tape, TapeEntry};
+pub use theme::Theme;
+pub use timing::Timings;
+pub use tolerance::Tolerance;
+pub use uncertain::Uncertain;
+pub use undo::{Aside, Journal};
+pub use value::{numbers, Matrix, Value};
+pub use workspace::Workspace;
+
+pub fn format_number(val: f64) -> String {
     if val.abs() >= 1e10 || (val != 0.0 && val.abs() < 1e-4) {
         format!("{:.6e}", val)
     } else {
@@ -6,13 +86,407 @@ fn format_number(val: f64) -> String {
     }
 }
 
+/// A value on one line, e.g. `[1 2; 3 4]` for a matrix.
+pub fn format_value(value: &Value) -> String {
+    format_value_with(value, format_number)
+}
+
+/// A value on one line, with its floating-point numbers shown by `number`,
+/// e.g. `prefix::format`.
+pub fn format_value_with(value: &Value, number: fn(f64) -> String) -> String {
+    match value {
+        Value::Number(n) => number(*n),
+        Value::Decimal(d) => d.format(2),
+        Value::Rational(r) => r.to_string(),
+        Value::Uncertain(u) => format!("{} ± {}", number(u.value), number(u.error)),
+        Value::Matrix(m) => m.format_with(number),
+        Value::Date(d) => d.to_string(),
+        Value::Formula(f) => f.describe(&|v| format_value_with(v, number)),
+    }
+}
+
+/// A value as shown in the stack: numbers on one line, matrices one row per
+/// line with the columns aligned.
+pub fn format_value_lines(value: &Value) -> Vec<String> {
+    format_value_lines_with(value, format_number)
+}
+
+/// `format_value_lines` with the numbers shown by `number`.
+pub fn format_value_lines_with(value: &Value, number: fn(f64) -> String) -> Vec<String> {
+    let m = match value {
+        Value::Matrix(m) if m.rows() > 1 => m,
+        _ => return vec![format_value_with(value, number)],
+    };
+    let cells: Vec<Vec<String>> = (0..m.rows())
+        .map(|r| m.row(r).iter().map(|&v| number(v)).collect())
+        .collect();
+    let widths: Vec<usize> = (0..m.cols())
+        .map(|c| cells.iter().map(|row| row[c].chars().count()).max().unwrap_or(0))
+        .collect();
+    cells
+        .iter()
+        .map(|row| {
+            let entries: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, &w)| format!("{:>w$}", cell, w = w))
+                .collect();
+            format!("[ {} ]", entries.join("  "))
+        })
+        .collect()
+}
+
+/// The stack one line per value, bottom first, for printing in a terminal:
+/// numbers are lined up on their decimal points and matrices take a line
+/// per row.
+pub fn format_stack_columns(stack: &[Value]) -> Vec<String> {
+    format_stack_columns_with(stack, format_number)
+}
+
+/// `format_stack_columns` with the numbers shown by `number`.
+pub fn format_stack_columns_with(stack: &[Value], number: fn(f64) -> String) -> Vec<String> {
+    let texts: Vec<Vec<String>> = stack
+        .iter()
+        .map(|v| format_value_lines_with(v, number))
+        .collect();
+    // The whole part ends at the decimal point, or at the space before an
+    // SI prefix or `±`.
+    let whole_part = |text: &str| text.find(['.', ' ']).unwrap_or(text.len());
+    let width = texts
+        .iter()
+        .filter(|lines| lines.len() == 1)
+        .map(|lines| whole_part(&lines[0]))
+        .max()
+        .unwrap_or(0);
+    texts
+        .into_iter()
+        .flat_map(|lines| match lines.as_slice() {
+            [text] => {
+                let pad = width - whole_part(text);
+                vec![format!("{}{}", " ".repeat(pad), text)]
+            }
+            _ => lines,
+        })
+        .collect()
+}
+
+/// Length of the longest bar in the `freq` table.
+const FREQ_BAR_WIDTH: usize = 30;
+
+/// Most runs `simulate` does, each leaving a value on the stack.
+const MAX_SIMULATION_RUNS: usize = 100_000;
+
+/// How deeply macros and scripts may call each other before giving up, which
+/// catches ones that (indirectly) call themselves.
+const MAX_NESTING: usize = 32;
+
+/// Most values the stack holds unless the config file says otherwise.
+pub const DEFAULT_MAX_DEPTH: usize = 10_000;
+
+/// Most input lines kept for recalling with Up.
+const MAX_INPUT_HISTORY: usize = 100;
+
+/// Splits an input line at whitespace, keeping each bracketed matrix literal
+/// and each double-quoted argument together as one token.
+pub(crate) fn tokenize(line: &str) -> Result<Vec<&str>, CalcError> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut depth = 0usize;
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' if depth == 0 => {
+                start.get_or_insert(i);
+                quoted = !quoted;
+            }
+            _ if quoted => {}
+            '[' => {
+                start.get_or_insert(i);
+                depth += 1;
+            }
+            ']' if depth > 0 => depth -= 1,
+            ']' => return Err(CalcError::InvalidArgument("Unmatched ']'".to_string())),
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(s) = start.take() {
+                    tokens.push(&line[s..i]);
+                }
+            }
+            _ => {
+                start.get_or_insert(i);
+            }
+        }
+    }
+    if depth > 0 {
+        return Err(CalcError::InvalidArgument("Unclosed '['".to_string()));
+    }
+    if quoted {
+        return Err(CalcError::InvalidArgument("Unclosed '\"'".to_string()));
+    }
+    tokens.extend(start.map(|s| &line[s..]));
+    Ok(tokens)
+}
+
+/// An inline argument without the double quotes that kept it together.
+fn unquote(arg: &str) -> &str {
+    arg.strip_prefix('"')
+        .and_then(|a| a.strip_suffix('"'))
+        .unwrap_or(arg)
+}
+
+/// Splits a number being typed, such as `-1.5e-3`, into its mantissa and
+/// what follows the exponent marker, if there is one yet. `None` if `word`
+/// cannot be the start of a number.
+fn split_number(word: &str) -> Option<(&str, Option<&str>)> {
+    let word = word.strip_prefix('-').unwrap_or(word);
+    let (mantissa, exponent) = match word.find(['e', 'E']) {
+        Some(i) => (&word[..i], Some(&word[i + 1..])),
+        None => (word, None),
+    };
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let valid_mantissa = mantissa.bytes().any(|b| b.is_ascii_digit())
+        && digits(&mantissa.replacen('.', "", 1));
+    let valid_exponent = exponent.is_none_or(|e| digits(e.strip_prefix(['+', '-']).unwrap_or(e)));
+    (valid_mantissa && valid_exponent).then_some((mantissa, exponent))
+}
+
+/// The explicit sign shown before positive changes.
+fn sign(value: f64) -> &'static str {
+    if value > 0.0 {
+        "+"
+    } else {
+        ""
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AngleMode {
+    #[default]
+    Degrees,
+    Radians,
+    Gradians,
+}
+
+impl AngleMode {
+    pub fn to_radians(self, angle: f64) -> f64 {
+        match self {
+            AngleMode::Degrees => angle.to_radians(),
+            AngleMode::Radians => angle,
+            AngleMode::Gradians => angle * std::f64::consts::PI / 200.0,
+        }
+    }
+
+    pub fn from_radians(self, radians: f64) -> f64 {
+        match self {
+            AngleMode::Degrees => radians.to_degrees(),
+            AngleMode::Radians => radians,
+            AngleMode::Gradians => radians * 200.0 / std::f64::consts::PI,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AngleMode::Degrees => "DEG",
+            AngleMode::Radians => "RAD",
+            AngleMode::Gradians => "GRAD",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<AngleMode> {
+        match name {
+            "DEG" => Some(AngleMode::Degrees),
+            "RAD" => Some(AngleMode::Radians),
+            "GRAD" => Some(AngleMode::Gradians),
+            _ => None,
+        }
+    }
+}
+
+/// How the input line behaves around numbers and Enter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EntryMode {
+    /// Enter runs the input line and does nothing on an empty one; `-` on
+    /// an empty line starts a negative number.
+    #[default]
+    Standard,
+    /// As on an HP calculator: Enter on an empty line runs `enter`, and
+    /// `-` always subtracts, leaving negative numbers to `_`.
+    Classic,
+}
+
+impl EntryMode {
+    pub fn from_name(name: &str) -> Option<EntryMode> {
+        match name {
+            "standard" => Some(EntryMode::Standard),
+            "classic" => Some(EntryMode::Classic),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EntryMode::Standard => "standard",
+            EntryMode::Classic => "classic",
+        }
+    }
+}
+
+/// A scrollable, read-only text window shown over the main screen.
+#[derive(Clone, Debug, Default)]
+pub struct Popup {
+    pub title: String,
+    pub lines: Vec<String>,
+    pub scroll: u16,
+}
+
+impl Popup {
+    pub fn new(title: &str, lines: Vec<String>) -> Popup {
+        Popup {
+            title: title.to_string(),
+            lines,
+            scroll: 0,
+        }
+    }
+}
+
 pub struct App {
-    pub stack: Vec<f64>,
+    pub stack: Vec<Value>,
     pub input: String,
+    /// Where typing goes in `input`, as a byte offset; `None` is the end,
+    /// where it usually goes.
+    pub cursor: Option<usize>,
+    /// Lines entered so far, oldest first.
+    pub input_history: Vec<String>,
+    /// The `input_history` entry shown in the input while recalling, and the
+    /// line that was being typed before.
+    recall: Option<(usize, String)>,
     pub message: String,
-    pub history: Vec<Vec<f64>>,
-    pub calc_history: Vec<String>,
+    /// What the input line would leave on top of the stack, for the status
+    /// bar; see `update_preview`.
+    pub preview: Option<String>,
+    /// The input and stack `preview` was worked out for.
+    preview_key: Option<(String, Vec<Value>)>,
+    /// What `undo` needs to reverse each step.
+    pub journal: Journal,
+    pub calc_history: Vec<HistoryEntry>,
+    /// Most calculations kept in `calc_history` and loaded from the log.
+    pub history_limit: usize,
+    /// When set, every calculation is also appended to this file.
+    pub history_file: Option<PathBuf>,
+    /// Whether sessions keep a replay log; see `replay`.
+    pub replay_log: bool,
+    /// When set, every line entered is appended to this file before it
+    /// runs.
+    pub replay_file: Option<PathBuf>,
+    /// The date last written to the replay log.
+    logged_date: Option<Date>,
+    /// The date `today` gives, when a replay fixes it to the day its lines
+    /// were entered; the clock's when `None`.
+    pub fixed_date: Option<Date>,
+    /// While a line is replayed, what each external command in it did when
+    /// it was logged, in order; see `replay::run`.
+    pub replay_outcomes: Option<VecDeque<Result<Vec<Value>, String>>>,
+    /// How many entries the history pane is scrolled up from the newest.
+    pub history_scroll: usize,
+    pub history_search: Option<HistorySearch>,
     pub show_help: bool,
+    pub help_scroll: u16,
+    /// Every number and operation entered this session; see `tape`.
+    pub tape: Tape,
+    pub show_tape: bool,
+    /// Tape lines scrolled back from the end.
+    pub tape_scroll: usize,
+    pub registry: CommandRegistry,
+    pub table_import: Option<TableImport>,
+    pub angle_mode: AngleMode,
+    pub notes: String,
+    pub show_notes: bool,
+    pub editing_notes: bool,
+    /// What F1 to F8 run, and whether their labels are shown.
+    pub softkeys: SoftKeys,
+    pub show_softkeys: bool,
+    /// The page of soft keys F1 to F8 run from, turned by F9.
+    pub softkey_page: usize,
+    pub tvm: Tvm,
+    /// Statistical registers for (x, y) pairs; see `sadd`.
+    pub pairs: PairStats,
+    pub popup: Option<Popup>,
+    /// Inline arguments of the command currently executing.
+    pub args: Vec<String>,
+    /// Reference value set by `mark`; the top of the stack is compared to it.
+    pub mark: Option<f64>,
+    /// The x operand of the most recent operation, pushed back by `lastx`.
+    pub last_x: Option<Value>,
+    /// Numbers entered since the last `clear` or `subtotal`, which
+    /// `subtotal` adds up whether or not they are still on the stack.
+    pub entered: Vec<Value>,
+    /// Values set aside by `park`, most recent last.
+    pub shelf: Vec<Value>,
+    /// Stack positions flagged by `outliers`, highlighted until the next
+    /// input.
+    pub outliers: Vec<usize>,
+    /// What the plot pane shows, if it is open.
+    pub plot: Option<Plot>,
+    /// The data chart shown over the screen, if one is open.
+    pub chart: Option<DataChart>,
+    /// Source of `rand`, `randn` and simulations; see `seed`.
+    pub rng: Rng,
+    /// Every workspace; the entry for the current one is left empty while
+    /// its state is in `stack`, `journal`, `mark` and `last_x`.
+    pub workspaces: Vec<Workspace>,
+    /// Index of the current workspace.
+    pub workspace: usize,
+    /// Money mode: numbers are entered as exact decimals and shown to the
+    /// cent.
+    pub money: bool,
+    /// Fraction mode: numbers are entered as exact fractions, which `+ - * /`
+    /// keep exact.
+    pub frac: bool,
+    /// Show floating-point numbers with SI prefixes, e.g. `4.7 µ`.
+    pub eng_prefixes: bool,
+    pub entry: EntryMode,
+    /// Set by `enter`: the next value pushed replaces the copy it made.
+    lift_disabled: bool,
+    /// Most values the stack may hold; operations that would push more
+    /// fail.
+    pub max_depth: usize,
+    /// Let operations push NaN and infinities instead of rejecting operands
+    /// outside their domain.
+    pub allow_nan: bool,
+    /// When `~=` and macro tests count values as equal.
+    pub tolerance: Tolerance,
+    /// How hard `solve` tries to converge.
+    pub solver: SolverSettings,
+    /// Draw bars in the `freq` table.
+    pub freq_bars: bool,
+    /// Poll less often and skip redraws while idle, to save battery.
+    pub low_power: bool,
+    /// How `export` writes numbers and cells.
+    pub export: ExportProfile,
+    pub theme: Theme,
+    pub macros: MacroLibrary,
+    /// The most recent input line that failed, and why, for `report`.
+    pub last_error: Option<(String, CalcError)>,
+    /// How long recent commands and macros took, for `slowops`.
+    pub timings: Timings,
+    /// Exchange rates for `convertccy`, from the rates file.
+    pub rates: RateTable,
+    /// Where `report` saves bug report bundles; `None` disables it.
+    pub report_dir: Option<PathBuf>,
+    /// Version shown in bug reports.
+    pub version: &'static str,
+    /// How many macros and scripts are currently running inside each other.
+    nesting: usize,
+    /// Tokens and loop iterations run so far for the current input line.
+    steps: usize,
+    /// Set on the copy a preview runs on; commands that are not
+    /// previewable refuse to run.
+    dry_run: bool,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl App {
@@ -21,244 +495,2564 @@ impl App {
             stack: Vec::new(),
             input: String::new(),
             message: "Type numbers or commands (help for list), Enter to execute, q to quit".to_string(),
-            history: Vec::new(),
+            preview: None,
+            preview_key: None,
+            cursor: None,
+            input_history: Vec::new(),
+            recall: None,
+            journal: Journal::default(),
             calc_history: Vec::new(),
+            history_limit: history::HISTORY_LIMIT,
+            history_file: None,
+            replay_log: true,
+            replay_file: None,
+            logged_date: None,
+            fixed_date: None,
+            replay_outcomes: None,
+            history_scroll: 0,
+            history_search: None,
             show_help: false,
+            help_scroll: 0,
+            tape: Tape::default(),
+            show_tape: false,
+            tape_scroll: 0,
+            registry: CommandRegistry::with_builtins(),
+            table_import: None,
+            angle_mode: AngleMode::default(),
+            notes: String::new(),
+            show_notes: false,
+            editing_notes: false,
+            softkeys: SoftKeys::default(),
+            show_softkeys: true,
+            softkey_page: 0,
+            tvm: Tvm::default(),
+            pairs: PairStats::default(),
+            popup: None,
+            args: Vec::new(),
+            mark: None,
+            last_x: None,
+            entered: Vec::new(),
+            shelf: Vec::new(),
+            outliers: Vec::new(),
+            plot: None,
+            chart: None,
+            rng: Rng::from_time(),
+            workspaces: vec![Workspace::default()],
+            workspace: 0,
+            money: false,
+            frac: false,
+            eng_prefixes: false,
+            entry: EntryMode::default(),
+            lift_disabled: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            allow_nan: false,
+            tolerance: Tolerance::default(),
+            solver: SolverSettings::default(),
+            freq_bars: true,
+            low_power: false,
+            export: ExportProfile::default(),
+            theme: Theme::default(),
+            macros: MacroLibrary::default(),
+            last_error: None,
+            timings: Timings::default(),
+            rates: RateTable::default(),
+            report_dir: None,
+            version: env!("CARGO_PKG_VERSION"),
+            nesting: 0,
+            steps: 0,
+            dry_run: false,
         }
     }
 
+    /// Executes the input line, which may hold several space-separated
+    /// numbers and commands, and shows the outcome in the message.
     pub fn execute_command(&mut self) {
-        if self.input.is_empty() {
+        let line = std::mem::take(&mut self.input);
+        self.cursor = None;
+        self.recall = None;
+        if line.trim().is_empty() {
             return;
         }
-        
-        if let Ok(num) = self.input.parse::<f64>() {
-            self.history.push(self.stack.clone());
-            self.stack.push(num);
-            self.message = format!("Pushed {}", num);
-        } else {
-            match self.input.as_str() {
-                "undo" => {
-                    if let Some(prev_stack) = self.history.pop() {
-                        self.stack = prev_stack;
-                        self.message = "Undid last operation".to_string();
-                    } else {
-                        self.message = "Nothing to undo".to_string();
-                    }
-                },
-                "help" => {
-                    self.show_help = true;
-                    self.message = "Help shown (press any key to close)".to_string();
-                },
-                _ => {
-                    self.history.push(self.stack.clone());
-                    match self.input.as_str() {
-                        "+" => self.binary_op(|a, b| a + b, "+"),
-                        "-" => self.binary_op(|a, b| a - b, "-"),
-                        "*" => self.binary_op(|a, b| a * b, "*"),
-                        "/" => self.divide(),
-                        "^" | "pow" => self.binary_op(|a, b| a.powf(b), "^"),
-                        "%" | "mod" => self.binary_op(|a, b| a % b, "%"),
-                        "sin" => self.unary_op(|a| a.to_radians().sin(), "sin"),
-                        "cos" => self.unary_op(|a| a.to_radians().cos(), "cos"),
-                        "tan" => self.unary_op(|a| a.to_radians().tan(), "tan"),
-                        "asin" => self.unary_op(|a| a.asin().to_degrees(), "asin"),
-                        "acos" => self.unary_op(|a| a.acos().to_degrees(), "acos"),
-                        "atan" => self.unary_op(|a| a.atan().to_degrees(), "atan"),
-                        "sqrt" => self.unary_op(|a| a.sqrt(), "sqrt"),
-                        "ln" => self.unary_op(|a| a.ln(), "ln"),
-                        "log" => self.unary_op(|a| a.log10(), "log"),
-                        "exp" => self.unary_op(|a| a.exp(), "exp"),
-                        "10x" => self.unary_op(|a| 10.0_f64.powf(a), "10x"),
-                        "abs" => self.unary_op(|a| a.abs(), "abs"),
-                        "cbrt" => self.unary_op(|a| a.cbrt(), "cbrt"),
-                        "root" => self.root(),
-                        "inv" => self.reciprocal(),
-                        "!" | "fact" => self.factorial(),
-                        "swap" => self.swap(),
-                        "clear" | "clr" => {
-                            self.stack.clear();
-                            self.message = "Stack cleared".to_string();
-                        },
-                        "drop" => {
-                            if let Some(val) = self.stack.pop() {
-                                self.message = format!("Dropped {}", val);
-                            } else {
-                                self.message = "Stack is empty".to_string();
-                            }
-                        },
-                        "pi" => {
-                            self.stack.push(std::f64::consts::PI);
-                            self.message = "Pushed π".to_string();
-                        },
-                        "e" => {
-                            self.stack.push(std::f64::consts::E);
-                            self.message = "Pushed e".to_string();
-                        },
-                        _ => self.message = "Unknown command (type 'help' for list)".to_string(),
-                    }
-                }
+        let result = self.submit_line(line);
+        self.report(result);
+    }
+
+    /// Runs a line the user entered, keeping it for recall and, if it fails,
+    /// for `report`.
+    pub fn submit_line(&mut self, line: String) -> OpResult {
+        if self.input_history.last() != Some(&line) {
+            if self.input_history.len() == MAX_INPUT_HISTORY {
+                self.input_history.remove(0);
             }
+            self.input_history.push(line.clone());
         }
-        
-        self.input.clear();
+        let result = self.eval_line(&line);
+        if let Err(e) = &result {
+            self.last_error = Some((line, e.clone()));
+        }
+        result
     }
 
-    pub fn binary_op<F>(&mut self, op: F, name: &str)
-    where
-        F: Fn(f64, f64) -> f64,
-    {
-        if self.stack.len() < 2 {
-            self.message = format!("Need 2 numbers for {}", name);
-            return;
+    /// Shows an operation's outcome or error in the message line.
+    pub fn report(&mut self, result: OpResult) {
+        self.message = match result {
+            Ok(outcome) => outcome.message().to_string(),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    /// Executes each token of `line` in turn, stopping at the first error.
+    /// Returns the outcome of the last token.
+    pub fn eval_line(&mut self, line: &str) -> OpResult {
+        if self.nesting == 0 {
+            self.log_replay(line);
         }
-        let b = self.stack.pop().unwrap();
-        let a = self.stack.pop().unwrap();
-        let result = op(a, b);
-        self.stack.push(result);
-        let calc = format!("{} {} {} = {}", format_number(a), name, format_number(b), format_number(result));
-        self.message = calc.clone();
-        self.calc_history.push(calc);
-        if self.calc_history.len() > 10 {
-            self.calc_history.remove(0);
+        let tokens = tokenize(line)?;
+        let program = program::parse(&tokens)?;
+        if self.nesting == 0 {
+            self.steps = 0;
         }
+        self.run_block(&program)
     }
-    
-    pub fn unary_op<F>(&mut self, op: F, name: &str)
-    where
-        F: Fn(f64) -> f64,
-    {
-        if let Some(a) = self.stack.pop() {
-            let result = op(a);
-            self.stack.push(result);
-            let calc = format!("{}({}) = {}", name, format_number(a), format_number(result));
-            self.message = calc.clone();
-            self.calc_history.push(calc);
-            if self.calc_history.len() > 10 {
-                self.calc_history.remove(0);
+
+    /// Starts a session in the replay log at `path` and logs every line
+    /// entered from now on. The random numbers are reseeded from their
+    /// current state, so that the log can give the replay the same ones.
+    pub fn start_replay_log(&mut self, path: PathBuf) {
+        let seed = self.rng.next_u64();
+        self.rng = Rng::new(seed);
+        match replay::start(&path, &self.date().to_string(), seed) {
+            Ok(()) => {
+                self.replay_file = Some(path);
+                self.logged_date = None;
             }
-        } else {
-            self.message = format!("Need 1 number for {}", name);
+            Err(e) => self.message = format!("Could not start the replay log: {}", e),
         }
     }
-    
-    pub fn divide(&mut self) {
-        if self.stack.len() < 2 {
-            self.message = "Need 2 numbers for division".to_string();
+
+    /// Logs `line` for replay, after the date whenever it has changed since
+    /// the last line, so that `today` gives the same date in the replay.
+    fn log_replay(&mut self, line: &str) {
+        let Some(path) = &self.replay_file else {
             return;
+        };
+        let date = self.date();
+        let result = match self.logged_date {
+            Some(logged) if logged == date => replay::append(path, line),
+            _ => replay::append(path, &format!("{} {}", replay::DATE_MARK, date))
+                .and_then(|()| replay::append(path, line)),
+        };
+        match result {
+            Ok(()) => self.logged_date = Some(date),
+            Err(e) => {
+                self.message = format!("Replay log disabled: {}", e);
+                self.replay_file = None;
+            }
         }
-        let b = self.stack.pop().unwrap();
-        let a = self.stack.pop().unwrap();
-        if b == 0.0 {
-            self.stack.push(a);
-            self.stack.push(b);
-            self.message = "Division by zero".to_string();
-        } else {
-            self.stack.push(a / b);
-            let calc = format!("{} / {} = {}", format_number(a), format_number(b), format_number(a / b));
-            self.message = calc.clone();
-            self.calc_history.push(calc);
-            if self.calc_history.len() > 10 {
-                self.calc_history.remove(0);
-            }
-        }
-    }
-    
-    pub fn reciprocal(&mut self) {
-        if let Some(a) = self.stack.pop() {
-            if a == 0.0 {
-                self.stack.push(a);
-                self.message = "Cannot take reciprocal of zero".to_string();
-            } else {
-                let result = 1.0 / a;
-                self.stack.push(result);
-                let calc = format!("1/{} = {}", format_number(a), format_number(result));
-                self.message = calc.clone();
-                self.calc_history.push(calc);
-                if self.calc_history.len() > 10 {
-                    self.calc_history.remove(0);
+    }
+
+    /// Logs what an external command did, the values it pushed or how it
+    /// failed, for a replay to do the same without files or the clipboard.
+    fn log_outcome(&mut self, result: &OpResult, len: usize) {
+        let (Some(path), None) = (&self.replay_file, &self.replay_outcomes) else {
+            return;
+        };
+        let outcome = match result {
+            Ok(_) => value::encode_exact(&self.stack[len.min(self.stack.len())..]),
+            Err(e) => format!("{} {}", replay::FAILED, e),
+        };
+        if let Err(e) = replay::append(path, &format!("{} {}", replay::OUTCOME_MARK, outcome)) {
+            self.message = format!("Replay log disabled: {}", e);
+            self.replay_file = None;
+        }
+    }
+
+    /// Stands in for an external command in a replay, doing what the log
+    /// says it did.
+    fn replay_outcome(&mut self, name: &str) -> OpResult {
+        let outcome = self
+            .replay_outcomes
+            .as_mut()
+            .and_then(VecDeque::pop_front)
+            .ok_or_else(|| {
+                CalcError::InvalidArgument(format!("The replay log does not say what {} did", name))
+            })?;
+        let values = outcome.map_err(CalcError::InvalidArgument)?;
+        let message = format!("Replayed {} from the log", name);
+        self.stack.extend(values);
+        Ok(OpOutcome::info(message))
+    }
+
+    /// Today's date, unless a replay has fixed it.
+    pub fn date(&self) -> Date {
+        self.fixed_date.unwrap_or_else(Date::today)
+    }
+
+    /// A fingerprint of the state a replay rebuilds: the stacks of every
+    /// workspace with their marks, last x, subtotals and shelves, the angle
+    /// and other modes, and the registers. The history, which is loaded
+    /// from earlier sessions, and the undo journals and tape are left out,
+    /// so replaying a session gives the checksum the session ended with.
+    pub fn checksum(&self) -> u64 {
+        let workspaces: Vec<_> = self
+            .workspaces
+            .iter()
+            .map(|w| (&w.stack, w.mark, &w.last_x, &w.entered, &w.shelf))
+            .collect();
+        let state = format!(
+            "{:?}",
+            (
+                (&self.stack, self.mark, &self.last_x, &self.entered, &self.shelf),
+                (workspaces, self.workspace),
+                (self.angle_mode, self.money, self.frac, self.eng_prefixes, self.entry),
+                (self.tvm, self.pairs),
+            )
+        );
+        replay::fingerprint(state.as_bytes())
+    }
+
+    /// Runs parsed input. An `if` or `times` construct undoes as one step,
+    /// and if any part of it fails the stack is restored.
+    fn run_block(&mut self, block: &[Node<'_>]) -> OpResult {
+        let mut nodes = block.iter().peekable();
+        let mut outcome = OpOutcome::info("");
+        while let Some(node) = nodes.next() {
+            self.step()?;
+            outcome = match node {
+                Node::Token(token) => {
+                    let mut args = std::iter::from_fn(|| match nodes.peek() {
+                        Some(Node::Token(arg)) => {
+                            nodes.next();
+                            Some(*arg)
+                        }
+                        _ => None,
+                    });
+                    self.execute_token(token, &mut args)?
                 }
+                Node::If { then, otherwise } => self
+                    .run_nested("if", |app| {
+                        let [condition] = app.top_numbers("if")?;
+                        app.pop_operand();
+                        app.run_block(if condition != 0.0 { then } else { otherwise })
+                    })
+                    .inspect(|_| self.tape_operation(|| "if".to_string()))?,
+                Node::Times(body) => self.run_nested("times", |app| {
+                    let [count] = app.top_numbers("times")?;
+                    if count < 0.0 || count.fract() != 0.0 {
+                        return Err(CalcError::domain("times needs a whole number of runs"));
+                    }
+                    app.pop_operand();
+                    let mut outcome = OpOutcome::info(format!("Ran {} times", count));
+                    for _ in 0..count as u64 {
+                        app.step()?;
+                        outcome = app.run_block(body)?;
+                    }
+                    Ok(outcome)
+                })
+                .inspect(|_| self.tape_operation(|| "times".to_string()))?,
+            };
+        }
+        Ok(outcome)
+    }
+
+    /// Counts one step of the current input line, giving up once it has run
+    /// `program::MAX_STEPS`, or `MAX_PREVIEW_STEPS` in a preview.
+    fn step(&mut self) -> Result<(), CalcError> {
+        let limit = if self.dry_run { program::MAX_PREVIEW_STEPS } else { program::MAX_STEPS };
+        self.steps += 1;
+        if self.steps > limit {
+            return Err(CalcError::InvalidArgument(format!(
+                "Stopped after {} steps; does a loop run too long?",
+                limit
+            )));
+        }
+        Ok(())
+    }
+
+    /// Runs `line` on a copy of the calculator and returns the stack it
+    /// would leave, changing nothing here. Commands that touch files or the
+    /// clipboard, or may take long, are refused.
+    pub fn dry_run(&self, line: &str) -> Result<Vec<Value>, CalcError> {
+        let mut copy = self.scratch();
+        copy.stack = self.stack.clone();
+        copy.money = self.money;
+        copy.frac = self.frac;
+        copy.tvm = self.tvm;
+        copy.pairs = self.pairs;
+        copy.rng = self.rng.clone();
+        copy.last_x = self.last_x.clone();
+        copy.entered = self.entered.clone();
+        copy.shelf = self.shelf.clone();
+        copy.dry_run = true;
+        copy.eval_line(line)?;
+        Ok(copy.stack)
+    }
+
+    /// The preview of the input line: `→` and the value it would leave on
+    /// top of the stack, or the error it would give. `None` until the line
+    /// holds a command or macro, since numbers alone are only pushed.
+    pub fn input_preview(&self) -> Option<String> {
+        let tokens = tokenize(&self.input).ok()?;
+        let has_command = tokens
+            .iter()
+            .any(|t| self.registry.lookup(t).is_some() || self.macros.get(t).is_some());
+        if !has_command {
+            return None;
+        }
+        Some(match self.dry_run(&self.input) {
+            Ok(stack) => match stack.last() {
+                Some(top) => format!("→ {}", format_value(top)),
+                None => "→ empty stack".to_string(),
+            },
+            Err(e) => format!("→ {}", e),
+        })
+    }
+
+    /// Works out `preview` again if the input or the stack has changed.
+    pub fn update_preview(&mut self) {
+        let key = (self.input.clone(), self.stack.clone());
+        if self.preview_key.as_ref() != Some(&key) {
+            self.preview = self.input_preview();
+            self.preview_key = Some(key);
+        }
+    }
+
+    /// Runs one number or command, taking any inline arguments the command
+    /// declares from `rest`. Calculations are recorded in the history; a
+    /// failed command leaves no undo step behind.
+    fn execute_token<'a>(
+        &mut self,
+        token: &str,
+        rest: &mut impl Iterator<Item = &'a str>,
+    ) -> OpResult {
+        self.outliers.clear();
+        if let Some(amount) = Decimal::parse(token).filter(|_| self.money) {
+            self.push_entered(amount.into())?;
+            return Ok(OpOutcome::info(format!("Pushed {}", amount.format(2))));
+        }
+        let fraction = Rational::parse(token).filter(|_| self.frac || token.contains('/'));
+        if let Some(fraction) = fraction {
+            self.push_entered(fraction.into())?;
+            return Ok(OpOutcome::info(format!("Pushed {}", fraction)));
+        }
+        if let Some(num) = token.parse::<f64>().ok().or_else(|| prefix::parse(token)) {
+            self.push_entered(num.into())?;
+            return Ok(OpOutcome::info(format!("Pushed {}", num)));
+        }
+        if token.starts_with('[') {
+            let value = Value::parse(token).map_err(CalcError::InvalidArgument)?;
+            let message = format!("Pushed {}", format_value(&value));
+            self.push_literal(value)?;
+            return Ok(OpOutcome::info(message));
+        }
+        if let Some(placeholder) = Formula::parse_placeholder(token) {
+            self.push_literal(Value::Formula(placeholder))?;
+            return Ok(OpOutcome::info(format!("Pushed placeholder {}", token)));
+        }
+        if let Some(date) = Date::parse(token) {
+            self.push_literal(date.into())?;
+            return Ok(OpOutcome::info(format!("Pushed {}, a {}", date, date.weekday_name())));
+        }
+        if let Some(measurement) = Uncertain::parse(token) {
+            let value = Value::from(measurement);
+            let message = format!("Pushed {}", format_value(&value));
+            self.push_literal(value)?;
+            return Ok(OpOutcome::info(message));
+        }
+        // Only a value entered right after `enter` replaces its copy.
+        self.lift_disabled = false;
+        let Some(cmd) = self.registry.lookup(token) else {
+            let body = self.macros.get(token).map(|m| m.body.clone());
+            let Some(body) = body else {
+                return Err(CalcError::UnknownCommand(token.to_string()));
+            };
+            let start = Instant::now();
+            let result = self.run_macro(token, &body);
+            self.timings.record(token, start.elapsed());
+            if result.is_ok() {
+                self.tape_operation(|| token.to_string());
             }
+            return result;
+        };
+        if self.dry_run && !cmd.previewable {
+            return Err(CalcError::InvalidArgument(format!("{} is not previewed", cmd.name)));
+        }
+        let (handler, undoable, params) = (cmd.handler, cmd.undoable, cmd.params);
+        let external = cmd.external;
+        // How far down the stack undo has to keep what the command replaces.
+        let depth = (!cmd.whole_stack).then_some(cmd.arity);
+        let args: Vec<String> = rest.take(params.len()).map(str::to_string).collect();
+        if args.len() < params.len() {
+            return Err(CalcError::Usage(cmd.usage()));
+        }
+        // A calculation on a placeholder waits until the placeholder is bound.
+        let (name, arity) = (cmd.name, cmd.arity);
+        if cmd.deferrable
+            && arity > 0
+            && cmd.produces == 1
+            && params.is_empty()
+            && self.stack.len() >= arity
+            && self.stack[self.stack.len() - arity..].iter().any(Value::is_formula)
+        {
+            let outcome = self.defer(name, arity);
+            self.tape_operation(|| token.to_string());
+            return Ok(outcome);
+        }
+        let pending = undoable.then(|| self.journal.begin(&self.stack, depth));
+        let len = self.stack.len();
+        // The command as typed, with its inline arguments, for the tape;
+        // commands that leave the stack alone, such as views, stay off it.
+        let text = (self.nesting == 0 && undoable).then(|| {
+            let mut words = vec![token.to_string()];
+            words.extend(args.iter().cloned());
+            words.join(" ")
+        });
+        self.args = args;
+        let start = Instant::now();
+        let mut result = if external && self.replay_outcomes.is_some() {
+            self.replay_outcome(name)
         } else {
-            self.message = "Need 1 number for reciprocal".to_string();
-        }
-    }
-    
-    pub fn factorial(&mut self) {
-        if let Some(a) = self.stack.pop() {
-            if a < 0.0 || a.fract() != 0.0 {
-                self.stack.push(a);
-                self.message = "Factorial needs non-negative integer".to_string();
-            } else {
-                let n = a as u64;
-                let result = (1..=n).product::<u64>() as f64;
-                self.stack.push(result);
-                let calc = format!("{}! = {}", n, format_number(result));
-                self.message = calc.clone();
-                self.calc_history.push(calc);
-                if self.calc_history.len() > 10 {
-                    self.calc_history.remove(0);
+            handler(self)
+        };
+        let elapsed = start.elapsed();
+        self.timings.record(name, elapsed);
+        self.args.clear();
+        if external {
+            self.log_outcome(&result, len);
+        }
+        if result.is_ok() && self.stack.len() > self.max_depth.max(len) {
+            // Without a journal entry to put the stack back, the values
+            // beyond the limit are dropped.
+            if !pending.is_some_and(|pending| pending.revert(&mut self.stack)) {
+                self.stack.truncate(self.max_depth.max(len));
+            }
+            result = Err(CalcError::StackFull(self.max_depth));
+        } else if let (Some(pending), Ok(_)) = (pending, &result) {
+            self.journal.commit(pending, &self.stack);
+        }
+        if result.is_err() {
+            let noted = self.journal.take_noted();
+            self.restore(noted);
+        }
+        if let (Some(text), Ok(outcome)) = (text, &result) {
+            let calc = match outcome {
+                OpOutcome::Calc { text, .. } => Some(text.clone()),
+                OpOutcome::Info(_) => None,
+            };
+            self.tape.push_operation(text, self.stack.last().cloned(), calc);
+        }
+        match result {
+            Ok(OpOutcome::Calc { text, result }) => {
+                let outcome = self.record(text, result);
+                if let Some(entry) = self.calc_history.last_mut() {
+                    entry.elapsed = Some(elapsed);
                 }
+                Ok(outcome)
             }
-        } else {
-            self.message = "Need 1 number for factorial".to_string();
+            result => result,
         }
     }
-    
-    pub fn swap(&mut self) {
-        if self.stack.len() < 2 {
-            self.message = "Need 2 numbers to swap".to_string();
-        } else {
-            let len = self.stack.len();
-            self.stack.swap(len - 1, len - 2);
-            self.message = "Swapped top 2 values".to_string();
+
+    /// Notes an operation run from the input line, not from within a macro
+    /// or loop, on the tape.
+    fn tape_operation(&mut self, text: impl FnOnce() -> String) {
+        if self.nesting == 0 {
+            self.tape.push_operation(text(), self.stack.last().cloned(), None);
         }
     }
 
-    pub fn execute_single_char(&mut self, c: char) {
-        if !self.input.is_empty() {
-            self.execute_command();
+    /// Pushes a value typed on the input line as one undo step.
+    fn push_literal(&mut self, value: Value) -> Result<(), CalcError> {
+        if std::mem::take(&mut self.lift_disabled) && !self.stack.is_empty() {
+            if self.nesting == 0 {
+                self.tape.push_number(value.clone());
+            }
+            let pending = self.journal.begin(&self.stack, Some(1));
+            self.stack.pop();
+            self.stack.push(value);
+            self.journal.commit(pending, &self.stack);
+            return Ok(());
         }
-        
-        self.history.push(self.stack.clone());
-        
-        match c {
-            '+' => self.binary_op(|a, b| a + b, "+"),
-            '-' => self.binary_op(|a, b| a - b, "-"),
-            '*' => self.binary_op(|a, b| a * b, "*"),
-            '/' => self.divide(),
-            '^' => self.binary_op(|a, b| a.powf(b), "^"),
-            '%' => self.binary_op(|a, b| a % b, "%"),
-            '!' => self.factorial(),
-            _ => {}
+        self.check_room(1)?;
+        if self.nesting == 0 {
+            self.tape.push_number(value.clone());
         }
+        self.journal.pushed(self.stack.len());
+        self.stack.push(value);
+        Ok(())
     }
 
-    pub fn clear(&mut self) {
-        self.stack.clear();
-        self.message = "Stack cleared".to_string();
+    /// Pushes a number typed in, counting it for `subtotal`.
+    fn push_entered(&mut self, value: Value) -> Result<(), CalcError> {
+        self.journal.note(Aside::EnteredLen(self.entered.len()));
+        if let Err(e) = self.push_literal(value.clone()) {
+            self.journal.take_noted();
+            return Err(e);
+        }
+        self.entered.push(value);
+        Ok(())
     }
 
-    fn root(&mut self) {
-        if self.stack.len() < 2 {
-            self.message = "Need 2 numbers for root (y root x = x^(1/y))".to_string();
-            return;
+    /// Fails unless `count` more values fit under the stack depth limit.
+    fn check_room(&self, count: usize) -> Result<(), CalcError> {
+        if self.stack.len() + count > self.max_depth {
+            return Err(CalcError::StackFull(self.max_depth));
         }
-        let y = self.stack.pop().unwrap(); // root index
-        let x = self.stack.pop().unwrap(); // base
-        if y == 0.0 {
-            self.stack.push(x);
-            self.stack.push(y);
-            self.message = "Cannot take 0th root".to_string();
+        Ok(())
+    }
+
+    /// `depth` pushes how many values the stack holds.
+    pub fn depth(&mut self) -> OpResult {
+        let depth = self.stack.len();
+        self.stack.push((depth as f64).into());
+        Ok(OpOutcome::info(format!("Stack depth {} of at most {}", depth, self.max_depth)))
+    }
+
+    /// Drops the top of the stack as a step of the journal, so that a
+    /// block that fails afterwards can put it back.
+    fn pop_operand(&mut self) {
+        let pending = self.journal.begin(&self.stack, Some(1));
+        self.stack.pop();
+        self.journal.commit(pending, &self.stack);
+    }
+
+    /// Leaves `op` waiting on the placeholders among its operands. It goes on
+    /// the tape with an unknown result until they are bound.
+    fn defer(&mut self, op: &str, arity: usize) -> OpOutcome {
+        let pending = self.journal.begin(&self.stack, Some(arity));
+        let args = self.stack.split_off(self.stack.len() - arity);
+        let formula = Value::Formula(Formula::Apply {
+            op: op.to_string(),
+            args,
+        });
+        self.stack.push(formula.clone());
+        self.journal.commit(pending, &self.stack);
+        self.record(format!("{} = ?", format_value(&formula)), formula)
+    }
+
+    /// Works out `value` as far as its placeholders allow.
+    fn evaluate(&self, value: &Value) -> Result<Value, CalcError> {
+        let Value::Formula(Formula::Apply { op, args }) = value else {
+            return Ok(value.clone());
+        };
+        let args = args
+            .iter()
+            .map(|a| self.evaluate(a))
+            .collect::<Result<Vec<_>, _>>()?;
+        if args.iter().any(Value::is_formula) {
+            return Ok(Value::Formula(Formula::Apply {
+                op: op.clone(),
+                args,
+            }));
+        }
+        let mut scratch = self.scratch();
+        scratch.stack = args;
+        scratch.eval_line(op)?;
+        scratch.stack.pop().ok_or(CalcError::EmptyStack)
+    }
+
+    /// `bind <name> <value>` replaces `?name` with the value and works out
+    /// the calculations that were waiting on it, both on the stack and on
+    /// the history tape.
+    pub fn bind(&mut self) -> OpResult {
+        let name = self.args[0].trim_start_matches('?').to_string();
+        if !formula::valid_name(&name) {
+            return Err(CalcError::InvalidArgument(format!(
+                "'{}' is not a placeholder name",
+                self.args[0]
+            )));
+        }
+        let bound = Value::parse(&self.args[1]).map_err(CalcError::InvalidArgument)?;
+        let stack = self
+            .stack
+            .iter()
+            .map(|v| self.evaluate(&formula::substitute(v, &name, &bound)))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Tape entries that cannot be worked out (say, a square root that
+        // turns out negative) keep waiting.
+        let tape: Vec<(usize, Value)> = self
+            .calc_history
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.result.is_formula())
+            .filter_map(|(i, entry)| {
+                let result = self.evaluate(&formula::substitute(&entry.result, &name, &bound));
+                Some((i, result.ok().filter(|r| *r != entry.result)?))
+            })
+            .collect();
+        if stack == self.stack && tape.is_empty() {
+            return Err(CalcError::InvalidArgument(format!("Nothing uses ?{}", name)));
+        }
+        let mut changed = Vec::new();
+        for (i, result) in &tape {
+            let entry = &mut self.calc_history[*i];
+            let was = entry.clone();
+            let lhs = entry.text.rsplit_once(" = ").map_or(entry.text.as_str(), |(l, _)| l);
+            let shown = if result.is_formula() { "?".to_string() } else { format_value(result) };
+            entry.text = format!("{} = {}", lhs, shown);
+            entry.result = result.clone();
+            changed.push((was, entry.clone()));
+        }
+        if !changed.is_empty() {
+            self.journal.note(Aside::Tape(changed));
+        }
+        self.stack = stack;
+        Ok(OpOutcome::info(format!(
+            "Bound ?{} = {}; recomputed {} tape {}",
+            name,
+            format_value(&bound),
+            tape.len(),
+            if tape.len() == 1 { "entry" } else { "entries" }
+        )))
+    }
+
+    /// Runs a macro body as one step: it is undone as a whole, and if any
+    /// part fails the stack is restored.
+    fn run_macro(&mut self, name: &str, body: &str) -> OpResult {
+        self.run_nested(&format!("Macro {}", name), |app| app.eval_line(body))
+    }
+
+    /// Runs the script file named in the argument as one step, like a macro.
+    pub fn run_script(&mut self) -> OpResult {
+        let path = self.args[0].clone();
+        if self.replay_outcomes.is_some() {
+            return Err(CalcError::InvalidArgument(format!(
+                "A replay does not run {}, which may have changed since",
+                path
+            )));
+        }
+        self.run_nested(&format!("Script {}", path), |app| {
+            script::run_file(app, Path::new(&path))
+        })
+    }
+
+    /// Runs `f`, which evaluates user-supplied input that may in turn call
+    /// macros or scripts, guarding against unbounded recursion.
+    fn run_nested(&mut self, what: &str, f: impl FnOnce(&mut App) -> OpResult) -> OpResult {
+        if self.nesting >= MAX_NESTING {
+            return Err(CalcError::InvalidArgument(format!(
+                "{} nests more than {} deep",
+                what, MAX_NESTING
+            )));
+        }
+        let scope = self.journal.open_scope();
+        self.nesting += 1;
+        let result = f(self);
+        self.nesting -= 1;
+        match result {
+            Ok(outcome) => {
+                self.journal.close_scope(scope, &self.stack);
+                Ok(outcome)
+            }
+            Err(e) => {
+                let asides = self.journal.abandon_scope(scope, &mut self.stack);
+                self.restore(asides);
+                Err(e)
+            }
+        }
+    }
+
+    /// `def <name> "<body>"` defines a macro until the calculator exits.
+    pub fn define_macro(&mut self) -> OpResult {
+        let (name, body) = (self.args[0].clone(), unquote(&self.args[1]).to_string());
+        if self.registry.lookup(&name).is_some() {
+            return Err(CalcError::InvalidArgument(format!("'{}' is already a command", name)));
+        }
+        self.macros.define(&name, &body).map_err(CalcError::InvalidArgument)?;
+        Ok(OpOutcome::info(format!("Defined {} as {}", name, body)))
+    }
+
+    /// Runs the macro tests, or only those of the macro named in the
+    /// argument, and shows a pass/fail report.
+    pub fn run_macro_tests(&mut self) -> OpResult {
+        let only = self.args.first().cloned();
+        if let Some(name) = &only {
+            if self.macros.get(name).is_none() {
+                return Err(CalcError::InvalidArgument(format!("No macro named '{}'", name)));
+            }
+        }
+        let tests: Vec<&MacroTest> = match &only {
+            Some(name) => self.macros.tests_for(name).collect(),
+            None => self.macros.tests.iter().collect(),
+        };
+        if tests.is_empty() {
+            return Err(CalcError::InvalidArgument("No macro tests to run".to_string()));
+        }
+        let mut lines = Vec::new();
+        let mut failed = 0;
+        for test in &tests {
+            let mut scratch = self.scratch();
+            let failure = match scratch.eval_line(&test.input) {
+                Err(e) => Some(e.to_string()),
+                Ok(_) if self.tolerance.stacks_match(&scratch.stack, &test.expect) => None,
+                Ok(_) => {
+                    let got: Vec<String> = scratch.stack.iter().map(format_value).collect();
+                    Some(format!("got {}", got.join(" ")))
+                }
+            };
+            let status = if failure.is_some() { "FAIL" } else { "pass" };
+            lines.push(format!("{} line {}: {}", status, test.line, test.input));
+            if let Some(failure) = failure {
+                failed += 1;
+                let expected: Vec<String> = test.expect.iter().map(format_value).collect();
+                lines.push(format!("     expected {}, {}", expected.join(" "), failure));
+            }
+        }
+        let summary = format!("{} passed, {} failed", tests.len() - failed, failed);
+        lines.push(String::new());
+        lines.push(summary.clone());
+        let title = match &only {
+            Some(name) => format!("test {}", name),
+            None => "runtests".to_string(),
+        };
+        self.popup = Some(Popup::new(&title, lines));
+        Ok(OpOutcome::info(format!("Macro tests: {}", summary)))
+    }
+
+    /// Runs the built-in checks of known results, each on a fresh calculator
+    /// with default settings, and reports any mismatches.
+    pub fn selftest(&mut self) -> OpResult {
+        let start = Instant::now();
+        let tolerance = Tolerance::default();
+        let mut lines = Vec::new();
+        let mut failed = 0;
+        for check in selftest::CHECKS {
+            let mut scratch = App::new();
+            let result = scratch.eval_line(check.input);
+            let passed = match (check.expect, &result) {
+                (Expect::Error, result) => result.is_err(),
+                (Expect::Number(n), Ok(_)) => {
+                    tolerance.stacks_match(&scratch.stack, &[Value::Number(n)])
+                }
+                (Expect::Number(_), Err(_)) => false,
+            };
+            if !passed {
+                let got = match result {
+                    Err(e) => e.to_string(),
+                    Ok(_) => {
+                        let got: Vec<String> = scratch.stack.iter().map(format_value).collect();
+                        got.join(" ")
+                    }
+                };
+                let expected = match check.expect {
+                    Expect::Number(n) => format_number(n),
+                    Expect::Error => "an error".to_string(),
+                };
+                failed += 1;
+                lines.push(format!("FAIL {}", check.input));
+                lines.push(format!("     expected {}, got {}", expected, got));
+            }
+        }
+        let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+        let total = selftest::CHECKS.len();
+        if failed == 0 {
+            return Ok(OpOutcome::info(format!(
+                "Self-test: all {} checks passed in {:.1} ms",
+                total, elapsed
+            )));
+        }
+        let summary = format!("{} of {} checks failed", failed, total);
+        lines.push(String::new());
+        lines.push(summary.clone());
+        self.popup = Some(Popup::new("selftest", lines));
+        Ok(OpOutcome::info(format!("Self-test: {}", summary)))
+    }
+
+    /// `amount convertccy <from> <to>` converts between currencies with the
+    /// loaded exchange rates. A money amount stays one.
+    pub fn convert_currency(&mut self) -> OpResult {
+        let [amount] = self.top_numbers("convertccy")?;
+        let (from, to) = (self.args[0].to_ascii_uppercase(), self.args[1].to_ascii_uppercase());
+        let converted = self
+            .rates
+            .convert(amount, &from, &to)
+            .map_err(CalcError::InvalidArgument)?;
+        let result = match self.stack.last() {
+            Some(Value::Decimal(_)) => Decimal::from_f64(converted)
+                .map(Value::from)
+                .ok_or_else(|| CalcError::domain("The converted amount is too large"))?,
+            _ => Value::from(converted),
+        };
+        let calc = format!(
+            "{} {} in {} = {}",
+            format_number(amount),
+            from,
+            to,
+            format_value(&result)
+        );
+        self.replace_top(1, result.clone());
+        Ok(OpOutcome::calc(calc, result))
+    }
+
+    /// Shows the loaded exchange rates and when they were taken.
+    pub fn show_rates(&mut self) -> OpResult {
+        if self.rates.is_empty() {
+            return Err(CalcError::InvalidArgument(
+                "No exchange rates loaded; see rates.toml in the config directory".to_string(),
+            ));
+        }
+        let updated = self.rates.updated.as_deref().unwrap_or("unknown");
+        let mut lines = vec![
+            format!("1 {} buys, as of {}:", self.rates.base, updated),
+            String::new(),
+        ];
+        lines.extend(
+            self.rates
+                .rates
+                .iter()
+                .map(|(code, rate)| format!("{}  {}", code, format_number(*rate))),
+        );
+        self.popup = Some(Popup::new("rates", lines));
+        Ok(OpOutcome::info(format!(
+            "{} exchange rates against {}, as of {}",
+            self.rates.rates.len(),
+            self.rates.base,
+            updated
+        )))
+    }
+
+    /// Lists the slowest of the recent operations in a popup.
+    pub fn slow_ops(&mut self) -> OpResult {
+        if self.timings.is_empty() {
+            return Err(CalcError::InvalidArgument("No operations timed yet".to_string()));
+        }
+        let lines = self
+            .timings
+            .slowest(timing::SLOWEST_SHOWN)
+            .into_iter()
+            .map(|t| format!("{:>14}  {}", timing::format_elapsed(t.elapsed), t.name))
+            .collect();
+        let title = format!("slowops: slowest of the last {}", self.timings.len());
+        self.popup = Some(Popup::new(&title, lines));
+        Ok(OpOutcome::info(format!(
+            "Slowest of the last {} operations shown",
+            self.timings.len()
+        )))
+    }
+
+    /// Saves a bug report bundle (see `report::bundle`) and names the file.
+    pub fn save_report(&mut self) -> OpResult {
+        let dir = self.report_dir.clone().ok_or_else(|| {
+            CalcError::InvalidArgument("No data directory to save reports in".to_string())
+        })?;
+        let path = report::save(&dir, &report::bundle(self)).map_err(|e| {
+            CalcError::InvalidArgument(format!("Could not save report: {}", e))
+        })?;
+        Ok(OpOutcome::info(format!("Saved bug report to {}", path.display())))
+    }
+
+    /// `export <format> <file>` writes the stack and tape as `csv` or
+    /// `fixed`-width text, laid out by the export profile, or as `json`.
+    pub fn export_file(&mut self) -> OpResult {
+        let format = export::Format::from_name(&self.args[0]).ok_or_else(|| {
+            CalcError::InvalidArgument(format!(
+                "Unknown export format '{}'; use csv, fixed or json",
+                self.args[0]
+            ))
+        })?;
+        let path = PathBuf::from(&self.args[1]);
+        export::save(self, format, &path).map_err(|e| {
+            CalcError::InvalidArgument(format!("Could not write {}: {}", path.display(), e))
+        })?;
+        let calcs = self.calc_history.len();
+        Ok(OpOutcome::info(format!(
+            "Exported {} stack {} and {} {} to {}",
+            self.stack.len(),
+            if self.stack.len() == 1 { "value" } else { "values" },
+            calcs,
+            if calcs == 1 { "calculation" } else { "calculations" },
+            path.display()
+        )))
+    }
+
+    /// `import <file>` pushes the numbers in a one-column file, and
+    /// `importcol <file> <n>` those in column n of any file.
+    pub fn import_file(&mut self) -> OpResult {
+        let path = PathBuf::from(&self.args[0]);
+        let text = fs::read_to_string(&path).map_err(|e| {
+            CalcError::InvalidArgument(format!("Could not read {}: {}", path.display(), e))
+        })?;
+        let mut import = TableImport::new(&text);
+        let columns = import.column_count();
+        match self.args.get(1) {
+            Some(arg) => {
+                import.column = arg
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| (1..=columns).contains(n))
+                    .ok_or_else(|| {
+                        CalcError::InvalidArgument(format!(
+                            "No column {} in {}, which has {}",
+                            arg,
+                            path.display(),
+                            columns
+                        ))
+                    })?
+                    - 1;
+            }
+            None if columns > 1 => {
+                return Err(CalcError::InvalidArgument(format!(
+                    "{} has {} columns; pick one with importcol <file> <n>",
+                    path.display(),
+                    columns
+                )));
+            }
+            None => {}
+        }
+        let (values, skipped) = import.values();
+        if values.is_empty() {
+            return Err(CalcError::InvalidArgument(format!(
+                "No numbers in {}",
+                path.display()
+            )));
+        }
+        self.check_room(values.len())?;
+        self.stack.extend(numbers(&values));
+        Ok(OpOutcome::info(if skipped > 0 {
+            format!("Imported {} values ({} skipped)", values.len(), skipped)
         } else {
-            let result = x.powf(1.0 / y);
-            self.stack.push(result);
-            let calc = format!("{} root {} = {}", format_number(y), format_number(x), format_number(result));
-            self.message = calc.clone();
-            self.calc_history.push(calc);
-            if self.calc_history.len() > 10 {
-                self.calc_history.remove(0);
+            format!("Imported {} values", values.len())
+        }))
+    }
+
+    /// `theme <name>` switches to one of the built-in color themes.
+    pub fn set_theme(&mut self) -> OpResult {
+        self.theme = Theme::named(&self.args[0]).ok_or_else(|| {
+            CalcError::InvalidArgument(format!(
+                "Unknown theme '{}'; use {}",
+                self.args[0],
+                theme::NAMES.join(", ")
+            ))
+        })?;
+        Ok(OpOutcome::info(format!("Theme {}", self.theme.name)))
+    }
+
+    /// An empty calculator with the same commands, macros and settings, for
+    /// evaluating input without touching this one.
+    fn scratch(&self) -> App {
+        let mut scratch = App::new();
+        scratch.registry = self.registry.clone();
+        scratch.macros = self.macros.clone();
+        scratch.angle_mode = self.angle_mode;
+        scratch.allow_nan = self.allow_nan;
+        scratch.tolerance = self.tolerance;
+        scratch.solver = self.solver;
+        scratch.max_depth = self.max_depth;
+        scratch.entry = self.entry;
+        scratch.fixed_date = self.fixed_date;
+        scratch.lift_disabled = self.lift_disabled;
+        scratch.nesting = self.nesting + 1;
+        scratch.dry_run = self.dry_run;
+        scratch
+    }
+
+    /// `seed <n>` restarts the random numbers at a fixed point, so that
+    /// simulations can be repeated exactly.
+    pub fn seed_random(&mut self) -> OpResult {
+        let seed = self.args[0].parse::<u64>().map_err(|_| {
+            CalcError::InvalidArgument(format!("Seed must be a whole number, not '{}'", self.args[0]))
+        })?;
+        self.rng = Rng::new(seed);
+        Ok(OpOutcome::info(format!("Random numbers seeded with {}", seed)))
+    }
+
+    pub fn push_random(&mut self, normal: bool) -> OpResult {
+        let value = if normal { self.rng.normal() } else { self.rng.uniform() };
+        self.stack.push(value.into());
+        Ok(OpOutcome::info(format!("Pushed {}", format_number(value))))
+    }
+
+    /// `simulate <n> <macro>` runs a macro (or quoted input) n times, each
+    /// time on the current stack, and pushes the number each run leaves on
+    /// top.
+    pub fn simulate(&mut self) -> OpResult {
+        let runs = self.args[0]
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=MAX_SIMULATION_RUNS).contains(n))
+            .ok_or_else(|| {
+                CalcError::InvalidArgument(format!(
+                    "Runs must be a whole number from 1 to {}",
+                    MAX_SIMULATION_RUNS
+                ))
+            })?;
+        let body = unquote(&self.args[1]).to_string();
+        if self.nesting >= MAX_NESTING {
+            return Err(CalcError::InvalidArgument(format!(
+                "Simulation {} nests more than {} deep",
+                body, MAX_NESTING
+            )));
+        }
+        // Runs happen in a scratch calculator so they stay out of the
+        // history, drawing from this calculator's random numbers.
+        let mut scratch = self.scratch();
+        scratch.rng = std::mem::take(&mut self.rng);
+        let mut results = Vec::with_capacity(runs);
+        let mut failure = None;
+        for _ in 0..runs {
+            scratch.stack = self.stack.clone();
+            if let Err(e) = scratch.eval_line(&body) {
+                failure = Some(e);
+                break;
+            }
+            match scratch.stack.last().and_then(Value::as_number) {
+                Some(top) => results.push(top),
+                None => {
+                    failure = Some(CalcError::wrong_type("simulate", "each run to leave a number"));
+                    break;
+                }
+            }
+        }
+        self.rng = scratch.rng;
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        let mut summary = format!(
+            "Simulated {} {} times: mean {}",
+            body,
+            runs,
+            format_number(stats::mean(&results))
+        );
+        if runs > 1 {
+            summary.push_str(&format!(", sd {}", format_number(stats::std_dev(&results))));
+        }
+        self.stack.extend(numbers(&results));
+        Ok(OpOutcome::info(summary))
+    }
+
+    /// `solve <macro>` replaces the guess in x with a root of the macro,
+    /// which is run as f(x) with x alone on the stack.
+    pub fn solve(&mut self) -> OpResult {
+        let [guess] = self.top_numbers("solve")?;
+        let body = unquote(&self.args[0]).to_string();
+        let mut f = self.function_of_x("solve", &body)?;
+        // Errors at the guess are the macro's own; elsewhere they only mean
+        // the methods have to step around that x.
+        f(guess)?;
+        let root = solver::solve(|x| f(x).ok(), guess, &self.solver).map_err(|e| {
+            CalcError::InvalidArgument(format!("Solving {}: {}", body, e))
+        })?;
+        let calc = format!(
+            "{} solve {} = {}",
+            format_number(guess),
+            body,
+            format_number(root.x)
+        );
+        self.replace_top(1, root.x);
+        Ok(OpOutcome::calc(calc, root.x))
+    }
+
+    /// `a b integ <macro>` replaces a and b with the integral of the macro,
+    /// as f(x), from a to b.
+    pub fn integrate(&mut self) -> OpResult {
+        let [a, b] = self.top_numbers("integ")?;
+        let body = unquote(&self.args[0]).to_string();
+        let result = calculus::integrate(self.function_of_x("integ", &body)?, a, b)?;
+        let calc = format!(
+            "{} {} integ {} = {}",
+            format_number(a),
+            format_number(b),
+            body,
+            format_number(result)
+        );
+        self.replace_top(2, result);
+        Ok(OpOutcome::calc(calc, result))
+    }
+
+    /// `x deriv <macro>` replaces x with the slope of the macro, as f(x),
+    /// there.
+    pub fn differentiate(&mut self) -> OpResult {
+        let [x] = self.top_numbers("deriv")?;
+        let body = unquote(&self.args[0]).to_string();
+        let result = calculus::derivative(self.function_of_x("deriv", &body)?, x)?;
+        let calc = format!("{} deriv {} = {}", format_number(x), body, format_number(result));
+        self.replace_top(1, result);
+        Ok(OpOutcome::calc(calc, result))
+    }
+
+    /// `body`, a macro name or quoted input, as a function run in a scratch
+    /// calculator with x alone on the stack and giving the number it leaves
+    /// on top.
+    fn function_of_x<'a>(
+        &self,
+        op: &'static str,
+        body: &'a str,
+    ) -> Result<impl FnMut(f64) -> Result<f64, CalcError> + 'a, CalcError> {
+        if self.nesting >= MAX_NESTING {
+            return Err(CalcError::InvalidArgument(format!(
+                "{} {} nests more than {} deep",
+                op, body, MAX_NESTING
+            )));
+        }
+        let mut scratch = self.scratch();
+        Ok(move |x: f64| {
+            // Each evaluation gets the steps of a line of its own; the
+            // caller limits how many evaluations there are.
+            scratch.steps = 0;
+            scratch.stack = vec![x.into()];
+            scratch.eval_line(body)?;
+            scratch
+                .stack
+                .last()
+                .and_then(Value::as_number)
+                .ok_or_else(|| CalcError::wrong_type(op, "the macro to leave a number"))
+        })
+    }
+
+    /// Shows the stack in the plot pane, or hides the pane if it already
+    /// does.
+    pub fn toggle_plot(&mut self) -> OpResult {
+        if self.plot == Some(Plot::Stack) {
+            self.plot = None;
+            return Ok(OpOutcome::info("Plot hidden"));
+        }
+        self.plot = Some(Plot::Stack);
+        Ok(OpOutcome::info("Plotting the stack (plot again to hide)"))
+    }
+
+    /// `chart <style>`: charts the numbers on the stack against their
+    /// position.
+    pub fn chart_stack(&mut self) -> OpResult {
+        self.open_chart(false)
+    }
+
+    /// `chartxy <style>`: charts the stack read as x,y pairs from the bottom.
+    pub fn chart_pairs(&mut self) -> OpResult {
+        self.open_chart(true)
+    }
+
+    fn open_chart(&mut self, pairs: bool) -> OpResult {
+        let style = ChartStyle::from_name(&self.args[0]).ok_or_else(|| {
+            CalcError::InvalidArgument(format!(
+                "Unknown chart style '{}'; use line or scatter",
+                self.args[0]
+            ))
+        })?;
+        let points = plot::chart_points(&self.stack, pairs)?;
+        let message = format!("Charted {} points (any key to close)", points.len());
+        self.chart = Some(DataChart { style, pairs, points });
+        Ok(OpOutcome::info(message))
+    }
+
+    /// `plotf "<expr>" <from> <to> <samples>`: plots `expr` evaluated with x
+    /// on the stack, where `x` in `expr` also stands for it.
+    pub fn plot_function(&mut self) -> OpResult {
+        let expr = unquote(&self.args[0]).to_string();
+        let number = |arg: &str| {
+            arg.parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| CalcError::InvalidArgument(format!("'{}' is not a number", arg)))
+        };
+        let (from, to) = (number(&self.args[1])?, number(&self.args[2])?);
+        let samples = self.args[3]
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (2..=plot::MAX_SAMPLES).contains(n))
+            .ok_or_else(|| {
+                CalcError::InvalidArgument(format!(
+                    "Samples must be a whole number from 2 to {}",
+                    plot::MAX_SAMPLES
+                ))
+            })?;
+        let tokens = tokenize(&expr)?;
+        let mut points = Vec::new();
+        let mut first_error = None;
+        for x in plot::sample_points(from, to, samples) {
+            let x_text = x.to_string();
+            let line: Vec<&str> = tokens
+                .iter()
+                .map(|&t| if t == "x" { x_text.as_str() } else { t })
+                .collect();
+            let mut scratch = self.scratch();
+            scratch.stack.push(x.into());
+            let result = scratch.eval_line(&line.join(" "));
+            match (result, scratch.stack.last().and_then(Value::as_number)) {
+                (Ok(_), Some(y)) if y.is_finite() => points.push((x, y)),
+                (Err(e), _) => {
+                    first_error.get_or_insert(e);
+                }
+                _ => {}
             }
         }
+        if points.is_empty() {
+            return Err(first_error.unwrap_or_else(|| {
+                CalcError::InvalidArgument(format!("{} gave no numbers to plot", expr))
+            }));
+        }
+        let message = format!(
+            "Plotted {} at {} of {} points from {} to {}",
+            expr,
+            points.len(),
+            samples,
+            format_number(from),
+            format_number(to)
+        );
+        self.plot = Some(Plot::Function { expr, points });
+        Ok(OpOutcome::info(message))
     }
-}
\ No newline at end of file
+
+    /// Command and macro names starting with `word`, in order.
+    pub fn completions(&self, word: &str) -> Vec<String> {
+        let mut matches = self.registry.complete(word);
+        matches.extend(
+            self.macros
+                .macros
+                .iter()
+                .map(|m| m.name.as_str())
+                .filter(|n| n.starts_with(word)),
+        );
+        matches.sort_unstable();
+        matches.dedup();
+        matches.into_iter().map(str::to_string).collect()
+    }
+
+    /// Completes the last word of the input against registered command
+    /// names. A unique match replaces the word; otherwise it is extended to
+    /// the longest common prefix and the candidates are listed in the message.
+    pub fn complete_input(&mut self) {
+        let start = self.input.rfind(' ').map_or(0, |i| i + 1);
+        let word = &self.input[start..];
+        if word.is_empty() {
+            return;
+        }
+        let matches = self.completions(word);
+        let completion = match matches.as_slice() {
+            [] => {
+                self.message = format!("No command starts with '{}'", word);
+                return;
+            }
+            [only] => only.to_string(),
+            [first, rest @ ..] => {
+                let common = rest.iter().fold(first.len(), |common, m| {
+                    common.min(first.bytes().zip(m.bytes()).take_while(|(a, b)| a == b).count())
+                });
+                self.message = matches.join(" ");
+                first[..common].to_string()
+            }
+        };
+        self.input.truncate(start);
+        self.input.push_str(&completion);
+        self.cursor = None;
+    }
+
+    /// The cursor's byte offset in the input.
+    pub fn cursor_position(&self) -> usize {
+        self.cursor
+            .filter(|&at| at < self.input.len() && self.input.is_char_boundary(at))
+            .unwrap_or(self.input.len())
+    }
+
+    pub fn cursor_at_end(&self) -> bool {
+        self.cursor_position() == self.input.len()
+    }
+
+    /// Types `c` at the cursor.
+    pub fn insert_input(&mut self, c: char) {
+        let at = self.cursor_position();
+        self.input.insert(at, c);
+        if self.cursor.is_some() {
+            self.cursor = Some(at + c.len_utf8());
+        }
+    }
+
+    /// Deletes the character before the cursor.
+    pub fn delete_input_back(&mut self) {
+        let at = self.cursor_position();
+        let Some(c) = self.input[..at].chars().next_back() else {
+            return;
+        };
+        self.input.remove(at - c.len_utf8());
+        if self.cursor.is_some() {
+            self.cursor = Some(at - c.len_utf8());
+        }
+    }
+
+    /// Moves the cursor one character left, or right with `right`.
+    pub fn move_cursor(&mut self, right: bool) {
+        let at = self.cursor_position();
+        let at = if right {
+            at + self.input[at..].chars().next().map_or(0, char::len_utf8)
+        } else {
+            at - self.input[..at].chars().next_back().map_or(0, char::len_utf8)
+        };
+        self.cursor = (at < self.input.len()).then_some(at);
+    }
+
+    pub fn cursor_home(&mut self) {
+        self.cursor = (!self.input.is_empty()).then_some(0);
+    }
+
+    pub fn cursor_end(&mut self) {
+        self.cursor = None;
+    }
+
+    pub fn clear_input(&mut self) {
+        self.input.clear();
+        self.cursor = None;
+        self.recall = None;
+    }
+
+    /// Replaces the input with the previous entered line, or the next with
+    /// `newer`; going past the newest brings back what was being typed.
+    pub fn recall_input(&mut self, newer: bool) {
+        let (index, draft) = match self.recall.take() {
+            Some(recall) => recall,
+            None if newer => return,
+            None => (self.input_history.len(), self.input.clone()),
+        };
+        let index = if newer {
+            index + 1
+        } else {
+            index.saturating_sub(1)
+        };
+        match self.input_history.get(index) {
+            Some(line) => {
+                self.input = line.clone();
+                self.recall = Some((index, draft));
+            }
+            None => self.input = draft,
+        }
+        self.cursor = None;
+    }
+
+    /// Whether `+` or `-` typed now belongs to the number being entered
+    /// rather than being an operation: `-` on an empty line starts a negative
+    /// number, except in classic entry, and a sign right after the `e` of
+    /// `1.5e` is the exponent's.
+    pub fn sign_continues_number(&self, c: char) -> bool {
+        if self.input.is_empty() {
+            return c == '-' && self.entry == EntryMode::Standard;
+        }
+        let start = self.input.rfind(' ').map_or(0, |i| i + 1);
+        matches!(split_number(&self.input[start..]), Some((_, Some(""))))
+    }
+
+    /// Changes the sign of the number being typed, or of its exponent once
+    /// one has been started. Other input is left alone.
+    pub fn change_input_sign(&mut self) {
+        let start = self.input.rfind(' ').map_or(0, |i| i + 1);
+        let word = &self.input[start..];
+        let at = match split_number(word) {
+            _ if word.is_empty() => start,
+            Some((_, Some(exponent))) => start + word.len() - exponent.len(),
+            Some((_, None)) => start,
+            None => return,
+        };
+        match self.input[at..].chars().next() {
+            Some('-') => {
+                self.input.remove(at);
+            }
+            Some('+') => self.input.replace_range(at..at + 1, "-"),
+            _ => self.input.insert(at, '-'),
+        }
+    }
+
+    /// Shows a detailed description of the command named in the argument,
+    /// running its example on a scratch calculator to show the result.
+    pub fn explain(&mut self) -> OpResult {
+        let name = self.args[0].clone();
+        let cmd = self
+            .registry
+            .lookup(&name)
+            .cloned()
+            .ok_or(CalcError::UnknownCommand(name))?;
+        let mut lines = vec![
+            format!("{}: {}", cmd.name, cmd.description),
+            String::new(),
+            format!("Usage:     {}", cmd.usage()),
+        ];
+        if !cmd.aliases.is_empty() {
+            lines.push(format!("Aliases:   {}", cmd.aliases.join(", ")));
+        }
+        lines.push(format!("Category:  {}", cmd.category));
+        lines.push(format!("Stack:     consumes {}, produces {}", cmd.arity, cmd.produces));
+        lines.push(format!("Domain:    {}", cmd.domain.unwrap_or("any value")));
+        for mode in cmd.modes {
+            let current = match mode {
+                Mode::Angle => self.angle_mode.name(),
+            };
+            lines.push(format!(
+                "Mode:      depends on the {} (currently {})",
+                mode.name(),
+                current
+            ));
+        }
+        if !cmd.undoable {
+            lines.push("Undo:      not recorded in undo history".to_string());
+        }
+        if let Some(example) = cmd.example {
+            lines.push(format!("Example:   {}  →  {}", example, self.example_result(example)));
+        }
+        self.popup = Some(Popup::new(&format!("explain {}", cmd.name), lines));
+        Ok(OpOutcome::info(format!("Explaining {} (any key to close)", cmd.name)))
+    }
+
+    /// Runs `example` on an empty stack and shows what it leaves behind, or
+    /// the error it stops with.
+    pub fn example_result(&self, example: &str) -> String {
+        let mut scratch = self.scratch();
+        match scratch.eval_line(example) {
+            Ok(_) => scratch
+                .stack
+                .iter()
+                .map(format_value)
+                .collect::<Vec<_>>()
+                .join(" "),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    /// Handles pasted text. Multi-line or delimited text opens the table
+    /// import dialog; anything else is appended to the input line.
+    pub fn paste(&mut self, text: &str) {
+        if TableImport::is_tabular(text) {
+            let import = TableImport::new(text);
+            self.message = format!(
+                "Import: {} rows, {} columns (Enter to push, Esc to cancel)",
+                import.rows().len(),
+                import.column_count()
+            );
+            self.table_import = Some(import);
+        } else {
+            self.input.push_str(text.trim());
+        }
+    }
+
+    /// Pushes every whitespace-separated number in `text`, skipping other
+    /// tokens.
+    pub fn push_text_numbers(&mut self, text: &str) -> OpResult {
+        let mut values = Vec::new();
+        let mut skipped = 0;
+        for token in text.split_whitespace() {
+            match token.parse::<f64>() {
+                Ok(v) => values.push(v),
+                Err(_) => skipped += 1,
+            }
+        }
+        if values.is_empty() {
+            return Err(CalcError::InvalidArgument("No numbers to paste".to_string()));
+        }
+        self.check_room(values.len())?;
+        self.stack.extend(numbers(&values));
+        Ok(OpOutcome::info(if skipped > 0 {
+            format!("Pasted {} values ({} skipped)", values.len(), skipped)
+        } else {
+            format!("Pasted {} values", values.len())
+        }))
+    }
+
+    pub fn paste_clipboard(&mut self) -> OpResult {
+        let text = clipboard::get_text().map_err(CalcError::Clipboard)?;
+        self.push_text_numbers(&text)
+    }
+
+    /// Copies the top of the stack, or the whole stack one value per line.
+    pub fn copy_to_clipboard(&mut self, all: bool) -> OpResult {
+        let top = self.stack.last().ok_or(CalcError::EmptyStack)?;
+        let text = if all {
+            self.stack.iter().map(|v| v.to_string()).collect::<Vec<_>>().join("\n")
+        } else {
+            top.to_string()
+        };
+        clipboard::set_text(&text).map_err(CalcError::Clipboard)?;
+        Ok(OpOutcome::info(if all {
+            format!("Copied {} values", self.stack.len())
+        } else {
+            format!("Copied {}", text)
+        }))
+    }
+
+    pub fn confirm_import(&mut self) {
+        let Some(import) = self.table_import.take() else {
+            return;
+        };
+        let (values, skipped) = import.values();
+        if values.is_empty() {
+            self.message = format!("No numbers in column {}", import.column + 1);
+            return;
+        }
+        if let Err(e) = self.check_room(values.len()) {
+            self.message = e.to_string();
+            return;
+        }
+        self.log_pushed(&numbers(&values));
+        let pending = self.journal.begin(&self.stack, Some(0));
+        self.stack.extend(numbers(&values));
+        self.journal.commit(pending, &self.stack);
+        self.message = if skipped > 0 {
+            format!("Imported {} values ({} skipped)", values.len(), skipped)
+        } else {
+            format!("Imported {} values", values.len())
+        };
+    }
+
+    pub fn cancel_import(&mut self) {
+        if self.table_import.take().is_some() {
+            self.message = "Import cancelled".to_string();
+        }
+    }
+
+    fn require(&self, needed: usize, op: &str) -> Result<(), CalcError> {
+        if self.stack.len() < needed {
+            Err(CalcError::underflow(op, needed))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The top `N` values, deepest first, all of which must be numbers.
+    fn top_numbers<const N: usize>(&self, op: &str) -> Result<[f64; N], CalcError> {
+        self.require(N, op)?;
+        let mut values = [0.0; N];
+        for (slot, value) in values.iter_mut().zip(&self.stack[self.stack.len() - N..]) {
+            *slot = value
+                .as_number()
+                .ok_or_else(|| CalcError::wrong_type(op, "numbers"))?;
+        }
+        Ok(values)
+    }
+
+    /// Replaces the top `n` values with `result`.
+    fn replace_top(&mut self, n: usize, result: impl Into<Value>) {
+        if n > 0 {
+            self.last_x = self.stack.last().cloned();
+        }
+        self.stack.truncate(self.stack.len() - n);
+        self.stack.push(result.into());
+    }
+
+    pub fn binary_op<F>(&mut self, op: F, name: &str) -> OpResult
+    where
+        F: Fn(f64, f64) -> f64,
+    {
+        self.checked_binary_op(|a, b| Ok(op(a, b)), name)
+    }
+
+    /// Like `binary_op`, but `op` may reject its operands with a domain
+    /// error, in which case the stack is left untouched.
+    pub fn checked_binary_op<F>(&mut self, op: F, name: &str) -> OpResult
+    where
+        F: Fn(f64, f64) -> Result<f64, &'static str>,
+    {
+        let [a, b] = self.top_numbers(name)?;
+        let result = op(a, b).map_err(CalcError::domain)?;
+        self.check_domain(name, &[&a.into(), &b.into()], &result.into())?;
+        self.replace_top(2, result);
+        let calc = format!(
+            "{} {} {} = {}",
+            format_number(a),
+            name,
+            format_number(b),
+            format_number(result)
+        );
+        Ok(OpOutcome::calc(calc, result))
+    }
+
+    pub fn unary_op<F>(&mut self, op: F, name: &str) -> OpResult
+    where
+        F: Fn(f64) -> f64,
+    {
+        self.checked_unary_op(|a| Ok(op(a)), name)
+    }
+
+    /// Like `unary_op`, but `op` may reject its operand with a domain error,
+    /// in which case the stack is left untouched.
+    pub fn checked_unary_op<F>(&mut self, op: F, name: &str) -> OpResult
+    where
+        F: Fn(f64) -> Result<f64, &'static str>,
+    {
+        let [a] = self.top_numbers(name)?;
+        let result = op(a).map_err(CalcError::domain)?;
+        self.check_domain(name, &[&a.into()], &result.into())?;
+        self.replace_top(1, result);
+        let calc = format!("{}({}) = {}", name, format_number(a), format_number(result));
+        Ok(OpOutcome::calc(calc, result))
+    }
+
+    /// Rejects a NaN or infinite result from finite operands, unless
+    /// `allow_nan` asks for IEEE semantics.
+    fn check_domain(
+        &self,
+        name: &str,
+        operands: &[&Value],
+        result: &Value,
+    ) -> Result<(), CalcError> {
+        if self.allow_nan || result.is_finite() || !operands.iter().all(|v| v.is_finite()) {
+            return Ok(());
+        }
+        let call = match operands {
+            [a, b] => format!("{} {} {}", format_value(a), name, format_value(b)),
+            _ => {
+                let args: Vec<String> = operands.iter().map(|v| format_value(v)).collect();
+                format!("{}({})", name, args.join(", "))
+            }
+        };
+        let domain = self.registry.lookup(name).and_then(|c| c.domain);
+        Err(CalcError::DomainError(match domain {
+            _ if result.as_number().is_some_and(f64::is_infinite) => {
+                format!("{} is infinite", call)
+            }
+            Some(domain) => format!("{} is undefined ({})", call, domain),
+            None => format!("{} is undefined", call),
+        }))
+    }
+
+    /// Applies `op` to the top two values, which may be numbers or matrices.
+    pub fn value_binary_op<F>(&mut self, op: F, name: &str) -> OpResult
+    where
+        F: Fn(&Value, &Value) -> Result<Value, CalcError>,
+    {
+        self.require(2, name)?;
+        let len = self.stack.len();
+        let result = op(&self.stack[len - 2], &self.stack[len - 1])?;
+        self.check_domain(name, &[&self.stack[len - 2], &self.stack[len - 1]], &result)?;
+        let calc = format!(
+            "{} {} {} = {}",
+            format_value(&self.stack[len - 2]),
+            name,
+            format_value(&self.stack[len - 1]),
+            format_value(&result)
+        );
+        self.replace_top(2, result.clone());
+        Ok(OpOutcome::calc(calc, result))
+    }
+
+    /// Applies `op` to the top value, which may be a number or a matrix.
+    pub fn value_unary_op<F>(&mut self, op: F, name: &str) -> OpResult
+    where
+        F: Fn(&Value) -> Result<Value, CalcError>,
+    {
+        let top = self.stack.last().ok_or_else(|| CalcError::underflow(name, 1))?;
+        let result = op(top)?;
+        self.check_domain(name, &[top], &result)?;
+        let calc = format!("{}({}) = {}", name, format_value(top), format_value(&result));
+        self.replace_top(1, result.clone());
+        Ok(OpOutcome::calc(calc, result))
+    }
+
+    /// `x1 … xn n vec`: collects n numbers into a vector.
+    pub fn collect_vector(&mut self) -> OpResult {
+        let entries = self.counted_numbers("vec")?;
+        let count = entries.len();
+        let result = Value::from(Matrix::vector(entries));
+        self.replace_top(count + 1, result.clone());
+        Ok(OpOutcome::calc(format!("vec({}) = {}", count, format_value(&result)), result))
+    }
+
+    /// For commands taking a count N on top of N numbers: those numbers,
+    /// deepest first. The stack is not changed.
+    fn counted_numbers(&self, op: &str) -> Result<Vec<f64>, CalcError> {
+        let [count] = self.top_numbers(op)?;
+        if count < 1.0 || count.fract() != 0.0 {
+            return Err(CalcError::InvalidArgument(format!(
+                "{} needs a positive integer count on top",
+                op
+            )));
+        }
+        // A count beyond the stack saturates rather than overflows.
+        let count = count as usize;
+        self.require(count.saturating_add(1), op)?;
+        let start = self.stack.len() - count - 1;
+        self.stack[start..self.stack.len() - 1]
+            .iter()
+            .map(Value::as_number)
+            .collect::<Option<Vec<f64>>>()
+            .ok_or_else(|| CalcError::wrong_type(op, "numbers"))
+    }
+
+    /// Adds a calculation to the history (and the history log, if any).
+    fn record(&mut self, calc: String, result: Value) -> OpOutcome {
+        let entry = HistoryEntry::new(calc, result);
+        let mut outcome = OpOutcome::calc(entry.text.clone(), entry.result.clone());
+        if let Some(path) = &self.history_file {
+            if let Err(e) = history::append(path, &entry) {
+                outcome = OpOutcome::info(format!("{} (history log disabled: {})", entry.text, e));
+                self.history_file = None;
+            }
+        }
+        self.calc_history.push(entry);
+        if self.calc_history.len() > self.history_limit {
+            let excess = self.calc_history.len() - self.history_limit;
+            self.calc_history.drain(..excess);
+        }
+        outcome
+    }
+
+    pub fn scroll_history(&mut self, delta: isize) {
+        self.history_scroll = self
+            .history_scroll
+            .saturating_add_signed(delta)
+            .min(self.calc_history.len().saturating_sub(1));
+    }
+
+    pub fn start_history_search(&mut self) -> OpResult {
+        self.history_search = Some(HistorySearch::default());
+        self.select_last_history_match();
+        Ok(OpOutcome::info("Search history: type to filter, ↑/↓ to select, Enter to push"))
+    }
+
+    /// History entries matching the current search, oldest first.
+    pub fn history_matches(&self) -> Vec<&HistoryEntry> {
+        let query = self
+            .history_search
+            .as_ref()
+            .map(|s| s.query.to_lowercase())
+            .unwrap_or_default();
+        self.calc_history
+            .iter()
+            .filter(|e| e.text.to_lowercase().contains(&query))
+            .collect()
+    }
+
+    pub fn update_history_query(&mut self, edit: impl FnOnce(&mut String)) {
+        if let Some(search) = self.history_search.as_mut() {
+            edit(&mut search.query);
+            self.select_last_history_match();
+        }
+    }
+
+    fn select_last_history_match(&mut self) {
+        let last = self.history_matches().len().saturating_sub(1);
+        if let Some(search) = self.history_search.as_mut() {
+            search.selected = last;
+        }
+    }
+
+    pub fn move_history_selection(&mut self, delta: isize) {
+        let count = self.history_matches().len();
+        if let Some(search) = self.history_search.as_mut() {
+            search.selected = search
+                .selected
+                .saturating_add_signed(delta)
+                .min(count.saturating_sub(1));
+        }
+    }
+
+    /// Pushes the result of the selected search match and ends the search.
+    pub fn push_selected_history(&mut self) {
+        let Some(search) = &self.history_search else {
+            return;
+        };
+        let selected = self.history_matches().get(search.selected).map(|e| e.result.clone());
+        self.history_search = None;
+        match selected {
+            Some(result) => self.push_history_result(result),
+            None => self.message = "No matching history entry".to_string(),
+        }
+    }
+
+    /// Pushes the result of the history entry at `index`, oldest first.
+    pub fn push_history_entry(&mut self, index: usize) {
+        if let Some(entry) = self.calc_history.get(index) {
+            self.push_history_result(entry.result.clone());
+        }
+    }
+
+    fn push_history_result(&mut self, result: Value) {
+        self.message = format!("Pushed {} from history", format_value(&result));
+        self.log_pushed(std::slice::from_ref(&result));
+        if let Err(e) = self.push_literal(result) {
+            self.message = e.to_string();
+        }
+    }
+
+    /// Pushes a copy of the stack entry at `index`, counted from the bottom,
+    /// by running `pick` so that the line is logged for replay.
+    pub fn pick(&mut self, index: usize) -> OpResult {
+        let level = self.stack_level(index)?;
+        self.eval_line(&format!("pick {}", level))
+    }
+
+    /// Moves the stack entry at `from` to position `to`, shifting the
+    /// entries in between; both count from the bottom. Runs `move`, so that
+    /// the line is logged for replay.
+    pub fn move_stack_entry(&mut self, from: usize, to: usize) -> OpResult {
+        let (from, to) = (self.stack_level(from)?, self.stack_level(to)?);
+        self.eval_line(&format!("move {} {}", from, to))
+    }
+
+    /// The stack level, counting from 1 for x, of the entry at `index` from
+    /// the bottom.
+    fn stack_level(&self, index: usize) -> Result<usize, CalcError> {
+        if index >= self.stack.len() {
+            return Err(CalcError::InvalidArgument(format!("No stack entry {}", index)));
+        }
+        Ok(self.stack.len() - index)
+    }
+
+    /// The entry at stack level `arg` as an index from the bottom.
+    fn level_arg(&self, arg: usize) -> Result<usize, CalcError> {
+        let level = self.args[arg].parse::<usize>().unwrap_or(0);
+        if !(1..=self.stack.len()).contains(&level) {
+            return Err(CalcError::InvalidArgument(format!(
+                "No stack level {}; the stack holds {}",
+                self.args[arg],
+                self.stack.len()
+            )));
+        }
+        Ok(self.stack.len() - level)
+    }
+
+    /// `pick <n>`: pushes a copy of stack level n, 1 being x.
+    pub fn pick_level(&mut self) -> OpResult {
+        let value = self.stack[self.level_arg(0)?].clone();
+        let message = format!("Copied {} to the top", format_value(&value));
+        self.stack.push(value);
+        Ok(OpOutcome::info(message))
+    }
+
+    /// `move <from> <to>`: moves the value at stack level `from` to level
+    /// `to`, shifting the values in between; 1 is x.
+    pub fn move_level(&mut self) -> OpResult {
+        let (from, to) = (self.level_arg(0)?, self.level_arg(1)?);
+        let value = self.stack.remove(from);
+        let message = format!(
+            "Moved {} from level {} to {}",
+            format_value(&value),
+            self.args[0],
+            self.args[1]
+        );
+        self.stack.insert(to, value);
+        Ok(OpOutcome::info(message))
+    }
+
+    /// `push "<values>"`: pushes values written exactly, as the replay log
+    /// records those picked from the history or imported; see
+    /// `value::encode_exact`.
+    pub fn push_exact(&mut self) -> OpResult {
+        let values = value::decode_exact(unquote(&self.args[0])).ok_or_else(|| {
+            CalcError::InvalidArgument(format!("'{}' is not a list of exact values", self.args[0]))
+        })?;
+        self.check_room(values.len())?;
+        let message = format!("Pushed {} values", values.len());
+        self.stack.extend(values);
+        Ok(OpOutcome::info(message))
+    }
+
+    /// Logs a line for replay that pushes `values` as they are, for values
+    /// pushed from the history or an import rather than typed.
+    fn log_pushed(&mut self, values: &[Value]) {
+        if self.nesting == 0 {
+            let line = format!("push \"{}\"", value::encode_exact(values));
+            self.log_replay(&line);
+        }
+    }
+
+    pub fn cancel_history_search(&mut self) {
+        if self.history_search.take().is_some() {
+            self.message = "Search cancelled".to_string();
+        }
+    }
+
+    pub fn divide(&mut self) -> OpResult {
+        self.value_binary_op(value::div, "/")
+    }
+
+    /// Rounds the top value with `f`, keeping money amounts as money and
+    /// rounding matrices entrywise.
+    pub fn rounding_op(&mut self, f: fn(f64) -> f64, name: &str) -> OpResult {
+        self.value_unary_op(|v| value::round_with(v, name, f), name)
+    }
+
+    /// `x rnd <n>`: rounds to n decimal places, or to tens, hundreds, … for
+    /// negative n. Money amounts are rounded exactly.
+    pub fn round_to(&mut self) -> OpResult {
+        let places = self.args[0]
+            .parse::<i32>()
+            .ok()
+            .filter(|n| n.abs() <= 15)
+            .ok_or_else(|| {
+                CalcError::InvalidArgument(format!(
+                    "Places must be a whole number from -15 to 15, not '{}'",
+                    self.args[0]
+                ))
+            })?;
+        self.value_unary_op(
+            |v| match v {
+                Value::Decimal(d) if places >= 0 => Ok(Value::Decimal(
+                    Decimal::parse(&d.format(places as u32)).expect("formatted decimal"),
+                )),
+                v => value::round_with(v, "rnd", |x| value::round_places(x, places)),
+            },
+            "rnd",
+        )
+    }
+
+    /// 1/x for numbers, the inverse for square matrices.
+    pub fn reciprocal(&mut self) -> OpResult {
+        if let Some(Value::Matrix(_)) = self.stack.last() {
+            return self.value_unary_op(value::inverse, "inv");
+        }
+        let [a] = self.top_numbers("reciprocal")?;
+        if a == 0.0 {
+            return Err(CalcError::domain("Cannot take reciprocal of zero"));
+        }
+        let result = 1.0 / a;
+        self.replace_top(1, result);
+        let calc = format!("1/{} = {}", format_number(a), format_number(result));
+        Ok(OpOutcome::calc(calc, result))
+    }
+
+    pub fn factorial(&mut self) -> OpResult {
+        let [a] = self.top_numbers("factorial")?;
+        if a < 0.0 || a.fract() != 0.0 {
+            return Err(CalcError::domain("Factorial needs non-negative integer"));
+        }
+        // 171! is already beyond f64, so larger n need not be multiplied out.
+        let result = if a > 170.0 {
+            f64::INFINITY
+        } else {
+            (1..=a as u64).map(|k| k as f64).product()
+        };
+        self.check_domain("!", &[&a.into()], &result.into())?;
+        let n = a as u64;
+        self.replace_top(1, result);
+        let calc = format!("{}! = {}", n, format_number(result));
+        Ok(OpOutcome::calc(calc, result))
+    }
+
+    pub fn swap(&mut self) -> OpResult {
+        self.require(2, "swap")?;
+        let len = self.stack.len();
+        self.stack.swap(len - 1, len - 2);
+        Ok(OpOutcome::info("Swapped top 2 values"))
+    }
+
+    pub fn dup(&mut self) -> OpResult {
+        let top = self.stack.last().cloned().ok_or(CalcError::EmptyStack)?;
+        let message = format!("Duplicated {}", format_value(&top));
+        self.stack.push(top);
+        Ok(OpOutcome::info(message))
+    }
+
+    /// `enter` duplicates the top like ENTER on an HP calculator, and the
+    /// next value entered replaces the copy: `3 enter 4 +` gives 7 and
+    /// `3 enter *` squares.
+    pub fn enter(&mut self) -> OpResult {
+        let outcome = self.dup()?;
+        self.lift_disabled = true;
+        Ok(outcome)
+    }
+
+    pub fn execute_single_char(&mut self, c: char) {
+        if !self.input.is_empty() {
+            self.execute_command();
+        }
+        self.input.push(c);
+        self.execute_command();
+    }
+
+    pub fn clear(&mut self) -> OpResult {
+        self.stack.clear();
+        self.outliers.clear();
+        self.journal.note(Aside::Entered(std::mem::take(&mut self.entered)));
+        Ok(OpOutcome::info("Stack cleared"))
+    }
+
+    /// Pushes the sum of the numbers typed since the last `clear` or
+    /// `subtotal`, like an adding machine, and starts a new run. Numbers
+    /// imported or pasted are not counted.
+    pub fn subtotal(&mut self) -> OpResult {
+        let (first, rest) = self.entered.split_first().ok_or_else(|| {
+            CalcError::InvalidArgument(
+                "Nothing entered since the last clear or subtotal".to_string(),
+            )
+        })?;
+        let sum = rest
+            .iter()
+            .try_fold(first.clone(), |sum, v| value::add(&sum, v))?;
+        let calc = format!(
+            "subtotal of {} {} = {}",
+            self.entered.len(),
+            if self.entered.len() == 1 { "entry" } else { "entries" },
+            format_value(&sum)
+        );
+        self.journal.note(Aside::Entered(std::mem::take(&mut self.entered)));
+        self.stack.push(sum.clone());
+        Ok(OpOutcome::calc(calc, sum))
+    }
+
+    /// `y x ~=`: 1 if y and x are equal within the tolerance, else 0.
+    pub fn approx_equal(&mut self) -> OpResult {
+        self.require(2, "~=")?;
+        let len = self.stack.len();
+        let (y, x) = (&self.stack[len - 2], &self.stack[len - 1]);
+        let result = if self.tolerance.values_match(y, x) { 1.0 } else { 0.0 };
+        let calc = format!("{} ~= {} = {}", format_value(y), format_value(x), result);
+        self.replace_top(2, result);
+        Ok(OpOutcome::calc(calc, result))
+    }
+
+    /// Pushes 1 if `test` holds for y and x, else 0, e.g. as the condition
+    /// of an `if`.
+    pub fn compare(&mut self, test: fn(f64, f64) -> bool, name: &str) -> OpResult {
+        self.binary_op(|y, x| if test(y, x) { 1.0 } else { 0.0 }, name)
+    }
+
+    /// `outliers <k>` flags values more than k standard deviations from the
+    /// mean; `outliers <k>iqr` those more than k interquartile ranges beyond
+    /// the quartiles. The stack is not changed.
+    pub fn flag_outliers(&mut self) -> OpResult {
+        let arg = self.args[0].clone();
+        let (k, spread) = match arg.strip_suffix("iqr") {
+            Some(k) => (k, Spread::Iqr),
+            None => (arg.as_str(), Spread::StdDev),
+        };
+        let k = k
+            .parse::<f64>()
+            .ok()
+            .filter(|k| *k > 0.0 && k.is_finite())
+            .ok_or_else(|| {
+                CalcError::InvalidArgument(format!("'{}' is not a positive number of spreads", arg))
+            })?;
+        let values = self.stack_numbers("outliers")?;
+        if values.len() < 3 {
+            return Err(CalcError::underflow("outliers", 3));
+        }
+        let (low, high) = stats::fences(&values, k, spread);
+        self.outliers = stats::outliers(&values, (low, high));
+        let unit = match spread {
+            Spread::StdDev => "σ",
+            Spread::Iqr => " IQR",
+        };
+        let range = format!("{} to {}", format_number(low), format_number(high));
+        Ok(OpOutcome::info(match self.outliers.len() {
+            0 => format!("No outliers: all within {} ({}{})", range, k, unit),
+            1 => format!("1 outlier outside {} ({}{})", range, k, unit),
+            n => format!("{} outliers outside {} ({}{})", n, range, k, unit),
+        }))
+    }
+
+    /// The whole stack as numbers, for statistics.
+    fn stack_numbers(&self, op: &str) -> Result<Vec<f64>, CalcError> {
+        self.stack
+            .iter()
+            .map(Value::as_number)
+            .collect::<Option<Vec<f64>>>()
+            .ok_or_else(|| CalcError::wrong_type(op, "numbers"))
+    }
+
+    /// Pops the whole stack and shows how often each value occurs.
+    pub fn frequency(&mut self) -> OpResult {
+        if self.stack.is_empty() {
+            return Err(CalcError::EmptyStack);
+        }
+        let values = self.stack_numbers("freq")?;
+        let table = stats::frequencies(&values, stats::FREQ_PLACES);
+        let (modes, top) = stats::modes(&table);
+        let labels: Vec<String> = table.iter().map(|&(v, _)| format_number(v)).collect();
+        let width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0).max(5);
+        let mut lines = vec![format!("{:>w$}  count", "value", w = width)];
+        for (label, &(_, count)) in labels.iter().zip(&table) {
+            let mut line = format!("{:>w$}  {:>5}", label, count, w = width);
+            if self.freq_bars {
+                line.push_str("  ");
+                line.push_str(&"█".repeat((count * FREQ_BAR_WIDTH).div_ceil(top)));
+            }
+            lines.push(line);
+        }
+        let summary = if top == 1 && table.len() > 1 {
+            format!("{} values, all distinct", values.len())
+        } else {
+            let modes: Vec<String> = modes.iter().map(|&m| format_number(m)).collect();
+            format!(
+                "{} values, {} distinct, mode {} ({}×)",
+                values.len(),
+                table.len(),
+                modes.join(" "),
+                top
+            )
+        };
+        lines.push(String::new());
+        lines.push(summary.clone());
+        self.stack.clear();
+        self.popup = Some(Popup::new("freq", lines));
+        Ok(OpOutcome::info(summary))
+    }
+
+    pub fn drop_top(&mut self) -> OpResult {
+        let val = self.stack.pop().ok_or(CalcError::EmptyStack)?;
+        Ok(OpOutcome::info(format!("Dropped {}", format_value(&val))))
+    }
+
+    /// Moves the top of the stack to the shelf.
+    pub fn park(&mut self) -> OpResult {
+        let value = self.stack.pop().ok_or(CalcError::EmptyStack)?;
+        let message = format!("Parked {}", format_value(&value));
+        self.journal.note(Aside::Shelf(self.shelf.clone()));
+        self.shelf.push(value);
+        Ok(OpOutcome::info(message))
+    }
+
+    /// Moves the most recently parked value back onto the stack.
+    pub fn unpark(&mut self) -> OpResult {
+        let shelf = self.shelf.clone();
+        let value = self.shelf.pop().ok_or_else(|| {
+            CalcError::InvalidArgument("Nothing is parked".to_string())
+        })?;
+        self.journal.note(Aside::Shelf(shelf));
+        let message = format!("Unparked {}", format_value(&value));
+        self.stack.push(value);
+        Ok(OpOutcome::info(message))
+    }
+
+    pub fn undo(&mut self) -> OpResult {
+        if !self.journal.is_enabled() {
+            return Err(CalcError::InvalidArgument("Undo is turned off".to_string()));
+        }
+        let asides = self.journal.undo(&mut self.stack).ok_or(CalcError::NothingToUndo)?;
+        self.restore(asides);
+        Ok(OpOutcome::info("Undid last operation"))
+    }
+
+    /// Puts back state beside the stack that undo or a failed operation
+    /// handed back.
+    fn restore(&mut self, asides: Vec<Aside>) {
+        for aside in asides {
+            match aside {
+                Aside::Shelf(shelf) => self.shelf = shelf,
+                Aside::Entered(entered) => self.entered = entered,
+                Aside::EnteredLen(len) => self.entered.truncate(len),
+                Aside::Pairs(pairs) => self.pairs = pairs,
+                Aside::Tvm(tvm) => self.tvm = tvm,
+                Aside::Tape(entries) => {
+                    // Later entries may have pushed older ones out.
+                    for (was, now) in entries {
+                        let found = self.calc_history.iter_mut().rev().find(|e| **e == now);
+                        if let Some(entry) = found {
+                            *entry = was;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Pushes the x operand of the most recent operation again.
+    pub fn push_last_x(&mut self) -> OpResult {
+        let value = self
+            .last_x
+            .clone()
+            .ok_or_else(|| CalcError::InvalidArgument("No operation has used an x yet".to_string()))?;
+        let message = format!("Pushed last x {}", format_value(&value));
+        self.stack.push(value);
+        Ok(OpOutcome::info(message))
+    }
+
+    /// `ws <n>`: switches to workspace n, counting from 1.
+    pub fn select_workspace(&mut self) -> OpResult {
+        let index = self.args[0]
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=workspace::MAX_WORKSPACES).contains(n))
+            .ok_or_else(|| {
+                CalcError::InvalidArgument(format!(
+                    "Workspaces are numbered 1 to {}",
+                    workspace::MAX_WORKSPACES
+                ))
+            })?;
+        self.switch_workspace(index - 1)
+    }
+
+    /// Makes workspace `index` (from 0) current, creating it if needed.
+    pub fn switch_workspace(&mut self, index: usize) -> OpResult {
+        // A macro undoes as one step of the workspace it started in.
+        if self.nesting > 0 {
+            return Err(CalcError::InvalidArgument(
+                "Workspaces cannot be switched inside a macro, script or block".to_string(),
+            ));
+        }
+        if index >= workspace::MAX_WORKSPACES {
+            return Err(CalcError::InvalidArgument(format!(
+                "Workspaces are numbered 1 to {}",
+                workspace::MAX_WORKSPACES
+            )));
+        }
+        if index != self.workspace {
+            if self.workspaces.len() <= index {
+                self.workspaces.resize_with(index + 1, Workspace::default);
+            }
+            self.workspaces[self.workspace] = Workspace {
+                stack: std::mem::take(&mut self.stack),
+                journal: std::mem::take(&mut self.journal),
+                mark: self.mark.take(),
+                last_x: self.last_x.take(),
+                entered: std::mem::take(&mut self.entered),
+                shelf: std::mem::take(&mut self.shelf),
+            };
+            let next = std::mem::take(&mut self.workspaces[index]);
+            self.stack = next.stack;
+            self.journal = next.journal;
+            self.mark = next.mark;
+            self.last_x = next.last_x;
+            self.entered = next.entered;
+            self.shelf = next.shelf;
+            self.workspace = index;
+            self.outliers.clear();
+        }
+        Ok(OpOutcome::info(format!(
+            "Workspace {} ({} on the stack)",
+            index + 1,
+            self.stack.len()
+        )))
+    }
+
+    /// Remembers the top of the stack as the reference for `mark_status`.
+    pub fn set_mark(&mut self) -> OpResult {
+        let [top] = self.top_numbers("mark").map_err(|e| match e {
+            CalcError::StackUnderflow { .. } => CalcError::EmptyStack,
+            e => e,
+        })?;
+        self.mark = Some(top);
+        Ok(OpOutcome::info(format!("Marked {}", format_number(top))))
+    }
+
+    pub fn clear_mark(&mut self) -> OpResult {
+        self.mark = None;
+        Ok(OpOutcome::info("Mark cleared"))
+    }
+
+    /// The top of the stack compared to the mark, e.g.
+    /// `mark 80  Δ +20 (+25%)`. The percentage is left out for a zero mark.
+    pub fn mark_status(&self) -> Option<String> {
+        let mark = self.mark?;
+        let mut status = format!("mark {}", format_number(mark));
+        if let Some(top) = self.stack.last().and_then(Value::as_number) {
+            let delta = top - mark;
+            status.push_str(&format!("  Δ {}{}", sign(delta), format_number(delta)));
+            if mark != 0.0 {
+                let pct = delta / mark.abs() * 100.0;
+                status.push_str(&format!(" ({}{}%)", sign(pct), format_number(pct)));
+            }
+        }
+        Some(status)
+    }
+
+    /// Shows the whole tape, scrolled to the end.
+    pub fn open_tape(&mut self) -> OpResult {
+        if self.tape.is_empty() {
+            return Err(CalcError::InvalidArgument("The tape is empty".to_string()));
+        }
+        self.show_tape = true;
+        self.tape_scroll = 0;
+        Ok(OpOutcome::info(
+            "Tape shown (arrows, PgUp/PgDn, Home/End scroll, any other key closes)",
+        ))
+    }
+
+    /// Scrolls the tape `delta` lines further back, staying within it.
+    pub fn scroll_tape(&mut self, delta: isize) {
+        self.tape_scroll = self
+            .tape_scroll
+            .saturating_add_signed(delta)
+            .min(self.tape.len().saturating_sub(1));
+    }
+
+    /// `savetape <file>` writes the tape as text, one entry per line.
+    pub fn save_tape(&mut self) -> OpResult {
+        let path = PathBuf::from(&self.args[0]);
+        let mut text = self.tape.lines(0..self.tape.len(), self.number_format()).join("\n");
+        text.push('\n');
+        fs::write(&path, text).map_err(|e| {
+            CalcError::InvalidArgument(format!("Could not write {}: {}", path.display(), e))
+        })?;
+        Ok(OpOutcome::info(format!(
+            "Saved {} tape {} to {}",
+            self.tape.len(),
+            if self.tape.len() == 1 { "entry" } else { "entries" },
+            path.display()
+        )))
+    }
+
+    pub fn open_help(&mut self) -> OpResult {
+        self.show_help = true;
+        self.help_scroll = 0;
+        Ok(OpOutcome::info("Help shown (arrows to scroll, any other key to close)"))
+    }
+
+    /// Shows the notes panel and starts editing it, or hides it if shown.
+    pub fn toggle_notes(&mut self) -> OpResult {
+        self.show_notes = !self.show_notes;
+        self.editing_notes = self.show_notes;
+        Ok(OpOutcome::info(if self.show_notes {
+            "Editing notes (Esc to finish)"
+        } else {
+            "Notes hidden"
+        }))
+    }
+
+    /// Shows or hides the soft-key labels; the keys work either way.
+    pub fn toggle_softkeys(&mut self) -> OpResult {
+        self.show_softkeys = !self.show_softkeys;
+        Ok(OpOutcome::info(if self.show_softkeys {
+            "Soft keys shown (F1-F8 run them, F9 turns the page)"
+        } else {
+            "Soft keys hidden (F1-F8 still run them)"
+        }))
+    }
+
+    /// Runs what soft key `n` (0 for F1) runs on the page shown.
+    pub fn press_softkey(&mut self, n: usize) -> OpResult {
+        let action = self.softkeys.action(self.softkey_page, n).ok_or_else(|| {
+            CalcError::InvalidArgument(format!("F{} has nothing to run on this page", n + 1))
+        })?;
+        let action = action.to_string();
+        self.eval_line(&action)
+    }
+
+    /// Turns to the next page of soft keys, back to the first after the
+    /// last.
+    pub fn next_softkey_page(&mut self) -> OpResult {
+        let pages = self.softkeys.pages();
+        self.softkey_page = (self.softkey_page + 1) % pages;
+        Ok(OpOutcome::info(format!("Soft keys page {} of {}", self.softkey_page + 1, pages)))
+    }
+
+    pub fn load_notes(&mut self, path: &Path) -> io::Result<()> {
+        match fs::read_to_string(path) {
+            Ok(notes) => self.notes = notes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+
+    pub fn save_notes(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, &self.notes)
+    }
+
+    pub fn set_angle_mode(&mut self, mode: AngleMode) -> OpResult {
+        self.angle_mode = mode;
+        Ok(OpOutcome::info(format!("Angle mode: {}", mode.name())))
+    }
+
+    /// Switches money mode on or off, converting the numbers on the stack.
+    /// Either way fraction mode ends, so `float` leaves both.
+    pub fn set_money_mode(&mut self, money: bool) -> OpResult {
+        self.money = money;
+        self.frac = false;
+        for value in &mut self.stack {
+            match value {
+                Value::Number(n) if money => {
+                    if let Some(d) = Decimal::from_f64(*n) {
+                        *value = Value::Decimal(d);
+                    }
+                }
+                Value::Rational(r) if money => {
+                    if let Some(d) = Decimal::from_f64(r.to_f64()) {
+                        *value = Value::Decimal(d);
+                    }
+                }
+                Value::Decimal(d) if !money => *value = Value::Number(d.to_f64()),
+                Value::Rational(r) if !money => *value = Value::Number(r.to_f64()),
+                _ => {}
+            }
+        }
+        Ok(OpOutcome::info(if money {
+            "Money mode: exact decimal arithmetic, shown to the cent"
+        } else {
+            "Float mode"
+        }))
+    }
+
+    /// Enters fraction mode, leaving money mode. Numbers and money amounts
+    /// on the stack that are close to a simple fraction become one.
+    pub fn set_frac_mode(&mut self) -> OpResult {
+        self.money = false;
+        self.frac = true;
+        for value in &mut self.stack {
+            if let Value::Number(_) | Value::Decimal(_) = value {
+                if let Some(r) = value.as_number().and_then(Rational::from_f64) {
+                    *value = Value::Rational(r);
+                }
+            }
+        }
+        Ok(OpOutcome::info(
+            "Fraction mode: exact fractions for + - * /, other operations give floats",
+        ))
+    }
+
+    /// Shows numbers with SI prefixes, or plainly again if it already does.
+    pub fn toggle_eng_prefixes(&mut self) -> OpResult {
+        self.eng_prefixes = !self.eng_prefixes;
+        Ok(OpOutcome::info(if self.eng_prefixes {
+            "Showing numbers with SI prefixes, e.g. 4.7 k (engpre again to stop)"
+        } else {
+            "Showing numbers without prefixes"
+        }))
+    }
+
+    /// How floating-point numbers are shown: with SI prefixes in `engpre`
+    /// mode.
+    pub fn number_format(&self) -> fn(f64) -> String {
+        if self.eng_prefixes {
+            prefix::format
+        } else {
+            format_number
+        }
+    }
+
+    /// Turns the top number into the closest simple fraction, which
+    /// `+ - * /` then keep exact.
+    pub fn to_fraction(&mut self) -> OpResult {
+        self.value_unary_op(
+            |v| match v {
+                Value::Rational(_) => Ok(v.clone()),
+                Value::Number(_) | Value::Decimal(_) => v
+                    .as_number()
+                    .and_then(Rational::from_f64)
+                    .map(Value::Rational)
+                    .ok_or_else(|| {
+                        CalcError::domain(&format!(
+                            "Not close to a fraction with a denominator up to {}",
+                            rational::MAX_DENOMINATOR
+                        ))
+                    }),
+                _ => Err(CalcError::wrong_type("tofrac", "a number")),
+            },
+            "tofrac",
+        )
+    }
+
+    /// Turns the top fraction or money amount into a plain number.
+    pub fn to_float(&mut self) -> OpResult {
+        self.value_unary_op(
+            |v| match v {
+                Value::Number(_) | Value::Decimal(_) | Value::Rational(_) => {
+                    Ok(Value::Number(v.as_number().expect("a number")))
+                }
+                _ => Err(CalcError::wrong_type("tofloat", "a number")),
+            },
+            "tofloat",
+        )
+    }
+
+    pub fn push_constant(&mut self, value: f64, label: &str) -> OpResult {
+        self.stack.push(value.into());
+        Ok(OpOutcome::info(format!("Pushed {}", label)))
+    }
+
+    /// `y x pct`: replaces x with x% of y, leaving y in place so the result
+    /// can be added to or subtracted from it.
+    pub fn percent(&mut self) -> OpResult {
+        self.top_numbers::<2>("pct")?;
+        let len = self.stack.len();
+        let (y, x) = (&self.stack[len - 2], &self.stack[len - 1]);
+        // Through the value operations so money amounts stay exact.
+        let result = value::div(&value::mul(y, x)?, &Value::Number(100.0))?;
+        let calc = format!(
+            "{}% of {} = {}",
+            format_value(x),
+            format_value(y),
+            format_value(&result)
+        );
+        self.replace_top(1, result.clone());
+        Ok(OpOutcome::calc(calc, result))
+    }
+
+    pub fn store_tvm(&mut self, reg: TvmRegister) -> OpResult {
+        let [value] = self.top_numbers(&format!("set{}", reg.name().to_lowercase()))?;
+        self.stack.pop();
+        self.journal.note(Aside::Tvm(self.tvm));
+        self.tvm.set(reg, value);
+        Ok(OpOutcome::info(format!("{} = {}", reg.name(), format_number(value))))
+    }
+
+    /// `x y sadd` adds the pair to the statistical registers, or with
+    /// `remove` takes it back out, leaving the number of pairs.
+    pub fn accumulate_pair(&mut self, remove: bool) -> OpResult {
+        let name = if remove { "ssub" } else { "sadd" };
+        let [x, y] = self.top_numbers(name)?;
+        self.journal.note(Aside::Pairs(self.pairs));
+        if remove {
+            self.pairs.remove(x, y).map_err(CalcError::domain)?;
+        } else {
+            self.pairs.add(x, y);
+        }
+        self.replace_top(2, self.pairs.n as f64);
+        Ok(OpOutcome::info(format!(
+            "{} ({}, {}); n = {}",
+            if remove { "Removed" } else { "Added" },
+            format_number(x),
+            format_number(y),
+            self.pairs.n
+        )))
+    }
+
+    pub fn clear_pairs(&mut self) -> OpResult {
+        self.journal.note(Aside::Pairs(self.pairs));
+        self.pairs = PairStats::default();
+        Ok(OpOutcome::info("Statistical registers cleared"))
+    }
+
+    /// Pushes the slope and then the intercept of the regression line.
+    pub fn linear_regression(&mut self) -> OpResult {
+        let (slope, intercept) = self.pairs.linreg().map_err(CalcError::domain)?;
+        self.stack.push(slope.into());
+        self.stack.push(intercept.into());
+        Ok(OpOutcome::info(format!(
+            "y = {} + {} x over {} pairs",
+            format_number(intercept),
+            format_number(slope),
+            self.pairs.n
+        )))
+    }
+
+    pub fn correlation(&mut self) -> OpResult {
+        let r = self.pairs.corr().map_err(CalcError::domain)?;
+        self.stack.push(r.into());
+        let calc = format!("corr({} pairs) = {}", self.pairs.n, format_number(r));
+        Ok(OpOutcome::calc(calc, r))
+    }
+
+    /// Replaces x with the regression line's y there.
+    pub fn predict(&mut self) -> OpResult {
+        let [x] = self.top_numbers("predict")?;
+        let y = self.pairs.predict(x).map_err(CalcError::domain)?;
+        self.replace_top(1, y);
+        let calc = format!("predict({}) = {}", format_number(x), format_number(y));
+        Ok(OpOutcome::calc(calc, y))
+    }
+
+    /// Solves the TVM equation for `reg`, stores and pushes the result.
+    pub fn solve_tvm(&mut self, reg: TvmRegister) -> OpResult {
+        let value = self.tvm.solve(reg).map_err(CalcError::domain)?;
+        self.journal.note(Aside::Tvm(self.tvm));
+        self.tvm.set(reg, value);
+        self.stack.push(value.into());
+        Ok(OpOutcome::calc(format!("{} = {}", reg.name(), format_number(value)), value))
+    }
+
+    /// Pops a count N, then N cash flows (the deepest is time 0), and pushes
+    /// `op` applied to them.
+    pub fn cash_flow_op<F>(&mut self, op: F, name: &str) -> OpResult
+    where
+        F: Fn(&[f64]) -> Result<f64, &'static str>,
+    {
+        let flows = self.counted_numbers(name)?;
+        let result = op(&flows).map_err(CalcError::domain)?;
+        self.replace_top(flows.len() + 1, result);
+        let calc = format!("{}({} flows) = {}", name, flows.len(), format_number(result));
+        Ok(OpOutcome::calc(calc, result))
+    }
+
+    pub fn root(&mut self) -> OpResult {
+        let [x, y] = self.top_numbers("root")?; // base, root index
+        if y == 0.0 {
+            return Err(CalcError::domain("Cannot take 0th root"));
+        }
+        let result = x.powf(1.0 / y);
+        self.replace_top(2, result);
+        let calc = format!(
+            "{} root {} = {}",
+            format_number(y),
+            format_number(x),
+            format_number(result)
+        );
+        Ok(OpOutcome::calc(calc, result))
+    }
+}
@@ -0,0 +1,133 @@
+//! User macros and their tests, read from a file like:
+//!
+//! ```text
+//! # Fahrenheit to Celsius
+//! def ftoc = 32 - 5 * 9 /
+//! test ftoc: 212 ftoc expect 100
+//! test ftoc: -40 ftoc expect -40
+//! ```
+//!
+//! A macro runs its body as if typed on the input line. A test runs its input
+//! on an empty stack and expects exactly the listed values to remain.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::Value;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Macro {
+    pub name: String,
+    pub body: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MacroTest {
+    /// The macro under test.
+    pub name: String,
+    pub input: String,
+    pub expect: Vec<Value>,
+    /// Line number in the macro file, for reports.
+    pub line: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MacroLibrary {
+    pub macros: Vec<Macro>,
+    pub tests: Vec<MacroTest>,
+}
+
+impl MacroLibrary {
+    /// Reads macros and tests from `text`. Lines that cannot be understood
+    /// are skipped and described in the returned warnings.
+    pub fn parse(text: &str) -> (MacroLibrary, Vec<String>) {
+        let mut library = MacroLibrary::default();
+        let mut warnings = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let result = if line.is_empty() || line.starts_with('#') {
+                Ok(())
+            } else if let Some(def) = line.strip_prefix("def ") {
+                library.parse_def(def)
+            } else if let Some(test) = line.strip_prefix("test ") {
+                library.parse_test(test, n + 1)
+            } else {
+                Err("expected 'def <name> = …' or 'test <name>: … expect …'".to_string())
+            };
+            if let Err(e) = result {
+                warnings.push(format!("line {}: {}", n + 1, e));
+            }
+        }
+        (library, warnings)
+    }
+
+    /// Reads the macro file at `path`; a missing file has no macros.
+    pub fn load(path: &Path) -> io::Result<(MacroLibrary, Vec<String>)> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(MacroLibrary::parse(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                Ok((MacroLibrary::default(), Vec::new()))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn parse_def(&mut self, def: &str) -> Result<(), String> {
+        let (name, body) = def
+            .split_once('=')
+            .ok_or("expected 'def <name> = <body>'")?;
+        self.define(name.trim(), body.trim())
+    }
+
+    /// Adds the macro `name`, replacing any macro of that name.
+    pub fn define(&mut self, name: &str, body: &str) -> Result<(), String> {
+        if name.is_empty()
+            || name.contains(char::is_whitespace)
+            || name.parse::<f64>().is_ok()
+            || crate::prefix::parse(name).is_some()
+            || crate::program::KEYWORDS.contains(&name)
+        {
+            return Err(format!("'{}' is not a valid macro name", name));
+        }
+        if body.is_empty() {
+            return Err(format!("macro '{}' has no body", name));
+        }
+        let body = body.to_string();
+        match self.macros.iter_mut().find(|m| m.name == name) {
+            Some(existing) => existing.body = body,
+            None => self.macros.push(Macro {
+                name: name.to_string(),
+                body,
+            }),
+        }
+        Ok(())
+    }
+
+    fn parse_test(&mut self, test: &str, line: usize) -> Result<(), String> {
+        let (name, rest) = test.split_once(':').ok_or("expected 'test <name>: …'")?;
+        let (input, expect) = rest
+            .split_once(" expect")
+            .ok_or("a test needs 'expect' followed by the resulting stack")?;
+        let expect = crate::tokenize(expect)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(Value::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.tests.push(MacroTest {
+            name: name.trim().to_string(),
+            input: input.trim().to_string(),
+            expect,
+            line,
+        });
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Macro> {
+        self.macros.iter().find(|m| m.name == name)
+    }
+
+    pub fn tests_for<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a MacroTest> {
+        self.tests.iter().filter(move |t| t.name == name)
+    }
+}
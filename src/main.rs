@@ -1,216 +1,353 @@
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+mod repl;
+mod tui;
+
+use std::{
+    error::Error,
+    io::{self, IsTerminal},
+    path::PathBuf,
 };
-use ratatui::{
-    backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Style},
-    text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Clear},
-    Frame, Terminal,
+use crossterm::style::Stylize;
+use rpncalc::{
+    format_stack_columns_with, format_value_with, history, paths, rates, reference, replay,
+    script, App, Config, Date, Journal, MacroLibrary, RateTable,
 };
-use std::{error::Error, io};
-use rpncalc::App;
+#[cfg(unix)]
+use rpncalc::session::{self, Role};
+#[cfg(not(unix))]
+use tui::session;
 
 const VERSION: &str = match option_env!("RPNCALC_VERSION") {
     Some(v) => v,
     None => git_version::git_version!(args = ["--tags", "--always", "--dirty=-modified"], fallback = env!("CARGO_PKG_VERSION"))
 };
 
-fn main() -> Result<(), Box<dyn Error>> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    let mut app = App::new();
-    let res = run_app(&mut terminal, &mut app);
-
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+const USAGE: &str = "Usage: rpncalc [--share <socket>] [--attach <socket> [--control]]
+       rpncalc [--plain] [--no-undo] --script <file|->
+       rpncalc [--plain] [--no-undo] --eval <input>
+       rpncalc --repl | --plain
+       rpncalc [--plain] --replay <file>
+       rpncalc --dump-reference md|man
+       rpncalc --update-rates <url>";
 
-    if let Err(err) = res {
-        println!("{:?}", err)
-    }
-
-    Ok(())
+#[derive(Default)]
+struct Options {
+    share: Option<PathBuf>,
+    attach: Option<PathBuf>,
+    control: bool,
+    script: Option<PathBuf>,
+    eval: Option<String>,
+    /// No colors or column alignment in `--script` and `--eval` output; on
+    /// its own, the line frontend describing each change in plain words.
+    plain: bool,
+    /// Keep no undo journal for `--script` and `--eval`, for long inputs.
+    no_undo: bool,
+    reference: Option<reference::Format>,
+    /// JSON endpoint to refresh the rates file from.
+    update_rates: Option<String>,
+    /// Read lines and print the stack instead of running the terminal UI.
+    repl: bool,
+    /// A replay log to run again, printing where each session ended.
+    replay: Option<PathBuf>,
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<()> {
-    loop {
-        terminal.draw(|f| ui(f, app))?;
-
-        if let Event::Key(key) = event::read()? {
-            // On Windows, filter out key release events to prevent double input
-            if key.kind == KeyEventKind::Release {
-                continue;
-            }
-            if app.show_help {
-                app.show_help = false;
-                app.message = "Help closed".to_string();
-                continue;
-            }
-            
-            match key.code {
-                KeyCode::Char('q') if app.input.is_empty() => return Ok(()),
-                KeyCode::Char(c) if c.is_ascii_digit() || c == '.' => {
-                    app.input.push(c);
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--share" => {
+                    options.share = Some(args.next().ok_or("--share needs a socket path")?.into())
+                }
+                "--attach" => {
+                    options.attach = Some(args.next().ok_or("--attach needs a socket path")?.into())
                 }
-                KeyCode::Char(c @ ('+' | '-' | '*' | '/' | '^' | '%' | '!')) => {
-                    app.execute_single_char(c);
+                "--control" => options.control = true,
+                "--script" => {
+                    options.script = Some(args.next().ok_or("--script needs a file")?.into())
                 }
-                KeyCode::Char(c) if c.is_ascii_alphabetic() => {
-                    app.input.push(c);
+                "--eval" => options.eval = Some(args.next().ok_or("--eval needs input")?),
+                "--plain" => options.plain = true,
+                "--no-undo" => options.no_undo = true,
+                "--repl" => options.repl = true,
+                "--replay" => {
+                    options.replay = Some(args.next().ok_or("--replay needs a file")?.into())
                 }
-                KeyCode::Enter => {
-                    app.execute_command();
+                "--dump-reference" => {
+                    let format = args.next().ok_or("--dump-reference needs md or man")?;
+                    options.reference = Some(
+                        reference::Format::from_name(&format)
+                            .ok_or(format!("Unknown reference format '{}'", format))?,
+                    )
                 }
-                KeyCode::Backspace => {
-                    app.input.pop();
+                "--update-rates" => {
+                    options.update_rates =
+                        Some(args.next().ok_or("--update-rates needs a URL")?)
                 }
-                KeyCode::Esc => {
-                    app.clear();
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
                 }
-                _ => {}
+                _ => return Err(format!("Unknown argument '{}'", arg)),
             }
         }
+        if options.share.is_some() && options.attach.is_some() {
+            return Err("--share and --attach cannot be combined".to_string());
+        }
+        let batch = options.script.is_some() || options.eval.is_some();
+        if options.script.is_some() && options.eval.is_some() {
+            return Err("--script and --eval cannot be combined".to_string());
+        }
+        if batch && (options.share.is_some() || options.attach.is_some()) {
+            return Err("--script and --eval run without a terminal UI and cannot share".to_string());
+        }
+        if options.no_undo && !batch {
+            return Err("--no-undo only applies with --script or --eval".to_string());
+        }
+        let interactive = options.share.is_some() || options.attach.is_some();
+        if options.update_rates.is_some() && (batch || interactive) {
+            return Err("--update-rates runs on its own".to_string());
+        }
+        if options.repl && (batch || interactive || options.update_rates.is_some()) {
+            return Err("--repl runs on its own".to_string());
+        }
+        let alone = batch || interactive || options.repl || options.update_rates.is_some();
+        if options.replay.is_some() && alone {
+            return Err("--replay runs on its own".to_string());
+        }
+        if options.plain && (interactive || options.update_rates.is_some()) {
+            return Err("--plain runs on its own or with --script or --eval".to_string());
+        }
+        if options.control && options.attach.is_none() {
+            return Err("--control only applies with --attach".to_string());
+        }
+        if cfg!(not(unix)) && (options.share.is_some() || options.attach.is_some()) {
+            return Err("Shared sessions need Unix domain sockets".to_string());
+        }
+        Ok(options)
     }
 }
 
-fn format_number(val: f64) -> String {
-    if val.abs() >= 1e10 || (val != 0.0 && val.abs() < 1e-4) {
-        format!("{:.6e}", val)
+/// Applies the config file and loads user macros, leaving the first problem
+/// in the message line.
+fn load_settings(app: &mut App) {
+    if let Some(path) = paths::config_file() {
+        match Config::load(&path) {
+            Ok((config, warnings)) => {
+                config.apply(app);
+                if let Some(warning) = warnings.first() {
+                    app.message = format!("{}: {}", path.display(), warning);
+                }
+            }
+            Err(e) => app.message = format!("Could not load config: {}", e),
+        }
+    }
+    if let Some(path) = paths::rates_file() {
+        match RateTable::load(&path) {
+            Ok((rates, warnings)) => {
+                app.rates = rates;
+                if let Some(warning) = warnings.first() {
+                    app.message = format!("{}: {}", path.display(), warning);
+                }
+            }
+            Err(e) => app.message = format!("Could not load exchange rates: {}", e),
+        }
+    }
+    if let Some(path) = paths::macros_file() {
+        match MacroLibrary::load(&path) {
+            Ok((macros, warnings)) => {
+                app.macros = macros;
+                if let Some(warning) = warnings.first() {
+                    app.message = format!("{}: {}", path.display(), warning);
+                }
+            }
+            Err(e) => app.message = format!("Could not load macros: {}", e),
+        }
+    }
+}
+
+/// Loads the calculation history and has new calculations appended to it.
+fn load_history(app: &mut App) {
+    if let Some(path) = paths::history_file() {
+        match history::load(&path, app.history_limit) {
+            Ok(entries) => app.calc_history = entries,
+            Err(e) => app.message = format!("Could not load history: {}", e),
+        }
+        app.history_file = Some(path);
+    }
+}
+
+/// Logs the lines of this session for `--replay`, unless the config turns
+/// the log off.
+fn start_replay_log(app: &mut App) {
+    if let Some(path) = paths::replay_file().filter(|_| app.replay_log) {
+        app.start_replay_log(path);
+    }
+}
+
+/// Replaces the rates file with the rates at `url`.
+fn update_rates(url: &str) -> Result<String, String> {
+    let path = paths::rates_file().ok_or("No config directory for the rates file")?;
+    let json = rates::fetch(url).map_err(|e| format!("Could not fetch {}: {}", url, e))?;
+    let table = RateTable::from_json(&json, &Date::today().to_string())
+        .map_err(|e| format!("Could not read rates from {}: {}", url, e))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(&path, table.to_string())
+        .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+    Ok(format!("Saved {} rates against {} to {}", table.rates.len(), table.base, path.display()))
+}
+
+/// Runs `--script` or `--eval` input without the terminal UI and prints the
+/// resulting stack, exiting with status 1 if the input fails.
+fn run_batch(options: &Options) {
+    let color = !options.plain
+        && io::stderr().is_terminal()
+        && std::env::var_os("NO_COLOR").is_none();
+    let mut app = App::new();
+    app.message.clear();
+    if options.no_undo {
+        app.journal = Journal::disabled();
+    }
+    load_settings(&mut app);
+    if !app.message.is_empty() {
+        let warning = format!("warning: {}", app.message);
+        eprintln!("{}", if color { warning.yellow().to_string() } else { warning });
+    }
+    let result = match (&options.script, &options.eval) {
+        (Some(path), _) => script::run_file(&mut app, path),
+        (None, Some(input)) => app.eval_line(input),
+        (None, None) => unreachable!("run_batch needs --script or --eval"),
+    };
+    if let Err(e) = result {
+        let error = format!("error: {}", e);
+        eprintln!("{}", if color { error.red().bold().to_string() } else { error });
+        std::process::exit(1);
+    }
+    print_stack(&app, options.plain);
+}
+
+/// Prints the stack bottom first, aligned unless `plain`.
+fn print_stack(app: &App, plain: bool) {
+    let lines = if plain {
+        let number = app.number_format();
+        app.stack.iter().map(|v| format_value_with(v, number)).collect()
     } else {
-        format!("{}", val)
+        format_stack_columns_with(&app.stack, app.number_format())
+    };
+    for line in lines {
+        println!("{}", line);
+    }
+}
+
+/// Runs each session of a replay log on a fresh calculator with the
+/// current settings and macros, and prints the stack and checksum it ends
+/// with. Lines that failed in the session fail again and are listed on
+/// stderr.
+fn run_replay(path: &std::path::Path, plain: bool) {
+    let text = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Could not read {}: {}", path.display(), e);
+        std::process::exit(1);
+    });
+    let sessions = replay::sessions(&text);
+    for (i, lines) in sessions.iter().enumerate() {
+        let mut app = App::new();
+        app.message.clear();
+        load_settings(&mut app);
+        if !app.message.is_empty() && i == 0 {
+            eprintln!("warning: {}", app.message);
+        }
+        if sessions.len() > 1 {
+            println!("session {} of {}", i + 1, sessions.len());
+        }
+        for (n, e) in replay::run(&mut app, lines) {
+            eprintln!("line {}: error: {}", n, e);
+        }
+        print_stack(&app, plain);
+        println!("checksum {:016x}", app.checksum());
     }
 }
 
-fn ui(f: &mut Frame, app: &App) {
-    let main_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
-        .split(f.size());
-
-    let left_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Min(5),
-            Constraint::Length(3),
-            Constraint::Length(3),
-        ])
-        .split(main_chunks[0]);
-
-    let title = Paragraph::new(format!("RPN Calculator {}", VERSION))
-        .block(Block::default().borders(Borders::ALL))
-        .style(Style::default().fg(Color::Cyan));
-    f.render_widget(title, left_chunks[0]);
-
-    let stack_items: Vec<ListItem> = app
-        .stack
-        .iter()
-        .enumerate()
-        .map(|(i, &val)| {
-            ListItem::new(Line::from(Span::raw(format!("{}: {}", i, format_number(val)))))
-        })
-        .collect();
-
-    let stack = List::new(stack_items)
-        .block(Block::default().borders(Borders::ALL).title("Stack"))
-        .style(Style::default().fg(Color::White));
-    f.render_widget(stack, left_chunks[1]);
-
-    let input = Paragraph::new(app.input.as_str())
-        .block(Block::default().borders(Borders::ALL).title("Input"))
-        .style(Style::default().fg(Color::Yellow));
-    f.render_widget(input, left_chunks[2]);
-
-    let message = Paragraph::new(app.message.as_str())
-        .block(Block::default().borders(Borders::ALL).title("Message"))
-        .style(Style::default().fg(Color::Green));
-    f.render_widget(message, left_chunks[3]);
-
-    let history_items: Vec<ListItem> = app
-        .calc_history
-        .iter()
-        .map(|calc| ListItem::new(Line::from(Span::raw(calc))))
-        .collect();
-
-    let history = List::new(history_items)
-        .block(Block::default().borders(Borders::ALL).title("History"))
-        .style(Style::default().fg(Color::Magenta));
-    f.render_widget(history, main_chunks[1]);
-
-    if app.show_help {
-        let version_str = format!("Version {}", VERSION);
-        let help_text = vec![
-            "RPN Calculator Help",
-            &version_str,
-            "",
-            "Basic Operations:",
-            "  +, -, *, /, ^, %",
-            "",
-            "Trigonometry:",
-            "  sin, cos, tan",
-            "  asin, acos, atan",
-            "",
-            "Logarithmic:",
-            "  ln, log, exp, 10x",
-            "",
-            "Other Math:",
-            "  sqrt, cbrt, abs, root",
-            "  inv (1/x), ! (factorial)",
-            "",
-            "Constants:",
-            "  pi, e",
-            "",
-            "Stack Operations:",
-            "  swap, drop, clear/clr, undo",
-            "",
-            "Press any key to close"
-        ];
-
-        let help_lines: Vec<Line> = help_text
-            .iter()
-            .map(|&text| Line::from(Span::raw(text)))
-            .collect();
-
-        let help_paragraph = Paragraph::new(help_lines)
-            .block(Block::default().borders(Borders::ALL).title("Help"))
-            .style(Style::default().fg(Color::White).bg(Color::Blue));
-
-        let area = f.size();
-        let popup_area = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Percentage(20),
-                Constraint::Percentage(60),
-                Constraint::Percentage(20),
-            ])
-            .split(area)[1];
-
-        let popup_area = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(20),
-                Constraint::Percentage(60),
-                Constraint::Percentage(20),
-            ])
-            .split(popup_area)[1];
-
-        // Clear background behind the modal
-        let clear_bg = Block::default().style(Style::default().bg(Color::Blue));
-        f.render_widget(clear_bg, popup_area);
-        
-        f.render_widget(Clear, popup_area);
-        f.render_widget(help_paragraph, popup_area);
-    }
-}
\ No newline at end of file
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|msg| {
+        eprintln!("{}\n{}", msg, USAGE);
+        std::process::exit(2);
+    });
+    if let Some(format) = options.reference {
+        // Only the built-in commands, so the output does not depend on who
+        // builds the package.
+        print!("{}", reference::render(&App::new(), format, VERSION, USAGE));
+        return Ok(());
+    }
+    if let Some(url) = &options.update_rates {
+        match update_rates(url) {
+            Ok(message) => println!("{}", message),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    if options.script.is_some() || options.eval.is_some() {
+        run_batch(&options);
+        return Ok(());
+    }
+    if let Some(path) = &options.replay {
+        run_replay(path, options.plain);
+        return Ok(());
+    }
+    if options.repl || options.plain {
+        let output = if options.plain { repl::Output::Plain } else { repl::Output::Columns };
+        let mut app = App::new();
+        app.message.clear();
+        app.version = VERSION;
+        app.report_dir = paths::reports_dir();
+        load_settings(&mut app);
+        load_history(&mut app);
+        start_replay_log(&mut app);
+        return Ok(repl::run(&mut app, output)?);
+    }
+    #[cfg(unix)]
+    if let Some(path) = &options.attach {
+        let role = if options.control { Role::Control } else { Role::View };
+        let mut client = session::Client::attach(path, role)
+            .map_err(|e| format!("Cannot attach to {}: {}", path.display(), e))?;
+        let mut app = App::new();
+        app.version = VERSION;
+        load_settings(&mut app);
+        return tui::with_terminal(|terminal| tui::run_attached(terminal, &mut app, &mut client));
+    }
+    #[cfg(unix)]
+    let mut server = match &options.share {
+        Some(path) => Some(
+            session::Server::bind(path)
+                .map_err(|e| format!("Cannot share on {}: {}", path.display(), e))?,
+        ),
+        None => None,
+    };
+    #[cfg(not(unix))]
+    let mut server: Option<session::Server> = None;
+
+    let mut app = App::new();
+    app.version = VERSION;
+    app.report_dir = paths::reports_dir();
+    load_settings(&mut app);
+    let notes_path = paths::notes_file();
+    if let Some(path) = &notes_path {
+        if let Err(e) = app.load_notes(path) {
+            app.message = format!("Could not load notes: {}", e);
+        }
+    }
+    load_history(&mut app);
+    start_replay_log(&mut app);
+    tui::with_terminal(|terminal| tui::run_app(terminal, &mut app, server.as_mut()))?;
+
+    if let Some(path) = &notes_path {
+        if let Err(e) = app.save_notes(path) {
+            eprintln!("Could not save notes to {}: {}", path.display(), e);
+        }
+    }
+
+    Ok(())
+}
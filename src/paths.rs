@@ -0,0 +1,63 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Per-user data directory (`$XDG_DATA_HOME/rpncalc`, falling back to
+/// `~/.local/share/rpncalc`, or `%APPDATA%\rpncalc` on Windows).
+pub fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir).join("rpncalc"));
+    }
+    if let Some(home) = env::var_os("HOME").filter(|h| !h.is_empty()) {
+        return Some(PathBuf::from(home).join(".local/share/rpncalc"));
+    }
+    env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("rpncalc"))
+}
+
+pub fn notes_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("notes.txt"))
+}
+
+pub fn history_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("history.log"))
+}
+
+/// Lines typed in `--repl`, for recalling with Up.
+pub fn repl_history_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("repl_history"))
+}
+
+/// Every line entered in recent sessions, for `--replay`.
+pub fn replay_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("replay.log"))
+}
+
+/// Where `report` saves bug report bundles.
+pub fn reports_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("reports"))
+}
+
+/// Per-user configuration directory (`$XDG_CONFIG_HOME/rpncalc`, falling back
+/// to `~/.config/rpncalc`, or `%APPDATA%\rpncalc` on Windows).
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir).join("rpncalc"));
+    }
+    if let Some(home) = env::var_os("HOME").filter(|h| !h.is_empty()) {
+        return Some(PathBuf::from(home).join(".config/rpncalc"));
+    }
+    env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("rpncalc"))
+}
+
+pub fn config_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config"))
+}
+
+/// Exchange rates for `convertccy`; see `rates`.
+pub fn rates_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("rates.toml"))
+}
+
+/// User macros and their tests; see `macros`.
+pub fn macros_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("macros"))
+}
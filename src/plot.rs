@@ -0,0 +1,137 @@
+//! What the plot pane shows: the numbers on the stack, or a function sampled
+//! over a range by `plotf`; and the data charts `chart` opens over the
+//! screen.
+
+use crate::{format_number, CalcError, Value};
+
+/// Most samples `plotf` takes; more would not show on a terminal anyway.
+pub const MAX_SAMPLES: usize = 2000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Plot {
+    /// The numbers on the stack against their position, following every
+    /// change.
+    Stack,
+    /// `expr` sampled at evenly spaced x. Points where it failed are left
+    /// out.
+    Function {
+        expr: String,
+        points: Vec<(f64, f64)>,
+    },
+}
+
+impl Plot {
+    pub fn title(&self) -> String {
+        match self {
+            Plot::Stack => "Plot: stack".to_string(),
+            Plot::Function { expr, .. } => format!("Plot: {}", expr),
+        }
+    }
+
+    /// The points to draw; matrices on the stack are skipped.
+    pub fn points(&self, stack: &[Value]) -> Vec<(f64, f64)> {
+        match self {
+            Plot::Stack => stack
+                .iter()
+                .enumerate()
+                .filter_map(|(i, v)| Some((i as f64, v.as_number()?)))
+                .filter(|(_, y)| y.is_finite())
+                .collect(),
+            Plot::Function { points, .. } => points.clone(),
+        }
+    }
+}
+
+/// `samples` evenly spaced values from `from` to `to`, both included.
+pub fn sample_points(from: f64, to: f64, samples: usize) -> Vec<f64> {
+    let step = (to - from) / (samples - 1) as f64;
+    (0..samples).map(|i| from + step * i as f64).collect()
+}
+
+/// The x and y ranges covering `points`, widened by one either way where
+/// all values are equal.
+pub fn bounds(points: &[(f64, f64)]) -> Option<([f64; 2], [f64; 2])> {
+    let range = |values: &mut dyn Iterator<Item = f64>| {
+        let (lo, hi) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+            (lo.min(v), hi.max(v))
+        });
+        if lo == hi {
+            [lo - 1.0, hi + 1.0]
+        } else {
+            [lo, hi]
+        }
+    };
+    if points.is_empty() {
+        return None;
+    }
+    Some((
+        range(&mut points.iter().map(|p| p.0)),
+        range(&mut points.iter().map(|p| p.1)),
+    ))
+}
+
+/// How `chart` draws its points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChartStyle {
+    /// Points joined in stack order.
+    Line,
+    Scatter,
+}
+
+impl ChartStyle {
+    pub fn from_name(name: &str) -> Option<ChartStyle> {
+        match name {
+            "line" => Some(ChartStyle::Line),
+            "scatter" => Some(ChartStyle::Scatter),
+            _ => None,
+        }
+    }
+}
+
+/// Stack data drawn in a popup by `chart` and `chartxy`. Unlike the plot
+/// pane it is a snapshot, so later changes to the stack do not move it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataChart {
+    pub style: ChartStyle,
+    /// Whether the stack held x,y pairs rather than y-values alone.
+    pub pairs: bool,
+    pub points: Vec<(f64, f64)>,
+}
+
+impl DataChart {
+    pub fn title(&self) -> String {
+        let (lo, hi) = self
+            .points
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| (lo.min(p.1), hi.max(p.1)));
+        format!(
+            "Chart: {} points, y min {} max {} (any key to close)",
+            self.points.len(),
+            format_number(lo),
+            format_number(hi)
+        )
+    }
+}
+
+/// The stack, bottom first, as points to chart: each number against its
+/// position, or with `pairs` alternately an x and its y. Every entry must be
+/// a finite number, so that pairs cannot silently shift.
+pub fn chart_points(stack: &[Value], pairs: bool) -> Result<Vec<(f64, f64)>, CalcError> {
+    let values = stack
+        .iter()
+        .map(|v| v.as_number().filter(|n| n.is_finite()))
+        .collect::<Option<Vec<f64>>>()
+        .ok_or_else(|| CalcError::InvalidArgument("chart needs finite numbers only".to_string()))?;
+    if pairs {
+        if values.len() < 2 || values.len() % 2 != 0 {
+            return Err(CalcError::InvalidArgument(
+                "chartxy needs an even number of values: x1 y1 x2 y2 …".to_string(),
+            ));
+        }
+        return Ok(values.chunks(2).map(|p| (p[0], p[1])).collect());
+    }
+    if values.is_empty() {
+        return Err(CalcError::EmptyStack);
+    }
+    Ok(values.iter().enumerate().map(|(i, &y)| (i as f64, y)).collect())
+}
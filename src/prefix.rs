@@ -0,0 +1,77 @@
+//! SI prefixes, for entering numbers such as `4.7k` or `100n` and for the
+//! `engpre` display, which shows `0.0000047` as `4.7 µ`.
+
+use crate::format_number;
+
+/// Each prefix and the power of ten it stands for. `E` (exa) is left out,
+/// since `2E` reads as the start of an exponent.
+const PREFIXES: [(&str, i32); 11] = [
+    ("a", -18),
+    ("f", -15),
+    ("p", -12),
+    ("n", -9),
+    ("µ", -6),
+    ("m", -3),
+    ("k", 3),
+    ("M", 6),
+    ("G", 9),
+    ("T", 12),
+    ("P", 15),
+];
+
+/// Other ways of typing a prefix: `u` and the Greek letter mu for micro.
+const ALIASES: [(&str, i32); 2] = [("u", -6), ("μ", -6)];
+
+/// Significant digits `format` shows, enough to hide rounding in the last
+/// place without losing entered digits.
+const DIGITS: usize = 12;
+
+/// Reads a plain decimal number followed by a prefix, such as `4.7k` or
+/// `-2.2M`; `None` for anything else.
+pub fn parse(text: &str) -> Option<f64> {
+    let (number, exponent) = PREFIXES.iter().chain(&ALIASES).find_map(|&(symbol, exponent)| {
+        text.strip_suffix(symbol).map(|number| (number, exponent))
+    })?;
+    let digits = number.strip_prefix(['-', '+']).unwrap_or(number);
+    let plain = digits.bytes().any(|b| b.is_ascii_digit())
+        && digits.bytes().all(|b| b.is_ascii_digit() || b == b'.')
+        && digits.matches('.').count() <= 1;
+    if !plain {
+        return None;
+    }
+    // Parsing with the exponent rounds once, where multiplying by a power
+    // of ten would round twice.
+    format!("{}e{}", number, exponent).parse().ok()
+}
+
+/// `val` scaled by the nearest prefix below it, e.g. `4.7 µ` or `470 k`.
+/// Numbers from 1 to 1000, zero and those beyond the prefixes are shown as
+/// `format_number` shows them.
+pub fn format(val: f64) -> String {
+    if val == 0.0 || !val.is_finite() {
+        return format_number(val);
+    }
+    let scientific = format!("{:.*e}", DIGITS - 1, val);
+    let Some((mantissa, exponent)) = scientific.split_once('e') else {
+        return format_number(val);
+    };
+    let Ok(exponent) = exponent.parse::<i32>() else {
+        return format_number(val);
+    };
+    let shift = exponent.rem_euclid(3) as usize;
+    let Some(&(symbol, _)) = PREFIXES.iter().find(|&&(_, e)| e == exponent - shift as i32) else {
+        return format_number(val);
+    };
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(m) => ("-", m),
+        None => ("", mantissa),
+    };
+    let digits = mantissa.replace('.', "");
+    let (whole, fraction) = digits.split_at(shift + 1);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{}{} {}", sign, whole, symbol)
+    } else {
+        format!("{}{}.{} {}", sign, whole, fraction, symbol)
+    }
+}
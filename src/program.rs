@@ -0,0 +1,87 @@
+//! Control flow in input lines and macros, Forth style:
+//!
+//! ```text
+//! dup 0 < if chs then                      absolute value
+//! dup 2 % 0 == if 2 / else 3 * 1 + then     one Collatz step
+//! 1 10 times 2 * loop                       2^10
+//! ```
+//!
+//! `if` takes the condition from the stack and runs its first branch when it
+//! is non-zero, the `else` branch otherwise. `n times … loop` takes n from the
+//! stack and runs the body n times.
+
+use crate::CalcError;
+
+/// Most tokens a line may execute, counting those run by the macros it calls,
+/// so that a runaway loop gives up rather than hanging the calculator.
+pub const MAX_STEPS: usize = 1_000_000;
+
+/// The same for a preview, which runs on every change to the input line.
+pub const MAX_PREVIEW_STEPS: usize = 10_000;
+
+/// Words that structure a program rather than run as commands.
+pub const KEYWORDS: [&str; 5] = ["if", "else", "then", "times", "loop"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node<'a> {
+    /// A number, command or inline argument.
+    Token(&'a str),
+    If {
+        then: Vec<Node<'a>>,
+        otherwise: Vec<Node<'a>>,
+    },
+    Times(Vec<Node<'a>>),
+}
+
+/// Groups `tokens` into nested blocks, checking that every `if` is closed by
+/// `then` and every `times` by `loop`.
+pub fn parse<'a>(tokens: &[&'a str]) -> Result<Vec<Node<'a>>, CalcError> {
+    let mut tokens = tokens.iter().copied();
+    let (block, end) = parse_block(&mut tokens)?;
+    match end {
+        None => Ok(block),
+        Some(word) => Err(unmatched(word)),
+    }
+}
+
+/// Reads nodes up to the end of the input or a closing keyword, which is
+/// returned.
+fn parse_block<'a>(
+    tokens: &mut impl Iterator<Item = &'a str>,
+) -> Result<(Vec<Node<'a>>, Option<&'a str>), CalcError> {
+    let mut block = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            "if" => {
+                let (then, end) = parse_block(tokens)?;
+                let otherwise = match end {
+                    Some("then") => Vec::new(),
+                    Some("else") => match parse_block(tokens)? {
+                        (otherwise, Some("then")) => otherwise,
+                        (_, Some(word)) => return Err(unmatched(word)),
+                        (_, None) => return Err(unclosed("if", "then")),
+                    },
+                    Some(word) => return Err(unmatched(word)),
+                    None => return Err(unclosed("if", "then")),
+                };
+                block.push(Node::If { then, otherwise });
+            }
+            "times" => match parse_block(tokens)? {
+                (body, Some("loop")) => block.push(Node::Times(body)),
+                (_, Some(word)) => return Err(unmatched(word)),
+                (_, None) => return Err(unclosed("times", "loop")),
+            },
+            "else" | "then" | "loop" => return Ok((block, Some(token))),
+            _ => block.push(Node::Token(token)),
+        }
+    }
+    Ok((block, None))
+}
+
+fn unmatched(word: &str) -> CalcError {
+    CalcError::InvalidArgument(format!("'{}' without a matching opening word", word))
+}
+
+fn unclosed(open: &str, close: &str) -> CalcError {
+    CalcError::InvalidArgument(format!("'{}' needs a closing '{}'", open, close))
+}
@@ -0,0 +1,243 @@
+//! Exchange rates for `convertccy`, read from a small TOML file:
+//!
+//! ```text
+//! base = "EUR"
+//! updated = "2024-03-01"
+//!
+//! [rates]
+//! USD = 1.0862
+//! GBP = 0.8571
+//! ```
+//!
+//! Each rate is how many units of that currency one unit of the base buys.
+//! The file is only read; `--update-rates` rewrites it from a JSON endpoint
+//! in builds with the `net` feature.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RateTable {
+    /// The currency the rates are quoted against, e.g. `EUR`.
+    pub base: String,
+    /// When the rates were taken, as written in the file.
+    pub updated: Option<String>,
+    /// Currency codes, upper case, and units per unit of the base, in file
+    /// order.
+    pub rates: Vec<(String, f64)>,
+}
+
+/// A currency code: three letters, in any case.
+fn valid_code(code: &str) -> bool {
+    code.len() == 3 && code.bytes().all(|b| b.is_ascii_alphabetic())
+}
+
+fn unquote(value: &str) -> Result<&str, String> {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(|| format!("expected a quoted string, not '{}'", value))
+}
+
+impl RateTable {
+    /// Reads rates from `text`. Lines that cannot be understood are skipped
+    /// and described in the returned warnings.
+    pub fn parse(text: &str) -> (RateTable, Vec<String>) {
+        let mut table = RateTable::default();
+        let mut warnings = Vec::new();
+        let mut in_rates = false;
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                in_rates = line == "[rates]";
+                if !in_rates {
+                    warnings.push(format!("line {}: unknown section {}", n + 1, line));
+                }
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                warnings.push(format!("line {}: expected key = value", n + 1));
+                continue;
+            };
+            let (key, value) = (key.trim().trim_matches('"'), value.trim());
+            let result = match key {
+                _ if in_rates => table.parse_rate(key, value),
+                "base" => unquote(value).and_then(|code| {
+                    if valid_code(code) {
+                        table.base = code.to_ascii_uppercase();
+                        Ok(())
+                    } else {
+                        Err(format!("'{}' is not a currency code", code))
+                    }
+                }),
+                "updated" => unquote(value).map(|v| table.updated = Some(v.to_string())),
+                _ => Err(format!("unknown setting '{}'", key)),
+            };
+            if let Err(e) = result {
+                warnings.push(format!("line {}: {}", n + 1, e));
+            }
+        }
+        if table.base.is_empty() && !table.rates.is_empty() {
+            warnings.push("no base currency; add e.g. base = \"EUR\"".to_string());
+        }
+        (table, warnings)
+    }
+
+    fn parse_rate(&mut self, code: &str, value: &str) -> Result<(), String> {
+        if !valid_code(code) {
+            return Err(format!("'{}' is not a currency code", code));
+        }
+        let rate = value
+            .parse::<f64>()
+            .ok()
+            .filter(|r| *r > 0.0 && r.is_finite())
+            .ok_or_else(|| format!("expected a positive rate for {}, not '{}'", code, value))?;
+        self.set(code, rate);
+        Ok(())
+    }
+
+    /// Sets the rate of `code`, replacing any earlier one.
+    pub fn set(&mut self, code: &str, rate: f64) {
+        let code = code.to_ascii_uppercase();
+        match self.rates.iter_mut().find(|(c, _)| *c == code) {
+            Some(entry) => entry.1 = rate,
+            None => self.rates.push((code, rate)),
+        }
+    }
+
+    /// Reads the rates file at `path`; a missing file has no rates.
+    pub fn load(path: &Path) -> io::Result<(RateTable, Vec<String>)> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(RateTable::parse(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok((RateTable::default(), Vec::new())),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+
+    /// Units of `code` per unit of the base; 1 for the base itself.
+    pub fn rate(&self, code: &str) -> Option<f64> {
+        let code = code.to_ascii_uppercase();
+        if code == self.base {
+            return Some(1.0);
+        }
+        self.rates.iter().find(|(c, _)| *c == code).map(|&(_, r)| r)
+    }
+
+    /// `amount` in currency `from` expressed in currency `to`.
+    pub fn convert(&self, amount: f64, from: &str, to: &str) -> Result<f64, String> {
+        if self.is_empty() {
+            return Err("No exchange rates loaded".to_string());
+        }
+        let rate = |code: &str| {
+            self.rate(code)
+                .ok_or_else(|| format!("No exchange rate for '{}'", code))
+        };
+        Ok(amount / rate(from)? * rate(to)?)
+    }
+
+    /// Reads rates from a JSON document such as
+    /// `{"base": "EUR", "date": "2024-03-01", "rates": {"USD": 1.0862}}`.
+    /// `base_code` and `conversion_rates` are accepted too. Without a date
+    /// the rates are taken as of `today`.
+    pub fn from_json(json: &str, today: &str) -> Result<RateTable, String> {
+        let base = json_string(json, "base")
+            .or_else(|| json_string(json, "base_code"))
+            .filter(|code| valid_code(code))
+            .ok_or("the JSON has no \"base\" currency code")?;
+        let object = json_object(json, "rates")
+            .or_else(|| json_object(json, "conversion_rates"))
+            .ok_or("the JSON has no \"rates\" object")?;
+        let mut table = RateTable {
+            base: base.to_ascii_uppercase(),
+            updated: Some(json_string(json, "date").unwrap_or_else(|| today.to_string())),
+            rates: Vec::new(),
+        };
+        for pair in object.split(',').filter(|p| !p.trim().is_empty()) {
+            let (code, rate) = pair
+                .split_once(':')
+                .ok_or_else(|| format!("expected \"CODE\": rate, not '{}'", pair.trim()))?;
+            table.parse_rate(code.trim().trim_matches('"'), rate.trim())?;
+        }
+        if table.is_empty() {
+            return Err("the JSON has no rates".to_string());
+        }
+        Ok(table)
+    }
+}
+
+/// Where `"key"` is followed by a colon, the text after the colon.
+fn json_field<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let quoted = format!("\"{}\"", key);
+    let mut rest = json;
+    while let Some(i) = rest.find(&quoted) {
+        rest = &rest[i + quoted.len()..];
+        if let Some(value) = rest.trim_start().strip_prefix(':') {
+            return Some(value.trim_start());
+        }
+    }
+    None
+}
+
+/// The string value of `key`, if it has one without escapes.
+fn json_string(json: &str, key: &str) -> Option<String> {
+    let value = json_field(json, key)?.strip_prefix('"')?;
+    Some(value[..value.find('"')?].to_string())
+}
+
+/// The inside of the flat object value of `key`.
+fn json_object<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let value = json_field(json, key)?.strip_prefix('{')?;
+    Some(&value[..value.find('}')?])
+}
+
+/// The table as a rates file, which `RateTable::parse` reads back.
+impl fmt::Display for RateTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Units of each currency per 1 {}", self.base)?;
+        writeln!(f, "base = \"{}\"", self.base)?;
+        if let Some(updated) = &self.updated {
+            writeln!(f, "updated = \"{}\"", updated)?;
+        }
+        writeln!(f, "\n[rates]")?;
+        for (code, rate) in &self.rates {
+            writeln!(f, "{} = {}", code, rate)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "net")]
+mod imp {
+    use std::process::Command;
+
+    /// Fetches `url` with `curl`, which handles HTTPS and proxies.
+    pub fn fetch(url: &str) -> Result<String, String> {
+        let output = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--location", "--max-time", "30"])
+            .arg(url)
+            .output()
+            .map_err(|e| format!("could not run curl: {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        String::from_utf8(output.stdout).map_err(|_| "the response is not UTF-8".to_string())
+    }
+}
+
+#[cfg(not(feature = "net"))]
+mod imp {
+    pub fn fetch(_url: &str) -> Result<String, String> {
+        Err("built without the net feature".to_string())
+    }
+}
+
+pub use imp::fetch;
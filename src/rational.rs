@@ -0,0 +1,165 @@
+//! Exact fractions for fraction mode. A fraction is kept in lowest terms
+//! with a positive denominator; when a result no longer fits in 64 bits the
+//! arithmetic gives `None`, and the calculation carries on in floating point.
+
+use std::fmt;
+
+/// Largest denominator `from_f64` tries, so that numbers which are not close
+/// to a simple fraction, such as π, stay floating point.
+pub const MAX_DENOMINATOR: i64 = 1_000_000;
+
+/// How close, relative to its size, a float must be to a fraction for
+/// `from_f64` to take it as that fraction: a few units in the last place,
+/// as left by rounding in `0.1 0.2 +` or `1 3 /`.
+const TOLERANCE: f64 = 4.0 * f64::EPSILON;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rational {
+    num: i64,
+    den: i64,
+}
+
+fn gcd(mut a: i128, mut b: i128) -> i128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.abs()
+}
+
+impl Rational {
+    /// `num / den` in lowest terms, or `None` for a zero denominator.
+    pub fn new(num: i64, den: i64) -> Option<Rational> {
+        Rational::reduce(num as i128, den as i128)
+    }
+
+    fn reduce(num: i128, den: i128) -> Option<Rational> {
+        if den == 0 {
+            return None;
+        }
+        let g = gcd(num, den);
+        let sign = den.signum();
+        Some(Rational {
+            num: (sign * num / g).try_into().ok()?,
+            den: (sign * den / g).try_into().ok()?,
+        })
+    }
+
+    pub fn numer(self) -> i64 {
+        self.num
+    }
+
+    pub fn denom(self) -> i64 {
+        self.den
+    }
+
+    /// A whole number held exactly, or `None` for fractions, infinities and
+    /// values too large to be exact.
+    pub fn from_integer(x: f64) -> Option<Rational> {
+        (x.fract() == 0.0 && x.abs() <= 9_007_199_254_740_992.0).then_some(Rational {
+            num: x as i64,
+            den: 1,
+        })
+    }
+
+    /// The simplest fraction that `x` is up to rounding, with a denominator
+    /// up to `MAX_DENOMINATOR`, found from its continued fraction; `None` if
+    /// there is none.
+    pub fn from_f64(x: f64) -> Option<Rational> {
+        if !x.is_finite() {
+            return None;
+        }
+        if let Some(whole) = Rational::from_integer(x) {
+            return Some(whole);
+        }
+        let target = x.abs();
+        // Successive convergents h/k, starting from 0/1 and 1/0.
+        let (mut h0, mut h1, mut k0, mut k1) = (0_i64, 1_i64, 1_i64, 0_i64);
+        let mut rest = target;
+        for _ in 0..64 {
+            let a = rest.floor();
+            if a >= i64::MAX as f64 {
+                return None;
+            }
+            let a = a as i64;
+            let h = a.checked_mul(h1)?.checked_add(h0)?;
+            let k = a.checked_mul(k1)?.checked_add(k0)?;
+            if k > MAX_DENOMINATOR {
+                return None;
+            }
+            (h0, h1, k0, k1) = (h1, h, k1, k);
+            if (h as f64 / k as f64 - target).abs() <= TOLERANCE * target {
+                return Rational::new(if x < 0.0 { -h } else { h }, k);
+            }
+            rest = 1.0 / (rest - a as f64);
+        }
+        None
+    }
+
+    /// Reads `n/d`, a whole number or plain decimal notation such as
+    /// `-1.25`, which is exactly `-5/4`.
+    pub fn parse(text: &str) -> Option<Rational> {
+        if let Some((num, den)) = text.split_once('/') {
+            return Rational::new(num.parse().ok()?, den.parse().ok()?);
+        }
+        let (int, frac) = text.split_once('.').unwrap_or((text, ""));
+        let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        let unsigned = int.strip_prefix(['-', '+']).unwrap_or(int);
+        if unsigned.is_empty() && frac.is_empty() || !digits(unsigned) || !digits(frac) {
+            return None;
+        }
+        let num: i64 = format!("{}{}", int, frac).parse().ok()?;
+        Rational::new(num, 10_i64.checked_pow(frac.len() as u32)?)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    pub fn is_integer(self) -> bool {
+        self.den == 1
+    }
+
+    pub fn checked_add(self, other: Rational) -> Option<Rational> {
+        let (a, b) = (self.wide(), other.wide());
+        Rational::reduce((a.0 * b.1).checked_add(b.0 * a.1)?, a.1 * b.1)
+    }
+
+    pub fn checked_sub(self, other: Rational) -> Option<Rational> {
+        self.checked_add(other.checked_neg()?)
+    }
+
+    pub fn checked_mul(self, other: Rational) -> Option<Rational> {
+        let (a, b) = (self.wide(), other.wide());
+        Rational::reduce(a.0 * b.0, a.1 * b.1)
+    }
+
+    /// The quotient, or `None` when `other` is zero.
+    pub fn checked_div(self, other: Rational) -> Option<Rational> {
+        let (a, b) = (self.wide(), other.wide());
+        Rational::reduce(a.0 * b.1, a.1 * b.0)
+    }
+
+    pub fn checked_neg(self) -> Option<Rational> {
+        Some(Rational {
+            num: self.num.checked_neg()?,
+            den: self.den,
+        })
+    }
+
+    /// Numerator and denominator widened so that a product of two cannot
+    /// overflow.
+    fn wide(self) -> (i128, i128) {
+        (self.num as i128, self.den as i128)
+    }
+}
+
+/// `n/d`, or just `n` for a whole number.
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
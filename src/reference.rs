@@ -0,0 +1,129 @@
+//! The command reference, generated from the registry so that packaged
+//! documentation lists exactly the commands a build understands.
+
+use crate::registry::Command;
+use crate::App;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Man,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "md" | "markdown" => Some(Format::Markdown),
+            "man" => Some(Format::Man),
+            _ => None,
+        }
+    }
+}
+
+/// The full reference for `app`'s commands, with each example run to show
+/// its result. `usage` is the command-line synopsis, one invocation per line.
+pub fn render(app: &App, format: Format, version: &str, usage: &str) -> String {
+    match format {
+        Format::Markdown => markdown(app, usage),
+        Format::Man => man(app, version, usage),
+    }
+}
+
+/// What is known about a command besides its usage, description and
+/// example.
+fn details(cmd: &Command) -> Vec<(&'static str, String)> {
+    let mut details = Vec::new();
+    if !cmd.aliases.is_empty() {
+        details.push(("Aliases", cmd.aliases.join(", ")));
+    }
+    details.push((
+        "Stack",
+        format!("consumes {}, produces {}", cmd.arity, cmd.produces),
+    ));
+    if let Some(domain) = cmd.domain {
+        details.push(("Domain", domain.to_string()));
+    }
+    for mode in cmd.modes {
+        details.push(("Mode", format!("depends on the {}", mode.name())));
+    }
+    if !cmd.undoable {
+        details.push(("Undo", "not recorded in undo history".to_string()));
+    }
+    details
+}
+
+/// The command's example and what it leaves on the stack.
+fn example(app: &App, cmd: &Command) -> Option<(&'static str, String)> {
+    cmd.example.map(|e| (e, app.example_result(e)))
+}
+
+/// The synopsis lines without the `Usage:` label and alignment.
+fn synopsis(usage: &str) -> impl Iterator<Item = &str> {
+    usage
+        .lines()
+        .map(|line| line.trim_start_matches("Usage: ").trim())
+}
+
+fn markdown(app: &App, usage: &str) -> String {
+    let mut out = String::from("# rpncalc command reference\n\n```\n");
+    for line in synopsis(usage) {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str("```\n");
+    for category in app.registry.categories() {
+        out.push_str(&format!("\n## {}\n", category));
+        for cmd in app.registry.commands().iter().filter(|c| c.category == category) {
+            out.push_str(&format!("\n### `{}`\n\n{}\n\n", cmd.name, cmd.description));
+            out.push_str(&format!("- Usage: `{}`\n", cmd.usage()));
+            for (label, text) in details(cmd) {
+                out.push_str(&format!("- {}: {}\n", label, text));
+            }
+            if let Some((input, result)) = example(app, cmd) {
+                out.push_str(&format!("- Example: `{}` → `{}`\n", input, result));
+            }
+        }
+    }
+    out
+}
+
+/// Escapes text for roff: backslashes and hyphens, and a leading control
+/// character that would otherwise start a request.
+fn roff(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with(['.', '\'']) {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+fn man(app: &App, version: &str, usage: &str) -> String {
+    let mut out = format!(
+        ".TH RPNCALC 1 \"\" \"rpncalc {}\" \"User Commands\"\n",
+        roff(version)
+    );
+    out.push_str(".SH NAME\nrpncalc \\- terminal RPN calculator\n.SH SYNOPSIS\n.nf\n");
+    for line in synopsis(usage) {
+        out.push_str(&roff(line));
+        out.push('\n');
+    }
+    out.push_str(".fi\n.SH COMMANDS\n");
+    for category in app.registry.categories() {
+        out.push_str(&format!(".SS {}\n", roff(category)));
+        for cmd in app.registry.commands().iter().filter(|c| c.category == category) {
+            out.push_str(&format!(".TP\n.B {}\n{}\n", roff(&cmd.usage()), roff(cmd.description)));
+            for (label, text) in details(cmd) {
+                out.push_str(&format!(".br\n{}: {}\n", label, roff(&text)));
+            }
+            if let Some((input, result)) = example(app, cmd) {
+                out.push_str(&format!(
+                    ".br\nExample: \\fB{}\\fR \\(-> {}\n",
+                    roff(input),
+                    roff(&result)
+                ));
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,1424 @@
+use crate::{
+    combinatorics, finance, special, value, AngleMode, App, OpOutcome, OpResult, TvmRegister,
+};
+
+// Conventional names for stack levels, deepest first, used in usage strings.
+const STACK_NAMES: [&str; 4] = ["t", "z", "y", "x"];
+
+/// Runs a command against the calculator. Handlers find any inline arguments
+/// in `App::args` and return what they did; calculations are recorded in the
+/// history by the caller, and an error leaves nothing to undo.
+pub type Handler = fn(&mut App) -> OpResult;
+
+/// Calculator modes whose current setting changes what a command does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Angle,
+}
+
+impl Mode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Angle => "angle mode",
+        }
+    }
+}
+
+/// A named calculator command along with the metadata used for help and
+/// completion.
+#[derive(Clone, Debug)]
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// Number of stack values the command consumes.
+    pub arity: usize,
+    /// Number of stack values the command pushes.
+    pub produces: usize,
+    /// Names of inline arguments that follow the command on the input line,
+    /// e.g. `explain sin`.
+    pub params: &'static [&'static str],
+    pub category: &'static str,
+    pub description: &'static str,
+    /// Restrictions on the operands, in words.
+    pub domain: Option<&'static str>,
+    pub modes: &'static [Mode],
+    /// Input tokens demonstrating the command; `explain` runs them to show
+    /// the result.
+    pub example: Option<&'static str>,
+    pub handler: Handler,
+    /// Whether `undo` can reverse the command.
+    pub undoable: bool,
+    /// Whether the command may change entries below its `arity` operands,
+    /// so that `undo` has to keep the whole stack rather than the operands.
+    pub whole_stack: bool,
+    /// Whether the status bar may run the command to preview a result. Off
+    /// for commands that touch files or the clipboard, or may take long.
+    pub previewable: bool,
+    /// Whether the command is a calculation that can wait for a placeholder
+    /// among its operands to be bound; see `bind`. Off for commands that
+    /// read or change state beside the stack, or take a count of operands.
+    pub deferrable: bool,
+    /// Whether the command reads or writes files or the clipboard. The
+    /// replay log records what it did, and a replay does that instead.
+    pub external: bool,
+}
+
+impl Command {
+    pub fn new(
+        name: &'static str,
+        arity: usize,
+        category: &'static str,
+        description: &'static str,
+        handler: Handler,
+    ) -> Command {
+        Command {
+            name,
+            aliases: &[],
+            arity,
+            produces: 1,
+            params: &[],
+            category,
+            description,
+            domain: None,
+            modes: &[],
+            example: None,
+            handler,
+            undoable: true,
+            whole_stack: false,
+            previewable: true,
+            deferrable: true,
+            external: false,
+        }
+    }
+
+    pub fn produces(mut self, produces: usize) -> Command {
+        self.produces = produces;
+        self
+    }
+
+    pub fn params(mut self, params: &'static [&'static str]) -> Command {
+        self.params = params;
+        self
+    }
+
+    pub fn domain(mut self, domain: &'static str) -> Command {
+        self.domain = Some(domain);
+        self
+    }
+
+    pub fn modes(mut self, modes: &'static [Mode]) -> Command {
+        self.modes = modes;
+        self
+    }
+
+    pub fn example(mut self, example: &'static str) -> Command {
+        self.example = Some(example);
+        self
+    }
+
+    pub fn aliases(mut self, aliases: &'static [&'static str]) -> Command {
+        self.aliases = aliases;
+        self
+    }
+
+    pub fn no_undo(mut self) -> Command {
+        self.undoable = false;
+        self
+    }
+
+    pub fn whole_stack(mut self) -> Command {
+        self.whole_stack = true;
+        self
+    }
+
+    pub fn no_preview(mut self) -> Command {
+        self.previewable = false;
+        self
+    }
+
+    pub fn no_defer(mut self) -> Command {
+        self.deferrable = false;
+        self
+    }
+
+    pub fn external(mut self) -> Command {
+        self.external = true;
+        self
+    }
+
+    /// All spellings that invoke this command, primary name first.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        std::iter::once(self.name).chain(self.aliases.iter().copied())
+    }
+
+    pub fn matches(&self, token: &str) -> bool {
+        self.names().any(|n| n == token)
+    }
+
+    /// How the command is typed, e.g. `y x logb` or `explain <command>`.
+    pub fn usage(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        if self.arity > 0 {
+            parts.push(STACK_NAMES[STACK_NAMES.len().saturating_sub(self.arity)..].join(" "));
+        }
+        parts.push(self.name.to_string());
+        parts.extend(self.params.iter().map(|p| format!("<{}>", p)));
+        parts.join(" ")
+    }
+}
+
+/// The set of commands understood by `App::execute_command`.
+#[derive(Clone, Debug, Default)]
+pub struct CommandRegistry {
+    commands: Vec<Command>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> CommandRegistry {
+        CommandRegistry::default()
+    }
+
+    /// A registry pre-populated with every built-in command.
+    pub fn with_builtins() -> CommandRegistry {
+        let mut registry = CommandRegistry::new();
+        for cmd in builtins() {
+            registry.register(cmd);
+        }
+        registry
+    }
+
+    /// Adds a command, replacing any existing command with the same name.
+    pub fn register(&mut self, cmd: Command) {
+        if let Some(existing) = self.commands.iter_mut().find(|c| c.name == cmd.name) {
+            *existing = cmd;
+        } else {
+            self.commands.push(cmd);
+        }
+    }
+
+    pub fn lookup(&self, token: &str) -> Option<&Command> {
+        self.commands.iter().find(|c| c.matches(token))
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// Categories in the order they were first registered.
+    pub fn categories(&self) -> Vec<&'static str> {
+        let mut categories = Vec::new();
+        for cmd in &self.commands {
+            if !categories.contains(&cmd.category) {
+                categories.push(cmd.category);
+            }
+        }
+        categories
+    }
+
+    /// Every command name or alias starting with `prefix`, sorted.
+    pub fn complete(&self, prefix: &str) -> Vec<&'static str> {
+        let mut matches: Vec<&'static str> = self
+            .commands
+            .iter()
+            .flat_map(|c| c.names())
+            .filter(|n| n.starts_with(prefix))
+            .collect();
+        matches.sort_unstable();
+        matches.dedup();
+        matches
+    }
+
+    /// Help text grouped by category, one line per command.
+    pub fn help_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for category in self.categories() {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(format!("{}:", category));
+            for cmd in self.commands.iter().filter(|c| c.category == category) {
+                let names = cmd.names().collect::<Vec<_>>().join("/");
+                lines.push(format!("  {:<20}{}", names, cmd.description));
+            }
+        }
+        lines
+    }
+}
+
+fn builtins() -> Vec<Command> {
+    vec![
+        Command::new("+", 2, "Arithmetic", "Add", |app| {
+            app.value_binary_op(value::add, "+")
+        })
+        .domain("matrices must have the same shape")
+        .example("3 4 +"),
+        Command::new("-", 2, "Arithmetic", "Subtract", |app| {
+            app.value_binary_op(value::sub, "-")
+        })
+        .domain("matrices must have the same shape")
+        .example("10 3 -"),
+        Command::new(
+            "*",
+            2,
+            "Arithmetic",
+            "Multiply (matrix product for two matrices)",
+            |app| app.value_binary_op(value::mul, "*"),
+        )
+        .domain("for two matrices, y has as many columns as x has rows")
+        .example("6 7 *"),
+        Command::new("/", 2, "Arithmetic", "Divide", App::divide)
+            .domain("x must be a non-zero number")
+            .example("12 4 /"),
+        Command::new(
+            "tofrac",
+            1,
+            "Arithmetic",
+            "Convert x to the fraction it is up to rounding, denominator up to 1000000",
+            App::to_fraction,
+        )
+        .domain("x is such a fraction up to rounding")
+        .example("0.75 tofrac"),
+        Command::new(
+            "tofloat",
+            1,
+            "Arithmetic",
+            "Convert a fraction or money amount to a float",
+            App::to_float,
+        )
+        .example("1/3 tofloat"),
+        Command::new("^", 2, "Arithmetic", "Power (y^x)", |app| {
+            app.binary_op(|a, b| a.powf(b), "^")
+        })
+        .aliases(&["pow"])
+        .example("2 10 ^"),
+        Command::new("%", 2, "Arithmetic", "Remainder", |app| {
+            app.binary_op(|a, b| a % b, "%")
+        })
+        .aliases(&["mod"])
+        .example("10 3 %"),
+        Command::new(
+            "~=",
+            2,
+            "Arithmetic",
+            "1 if y and x are equal within the tolerance, else 0",
+            App::approx_equal,
+        )
+        .example("0.1 0.2 + 0.3 ~="),
+        Command::new("==", 2, "Arithmetic", "1 if y equals x exactly, else 0", |app| {
+            app.compare(|y, x| y == x, "==")
+        })
+        .example("0.5 0.25 2 * =="),
+        Command::new("<", 2, "Arithmetic", "1 if y is less than x, else 0", |app| {
+            app.compare(|y, x| y < x, "<")
+        })
+        .example("2 3 <"),
+        Command::new(">", 2, "Arithmetic", "1 if y is greater than x, else 0", |app| {
+            app.compare(|y, x| y > x, ">")
+        })
+        .example("2 3 >"),
+        Command::new("<=", 2, "Arithmetic", "1 if y is at most x, else 0", |app| {
+            app.compare(|y, x| y <= x, "<=")
+        })
+        .example("3 3 <="),
+        Command::new(">=", 2, "Arithmetic", "1 if y is at least x, else 0", |app| {
+            app.compare(|y, x| y >= x, ">=")
+        })
+        .example("2 3 >="),
+        Command::new("sin", 1, "Trigonometry", "Sine", |app| {
+            let mode = app.angle_mode;
+            app.unary_op(|a| mode.to_radians(a).sin(), "sin")
+        })
+        .modes(&[Mode::Angle])
+        .example("30 sin"),
+        Command::new("cos", 1, "Trigonometry", "Cosine", |app| {
+            let mode = app.angle_mode;
+            app.unary_op(|a| mode.to_radians(a).cos(), "cos")
+        })
+        .modes(&[Mode::Angle])
+        .example("60 cos"),
+        Command::new("tan", 1, "Trigonometry", "Tangent", |app| {
+            let mode = app.angle_mode;
+            app.unary_op(|a| mode.to_radians(a).tan(), "tan")
+        })
+        .modes(&[Mode::Angle])
+        .example("45 tan"),
+        Command::new("asin", 1, "Trigonometry", "Arcsine", |app| {
+            let mode = app.angle_mode;
+            app.unary_op(|a| mode.from_radians(a.asin()), "asin")
+        })
+        .domain("-1 <= x <= 1")
+        .modes(&[Mode::Angle])
+        .example("0.5 asin"),
+        Command::new("acos", 1, "Trigonometry", "Arccosine", |app| {
+            let mode = app.angle_mode;
+            app.unary_op(|a| mode.from_radians(a.acos()), "acos")
+        })
+        .domain("-1 <= x <= 1")
+        .modes(&[Mode::Angle])
+        .example("0.5 acos"),
+        Command::new("atan", 1, "Trigonometry", "Arctangent", |app| {
+            let mode = app.angle_mode;
+            app.unary_op(|a| mode.from_radians(a.atan()), "atan")
+        })
+        .modes(&[Mode::Angle])
+        .example("1 atan"),
+        Command::new(
+            "atan2",
+            2,
+            "Trigonometry",
+            "Angle of the point (x, y)",
+            |app| {
+                let mode = app.angle_mode;
+                app.binary_op(|y, x| mode.from_radians(y.atan2(x)), "atan2")
+            },
+        )
+        .modes(&[Mode::Angle])
+        .example("1 -1 atan2"),
+        Command::new("deg", 0, "Trigonometry", "Angles in degrees", |app| {
+            app.set_angle_mode(AngleMode::Degrees)
+        })
+        .produces(0)
+        .no_undo(),
+        Command::new("rad", 0, "Trigonometry", "Angles in radians", |app| {
+            app.set_angle_mode(AngleMode::Radians)
+        })
+        .produces(0)
+        .no_undo(),
+        Command::new("grad", 0, "Trigonometry", "Angles in gradians", |app| {
+            app.set_angle_mode(AngleMode::Gradians)
+        })
+        .produces(0)
+        .no_undo(),
+        Command::new("sinh", 1, "Hyperbolic", "Hyperbolic sine", |app| {
+            app.unary_op(|a| a.sinh(), "sinh")
+        })
+        .example("1 sinh"),
+        Command::new("cosh", 1, "Hyperbolic", "Hyperbolic cosine", |app| {
+            app.unary_op(|a| a.cosh(), "cosh")
+        })
+        .example("1 cosh"),
+        Command::new("tanh", 1, "Hyperbolic", "Hyperbolic tangent", |app| {
+            app.unary_op(|a| a.tanh(), "tanh")
+        })
+        .example("1 tanh"),
+        Command::new("asinh", 1, "Hyperbolic", "Inverse hyperbolic sine", |app| {
+            app.unary_op(|a| a.asinh(), "asinh")
+        })
+        .example("1 asinh"),
+        Command::new(
+            "acosh",
+            1,
+            "Hyperbolic",
+            "Inverse hyperbolic cosine",
+            |app| {
+                app.checked_unary_op(
+                    |a| {
+                        if a >= 1.0 {
+                            Ok(a.acosh())
+                        } else {
+                            Err("acosh needs x >= 1")
+                        }
+                    },
+                    "acosh",
+                )
+            },
+        )
+        .domain("x >= 1")
+        .example("2 acosh"),
+        Command::new(
+            "atanh",
+            1,
+            "Hyperbolic",
+            "Inverse hyperbolic tangent",
+            |app| {
+                app.checked_unary_op(
+                    |a| {
+                        if a.abs() < 1.0 {
+                            Ok(a.atanh())
+                        } else {
+                            Err("atanh needs -1 < x < 1")
+                        }
+                    },
+                    "atanh",
+                )
+            },
+        )
+        .domain("-1 < x < 1")
+        .example("0.5 atanh"),
+        Command::new("ln", 1, "Logarithmic", "Natural logarithm", |app| {
+            app.unary_op(|a| a.ln(), "ln")
+        })
+        .domain("x > 0")
+        .example("10 ln"),
+        Command::new("log", 1, "Logarithmic", "Base-10 logarithm", |app| {
+            app.unary_op(|a| a.log10(), "log")
+        })
+        .domain("x > 0")
+        .example("1000 log"),
+        Command::new("exp", 1, "Logarithmic", "e^x", |app| {
+            app.unary_op(|a| a.exp(), "exp")
+        })
+        .example("1 exp"),
+        Command::new("10x", 1, "Logarithmic", "10^x", |app| {
+            app.unary_op(|a| 10.0_f64.powf(a), "10^x")
+        })
+        .aliases(&["pow10", "10^x", "alog"])
+        .example("3 10x"),
+        Command::new("exp2", 1, "Logarithmic", "2^x", |app| {
+            app.unary_op(|a| a.exp2(), "2^x")
+        })
+        .aliases(&["2^x"])
+        .example("10 exp2"),
+        Command::new(
+            "logb",
+            2,
+            "Logarithmic",
+            "Logarithm of y in base x",
+            |app| {
+                app.checked_binary_op(
+                    |x, b| {
+                        if x <= 0.0 || b <= 0.0 || b == 1.0 {
+                            Err("logb needs y > 0 and a positive base other than 1")
+                        } else {
+                            Ok(x.ln() / b.ln())
+                        }
+                    },
+                    "logb",
+                )
+            },
+        )
+        .domain("y > 0; base x > 0 and not 1")
+        .example("8 2 logb"),
+        Command::new("sqrt", 1, "Other Math", "Square root", |app| {
+            app.value_unary_op(value::sqrt, "sqrt")
+        })
+        .domain("x >= 0")
+        .example("16 sqrt"),
+        Command::new("cbrt", 1, "Other Math", "Cube root", |app| {
+            app.unary_op(|a| a.cbrt(), "cbrt")
+        })
+        .example("27 cbrt"),
+        Command::new("chs", 1, "Other Math", "Change sign", |app| {
+            app.value_unary_op(value::neg, "chs")
+        })
+        .aliases(&["neg"])
+        .example("5 chs"),
+        Command::new("abs", 1, "Other Math", "Absolute value", |app| {
+            app.unary_op(|a| a.abs(), "abs")
+        })
+        .example("-5 abs"),
+        Command::new("pow2", 1, "Other Math", "Square (x^2)", |app| {
+            app.unary_op(|a| a * a, "sq")
+        })
+        .aliases(&["sq"])
+        .example("12 pow2"),
+        Command::new("hypot", 2, "Other Math", "sqrt(y^2 + x^2)", |app| {
+            app.binary_op(|a, b| a.hypot(b), "hypot")
+        })
+        .example("3 4 hypot"),
+        Command::new("gamma", 1, "Other Math", "Gamma function", |app| {
+            app.checked_unary_op(special::gamma, "gamma")
+        })
+        .domain("x is not 0 or a negative integer")
+        .example("5 gamma"),
+        Command::new("lgamma", 1, "Other Math", "Log of |gamma|", |app| {
+            app.checked_unary_op(special::ln_gamma, "lgamma")
+        })
+        .domain("x is not 0 or a negative integer")
+        .example("100 lgamma"),
+        Command::new("root", 2, "Other Math", "x-th root of y", App::root)
+            .aliases(&["xroot"])
+            .domain("x must not be 0")
+            .example("27 3 root"),
+        Command::new(
+            "inv",
+            1,
+            "Other Math",
+            "Reciprocal (1/x), or inverse of a matrix",
+            App::reciprocal,
+        )
+        .domain("x must not be 0; a matrix must be square and non-singular")
+        .example("4 inv"),
+        Command::new("!", 1, "Other Math", "Factorial", App::factorial)
+            .aliases(&["fact"])
+            .domain("x is a non-negative integer")
+            .example("5 !"),
+        Command::new("nPr", 2, "Combinatorics", "Permutations of x items from y", |app| {
+            app.checked_binary_op(combinatorics::permutations, "nPr")
+        })
+        .aliases(&["npr", "perm"])
+        .domain("whole numbers with 0 <= x <= y")
+        .example("5 2 nPr"),
+        Command::new("nCr", 2, "Combinatorics", "Combinations of x items from y", |app| {
+            app.checked_binary_op(combinatorics::combinations, "nCr")
+        })
+        .aliases(&["ncr", "comb"])
+        .domain("whole numbers with 0 <= x <= y")
+        .example("52 5 nCr"),
+        Command::new("gcd", 2, "Combinatorics", "Greatest common divisor", |app| {
+            app.checked_binary_op(combinatorics::gcd, "gcd")
+        })
+        .domain("integers no larger than 2^53")
+        .example("48 18 gcd"),
+        Command::new("lcm", 2, "Combinatorics", "Least common multiple", |app| {
+            app.checked_binary_op(combinatorics::lcm, "lcm")
+        })
+        .domain("integers no larger than 2^53")
+        .example("4 6 lcm"),
+        Command::new("fib", 1, "Combinatorics", "x-th Fibonacci number", |app| {
+            app.checked_unary_op(combinatorics::fibonacci, "fib")
+        })
+        .domain("x is a non-negative integer")
+        .example("10 fib"),
+        Command::new(
+            "round",
+            1,
+            "Rounding",
+            "Round to the nearest integer, halves away from zero",
+            |app| app.rounding_op(f64::round, "round"),
+        )
+        .example("2.5 round"),
+        Command::new("floor", 1, "Rounding", "Largest integer not above x", |app| {
+            app.rounding_op(f64::floor, "floor")
+        })
+        .example("-2.5 floor"),
+        Command::new("ceil", 1, "Rounding", "Smallest integer not below x", |app| {
+            app.rounding_op(f64::ceil, "ceil")
+        })
+        .example("2.1 ceil"),
+        Command::new("trunc", 1, "Rounding", "Integer part, toward zero", |app| {
+            app.rounding_op(f64::trunc, "trunc")
+        })
+        .aliases(&["ip"])
+        .example("-2.7 trunc"),
+        Command::new("frac", 1, "Rounding", "Fractional part (x - trunc x)", |app| {
+            app.rounding_op(f64::fract, "frac")
+        })
+        .example("3.25 frac"),
+        Command::new("rnd", 1, "Rounding", "Round to n decimal places", App::round_to)
+            .params(&["n"])
+            .domain("-15 <= n <= 15; negative n rounds to tens, hundreds, …")
+            .example("3.14159 rnd 2"),
+        Command::new("pct", 2, "Finance", "x% of y (keeps y)", App::percent)
+            .produces(2)
+            .example("200 15 pct"),
+        Command::new("pctchg", 2, "Finance", "% change from y to x", |app| {
+            app.checked_binary_op(
+                |y, x| {
+                    if y == 0.0 {
+                        Err("pctchg needs a non-zero base")
+                    } else {
+                        Ok((x - y) / y * 100.0)
+                    }
+                },
+                "pctchg",
+            )
+        })
+        .domain("y must not be 0")
+        .example("80 100 pctchg"),
+        Command::new("markup", 2, "Finance", "y plus x percent", |app| {
+            app.binary_op(|cost, pct| cost * (1.0 + pct / 100.0), "markup")
+        })
+        .example("40 25 markup"),
+        Command::new(
+            "subtotal",
+            0,
+            "Finance",
+            "Sum of the numbers typed since the last clear or subtotal (not imported or pasted)",
+            App::subtotal,
+        )
+        .example("12.5 drop 3.25 drop subtotal"),
+        Command::new(
+            "money",
+            0,
+            "Finance",
+            "Money mode: exact decimal arithmetic, shown to the cent",
+            |app| app.set_money_mode(true),
+        )
+        .produces(0)
+        .whole_stack()
+        .example("money 0.1 0.2 +"),
+        Command::new(
+            "fraction",
+            0,
+            "Finance",
+            "Fraction mode: exact fractions for + - * /, e.g. 1 3 / shows 1/3",
+            App::set_frac_mode,
+        )
+        .produces(0)
+        .whole_stack()
+        .example("fraction 1 3 / 1 6 / +"),
+        Command::new(
+            "float",
+            0,
+            "Finance",
+            "Leave money or fraction mode, converting to floating point",
+            |app| app.set_money_mode(false),
+        )
+        .produces(0)
+        .whole_stack(),
+        Command::new("setn", 1, "Finance", "Store x in n (periods)", |app| {
+            app.store_tvm(TvmRegister::N)
+        })
+        .produces(0),
+        Command::new(
+            "seti",
+            1,
+            "Finance",
+            "Store x in i (% rate per period)",
+            |app| app.store_tvm(TvmRegister::I),
+        )
+        .produces(0),
+        Command::new("setpv", 1, "Finance", "Store x in PV", |app| {
+            app.store_tvm(TvmRegister::Pv)
+        })
+        .produces(0),
+        Command::new("setpmt", 1, "Finance", "Store x in PMT", |app| {
+            app.store_tvm(TvmRegister::Pmt)
+        })
+        .produces(0),
+        Command::new("setfv", 1, "Finance", "Store x in FV", |app| {
+            app.store_tvm(TvmRegister::Fv)
+        })
+        .produces(0),
+        Command::new(
+            "pv",
+            0,
+            "Finance",
+            "Solve for PV from n, i, PMT, FV",
+            |app| app.solve_tvm(TvmRegister::Pv),
+        )
+        .example("12 setn 1 seti -100 setpmt 0 setfv pv"),
+        Command::new(
+            "pmt",
+            0,
+            "Finance",
+            "Solve for PMT from n, i, PV, FV",
+            |app| app.solve_tvm(TvmRegister::Pmt),
+        )
+        .domain("n > 0")
+        .example("360 setn 0.5 seti 200000 setpv 0 setfv pmt"),
+        Command::new(
+            "fv",
+            0,
+            "Finance",
+            "Solve for FV from n, i, PV, PMT",
+            |app| app.solve_tvm(TvmRegister::Fv),
+        )
+        .example("10 setn 5 seti -1000 setpv 0 setpmt fv"),
+        Command::new(
+            "tvm",
+            0,
+            "Finance",
+            "Show the TVM registers (out = negative)",
+            |app| Ok(OpOutcome::info(app.tvm.summary())),
+        )
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "npv",
+            1,
+            "Finance",
+            "NPV at rate i of the N cash flows below x = N",
+            |app| {
+                let rate = app.tvm.i;
+                app.cash_flow_op(|flows| Ok(finance::npv(rate, flows)), "npv")
+            },
+        )
+        .domain("x is a positive integer N with N values below it")
+        .whole_stack()
+        .no_defer()
+        .example("10 seti -100 60 60 3 npv"),
+        Command::new(
+            "irr",
+            1,
+            "Finance",
+            "IRR % of the N cash flows below x = N",
+            |app| app.cash_flow_op(finance::irr, "irr"),
+        )
+        .domain("x = N as for npv; flows must change sign")
+        .whole_stack()
+        .no_defer()
+        .example("-100 60 60 3 irr"),
+        Command::new(
+            "convertccy",
+            1,
+            "Finance",
+            "Convert x between currencies using the rates file",
+            App::convert_currency,
+        )
+        .params(&["from", "to"]),
+        Command::new(
+            "rates",
+            0,
+            "Finance",
+            "Show the loaded exchange rates and their date",
+            App::show_rates,
+        )
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "vec",
+            1,
+            "Matrix",
+            "Collect the N numbers below x = N into a vector",
+            App::collect_vector,
+        )
+        .domain("x is a positive integer N with N numbers below it")
+        .whole_stack()
+        .no_defer()
+        .example("1 2 3 3 vec"),
+        Command::new("dot", 2, "Matrix", "Dot product of two vectors", |app| {
+            app.value_binary_op(value::dot, "dot")
+        })
+        .domain("vectors of the same length")
+        .example("[1 2 3] [4 5 6] dot"),
+        Command::new(
+            "cross",
+            2,
+            "Matrix",
+            "Cross product of two vectors",
+            |app| app.value_binary_op(value::cross, "cross"),
+        )
+        .domain("3-element vectors")
+        .example("[1 0 0] [0 1 0] cross"),
+        Command::new(
+            "norm",
+            1,
+            "Matrix",
+            "Length of a vector (Frobenius norm of a matrix)",
+            |app| app.value_unary_op(value::norm, "norm"),
+        )
+        .domain("a vector or matrix")
+        .example("[3 4] norm"),
+        Command::new("transpose", 1, "Matrix", "Transpose a matrix", |app| {
+            app.value_unary_op(value::transpose, "transpose")
+        })
+        .aliases(&["trn"])
+        .domain("a vector or matrix")
+        .example("[1 2; 3 4] transpose"),
+        Command::new("det", 1, "Matrix", "Determinant", |app| {
+            app.value_unary_op(value::det, "det")
+        })
+        .domain("a square matrix")
+        .example("[1 2; 3 4] det"),
+        Command::new(
+            "freq",
+            0,
+            "Statistics",
+            "Pop the whole stack and show how often each value occurs",
+            App::frequency,
+        )
+        .produces(0)
+        .whole_stack(),
+        Command::new(
+            "plot",
+            0,
+            "Statistics",
+            "Toggle a pane plotting the stack values",
+            App::toggle_plot,
+        )
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "plotf",
+            0,
+            "Statistics",
+            "Plot an expression of x, e.g. plotf \"x dup *\" 0 10 100",
+            App::plot_function,
+        )
+        .params(&["expr", "from", "to", "samples"])
+        .produces(0)
+        .no_undo()
+        .no_preview(),
+        Command::new(
+            "chart",
+            0,
+            "Statistics",
+            "Chart the stack values as a line or scatter, e.g. chart scatter",
+            App::chart_stack,
+        )
+        .params(&["style"])
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "chartxy",
+            0,
+            "Statistics",
+            "Chart the stack as x,y pairs from the bottom, e.g. chartxy line",
+            App::chart_pairs,
+        )
+        .params(&["style"])
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "rand",
+            0,
+            "Statistics",
+            "Push a uniform random number in [0, 1)",
+            |app| app.push_random(false),
+        )
+        .example("rand"),
+        Command::new(
+            "randn",
+            0,
+            "Statistics",
+            "Push a standard normal random number",
+            |app| app.push_random(true),
+        )
+        .example("randn"),
+        Command::new(
+            "seed",
+            0,
+            "Statistics",
+            "Seed the random numbers, making simulations repeatable",
+            App::seed_random,
+        )
+        .params(&["n"])
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "simulate",
+            0,
+            "Statistics",
+            "Run a macro n times on the stack, pushing each top result",
+            App::simulate,
+        )
+        .params(&["n", "macro"])
+        .produces(0)
+        .no_preview(),
+        Command::new(
+            "sadd",
+            2,
+            "Statistics",
+            "Add the pair (x, y), entered in that order, to the registers; leaves n",
+            |app| app.accumulate_pair(false),
+        )
+        .aliases(&["Σ+"])
+        .no_defer()
+        .example("1 2 sadd"),
+        Command::new(
+            "ssub",
+            2,
+            "Statistics",
+            "Take the pair (x, y) back out of the statistical registers",
+            |app| app.accumulate_pair(true),
+        )
+        .aliases(&["Σ-"])
+        .no_defer(),
+        Command::new(
+            "linreg",
+            0,
+            "Statistics",
+            "Push the slope and intercept of the least-squares line",
+            App::linear_regression,
+        )
+        .produces(2)
+        .example("1 3 sadd 2 5 sadd linreg"),
+        Command::new(
+            "corr",
+            0,
+            "Statistics",
+            "Push the correlation coefficient of the pairs",
+            App::correlation,
+        )
+        .example("1 3 sadd 2 5 sadd 3 6 sadd corr"),
+        Command::new(
+            "predict",
+            1,
+            "Statistics",
+            "The regression line's y at x",
+            App::predict,
+        )
+        .no_defer()
+        .example("1 3 sadd 2 5 sadd 4 predict"),
+        Command::new(
+            "sclear",
+            0,
+            "Statistics",
+            "Clear the statistical registers",
+            App::clear_pairs,
+        )
+        .produces(0),
+        Command::new(
+            "sreg",
+            0,
+            "Statistics",
+            "Show the statistical registers",
+            |app| Ok(OpOutcome::info(app.pairs.summary())),
+        )
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "outliers",
+            0,
+            "Statistics",
+            "Highlight values k std devs (or e.g. 1.5iqr) from the center",
+            App::flag_outliers,
+        )
+        .params(&["k"])
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "solve",
+            1,
+            "Calculus",
+            "Find x where a macro, as f(x), is zero, starting from the guess in x",
+            App::solve,
+        )
+        .params(&["macro"])
+        .no_preview()
+        .example("2 solve \"dup * 2 -\""),
+        Command::new(
+            "integ",
+            2,
+            "Calculus",
+            "Integrate a macro, as f(x), from y to x",
+            App::integrate,
+        )
+        .params(&["macro"])
+        .no_preview()
+        .example("0 3 integ \"dup *\""),
+        Command::new(
+            "deriv",
+            1,
+            "Calculus",
+            "Slope of a macro, as f(x), at x",
+            App::differentiate,
+        )
+        .params(&["macro"])
+        .no_preview()
+        .example("2 deriv \"dup dup * *\""),
+        Command::new(
+            "ddays",
+            2,
+            "Dates",
+            "Days from date y to date x",
+            |app| app.value_binary_op(value::ddays, "ddays"),
+        )
+        .example("2024-01-01 2024-03-01 ddays"),
+        Command::new(
+            "dadd",
+            2,
+            "Dates",
+            "Add x days to date y",
+            |app| app.value_binary_op(value::dadd, "dadd"),
+        )
+        .example("2024-02-20 10 dadd"),
+        Command::new(
+            "dow",
+            1,
+            "Dates",
+            "Day of the week, 1 (Monday) to 7 (Sunday)",
+            |app| app.value_unary_op(value::weekday, "dow"),
+        )
+        .example("2024-03-01 dow"),
+        Command::new(
+            "unix",
+            1,
+            "Dates",
+            "Unix timestamp of midnight UTC on a date",
+            |app| app.value_unary_op(value::to_unix, "unix"),
+        )
+        .example("2024-03-01 unix"),
+        Command::new(
+            "fromunix",
+            1,
+            "Dates",
+            "Date (UTC) of a Unix timestamp",
+            |app| app.value_unary_op(value::from_unix, "fromunix"),
+        )
+        .example("1709251200 fromunix"),
+        Command::new("today", 0, "Dates", "Push today's date (UTC)", |app| {
+            let today = app.date();
+            app.stack.push(today.into());
+            Ok(OpOutcome::info(format!("Pushed {}", today)))
+        })
+        .example("today"),
+        Command::new("pi", 0, "Constants", "Push π", |app| {
+            app.push_constant(std::f64::consts::PI, "π")
+        })
+        .example("pi"),
+        Command::new("e", 0, "Constants", "Push e", |app| {
+            app.push_constant(std::f64::consts::E, "e")
+        })
+        .example("e"),
+        Command::new(
+            "depth",
+            0,
+            "Stack Operations",
+            "Push the number of values on the stack",
+            App::depth,
+        )
+        .example("1 2 3 depth"),
+        Command::new(
+            "dup",
+            1,
+            "Stack Operations",
+            "Duplicate the top value",
+            App::dup,
+        )
+        .produces(2)
+        .example("5 dup"),
+        Command::new(
+            "enter",
+            1,
+            "Stack Operations",
+            "Duplicate the top like ENTER on an HP; the next number replaces the copy",
+            App::enter,
+        )
+        .produces(2)
+        .example("3 enter 4 +"),
+        Command::new(
+            "swap",
+            2,
+            "Stack Operations",
+            "Swap top two values (Ctrl-W)",
+            App::swap,
+        )
+        .produces(2)
+        .example("1 2 swap"),
+        Command::new(
+            "drop",
+            1,
+            "Stack Operations",
+            "Remove top value (Ctrl-D)",
+            App::drop_top,
+        )
+        .produces(0)
+        .example("1 2 drop"),
+        Command::new(
+            "clear",
+            0,
+            "Stack Operations",
+            "Clear the stack",
+            App::clear,
+        )
+        .aliases(&["clr"])
+        .produces(0)
+        .whole_stack(),
+        Command::new(
+            "copy",
+            0,
+            "Stack Operations",
+            "Copy x to the clipboard",
+            |app| app.copy_to_clipboard(false),
+        )
+        .produces(0)
+        .no_undo()
+        .no_preview()
+        .external(),
+        Command::new(
+            "copyall",
+            0,
+            "Stack Operations",
+            "Copy the whole stack to the clipboard",
+            |app| app.copy_to_clipboard(true),
+        )
+        .produces(0)
+        .no_undo()
+        .no_preview()
+        .external(),
+        Command::new(
+            "paste",
+            0,
+            "Stack Operations",
+            "Push numbers from the clipboard (Ctrl-V)",
+            App::paste_clipboard,
+        )
+        .no_preview()
+        .external(),
+        Command::new(
+            "undo",
+            0,
+            "Stack Operations",
+            "Undo last operation",
+            App::undo,
+        )
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "lastx",
+            0,
+            "Stack Operations",
+            "Push the x operand of the last operation again",
+            App::push_last_x,
+        )
+        .example("6 7 * lastx"),
+        Command::new(
+            "park",
+            1,
+            "Stack Operations",
+            "Move x to the shelf, beside the history",
+            App::park,
+        )
+        .produces(0)
+        .example("1 2 park"),
+        Command::new(
+            "unpark",
+            0,
+            "Stack Operations",
+            "Move the last parked value back onto the stack",
+            App::unpark,
+        )
+        .example("1 park 2 unpark"),
+        Command::new(
+            "pick",
+            0,
+            "Stack Operations",
+            "Push a copy of stack level n, 1 being x (or click a stack entry)",
+            App::pick_level,
+        )
+        .params(&["n"])
+        .example("1 2 3 pick 3"),
+        Command::new(
+            "move",
+            0,
+            "Stack Operations",
+            "Move stack level from to level to, 1 being x (or drag a stack entry)",
+            App::move_level,
+        )
+        .params(&["from", "to"])
+        .produces(0)
+        .whole_stack()
+        .example("1 2 3 move 3 1"),
+        Command::new(
+            "push",
+            0,
+            "Stack Operations",
+            "Push values written exactly, as the replay log records history picks and imports",
+            App::push_exact,
+        )
+        .params(&["values"])
+        .example("push \"num:0.1 frac:1/3\""),
+        Command::new(
+            "ws",
+            0,
+            "Stack Operations",
+            "Switch to workspace n (1-9, or Alt-1 to Alt-9), each with its own stack",
+            App::select_workspace,
+        )
+        .params(&["n"])
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "bind",
+            0,
+            "Stack Operations",
+            "Give placeholder ?name a value and finish what waited on it",
+            App::bind,
+        )
+        .params(&["name", "value"])
+        .produces(0)
+        .whole_stack(),
+        Command::new(
+            "mark",
+            1,
+            "Stack Operations",
+            "Remember x as the reference for the delta readout",
+            App::set_mark,
+        )
+        .no_undo()
+        .no_defer()
+        .example("80 mark 100"),
+        Command::new(
+            "unmark",
+            0,
+            "Stack Operations",
+            "Forget the mark and hide the delta readout",
+            App::clear_mark,
+        )
+        .produces(0)
+        .no_undo(),
+        Command::new("help", 0, "General", "Show this help", App::open_help)
+            .produces(0)
+            .no_undo(),
+        Command::new(
+            "explain",
+            0,
+            "General",
+            "Describe a command in detail",
+            App::explain,
+        )
+        .params(&["command"])
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "engpre",
+            0,
+            "General",
+            "Toggle showing numbers with SI prefixes, e.g. 0.0000047 as 4.7 µ",
+            App::toggle_eng_prefixes,
+        )
+        .produces(0)
+        .no_undo()
+        .example("4.7k engpre"),
+        Command::new(
+            "notes",
+            0,
+            "General",
+            "Toggle the scratchpad notes panel",
+            App::toggle_notes,
+        )
+        .produces(0)
+        .no_undo()
+        .no_preview(),
+        Command::new(
+            "softkeys",
+            0,
+            "General",
+            "Show or hide the F1-F8 soft-key labels",
+            App::toggle_softkeys,
+        )
+        .produces(0)
+        .no_undo()
+        .no_preview(),
+        Command::new(
+            "run",
+            0,
+            "General",
+            "Run a file of input lines; undo reverts it as a whole",
+            App::run_script,
+        )
+        .params(&["file"])
+        .produces(0)
+        .no_undo()
+        .no_preview(),
+        Command::new(
+            "def",
+            0,
+            "General",
+            "Define a macro for this session, e.g. def cube \"dup dup * *\"",
+            App::define_macro,
+        )
+        .params(&["name", "body"])
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "test",
+            0,
+            "General",
+            "Run the tests of one macro",
+            App::run_macro_tests,
+        )
+        .params(&["macro"])
+        .produces(0)
+        .no_undo()
+        .no_preview(),
+        Command::new(
+            "runtests",
+            0,
+            "General",
+            "Run every macro test and report pass/fail",
+            App::run_macro_tests,
+        )
+        .produces(0)
+        .no_undo()
+        .no_preview(),
+        Command::new(
+            "tape",
+            0,
+            "General",
+            "Show every number and operation entered, with running results",
+            App::open_tape,
+        )
+        .produces(0)
+        .no_undo()
+        .no_preview(),
+        Command::new(
+            "savetape",
+            0,
+            "General",
+            "Write the tape to a text file",
+            App::save_tape,
+        )
+        .params(&["file"])
+        .produces(0)
+        .no_undo()
+        .no_preview()
+        .external(),
+        Command::new(
+            "export",
+            0,
+            "General",
+            "Write the stack and tape to a csv, fixed-width or json file",
+            App::export_file,
+        )
+        .params(&["format", "file"])
+        .produces(0)
+        .no_undo()
+        .no_preview()
+        .external(),
+        Command::new(
+            "import",
+            0,
+            "General",
+            "Push the numbers in a one-column file",
+            App::import_file,
+        )
+        .params(&["file"])
+        .produces(0)
+        .no_preview()
+        .external(),
+        Command::new(
+            "importcol",
+            0,
+            "General",
+            "Push the numbers in column n of a file, 1 being the first",
+            App::import_file,
+        )
+        .params(&["file", "n"])
+        .produces(0)
+        .no_preview()
+        .external(),
+        Command::new(
+            "theme",
+            0,
+            "General",
+            "Switch colors: dark, light, solarized or mono",
+            App::set_theme,
+        )
+        .params(&["name"])
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "report",
+            0,
+            "General",
+            "Save a bug report with version, settings, last error and tape",
+            App::save_report,
+        )
+        .produces(0)
+        .no_undo()
+        .no_preview()
+        .external(),
+        Command::new(
+            "slowops",
+            0,
+            "General",
+            "Show the slowest recent operations and how long they took",
+            App::slow_ops,
+        )
+        .produces(0)
+        .no_undo(),
+        Command::new(
+            "selftest",
+            0,
+            "General",
+            "Check known results and report any mismatches",
+            App::selftest,
+        )
+        .produces(0)
+        .no_undo()
+        .no_preview(),
+        Command::new(
+            "search",
+            0,
+            "General",
+            "Search history and re-push a result (Ctrl-R)",
+            |app| app.start_history_search(),
+        )
+        .produces(0)
+        .no_undo(),
+    ]
+}
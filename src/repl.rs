@@ -0,0 +1,240 @@
+//! `--repl` and `--plain`: line-oriented frontends for terminals where the
+//! full-screen UI gets in the way, such as editors, slow SSH sessions,
+//! screen readers and braille displays. Each line is run like the TUI's
+//! input line and what it did is printed after it.
+
+use std::io::{self, IsTerminal};
+
+use crossterm::style::Stylize;
+use rpncalc::{format_stack_columns_with, format_value_with, paths, App, EntryMode};
+
+/// Words that end the session, unless a command or macro has the name.
+const QUIT_WORDS: [&str; 3] = ["q", "quit", "exit"];
+
+/// How each line's effect is printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Output {
+    /// The whole stack in aligned columns after each line, and errors in
+    /// color, as `--repl` shows them.
+    Columns,
+    /// A plain line per change, e.g. `pushed 4` or `3 + 4 = 7`, then the
+    /// stack on one line as `stack: 3 7`, for `--plain`. Nothing is
+    /// colored or aligned, so screen readers read it as written.
+    Plain,
+}
+
+/// Runs lines from the terminal, or from standard input when it is not
+/// one, until end of input or a quit word.
+pub fn run(app: &mut App, output: Output) -> io::Result<()> {
+    let color = output == Output::Columns
+        && io::stderr().is_terminal()
+        && std::env::var_os("NO_COLOR").is_none();
+    let interactive = io::stdin().is_terminal();
+    if !app.message.is_empty() {
+        let warning = format!("warning: {}", app.message);
+        eprintln!("{}", if color { warning.yellow().to_string() } else { warning });
+        app.message.clear();
+    }
+    if interactive {
+        println!("rpncalc {}: help lists the commands, Ctrl-D quits", app.version);
+    }
+    // Piped input is not worth recalling.
+    let history = paths::repl_history_file().filter(|_| interactive);
+    let mut editor = imp::LineEditor::new(history)?;
+    while let Some(line) = editor.read_line("> ", app.completions(""))? {
+        let line = match line.trim() {
+            // An empty line is ENTER in classic entry.
+            "" if app.entry == EntryMode::Classic => "enter",
+            "" => continue,
+            line => line,
+        };
+        editor.remember(line);
+        let is_name = |word| app.registry.lookup(word).is_some() || app.macros.get(word).is_some();
+        if QUIT_WORDS.contains(&line) && !is_name(line) {
+            break;
+        }
+        let before = app.stack.clone();
+        let taped = app.tape.len();
+        let result = app.submit_line(line.to_string());
+        if output == Output::Plain {
+            for entry in &app.tape.entries()[taped..] {
+                println!("{}", entry.describe(app.number_format()));
+            }
+        }
+        match result {
+            Ok(outcome) => {
+                // The message says what a line did when the stack cannot.
+                if app.stack == before && !outcome.message().is_empty() {
+                    println!("{}", outcome.message());
+                }
+            }
+            Err(e) => {
+                let error = format!("error: {}", e);
+                eprintln!("{}", if color { error.red().bold().to_string() } else { error });
+            }
+        }
+        if let Some(popup) = app.popup.take() {
+            println!("{}", popup.title);
+            for line in popup.lines {
+                println!("  {}", line);
+            }
+        }
+        if app.show_help {
+            app.show_help = false;
+            for line in app.registry.help_lines() {
+                println!("{}", line);
+            }
+        }
+        if app.show_tape {
+            app.show_tape = false;
+            for line in app.tape.lines(0..app.tape.len(), app.number_format()) {
+                println!("{}", line);
+            }
+        }
+        match output {
+            Output::Columns => {
+                for line in format_stack_columns_with(&app.stack, app.number_format()) {
+                    println!("{}", line);
+                }
+            }
+            Output::Plain if app.stack == before => {}
+            Output::Plain if app.stack.is_empty() => println!("stack empty"),
+            Output::Plain => {
+                let number = app.number_format();
+                let values: Vec<String> =
+                    app.stack.iter().map(|v| format_value_with(v, number)).collect();
+                println!("stack: {}", values.join(" "));
+            }
+        }
+    }
+    editor.save();
+    Ok(())
+}
+
+#[cfg(feature = "repl")]
+mod imp {
+    use std::io;
+    use std::path::PathBuf;
+
+    use rustyline::completion::Completer;
+    use rustyline::error::ReadlineError;
+    use rustyline::highlight::Highlighter;
+    use rustyline::hint::Hinter;
+    use rustyline::history::DefaultHistory;
+    use rustyline::validate::Validator;
+    use rustyline::{Context, Editor, Helper};
+
+    /// Completes the word before the cursor from command and macro names.
+    struct Names(Vec<String>);
+
+    impl Completer for Names {
+        type Candidate = String;
+
+        fn complete(
+            &self,
+            line: &str,
+            pos: usize,
+            _ctx: &Context<'_>,
+        ) -> rustyline::Result<(usize, Vec<String>)> {
+            let start = line[..pos].rfind(' ').map_or(0, |i| i + 1);
+            let word = &line[start..pos];
+            let matches = self.0.iter().filter(|n| n.starts_with(word)).cloned().collect();
+            Ok((start, matches))
+        }
+    }
+
+    impl Hinter for Names {
+        type Hint = String;
+    }
+
+    impl Highlighter for Names {}
+
+    impl Validator for Names {}
+
+    impl Helper for Names {}
+
+    /// Line editing with history kept in `history`, as rustyline does it.
+    pub struct LineEditor {
+        editor: Editor<Names, DefaultHistory>,
+        history: Option<PathBuf>,
+    }
+
+    impl LineEditor {
+        pub fn new(history: Option<PathBuf>) -> io::Result<LineEditor> {
+            let mut editor = Editor::new().map_err(io::Error::other)?;
+            if let Some(path) = &history {
+                // A missing file is a first run; anything else shows up
+                // again when saving.
+                let _ = editor.load_history(path);
+            }
+            Ok(LineEditor { editor, history })
+        }
+
+        /// The next line, or `None` at end of input. Ctrl-C abandons the
+        /// line being typed.
+        pub fn read_line(
+            &mut self,
+            prompt: &str,
+            names: Vec<String>,
+        ) -> io::Result<Option<String>> {
+            self.editor.set_helper(Some(Names(names)));
+            loop {
+                match self.editor.readline(prompt) {
+                    Ok(line) => return Ok(Some(line)),
+                    Err(ReadlineError::Interrupted) => continue,
+                    Err(ReadlineError::Eof) => return Ok(None),
+                    Err(e) => return Err(io::Error::other(e)),
+                }
+            }
+        }
+
+        pub fn remember(&mut self, line: &str) {
+            let _ = self.editor.add_history_entry(line);
+        }
+
+        pub fn save(&mut self) {
+            let Some(path) = &self.history else {
+                return;
+            };
+            if let Some(dir) = path.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            if let Err(e) = self.editor.save_history(path) {
+                eprintln!("Could not save input history to {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Without the `repl` feature, lines are read as typed, with no editing
+/// beyond the terminal's own and no history.
+#[cfg(not(feature = "repl"))]
+mod imp {
+    use std::io::{self, BufRead, IsTerminal, Write};
+    use std::path::PathBuf;
+
+    pub struct LineEditor;
+
+    impl LineEditor {
+        pub fn new(_history: Option<PathBuf>) -> io::Result<LineEditor> {
+            Ok(LineEditor)
+        }
+
+        pub fn read_line(
+            &mut self,
+            prompt: &str,
+            _names: Vec<String>,
+        ) -> io::Result<Option<String>> {
+            if io::stdin().is_terminal() {
+                print!("{}", prompt);
+                io::stdout().flush()?;
+            }
+            let mut line = String::new();
+            Ok((io::stdin().lock().read_line(&mut line)? > 0).then_some(line))
+        }
+
+        pub fn remember(&mut self, _line: &str) {}
+
+        pub fn save(&mut self) {}
+    }
+}
@@ -0,0 +1,138 @@
+//! The replay log: every line entered at the top level of a session, in
+//! order, so that `--replay` can run the session again exactly. Each
+//! session starts with a mark and the seed of its random numbers:
+//!
+//! ```text
+//! # session 2024-03-01
+//! seed 8410361542870315264
+//! # date 2024-03-01
+//! 3 4 +
+//! rand *
+//! import sales.csv 2 *
+//! # outcome num:120.0 num:80.5
+//! ```
+//!
+//! Lines are logged before they run, so a line that crashes the calculator
+//! is the last one in the log. Before the first line, and again whenever
+//! the date changes, a `# date` line fixes the date `today` gives in the
+//! replay. Commands that read or write files or the clipboard log what
+//! they pushed, or how they failed, in `# outcome` lines after their line,
+//! and a replay does that instead of running them.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::{value, App, CalcError, Date, Value};
+
+/// The comment that starts each session.
+pub const SESSION_MARK: &str = "# session";
+
+/// The comment giving the date of the lines after it.
+pub const DATE_MARK: &str = "# date";
+
+/// The comment giving what an external command in the line before did.
+pub const OUTCOME_MARK: &str = "# outcome";
+
+/// How an outcome starts when the command failed, before the error.
+pub const FAILED: &str = "failed:";
+
+/// Sessions the log keeps; older ones are dropped when a new one starts.
+pub const KEEP_SESSIONS: usize = 20;
+
+/// Starts a session dated `date` in the log at `path`, whose random numbers
+/// start from `seed`, dropping the oldest sessions beyond `KEEP_SESSIONS`.
+pub fn start(path: &Path, date: &str, seed: u64) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    match fs::read_to_string(path) {
+        Ok(text) => {
+            let marks: Vec<usize> = text
+                .match_indices(SESSION_MARK)
+                .map(|(i, _)| i)
+                .filter(|&i| i == 0 || text[..i].ends_with('\n'))
+                .collect();
+            if marks.len() >= KEEP_SESSIONS {
+                fs::write(path, &text[marks[marks.len() + 1 - KEEP_SESSIONS]..])?;
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{} {}\nseed {}", SESSION_MARK, date, seed)
+}
+
+pub fn append(path: &Path, line: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line.trim())
+}
+
+/// The sessions in a replay log, oldest first, each as its lines with
+/// their line numbers in the file. Lines before the first mark make a
+/// session of their own, so a hand-written script replays too. Date and
+/// outcome lines are kept for `run`; blank lines and other comments are
+/// skipped.
+pub fn sessions(text: &str) -> Vec<Vec<(usize, &str)>> {
+    let mut sessions = vec![Vec::new()];
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with(SESSION_MARK) {
+            sessions.push(Vec::new());
+        } else if line.starts_with(DATE_MARK)
+            || line.starts_with(OUTCOME_MARK)
+            || !line.is_empty() && !line.starts_with('#')
+        {
+            if let Some(session) = sessions.last_mut() {
+                session.push((n + 1, line));
+            }
+        }
+    }
+    if sessions[0].is_empty() {
+        sessions.remove(0);
+    }
+    sessions
+}
+
+/// Runs a session's lines in order, as if typed, on the dates they were
+/// logged on, with external commands doing what they did then. Lines that
+/// failed in the session fail again; their line numbers and errors are
+/// returned.
+pub fn run(app: &mut App, lines: &[(usize, &str)]) -> Vec<(usize, CalcError)> {
+    let mut errors = Vec::new();
+    for (i, &(n, line)) in lines.iter().enumerate() {
+        if let Some(date) = line.strip_prefix(DATE_MARK) {
+            app.fixed_date = Date::parse(date.trim());
+        } else if !line.starts_with(OUTCOME_MARK) {
+            let outcomes = lines[i + 1..]
+                .iter()
+                .map_while(|(_, line)| line.strip_prefix(OUTCOME_MARK))
+                .map(outcome)
+                .collect();
+            app.replay_outcomes = Some(outcomes);
+            if let Err(e) = app.submit_line(line.to_string()) {
+                errors.push((n, e));
+            }
+        }
+    }
+    app.replay_outcomes = None;
+    errors
+}
+
+/// The values an outcome line says were pushed, or the error.
+fn outcome(text: &str) -> Result<Vec<Value>, String> {
+    let text = text.trim();
+    match text.strip_prefix(FAILED) {
+        Some(error) => Err(error.trim().to_string()),
+        None => value::decode_exact(text).ok_or_else(|| format!("Unreadable outcome '{}'", text)),
+    }
+}
+
+/// A 64-bit FNV-1a hash, which unlike the standard library's hasher is the
+/// same in every build, for checksums worth comparing across runs.
+pub fn fingerprint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
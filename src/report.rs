@@ -0,0 +1,68 @@
+//! Bug report bundles: everything useful for reproducing a problem, in one
+//! text file to attach to an issue.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{format_value, timing, App, Config};
+
+/// How many of the most recent calculations a report includes.
+pub const TAPE_ENTRIES: usize = 50;
+
+/// The report for `app`: version, platform, settings, current state, the
+/// last error and the most recent calculations. The home directory is
+/// replaced by `~` wherever it appears.
+pub fn bundle(app: &App) -> String {
+    let mut out = String::from("rpncalc bug report\n\n");
+    out.push_str(&format!("Version:  {}\n", app.version));
+    out.push_str(&format!("Platform: {} {}\n", env::consts::OS, env::consts::ARCH));
+    out.push_str(&format!("Angles:   {}\n", app.angle_mode.name()));
+    out.push_str(&format!("Money:    {}\n", app.money));
+
+    out.push_str("\n[settings]\n");
+    out.push_str(&Config::of(app).to_string());
+
+    out.push_str("\n[stack]\n");
+    for value in &app.stack {
+        out.push_str(&format!("{}\n", format_value(value)));
+    }
+
+    out.push_str("\n[last error]\n");
+    match &app.last_error {
+        Some((input, error)) => {
+            out.push_str(&format!("input: {}\nerror: {}\ndetail: {:?}\n", input, error, error))
+        }
+        None => out.push_str("none\n"),
+    }
+
+    out.push_str("\n[tape]\n");
+    let skip = app.calc_history.len().saturating_sub(TAPE_ENTRIES);
+    for entry in &app.calc_history[skip..] {
+        out.push_str(&format!("{}\n", entry.text));
+    }
+
+    out.push_str("\n[slowest operations]\n");
+    for t in app.timings.slowest(timing::SLOWEST_SHOWN) {
+        out.push_str(&format!("{}  {}\n", timing::format_elapsed(t.elapsed), t.name));
+    }
+
+    match env::var("HOME").ok().filter(|h| h.len() > 1) {
+        Some(home) => out.replace(&home, "~"),
+        None => out,
+    }
+}
+
+/// Writes `report` to a new timestamped file in `dir`, creating the
+/// directory if needed.
+pub fn save(dir: &Path, report: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let path = dir.join(format!("report-{}.txt", secs));
+    fs::write(&path, report)?;
+    Ok(path)
+}
@@ -0,0 +1,54 @@
+//! Running files of calculator input, such as a repeatable engineering
+//! calculation:
+//!
+//! ```text
+//! # Beam deflection, w L^4 / (8 E I)
+//! 2000 3 4 ^ *     # w L^4
+//! 8 200e9 * 8e-6 * /
+//! ```
+//!
+//! Each line is evaluated like a line typed at the prompt; `#` starts a
+//! comment. The first failing line stops the script.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::{App, CalcError, OpOutcome, OpResult};
+
+/// `line` without its comment and surrounding whitespace.
+pub fn strip_comment(line: &str) -> &str {
+    line.split('#').next().unwrap_or_default().trim()
+}
+
+/// Evaluates `text` line by line. Errors name the failing line of `name`.
+/// Returns the outcome of the last line.
+pub fn run(app: &mut App, name: &str, text: &str) -> OpResult {
+    let mut outcome = OpOutcome::info(format!("Ran {}", name));
+    for (n, line) in text.lines().enumerate() {
+        let line = strip_comment(line);
+        if line.is_empty() {
+            continue;
+        }
+        outcome = app.eval_line(line).map_err(|error| CalcError::Script {
+            file: name.to_string(),
+            line: n + 1,
+            error: Box::new(error),
+        })?;
+    }
+    Ok(outcome)
+}
+
+/// Reads and runs the script at `path`; `-` reads standard input.
+pub fn run_file(app: &mut App, path: &Path) -> OpResult {
+    let (name, text) = if path == Path::new("-") {
+        let mut text = String::new();
+        let read = io::stdin().read_to_string(&mut text);
+        ("stdin".to_string(), read.map(|_| text))
+    } else {
+        (path.display().to_string(), fs::read_to_string(path))
+    };
+    let text = text
+        .map_err(|e| CalcError::InvalidArgument(format!("Cannot read {}: {}", name, e)))?;
+    run(app, &name, &text)
+}
@@ -0,0 +1,67 @@
+//! Known calculations checked by `selftest`, covering floating-point edge
+//! cases, special functions and each angle mode.
+
+/// What a check's input should leave behind.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Expect {
+    /// A single number, equal within the default tolerance.
+    Number(f64),
+    /// The input is rejected with an error.
+    Error,
+}
+
+pub struct Check {
+    pub input: &'static str,
+    pub expect: Expect,
+}
+
+const fn number(input: &'static str, value: f64) -> Check {
+    Check {
+        input,
+        expect: Expect::Number(value),
+    }
+}
+
+const fn error(input: &'static str) -> Check {
+    Check {
+        input,
+        expect: Expect::Error,
+    }
+}
+
+pub const CHECKS: &[Check] = &[
+    number("0.1 0.2 +", 0.300_000_000_000_000_04),
+    number("0.1 0.2 + 0.3 ~=", 1.0),
+    number("money 0.1 0.2 + 0.3 -", 0.0),
+    number("fraction 1 3 / 1 6 / + 2 * 1 -", 0.0),
+    number("1e16 1 + 1e16 -", 0.0),
+    number("2 sqrt dup *", 2.000_000_000_000_000_4),
+    number("2 10 ^", 1024.0),
+    number("7 2 %", 1.0),
+    number("20 !", 2_432_902_008_176_640_000.0),
+    number("170 !", 7.257_415_615_307_994e306),
+    number("52 5 nCr", 2_598_960.0),
+    number("1000 500 nCr log", 299.431_827_151_863_7),
+    number("90 fib", 2_880_067_194_370_816_120.0),
+    number("5 gamma", 24.0),
+    number("0.5 gamma dup *", std::f64::consts::PI),
+    number("1000 log", 3.0),
+    number("1 exp ln", 1.0),
+    number("3 4 hypot", 5.0),
+    number("27 3 root", 3.0),
+    number("2.675 rnd 2", 2.68),
+    number("deg 30 sin", 0.5),
+    number("deg 60 cos", 0.5),
+    number("deg 45 tan", 1.0),
+    number("deg 0.5 asin", 30.0),
+    number("rad pi 6 / sin", 0.5),
+    number("rad 1 sin dup * 1 cos dup * +", 1.0),
+    number("grad 100 sin", 1.0),
+    number("grad 50 tan", 1.0),
+    number("[1 2; 3 4] det", -2.0),
+    number("2024-03-01 2024-02-01 -", 29.0),
+    error("1 0 /"),
+    error("-1 sqrt"),
+    error("1e308 10 *"),
+    error("171 !"),
+];
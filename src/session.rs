@@ -0,0 +1,270 @@
+//! Sharing one calculator between terminals over a Unix socket.
+//!
+//! The sharing instance runs a `Server` next to its own UI. Other instances
+//! attach with a `Client`, announcing themselves with a `view` or `control`
+//! line. Controllers then send input lines, which the server evaluates as if
+//! typed locally; every attached terminal receives a `Snapshot` line whenever
+//! the shared state changes.
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+use crate::value::{decode_exact, encode_exact};
+use crate::{AngleMode, App, Value};
+
+/// The part of the calculator state shown to attached terminals.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub stack: Vec<Value>,
+    pub angle_mode: AngleMode,
+    pub message: String,
+}
+
+impl Snapshot {
+    pub fn of(app: &App) -> Snapshot {
+        Snapshot {
+            stack: app.stack.clone(),
+            angle_mode: app.angle_mode,
+            message: app.message.clone(),
+        }
+    }
+
+    pub fn apply(&self, app: &mut App) {
+        app.stack = self.stack.clone();
+        app.angle_mode = self.angle_mode;
+        app.message = self.message.clone();
+    }
+
+    /// One line: angle mode, stack values and message, separated by tabs.
+    /// The values are written exactly; see `encode_exact`.
+    pub fn encode(&self) -> String {
+        let message = self.message.replace(['\t', '\n'], " ");
+        format!(
+            "{}\t{}\t{}",
+            self.angle_mode.name(),
+            encode_exact(&self.stack),
+            message
+        )
+    }
+
+    pub fn decode(line: &str) -> Option<Snapshot> {
+        let mut fields = line.splitn(3, '\t');
+        let angle_mode = AngleMode::from_name(fields.next()?)?;
+        let stack = decode_exact(fields.next()?)?;
+        let message = fields.next()?.to_string();
+        Some(Snapshot {
+            stack,
+            angle_mode,
+            message,
+        })
+    }
+}
+
+/// What an attached terminal may do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    View,
+    Control,
+}
+
+impl Role {
+    pub fn name(self) -> &'static str {
+        match self {
+            Role::View => "view",
+            Role::Control => "control",
+        }
+    }
+}
+
+/// A non-blocking stream that yields complete lines.
+struct LineStream {
+    stream: UnixStream,
+    buf: Vec<u8>,
+}
+
+impl LineStream {
+    fn new(stream: UnixStream) -> io::Result<LineStream> {
+        stream.set_nonblocking(true)?;
+        Ok(LineStream {
+            stream,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Lines received since the last call. Fails once the peer hangs up.
+    fn read_lines(&mut self) -> io::Result<Vec<String>> {
+        let mut chunk = [0; 4096];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let mut lines = Vec::new();
+        while let Some(end) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=end).collect();
+            lines.push(String::from_utf8_lossy(&line).trim_end().to_string());
+        }
+        Ok(lines)
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        // Lines are short, so a full socket buffer means the peer has stopped
+        // reading; treat it like any other write error.
+        self.stream.write_all(format!("{}\n", line).as_bytes())
+    }
+}
+
+struct Peer {
+    lines: LineStream,
+    role: Option<Role>,
+}
+
+/// Accepts attached terminals and keeps them in sync with an `App`.
+pub struct Server {
+    listener: UnixListener,
+    path: PathBuf,
+    peers: Vec<Peer>,
+    last: String,
+}
+
+impl Server {
+    /// Listens on `path`, replacing a stale socket left by a previous run.
+    pub fn bind(path: &Path) -> io::Result<Server> {
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    ErrorKind::AddrInUse,
+                    format!("{} is already shared", path.display()),
+                ));
+            }
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+        Ok(Server {
+            listener,
+            path: path.to_path_buf(),
+            peers: Vec::new(),
+            last: String::new(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of attached terminals that have announced their role.
+    pub fn attached(&self) -> usize {
+        self.peers.iter().filter(|p| p.role.is_some()).count()
+    }
+
+    /// Accepts new terminals, runs input from controllers against `app` and
+    /// sends the new state to everyone if it changed. Never blocks. Returns
+    /// whether anything visible changed.
+    pub fn poll(&mut self, app: &mut App) -> bool {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Ok(lines) = LineStream::new(stream) {
+                self.peers.push(Peer { lines, role: None });
+            }
+        }
+
+        let mut greeted = false;
+        let mut i = 0;
+        while i < self.peers.len() {
+            match self.peers[i].lines.read_lines() {
+                Ok(lines) => {
+                    for line in lines {
+                        greeted |= self.handle_line(i, &line, app);
+                    }
+                    i += 1;
+                }
+                Err(_) => {
+                    if let Some(role) = self.peers.remove(i).role {
+                        app.message = format!("A {} terminal detached", role.name());
+                    }
+                }
+            }
+        }
+
+        let state = Snapshot::of(app).encode();
+        let changed = greeted || state != self.last;
+        if changed {
+            self.peers
+                .retain_mut(|p| p.role.is_none() || p.lines.write_line(&state).is_ok());
+            self.last = state;
+        }
+        changed
+    }
+
+    /// Returns true when the line was a greeting, so the newcomer needs the
+    /// current state even if nothing changed.
+    fn handle_line(&mut self, peer: usize, line: &str, app: &mut App) -> bool {
+        match self.peers[peer].role {
+            None => {
+                let role = match line {
+                    "control" => Role::Control,
+                    _ => Role::View,
+                };
+                self.peers[peer].role = Some(role);
+                app.message = format!("A {} terminal attached", role.name());
+                true
+            }
+            Some(Role::Control) => {
+                let result = app.eval_line(line);
+                app.report(result);
+                false
+            }
+            Some(Role::View) => false,
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// A terminal attached to a shared calculator.
+pub struct Client {
+    lines: LineStream,
+    pub role: Role,
+}
+
+impl Client {
+    pub fn attach(path: &Path, role: Role) -> io::Result<Client> {
+        let mut stream = UnixStream::connect(path)?;
+        stream.write_all(format!("{}\n", role.name()).as_bytes())?;
+        Ok(Client {
+            lines: LineStream::new(stream)?,
+            role,
+        })
+    }
+
+    /// Sends an input line to be evaluated by the sharing instance.
+    pub fn send(&mut self, line: &str) -> io::Result<()> {
+        if self.role == Role::View {
+            return Err(io::Error::new(
+                ErrorKind::PermissionDenied,
+                "attached read-only",
+            ));
+        }
+        self.lines.write_line(line)
+    }
+
+    /// The newest state received since the last call, if any. Fails once the
+    /// sharing instance exits.
+    pub fn receive(&mut self) -> io::Result<Option<Snapshot>> {
+        Ok(self
+            .lines
+            .read_lines()?
+            .iter()
+            .rev()
+            .find_map(|line| Snapshot::decode(line)))
+    }
+}
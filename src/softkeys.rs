@@ -0,0 +1,91 @@
+//! The soft-key toolbar along the bottom of the screen: labels for F1 to
+//! F8, as on the menu keys of an HP-48. Each key runs a line of input, so
+//! it may be a command, a macro or several of them; the config file can
+//! rebind any key:
+//!
+//! ```text
+//! softkey.3 = cube
+//! softkey.9 = 2 /
+//! ```
+//!
+//! Keys are numbered across pages, so key 9 is F1 on the second page. With
+//! more than eight keys, F9 (`NXT`) turns to the next page.
+
+/// Keys on one page, F1 to F8.
+pub const PAGE_SIZE: usize = 8;
+
+/// Most keys the config file may bind, eight pages of them.
+pub const MAX_KEYS: usize = 64;
+
+/// The stack on the first page and common functions on the second.
+const DEFAULTS: [&str; 16] = [
+    "swap", "drop", "dup", "undo", "chs", "inv", "sqrt", "lastx", "sin", "cos", "tan", "ln",
+    "exp", "log", "pi", "sq",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SoftKeys {
+    /// What each key runs, the first page first; an empty entry is a blank
+    /// key.
+    keys: Vec<String>,
+}
+
+impl Default for SoftKeys {
+    fn default() -> SoftKeys {
+        SoftKeys {
+            keys: DEFAULTS.iter().map(|k| k.to_string()).collect(),
+        }
+    }
+}
+
+impl SoftKeys {
+    pub fn pages(&self) -> usize {
+        self.keys.len().div_ceil(PAGE_SIZE).max(1)
+    }
+
+    /// The keys on `page`, F1 first; the last page may have fewer than
+    /// eight.
+    pub fn page(&self, page: usize) -> &[String] {
+        let start = (page * PAGE_SIZE).min(self.keys.len());
+        &self.keys[start..(start + PAGE_SIZE).min(self.keys.len())]
+    }
+
+    /// What key `n` (0 for F1) of `page` runs, unless it is blank.
+    pub fn action(&self, page: usize, n: usize) -> Option<&str> {
+        self.page(page).get(n).map(String::as_str).filter(|a| !a.is_empty())
+    }
+
+    /// Binds key `number`, counting from 1 across pages, to `action`; an
+    /// empty action leaves the key blank.
+    pub fn bind(&mut self, number: usize, action: &str) -> Result<(), String> {
+        if !(1..=MAX_KEYS).contains(&number) {
+            return Err(format!("soft keys are numbered 1 to {}, not {}", MAX_KEYS, number));
+        }
+        if self.keys.len() < number {
+            self.keys.resize(number, String::new());
+        }
+        self.keys[number - 1] = action.to_string();
+        // Blank keys at the end would only add empty pages.
+        while self.keys.last().is_some_and(|k| k.is_empty()) {
+            self.keys.pop();
+        }
+        Ok(())
+    }
+
+    /// The keys bound differently from the defaults, numbered from 1.
+    pub fn changes(&self) -> Vec<(usize, &str)> {
+        (0..self.keys.len().max(DEFAULTS.len()))
+            .filter_map(|i| {
+                let mine = self.keys.get(i).map_or("", String::as_str);
+                (mine != DEFAULTS.get(i).copied().unwrap_or("")).then_some((i + 1, mine))
+            })
+            .collect()
+    }
+}
+
+/// The label of a key that runs `action`, centered in `width` columns and
+/// cut short if it does not fit.
+pub fn label(action: &str, width: usize) -> String {
+    let text: String = action.chars().take(width).collect();
+    format!("{:^width$}", text, width = width)
+}
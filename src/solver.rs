@@ -0,0 +1,191 @@
+//! Root finding for `solve`: Newton's method from the guess, and if that
+//! does not converge, bisection of a sign change found by searching outward
+//! from the guess.
+
+use crate::format_number;
+
+/// How hard `solve` tries, set in the config file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SolverSettings {
+    /// A root is found when the next step would move x by less than this
+    /// relative to its size.
+    pub tolerance: f64,
+    /// Most iterations Newton's method, the search for a sign change and
+    /// bisection each get.
+    pub max_iterations: usize,
+}
+
+impl Default for SolverSettings {
+    fn default() -> SolverSettings {
+        SolverSettings {
+            tolerance: 1e-12,
+            max_iterations: 100,
+        }
+    }
+}
+
+/// Which method found the root.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Method {
+    Newton,
+    Bisection,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Root {
+    pub x: f64,
+    pub method: Method,
+    /// Evaluations of f it took, all methods together.
+    pub evaluations: usize,
+}
+
+/// Counts the evaluations of f and treats non-finite values as no value.
+struct Counted<F> {
+    f: F,
+    evaluations: usize,
+}
+
+impl<F: FnMut(f64) -> Option<f64>> Counted<F> {
+    fn at(&mut self, x: f64) -> Option<f64> {
+        self.evaluations += 1;
+        (self.f)(x).filter(|y| y.is_finite())
+    }
+}
+
+/// Finds x near `guess` where `f` is zero. `f` gives `None` where it has no
+/// value, which the methods step around; the error says why no root was
+/// found.
+pub fn solve(
+    f: impl FnMut(f64) -> Option<f64>,
+    guess: f64,
+    settings: &SolverSettings,
+) -> Result<Root, String> {
+    let mut f = Counted { f, evaluations: 0 };
+    let Some(f_guess) = f.at(guess) else {
+        return Err(format!("f has no value at the guess {}", format_number(guess)));
+    };
+    let root = |x, method, f: &Counted<_>| Root {
+        x,
+        method,
+        evaluations: f.evaluations,
+    };
+    if let Some(x) = newton(&mut f, guess, f_guess, settings) {
+        return Ok(root(x, Method::Newton, &f));
+    }
+    let Some((a, b)) = bracket(&mut f, guess, f_guess, settings) else {
+        return Err(format!(
+            "no root found near {}: Newton's method did not converge and f does not \
+             change sign nearby",
+            format_number(guess)
+        ));
+    };
+    let x = bisect(&mut f, a, b, settings)?;
+    Ok(root(x, Method::Bisection, &f))
+}
+
+fn converged(step: f64, x: f64, settings: &SolverSettings) -> bool {
+    step.abs() <= settings.tolerance * x.abs().max(1.0)
+}
+
+/// Most times a Newton step is halved looking for a point where f is defined
+/// and smaller.
+const MAX_BACKTRACKS: usize = 30;
+
+/// Newton's method with a central-difference slope, halving steps that
+/// leave the domain of f or make it larger; `None` if it does not converge.
+fn newton<F: FnMut(f64) -> Option<f64>>(
+    f: &mut Counted<F>,
+    mut x: f64,
+    mut fx: f64,
+    settings: &SolverSettings,
+) -> Option<f64> {
+    for _ in 0..settings.max_iterations {
+        if fx == 0.0 {
+            return Some(x);
+        }
+        let h = 1e-7 * x.abs().max(1.0);
+        let slope = (f.at(x + h)? - f.at(x - h)?) / (2.0 * h);
+        let mut step = fx / slope;
+        if !step.is_finite() {
+            return None;
+        }
+        if converged(step, x - step, settings) {
+            return Some(x - step);
+        }
+        let mut next = f.at(x - step);
+        for _ in 0..MAX_BACKTRACKS {
+            if next.is_some_and(|y| y.abs() <= fx.abs()) {
+                break;
+            }
+            step /= 2.0;
+            next = f.at(x - step);
+        }
+        x -= step;
+        fx = next.filter(|y| y.abs() <= fx.abs())?;
+    }
+    None
+}
+
+/// Searches ever further either side of `guess` for a point where f has
+/// the other sign, giving the interval between them.
+fn bracket<F: FnMut(f64) -> Option<f64>>(
+    f: &mut Counted<F>,
+    guess: f64,
+    f_guess: f64,
+    settings: &SolverSettings,
+) -> Option<(f64, f64)> {
+    let mut distance = 0.01 * guess.abs().max(1.0);
+    for _ in 0..settings.max_iterations {
+        for x in [guess - distance, guess + distance] {
+            if f.at(x).is_some_and(|y| y.signum() != f_guess.signum()) {
+                return Some((guess.min(x), guess.max(x)));
+            }
+        }
+        distance *= 2.0;
+        if !distance.is_finite() {
+            break;
+        }
+    }
+    None
+}
+
+/// Halves `[a, b]`, over which f changes sign, until it is narrower than
+/// the tolerance.
+fn bisect<F: FnMut(f64) -> Option<f64>>(
+    f: &mut Counted<F>,
+    mut a: f64,
+    mut b: f64,
+    settings: &SolverSettings,
+) -> Result<f64, String> {
+    let no_value = |x| format!("f has no value at {} while bisecting", format_number(x));
+    let mut fa = f.at(a).ok_or_else(|| no_value(a))?;
+    let fb = f.at(b).ok_or_else(|| no_value(b))?;
+    let largest = fa.abs().max(fb.abs());
+    for _ in 0..settings.max_iterations {
+        let mid = a + (b - a) / 2.0;
+        let f_mid = f.at(mid).ok_or_else(|| no_value(mid))?;
+        if f_mid == 0.0 || converged(b - a, mid, settings) {
+            // A sign change where f grows rather than shrinks is a jump,
+            // as 1/x makes at 0, not a root.
+            if f_mid.abs() > largest {
+                return Err(format!(
+                    "f changes sign at {} but has no root there",
+                    format_number(mid)
+                ));
+            }
+            return Ok(mid);
+        }
+        if f_mid.signum() == fa.signum() {
+            a = mid;
+            fa = f_mid;
+        } else {
+            b = mid;
+        }
+    }
+    Err(format!(
+        "bisection did not converge in {} iterations; the root is between {} and {}",
+        settings.max_iterations,
+        format_number(a),
+        format_number(b)
+    ))
+}
@@ -0,0 +1,55 @@
+use std::f64::consts::PI;
+
+// Lanczos approximation with g = 7, n = 9.
+const LANCZOS_G: f64 = 7.0;
+const LANCZOS_COEF: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+fn lanczos_sum(x: f64) -> f64 {
+    LANCZOS_COEF[1..]
+        .iter()
+        .enumerate()
+        .fold(LANCZOS_COEF[0], |acc, (i, c)| {
+            acc + c / (x + i as f64 + 1.0)
+        })
+}
+
+fn is_pole(x: f64) -> bool {
+    x <= 0.0 && x.fract() == 0.0
+}
+
+/// The gamma function, Γ(n) = (n-1)! for positive integers.
+pub fn gamma(x: f64) -> Result<f64, &'static str> {
+    if is_pole(x) {
+        return Err("gamma is undefined at non-positive integers");
+    }
+    if x < 0.5 {
+        // Reflection formula
+        return Ok(PI / ((PI * x).sin() * gamma(1.0 - x)?));
+    }
+    let x = x - 1.0;
+    let t = x + LANCZOS_G + 0.5;
+    Ok((2.0 * PI).sqrt() * t.powf(x + 0.5) * (-t).exp() * lanczos_sum(x))
+}
+
+/// ln|Γ(x)|, usable well beyond the range where `gamma` overflows.
+pub fn ln_gamma(x: f64) -> Result<f64, &'static str> {
+    if is_pole(x) {
+        return Err("lgamma is undefined at non-positive integers");
+    }
+    if x < 0.5 {
+        return Ok((PI / (PI * x).sin().abs()).ln() - ln_gamma(1.0 - x)?);
+    }
+    let x = x - 1.0;
+    let t = x + LANCZOS_G + 0.5;
+    Ok(0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + lanczos_sum(x).ln())
+}
@@ -0,0 +1,184 @@
+//! Descriptive statistics over the numbers on the stack, and regression
+//! over accumulated (x, y) pairs.
+
+use crate::format_number;
+
+/// Decimal places values are rounded to before counting, so that e.g.
+/// `0.1 0.2 +` and `0.3` count as the same value.
+pub const FREQ_PLACES: i32 = 6;
+
+/// Each distinct value of `values`, after rounding to `places` decimal
+/// places, with how often it occurs, in ascending order of value.
+pub fn frequencies(values: &[f64], places: i32) -> Vec<(f64, usize)> {
+    let scale = 10f64.powi(places);
+    let mut rounded: Vec<f64> = values.iter().map(|v| (v * scale).round() / scale).collect();
+    rounded.sort_by(f64::total_cmp);
+    let mut table: Vec<(f64, usize)> = Vec::new();
+    for value in rounded {
+        match table.last_mut() {
+            Some((last, count)) if *last == value => *count += 1,
+            _ => table.push((value, 1)),
+        }
+    }
+    table
+}
+
+/// The most frequent values of a frequency table and their count.
+pub fn modes(table: &[(f64, usize)]) -> (Vec<f64>, usize) {
+    let top = table.iter().map(|&(_, count)| count).max().unwrap_or(0);
+    let values = table
+        .iter()
+        .filter(|&&(_, count)| count == top)
+        .map(|&(value, _)| value)
+        .collect();
+    (values, top)
+}
+
+pub fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Sample standard deviation.
+pub fn std_dev(values: &[f64]) -> f64 {
+    let m = mean(values);
+    let squares: f64 = values.iter().map(|v| (v - m).powi(2)).sum();
+    (squares / (values.len() - 1) as f64).sqrt()
+}
+
+/// The first and third quartiles, interpolating linearly between ranks.
+pub fn quartiles(values: &[f64]) -> (f64, f64) {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let quantile = |q: f64| {
+        let rank = q * (sorted.len() - 1) as f64;
+        let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+        sorted[lo] + (sorted[hi] - sorted[lo]) * (rank - lo as f64)
+    };
+    (quantile(0.25), quantile(0.75))
+}
+
+/// How `outliers` measures distance from the center of the data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spread {
+    /// Standard deviations from the mean.
+    StdDev,
+    /// Interquartile ranges beyond the quartiles (Tukey's fences).
+    Iqr,
+}
+
+/// The range outside which values are outliers, `k` spreads from the center.
+pub fn fences(values: &[f64], k: f64, spread: Spread) -> (f64, f64) {
+    match spread {
+        Spread::StdDev => {
+            let (m, s) = (mean(values), std_dev(values));
+            (m - k * s, m + k * s)
+        }
+        Spread::Iqr => {
+            let (q1, q3) = quartiles(values);
+            (q1 - k * (q3 - q1), q3 + k * (q3 - q1))
+        }
+    }
+}
+
+/// Positions of the values outside `fences`.
+pub fn outliers(values: &[f64], (low, high): (f64, f64)) -> Vec<usize> {
+    (0..values.len())
+        .filter(|&i| values[i] < low || values[i] > high)
+        .collect()
+}
+
+/// The statistical registers that `sadd` accumulates (x, y) pairs into,
+/// HP style: only the sums are kept, so `ssub` can take a pair back out.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PairStats {
+    pub n: usize,
+    pub sum_x: f64,
+    pub sum_y: f64,
+    pub sum_xx: f64,
+    pub sum_yy: f64,
+    pub sum_xy: f64,
+}
+
+impl PairStats {
+    pub fn add(&mut self, x: f64, y: f64) {
+        self.n += 1;
+        self.accumulate(x, y, 1.0);
+    }
+
+    /// Takes out a pair added earlier, to correct a mistyped one.
+    pub fn remove(&mut self, x: f64, y: f64) -> Result<(), &'static str> {
+        if self.n == 0 {
+            return Err("No pairs to remove");
+        }
+        self.n -= 1;
+        if self.n == 0 {
+            // Start again from exact zeros rather than rounding residue.
+            *self = PairStats::default();
+        } else {
+            self.accumulate(x, y, -1.0);
+        }
+        Ok(())
+    }
+
+    fn accumulate(&mut self, x: f64, y: f64, sign: f64) {
+        self.sum_x += sign * x;
+        self.sum_y += sign * y;
+        self.sum_xx += sign * x * x;
+        self.sum_yy += sign * y * y;
+        self.sum_xy += sign * x * y;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Sums of squares and products about the means: Sxx, Syy and Sxy.
+    fn spreads(&self) -> Result<(f64, f64, f64), &'static str> {
+        if self.n < 2 {
+            return Err("Regression needs at least 2 pairs");
+        }
+        let n = self.n as f64;
+        let sxx = self.sum_xx - self.sum_x * self.sum_x / n;
+        let syy = self.sum_yy - self.sum_y * self.sum_y / n;
+        let sxy = self.sum_xy - self.sum_x * self.sum_y / n;
+        if sxx <= 0.0 {
+            return Err("Regression needs x values that differ");
+        }
+        Ok((sxx, syy, sxy))
+    }
+
+    /// Slope and intercept of the least-squares line y = a + b x.
+    pub fn linreg(&self) -> Result<(f64, f64), &'static str> {
+        let (sxx, _, sxy) = self.spreads()?;
+        let slope = sxy / sxx;
+        let n = self.n as f64;
+        Ok((slope, self.sum_y / n - slope * self.sum_x / n))
+    }
+
+    /// Pearson's correlation coefficient.
+    pub fn corr(&self) -> Result<f64, &'static str> {
+        let (sxx, syy, sxy) = self.spreads()?;
+        if syy <= 0.0 {
+            return Err("Correlation needs y values that differ");
+        }
+        Ok((sxy / (sxx * syy).sqrt()).clamp(-1.0, 1.0))
+    }
+
+    /// The fitted line's y at `x`.
+    pub fn predict(&self, x: f64) -> Result<f64, &'static str> {
+        let (slope, intercept) = self.linreg()?;
+        Ok(intercept + slope * x)
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "n={} Σx={} Σy={} Σx²={} Σy²={} Σxy={}",
+            self.n,
+            format_number(self.sum_x),
+            format_number(self.sum_y),
+            format_number(self.sum_xx),
+            format_number(self.sum_yy),
+            format_number(self.sum_xy)
+        )
+    }
+}
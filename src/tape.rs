@@ -0,0 +1,104 @@
+//! The adding-machine tape: every number entered and every operation run
+//! from the input line, with the value each operation left on top. Unlike
+//! the history, which keeps only calculations and only the most recent
+//! ones, the tape keeps the whole session.
+
+use std::ops::Range;
+
+use crate::{format_value_with, Value};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TapeEntry {
+    /// A value pushed, whether typed, picked or recalled from the history.
+    Number(Value),
+    /// A command or macro with its inline arguments, and the top of the
+    /// stack after it ran; `None` if it left the stack empty. A command
+    /// that calculated something also has the calculation as the history
+    /// shows it, e.g. `3 + 4 = 7`.
+    Operation {
+        text: String,
+        top: Option<Value>,
+        calc: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tape {
+    entries: Vec<TapeEntry>,
+}
+
+impl TapeEntry {
+    /// The entry as a short sentence, e.g. `pushed 4`, or `3 + 4 = 7` for
+    /// a calculation, for frontends that speak rather than draw.
+    pub fn describe(&self, number: fn(f64) -> String) -> String {
+        match self {
+            TapeEntry::Number(value) => format!("pushed {}", format_value_with(value, number)),
+            TapeEntry::Operation { calc: Some(calc), .. } => calc.clone(),
+            TapeEntry::Operation { text, .. } => text.clone(),
+        }
+    }
+}
+
+impl Tape {
+    pub fn push_number(&mut self, value: Value) {
+        self.entries.push(TapeEntry::Number(value));
+    }
+
+    pub fn push_operation(&mut self, text: String, top: Option<Value>, calc: Option<String>) {
+        self.entries.push(TapeEntry::Operation { text, top, calc });
+    }
+
+    pub fn entries(&self) -> &[TapeEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries in `range` one per line, values lined up on their decimal
+    /// points with each operation after its result, as an adding machine
+    /// prints them:
+    ///
+    /// ```text
+    ///  12.5
+    ///   3.25
+    ///  15.75  +
+    /// ```
+    pub fn lines(&self, range: Range<usize>, number: fn(f64) -> String) -> Vec<String> {
+        let entries = &self.entries[range.start.min(self.len())..range.end.min(self.len())];
+        let texts: Vec<(String, &str)> = entries
+            .iter()
+            .map(|entry| match entry {
+                TapeEntry::Number(value) => (format_value_with(value, number), ""),
+                TapeEntry::Operation { text, top, .. } => {
+                    let top = top.as_ref().map(|v| format_value_with(v, number));
+                    (top.unwrap_or_default(), text.as_str())
+                }
+            })
+            .collect();
+        let whole_part = |text: &str| text.find(['.', ' ']).unwrap_or(text.len());
+        let whole = texts.iter().map(|(v, _)| whole_part(v)).max().unwrap_or(0);
+        let padded: Vec<String> = texts
+            .iter()
+            .map(|(v, _)| format!("{}{}", " ".repeat(whole - whole_part(v)), v))
+            .collect();
+        let width = padded.iter().map(|v| v.chars().count()).max().unwrap_or(0);
+        padded
+            .iter()
+            .zip(&texts)
+            .map(|(value, (_, op))| {
+                if op.is_empty() {
+                    value.clone()
+                } else {
+                    let pad = width - value.chars().count();
+                    format!("{}{}  {}", value, " ".repeat(pad), op)
+                }
+            })
+            .collect()
+    }
+}
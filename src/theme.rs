@@ -0,0 +1,256 @@
+//! Colors for each part of the screen. A theme starts from one of the
+//! built-in palettes, and the config file may change any part of it:
+//!
+//! ```text
+//! theme = solarized
+//! color.input = yellow
+//! color.help = black on #eee8d5
+//! ```
+
+use std::fmt;
+
+use ratatui::style::{Color, Style};
+
+/// The foreground and background of one part of the screen. `Reset` leaves
+/// the terminal's own color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Colors {
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl Colors {
+    const fn fg(fg: Color) -> Colors {
+        Colors { fg, bg: Color::Reset }
+    }
+
+    const fn on(fg: Color, bg: Color) -> Colors {
+        Colors { fg, bg }
+    }
+
+    pub fn style(self) -> Style {
+        let mut style = Style::default();
+        if self.fg != Color::Reset {
+            style = style.fg(self.fg);
+        }
+        if self.bg != Color::Reset {
+            style = style.bg(self.bg);
+        }
+        style
+    }
+
+    /// Reads `<fg>` or `<fg> on <bg>`, where a color is a name such as
+    /// `lightblue`, an index from 0 to 255 or `#rrggbb`.
+    pub fn parse(text: &str) -> Option<Colors> {
+        let (fg, bg) = match text.split_once(" on ") {
+            Some((fg, bg)) => (fg, bg.trim().parse().ok()?),
+            None => (text, Color::Reset),
+        };
+        Some(Colors::on(fg.trim().parse().ok()?, bg))
+    }
+}
+
+impl fmt::Display for Colors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bg == Color::Reset {
+            write!(f, "{}", self.fg)
+        } else {
+            write!(f, "{} on {}", self.fg, self.bg)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    /// The built-in theme this one starts from.
+    pub name: &'static str,
+    pub title: Colors,
+    pub stack: Colors,
+    /// Stack entries flagged by `outliers`.
+    pub outlier: Colors,
+    pub input: Colors,
+    /// Numbers, commands, quoted arguments and unknown words in an input
+    /// line of several tokens.
+    pub number: Colors,
+    pub command: Colors,
+    pub quoted: Colors,
+    pub unknown: Colors,
+    pub message: Colors,
+    /// The status bar with the modes, mark and preview.
+    pub status: Colors,
+    pub history: Colors,
+    /// The selected history entry while searching.
+    pub selection: Colors,
+    pub notes: Colors,
+    pub shelf: Colors,
+    pub plot: Colors,
+    /// Help and other popups.
+    pub help: Colors,
+    /// The labels of the soft keys.
+    pub softkey: Colors,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::DARK
+    }
+}
+
+/// The themes `theme` and the config file accept by name.
+pub const NAMES: [&str; 4] = ["dark", "light", "solarized", "mono"];
+
+impl Theme {
+    const DARK: Theme = Theme {
+        name: "dark",
+        title: Colors::fg(Color::Cyan),
+        stack: Colors::fg(Color::White),
+        outlier: Colors::on(Color::Black, Color::Red),
+        input: Colors::fg(Color::Yellow),
+        number: Colors::fg(Color::White),
+        command: Colors::fg(Color::LightGreen),
+        quoted: Colors::fg(Color::LightMagenta),
+        unknown: Colors::fg(Color::LightRed),
+        message: Colors::fg(Color::Green),
+        status: Colors::on(Color::Black, Color::Cyan),
+        history: Colors::fg(Color::Magenta),
+        selection: Colors::on(Color::Black, Color::Magenta),
+        notes: Colors::fg(Color::White),
+        shelf: Colors::fg(Color::Magenta),
+        plot: Colors::fg(Color::Cyan),
+        help: Colors::on(Color::White, Color::Blue),
+        softkey: Colors::on(Color::Black, Color::Gray),
+    };
+
+    const LIGHT: Theme = Theme {
+        name: "light",
+        title: Colors::fg(Color::Blue),
+        stack: Colors::fg(Color::Black),
+        outlier: Colors::on(Color::White, Color::Red),
+        input: Colors::fg(Color::Magenta),
+        number: Colors::fg(Color::Black),
+        command: Colors::fg(Color::Green),
+        quoted: Colors::fg(Color::Magenta),
+        unknown: Colors::fg(Color::Red),
+        message: Colors::fg(Color::Green),
+        status: Colors::on(Color::White, Color::Blue),
+        history: Colors::fg(Color::Blue),
+        selection: Colors::on(Color::White, Color::Blue),
+        notes: Colors::fg(Color::Black),
+        shelf: Colors::fg(Color::Magenta),
+        plot: Colors::fg(Color::Blue),
+        help: Colors::on(Color::Black, Color::LightCyan),
+        softkey: Colors::on(Color::White, Color::DarkGray),
+    };
+
+    // Ethan Schoonover's palette on its dark background.
+    const SOLARIZED: Theme = Theme {
+        name: "solarized",
+        title: Colors::fg(Color::Rgb(0x26, 0x8b, 0xd2)),
+        stack: Colors::fg(Color::Rgb(0x93, 0xa1, 0xa1)),
+        outlier: Colors::on(Color::Rgb(0xfd, 0xf6, 0xe3), Color::Rgb(0xdc, 0x32, 0x2f)),
+        input: Colors::fg(Color::Rgb(0xb5, 0x89, 0x00)),
+        number: Colors::fg(Color::Rgb(0x93, 0xa1, 0xa1)),
+        command: Colors::fg(Color::Rgb(0x26, 0x8b, 0xd2)),
+        quoted: Colors::fg(Color::Rgb(0x2a, 0xa1, 0x98)),
+        unknown: Colors::fg(Color::Rgb(0xdc, 0x32, 0x2f)),
+        message: Colors::fg(Color::Rgb(0x85, 0x99, 0x00)),
+        status: Colors::on(Color::Rgb(0x00, 0x2b, 0x36), Color::Rgb(0x2a, 0xa1, 0x98)),
+        history: Colors::fg(Color::Rgb(0x6c, 0x71, 0xc4)),
+        selection: Colors::on(Color::Rgb(0x00, 0x2b, 0x36), Color::Rgb(0x6c, 0x71, 0xc4)),
+        notes: Colors::fg(Color::Rgb(0x83, 0x94, 0x96)),
+        shelf: Colors::fg(Color::Rgb(0xd3, 0x36, 0x82)),
+        plot: Colors::fg(Color::Rgb(0x2a, 0xa1, 0x98)),
+        help: Colors::on(Color::Rgb(0x93, 0xa1, 0xa1), Color::Rgb(0x07, 0x36, 0x42)),
+        softkey: Colors::on(Color::Rgb(0x00, 0x2b, 0x36), Color::Rgb(0x93, 0xa1, 0xa1)),
+    };
+
+    // The terminal's own colors, with highlights shown as black on white.
+    const MONO: Theme = Theme {
+        name: "mono",
+        title: Colors::fg(Color::Reset),
+        stack: Colors::fg(Color::Reset),
+        outlier: Colors::on(Color::Black, Color::White),
+        input: Colors::fg(Color::Reset),
+        number: Colors::fg(Color::Reset),
+        command: Colors::fg(Color::Reset),
+        quoted: Colors::fg(Color::Reset),
+        unknown: Colors::on(Color::Black, Color::White),
+        message: Colors::fg(Color::Reset),
+        status: Colors::on(Color::Black, Color::White),
+        history: Colors::fg(Color::Reset),
+        selection: Colors::on(Color::Black, Color::White),
+        notes: Colors::fg(Color::Reset),
+        shelf: Colors::fg(Color::Reset),
+        plot: Colors::fg(Color::Reset),
+        help: Colors::on(Color::Black, Color::White),
+        softkey: Colors::on(Color::Black, Color::White),
+    };
+
+    pub fn named(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::DARK),
+            "light" => Some(Theme::LIGHT),
+            "solarized" => Some(Theme::SOLARIZED),
+            "mono" => Some(Theme::MONO),
+            _ => None,
+        }
+    }
+
+    /// Each part's config name and colors.
+    pub fn parts(&self) -> [(&'static str, Colors); 17] {
+        [
+            ("title", self.title),
+            ("stack", self.stack),
+            ("outlier", self.outlier),
+            ("input", self.input),
+            ("number", self.number),
+            ("command", self.command),
+            ("quoted", self.quoted),
+            ("unknown", self.unknown),
+            ("message", self.message),
+            ("status", self.status),
+            ("history", self.history),
+            ("selection", self.selection),
+            ("notes", self.notes),
+            ("shelf", self.shelf),
+            ("plot", self.plot),
+            ("help", self.help),
+            ("softkey", self.softkey),
+        ]
+    }
+
+    pub fn part_mut(&mut self, part: &str) -> Option<&mut Colors> {
+        Some(match part {
+            "title" => &mut self.title,
+            "stack" => &mut self.stack,
+            "outlier" => &mut self.outlier,
+            "input" => &mut self.input,
+            "number" => &mut self.number,
+            "command" => &mut self.command,
+            "quoted" => &mut self.quoted,
+            "unknown" => &mut self.unknown,
+            "message" => &mut self.message,
+            "status" => &mut self.status,
+            "history" => &mut self.history,
+            "selection" => &mut self.selection,
+            "notes" => &mut self.notes,
+            "shelf" => &mut self.shelf,
+            "plot" => &mut self.plot,
+            "help" => &mut self.help,
+            "softkey" => &mut self.softkey,
+            _ => return None,
+        })
+    }
+
+    /// The parts whose colors differ from the built-in theme this one
+    /// starts from.
+    pub fn changes(&self) -> Vec<(&'static str, Colors)> {
+        let base = Theme::named(self.name).unwrap_or_default();
+        self.parts()
+            .into_iter()
+            .zip(base.parts())
+            .filter(|(mine, base)| mine != base)
+            .map(|(mine, _)| mine)
+            .collect()
+    }
+}
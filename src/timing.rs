@@ -0,0 +1,58 @@
+//! How long recent operations took, so that `slowops` can point out the
+//! ones worth optimizing.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// How many of the most recent operations are kept.
+pub const MAX_TIMINGS: usize = 1000;
+
+/// How many operations `slowops` and bug reports list.
+pub const SLOWEST_SHOWN: usize = 10;
+
+/// One command or macro run and how long it took, including any commands it
+/// ran in turn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpTiming {
+    pub name: String,
+    pub elapsed: Duration,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Timings {
+    recent: VecDeque<OpTiming>,
+}
+
+impl Timings {
+    pub fn record(&mut self, name: &str, elapsed: Duration) {
+        if self.recent.len() == MAX_TIMINGS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(OpTiming {
+            name: name.to_string(),
+            elapsed,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.recent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recent.is_empty()
+    }
+
+    /// The `n` slowest recent operations, slowest first; among equally slow
+    /// ones the most recent comes first.
+    pub fn slowest(&self, n: usize) -> Vec<&OpTiming> {
+        let mut timings: Vec<&OpTiming> = self.recent.iter().rev().collect();
+        timings.sort_by_key(|t| std::cmp::Reverse(t.elapsed));
+        timings.truncate(n);
+        timings
+    }
+}
+
+/// A duration in milliseconds, e.g. `12.345 ms`.
+pub fn format_elapsed(elapsed: Duration) -> String {
+    format!("{:.3} ms", elapsed.as_secs_f64() * 1000.0)
+}
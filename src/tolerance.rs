@@ -0,0 +1,57 @@
+//! When two values count as equal: used by `~=` and by macro tests, and
+//! set with the `tolerance` and `abs_tolerance` settings.
+
+use crate::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    /// Largest difference allowed relative to the larger magnitude.
+    pub relative: f64,
+    /// Largest difference allowed regardless of magnitude, for values near
+    /// zero.
+    pub absolute: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Tolerance {
+        Tolerance {
+            relative: 1e-9,
+            absolute: 1e-12,
+        }
+    }
+}
+
+impl Tolerance {
+    pub fn close(&self, a: f64, b: f64) -> bool {
+        a == b || (a - b).abs() <= self.absolute.max(self.relative * a.abs().max(b.abs()))
+    }
+
+    /// Numbers (including money amounts and the central values of
+    /// measurements) and matrices of the same shape compare within the
+    /// tolerance; anything else must be identical.
+    pub fn values_match(&self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Matrix(x), Value::Matrix(y)) => {
+                (x.rows(), x.cols()) == (y.rows(), y.cols())
+                    && x.data()
+                        .iter()
+                        .zip(y.data())
+                        .all(|(&x, &y)| self.close(x, y))
+            }
+            (Value::Matrix(_), _) | (_, Value::Matrix(_)) => false,
+            _ => match (a.as_number(), b.as_number()) {
+                (Some(x), Some(y)) => self.close(x, y),
+                _ => a == b,
+            },
+        }
+    }
+
+    /// Whether `actual` holds the `expected` values, in order.
+    pub fn stacks_match(&self, actual: &[Value], expected: &[Value]) -> bool {
+        actual.len() == expected.len()
+            && actual
+                .iter()
+                .zip(expected)
+                .all(|(a, e)| self.values_match(a, e))
+    }
+}
@@ -0,0 +1,992 @@
+//! The terminal user interface: the event loops for a local or shared
+//! calculator and for a terminal attached to one, and the screen layout.
+
+use crossterm::{
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::{Line, Span},
+    symbols,
+    widgets::{
+        block::Title, Axis, Block, Borders, Chart, Clear, Dataset, GraphType, List, ListItem,
+        ListState, Paragraph, Wrap,
+    },
+    Frame, Terminal,
+};
+use std::{error::Error, io, time::Duration};
+use rpncalc::highlight::{self, TokenKind};
+use rpncalc::softkeys::{self, PAGE_SIZE};
+use rpncalc::{
+    format_number, format_value_lines_with, format_value_with, plot, App, ChartStyle, DataChart,
+    EntryMode, Plot,
+};
+#[cfg(unix)]
+use rpncalc::session::{self, Role};
+
+// How often to check for attached terminals while waiting for a key.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// The same with `low_power` set, trading latency of shared updates for
+// fewer wake-ups.
+const LOW_POWER_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Most parked values the shelf pane shows at once.
+const MAX_SHELF_ROWS: usize = 5;
+
+// History lines scrolled per mouse wheel step.
+const WHEEL_LINES: isize = 3;
+
+/// Where the last frame drew the panes that react to the mouse.
+#[derive(Clone, Copy, Debug, Default)]
+struct Areas {
+    stack: Rect,
+    history: Rect,
+    /// Index of the first history entry shown, unless a search is filtering
+    /// the list.
+    history_offset: Option<usize>,
+    softkeys: Rect,
+}
+
+/// The line inside a bordered pane at screen row `row`, if the point is in
+/// it.
+fn line_in(area: Rect, column: u16, row: u16) -> Option<usize> {
+    let inside = column > area.x
+        && column + 1 < area.x + area.width
+        && row > area.y
+        && row + 1 < area.y + area.height;
+    inside.then(|| (row - area.y - 1) as usize)
+}
+
+/// The stack entries that fit in `height` lines with their text, bottom
+/// first, and how many entries below them are left out. Only the entries
+/// shown are formatted, so a deep stack draws as fast as a shallow one; the
+/// top is always shown, even if it is a matrix too tall to fit.
+fn visible_stack(app: &App, height: usize) -> (usize, Vec<(usize, Vec<String>)>) {
+    let mut entries = Vec::new();
+    let mut used = 0;
+    for (i, value) in app.stack.iter().enumerate().rev() {
+        let lines = format_value_lines_with(value, app.number_format());
+        // Unless this is the bottom entry, a line goes to "… N more".
+        let room = if i == 0 { height } else { height.saturating_sub(1) };
+        if used + lines.len() > room && !entries.is_empty() {
+            break;
+        }
+        used += lines.len();
+        entries.push((i, lines));
+    }
+    entries.reverse();
+    (entries.first().map_or(0, |&(i, _)| i), entries)
+}
+
+/// The stack entry drawn at a screen position; matrices span several lines.
+fn stack_entry_at(app: &App, areas: &Areas, column: u16, row: u16) -> Option<usize> {
+    let mut line = line_in(areas.stack, column, row)?;
+    let (hidden, entries) = visible_stack(app, areas.stack.height.saturating_sub(2) as usize);
+    if hidden > 0 {
+        line = line.checked_sub(1)?;
+    }
+    for (i, lines) in entries {
+        if line < lines.len() {
+            return Some(i);
+        }
+        line -= lines.len();
+    }
+    None
+}
+
+/// What the toolbar shows in each slot: the keys of the current page,
+/// blank ones included, then `NXT` if there are more pages.
+fn softkey_slots(app: &App) -> Vec<&str> {
+    let page = app.softkeys.page(app.softkey_page);
+    let mut slots: Vec<&str> =
+        (0..PAGE_SIZE).map(|n| page.get(n).map_or("", String::as_str)).collect();
+    if app.softkeys.pages() > 1 {
+        slots.push("NXT");
+    }
+    slots
+}
+
+/// The toolbar slot drawn at a screen position.
+fn softkey_at(app: &App, areas: &Areas, column: u16, row: u16) -> Option<usize> {
+    let area = areas.softkeys;
+    let inside =
+        area.height > 0 && row == area.y && (area.x..area.x + area.width).contains(&column);
+    let slots = softkey_slots(app).len();
+    let width = (area.width as usize / slots).max(1);
+    inside
+        .then(|| (column - area.x) as usize / width)
+        .filter(|&slot| slot < slots)
+}
+
+/// Click a stack entry to copy it to the top or drag it to another level;
+/// click a history line to push its result; click a soft key to run it;
+/// the wheel scrolls the history.
+fn handle_mouse(app: &mut App, areas: &Areas, dragging: &mut Option<usize>, mouse: MouseEvent) {
+    let (column, row) = (mouse.column, mouse.row);
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            if let Some(slot) = softkey_at(app, areas, column, row) {
+                let result = if slot == PAGE_SIZE {
+                    app.next_softkey_page()
+                } else {
+                    app.press_softkey(slot)
+                };
+                app.report(result);
+                return;
+            }
+            *dragging = stack_entry_at(app, areas, column, row);
+            if dragging.is_none() {
+                let line = line_in(areas.history, column, row);
+                if let (Some(offset), Some(line)) = (areas.history_offset, line) {
+                    app.push_history_entry(offset + line);
+                }
+            }
+        }
+        MouseEventKind::Up(MouseButton::Left) => {
+            let Some(from) = dragging.take() else {
+                return;
+            };
+            let result = match stack_entry_at(app, areas, column, row) {
+                Some(to) if to == from => app.pick(from),
+                Some(to) => app.move_stack_entry(from, to),
+                None => return,
+            };
+            app.report(result);
+        }
+        MouseEventKind::ScrollUp if line_in(areas.history, column, row).is_some() => {
+            app.scroll_history(WHEEL_LINES)
+        }
+        MouseEventKind::ScrollDown if line_in(areas.history, column, row).is_some() => {
+            app.scroll_history(-WHEEL_LINES)
+        }
+        _ => {}
+    }
+}
+
+/// Runs `run` on the alternate screen, restoring the terminal afterwards.
+pub fn with_terminal<F>(run: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&mut Terminal<CrosstermBackend<io::Stdout>>) -> io::Result<()>,
+{
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let res = run(&mut terminal);
+
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste
+    )?;
+    terminal.show_cursor()?;
+
+    if let Err(err) = res {
+        println!("{:?}", err)
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub mod session {
+    /// Stand-in so the event loop compiles where sharing is unsupported.
+    pub struct Server;
+
+    impl Server {
+        pub fn poll(&mut self, _app: &mut rpncalc::App) -> bool {
+            false
+        }
+    }
+}
+
+/// Mirrors a shared calculator. Controllers edit an input line locally and
+/// send it on Enter, or with an operator key as on the sharing terminal.
+#[cfg(unix)]
+pub fn run_attached<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    client: &mut session::Client,
+) -> io::Result<()> {
+    app.message = format!("Attached ({})", client.role.name());
+    let mut redraw = true;
+    loop {
+        match client.receive() {
+            Ok(Some(snapshot)) => {
+                snapshot.apply(app);
+                redraw = true;
+            }
+            Ok(None) => {}
+            // The sharing instance has exited.
+            Err(_) => return Ok(()),
+        }
+        if redraw || !app.low_power {
+            app.update_preview();
+            terminal.draw(|f| {
+                ui(f, app);
+            })?;
+            redraw = false;
+        }
+
+        if !event::poll(poll_interval(app))? {
+            continue;
+        }
+        redraw = true;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        let mut send = false;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc if app.input.is_empty() => return Ok(()),
+            _ if client.role == Role::View => {}
+            KeyCode::Char('_') if app.input.is_empty() => {
+                app.input.push_str("chs");
+                send = true;
+            }
+            KeyCode::Char('_') => app.change_input_sign(),
+            KeyCode::Char(c @ ('+' | '-' | '*' | '/' | '^' | '%' | '!'))
+                if !app.input.contains(' ') && !app.sign_continues_number(c) =>
+            {
+                app.input.push(' ');
+                app.input.push(c);
+                send = true;
+            }
+            KeyCode::Char(c) => app.input.push(c),
+            KeyCode::Backspace => {
+                app.input.pop();
+            }
+            KeyCode::Esc => app.input.clear(),
+            KeyCode::F(n @ 1..=8) => {
+                if let Some(action) = app.softkeys.action(app.softkey_page, n as usize - 1) {
+                    app.input = action.to_string();
+                    send = true;
+                }
+            }
+            KeyCode::F(9) => {
+                let result = app.next_softkey_page();
+                app.report(result);
+            }
+            // As ENTER on an HP calculator, the same as when not attached.
+            KeyCode::Enter if app.entry == EntryMode::Classic && app.input.is_empty() => {
+                app.input.push_str("enter");
+                send = true;
+            }
+            KeyCode::Enter => send = true,
+            _ => {}
+        }
+        if send {
+            let line = std::mem::take(&mut app.input);
+            if let Err(e) = client.send(line.trim()) {
+                app.message = format!("Could not send: {}", e);
+            }
+        }
+    }
+}
+
+pub fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    mut server: Option<&mut session::Server>,
+) -> io::Result<()> {
+    let mut redraw = true;
+    let mut areas = Areas::default();
+    let mut dragging = None;
+    loop {
+        if let Some(server) = server.as_mut() {
+            redraw |= server.poll(app);
+        }
+        if redraw || !app.low_power {
+            app.update_preview();
+            terminal.draw(|f| areas = ui(f, app))?;
+            redraw = false;
+        }
+        // While sharing, wake up regularly to serve attached terminals.
+        if server.is_some() && !event::poll(poll_interval(app))? {
+            continue;
+        }
+
+        redraw = true;
+        let key = match event::read()? {
+            Event::Key(key) => key,
+            Event::Paste(text) => {
+                app.paste(&text);
+                continue;
+            }
+            Event::Mouse(mouse) => {
+                if !modal_open(app) {
+                    handle_mouse(app, &areas, &mut dragging, mouse);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        // On Windows, filter out key release events to prevent double input
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        if app.show_tape {
+            match key.code {
+                KeyCode::Up => app.scroll_tape(1),
+                KeyCode::Down => app.scroll_tape(-1),
+                KeyCode::PageUp => app.scroll_tape(10),
+                KeyCode::PageDown => app.scroll_tape(-10),
+                KeyCode::Home => app.scroll_tape(isize::MAX),
+                KeyCode::End => app.tape_scroll = 0,
+                _ => {
+                    app.show_tape = false;
+                    app.message = "Tape closed".to_string();
+                }
+            }
+            continue;
+        }
+        if app.show_help {
+            match key.code {
+                KeyCode::Up => app.help_scroll = app.help_scroll.saturating_sub(1),
+                KeyCode::Down => app.help_scroll = app.help_scroll.saturating_add(1),
+                KeyCode::PageUp => app.help_scroll = app.help_scroll.saturating_sub(10),
+                KeyCode::PageDown => app.help_scroll = app.help_scroll.saturating_add(10),
+                _ => {
+                    app.show_help = false;
+                    app.message = "Help closed".to_string();
+                }
+            }
+            continue;
+        }
+        if let Some(popup) = app.popup.as_mut() {
+            match key.code {
+                KeyCode::Up => popup.scroll = popup.scroll.saturating_sub(1),
+                KeyCode::Down => popup.scroll = popup.scroll.saturating_add(1),
+                KeyCode::PageUp => popup.scroll = popup.scroll.saturating_sub(10),
+                KeyCode::PageDown => popup.scroll = popup.scroll.saturating_add(10),
+                _ => app.popup = None,
+            }
+            continue;
+        }
+        if app.chart.is_some() {
+            app.chart = None;
+            continue;
+        }
+        if app.editing_notes {
+            match key.code {
+                KeyCode::Char(c) => app.notes.push(c),
+                KeyCode::Enter => app.notes.push('\n'),
+                KeyCode::Backspace => {
+                    app.notes.pop();
+                }
+                KeyCode::Esc => {
+                    app.editing_notes = false;
+                    app.message = "Finished editing notes (type notes to hide)".to_string();
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if app.history_search.is_some() {
+            match key.code {
+                KeyCode::Char(c) => app.update_history_query(|q| q.push(c)),
+                KeyCode::Backspace => app.update_history_query(|q| {
+                    q.pop();
+                }),
+                KeyCode::Up => app.move_history_selection(-1),
+                KeyCode::Down => app.move_history_selection(1),
+                KeyCode::Enter => app.push_selected_history(),
+                KeyCode::Esc => app.cancel_history_search(),
+                _ => {}
+            }
+            continue;
+        }
+        if let Some(import) = app.table_import.as_mut() {
+            match key.code {
+                KeyCode::Left => import.prev_column(),
+                KeyCode::Right => import.next_column(),
+                KeyCode::Tab | KeyCode::Char('d') => import.cycle_delimiter(),
+                KeyCode::Enter => app.confirm_import(),
+                KeyCode::Esc => app.cancel_import(),
+                _ => {}
+            }
+            continue;
+        }
+        
+        match key.code {
+            KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let result = app.eval_line("paste");
+                app.report(result);
+            }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let result = app.start_history_search();
+                app.report(result);
+            }
+            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let result = app.eval_line("drop");
+                app.report(result);
+            }
+            KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let result = app.eval_line("swap");
+                app.report(result);
+            }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.clear_input();
+            }
+            KeyCode::Char('q') if app.input.is_empty() => return Ok(()),
+            KeyCode::Char(c @ '1'..='9') if key.modifiers.contains(KeyModifiers::ALT) => {
+                let result = app.eval_line(&format!("ws {}", c));
+                app.report(result);
+            }
+            KeyCode::F(n @ 1..=8) => {
+                let result = app.press_softkey(n as usize - 1);
+                app.report(result);
+            }
+            KeyCode::F(9) => {
+                let result = app.next_softkey_page();
+                app.report(result);
+            }
+            // Away from the end of the line, every character is just typed.
+            KeyCode::Char(c) if !app.cursor_at_end() && is_input_char(c) => {
+                app.insert_input(c);
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() || c == '.' => {
+                app.insert_input(c);
+            }
+            // Once a command has been followed by a space, or inside a matrix
+            // literal, operator characters are arguments (e.g. `explain +`) or
+            // signs rather than immediate operations.
+            KeyCode::Char(c @ ('+' | '-' | '*' | '/' | '^' | '%' | '!'))
+                if app.input.contains([' ', '[']) =>
+            {
+                app.insert_input(c);
+            }
+            // `-` on an empty line starts a negative number and a sign after
+            // `1.5e` is the exponent's; Enter on a lone `-` still subtracts.
+            KeyCode::Char(c @ ('+' | '-')) if app.sign_continues_number(c) => {
+                app.insert_input(c);
+            }
+            KeyCode::Char('_') if app.input.is_empty() => {
+                let result = app.eval_line("chs");
+                app.report(result);
+            }
+            KeyCode::Char('_') => app.change_input_sign(),
+            // `2024` then `-` may be the start of a date; Enter still
+            // subtracts.
+            KeyCode::Char('-') if is_date_prefix(&app.input) => {
+                app.insert_input('-');
+            }
+            KeyCode::Char(' ') if !app.input.is_empty() => {
+                app.insert_input(' ');
+            }
+            KeyCode::Char(c @ ('+' | '-' | '*' | '/' | '^' | '%' | '!')) => {
+                app.execute_single_char(c);
+            }
+            KeyCode::Char(c) if c.is_ascii_alphabetic() || "[];,±\"?<>=~".contains(c) => {
+                app.insert_input(c);
+            }
+            // As ENTER on an HP calculator.
+            KeyCode::Enter if app.entry == EntryMode::Classic && app.input.is_empty() => {
+                let result = app.eval_line("enter");
+                app.report(result);
+            }
+            KeyCode::Enter => {
+                let year = app.input.strip_suffix('-').filter(|n| !n.contains('-'));
+                if let Some(year) = year.filter(|y| is_date_prefix(y)) {
+                    app.input = format!("{} -", year);
+                }
+                app.execute_command();
+            }
+            KeyCode::Tab => {
+                app.complete_input();
+            }
+            KeyCode::Up => app.recall_input(false),
+            KeyCode::Down => app.recall_input(true),
+            KeyCode::Left => app.move_cursor(false),
+            KeyCode::Right => app.move_cursor(true),
+            KeyCode::Home => app.cursor_home(),
+            KeyCode::End => app.cursor_end(),
+            KeyCode::PageUp => app.scroll_history(10),
+            KeyCode::PageDown => app.scroll_history(-10),
+            KeyCode::Backspace => app.delete_input_back(),
+            KeyCode::Esc => {
+                let result = app.eval_line("clear");
+                app.report(result);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether a popup or dialog takes the keys and mouse rather than the main
+/// screen.
+fn modal_open(app: &App) -> bool {
+    app.show_help
+        || app.show_tape
+        || app.popup.is_some()
+        || app.chart.is_some()
+        || app.table_import.is_some()
+        || app.editing_notes
+}
+
+/// Characters the input line accepts as typed text.
+fn is_input_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || " .+-*/^%![];,±\"?<>=~".contains(c)
+}
+
+fn poll_interval(app: &App) -> Duration {
+    if app.low_power {
+        LOW_POWER_POLL_INTERVAL
+    } else {
+        POLL_INTERVAL
+    }
+}
+
+/// Whether `input` is a year or year and month being typed, e.g. `2024` or
+/// `2024-03`, so that `-` continues a date.
+fn is_date_prefix(input: &str) -> bool {
+    let digits = |s: &str, n: usize| s.len() == n && s.bytes().all(|b| b.is_ascii_digit());
+    match input.split_once('-') {
+        Some((year, month)) => digits(year, 4) && digits(month, 2),
+        None => digits(input, 4),
+    }
+}
+
+/// The bottom line: angle mode, number format, workspace, classic entry
+/// and mark on the left, and the preview of the input line on the right.
+fn status_bar(app: &App, width: u16) -> String {
+    let format = if app.money {
+        "MONEY"
+    } else if app.frac {
+        "FRAC"
+    } else if app.eng_prefixes {
+        "ENGPRE"
+    } else {
+        "FLOAT"
+    };
+    let mut left = format!(
+        " {}  {}  WS {}/{}",
+        app.angle_mode.name(),
+        format,
+        app.workspace + 1,
+        app.workspaces.len()
+    );
+    if app.entry == EntryMode::Classic {
+        left.push_str("  CLASSIC");
+    }
+    if !app.pairs.is_empty() {
+        left.push_str(&format!("  Σn={}", app.pairs.n));
+    }
+    if let Some(mark) = app.mark_status() {
+        left.push_str("  ");
+        left.push_str(&mark);
+    }
+    let right = match &app.preview {
+        Some(preview) => format!("{} ", preview),
+        None => return left,
+    };
+    let used = left.chars().count() + right.chars().count();
+    let gap = (width as usize).saturating_sub(used).max(2);
+    format!("{}{}{}", left, " ".repeat(gap), right)
+}
+
+fn ui(f: &mut Frame, app: &App) -> Areas {
+    let screen = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(app.show_softkeys as u16),
+        ])
+        .split(f.size());
+    let status = Paragraph::new(status_bar(app, screen[1].width))
+        .style(app.theme.status.style());
+    f.render_widget(status, screen[1]);
+    render_softkeys(f, app, screen[2]);
+
+    let main_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+        .split(screen[0]);
+
+    let left_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(3),
+            Constraint::Length(3),
+        ])
+        .split(main_chunks[0]);
+
+    let title = Paragraph::new(format!("RPN Calculator {}", app.version))
+        .block(Block::default().borders(Borders::ALL))
+        .style(app.theme.title.style());
+    f.render_widget(title, left_chunks[0]);
+
+    let (hidden, entries) = visible_stack(app, left_chunks[1].height.saturating_sub(2) as usize);
+    let more = (hidden > 0).then(|| ListItem::new(format!("… {} more", hidden)));
+    let stack_items: Vec<ListItem> = more
+        .into_iter()
+        .chain(entries.into_iter().map(|(i, lines)| {
+            // Matrix rows after the first line up under it.
+            let label = format!("{}: ", i);
+            let lines: Vec<Line> = lines
+                .into_iter()
+                .enumerate()
+                .map(|(row, text)| {
+                    let prefix = if row == 0 { label.clone() } else { " ".repeat(label.len()) };
+                    Line::from(Span::raw(prefix + &text))
+                })
+                .collect();
+            if app.outliers.contains(&i) {
+                return ListItem::new(lines).style(app.theme.outlier.style());
+            }
+            ListItem::new(lines)
+        }))
+        .collect();
+
+    let mut stack_block = Block::default().borders(Borders::ALL).title("Stack");
+    if let Some(last_x) = &app.last_x {
+        let last_x = format!("last x: {}", format_value_with(last_x, app.number_format()));
+        stack_block = stack_block.title(Title::from(last_x).alignment(Alignment::Right));
+    }
+    let stack = List::new(stack_items)
+        .block(stack_block)
+        .style(app.theme.stack.style());
+    f.render_widget(stack, left_chunks[1]);
+    let mut areas = Areas {
+        stack: left_chunks[1],
+        softkeys: screen[2],
+        ..Areas::default()
+    };
+
+    let input = Paragraph::new(input_line(app))
+        .block(Block::default().borders(Borders::ALL).title("Input"))
+        .style(app.theme.input.style());
+    f.render_widget(input, left_chunks[2]);
+    if !modal_open(app) && app.history_search.is_none() {
+        let column = app.input[..app.cursor_position()].chars().count() as u16;
+        let x = (left_chunks[2].x + 1 + column).min(left_chunks[2].right().saturating_sub(2));
+        f.set_cursor(x, left_chunks[2].y + 1);
+    }
+
+    let message = Paragraph::new(app.message.as_str())
+        .block(Block::default().borders(Borders::ALL).title("Message"))
+        .style(app.theme.message.style());
+    f.render_widget(message, left_chunks[3]);
+
+    let mut right = main_chunks[1];
+    if !app.shelf.is_empty() {
+        let rows = app.shelf.len().min(MAX_SHELF_ROWS) as u16;
+        let shelf_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(5), Constraint::Length(rows + 2)])
+            .split(right);
+        render_shelf(f, app, shelf_chunks[1]);
+        right = shelf_chunks[0];
+    }
+    if let Some(plot) = &app.plot {
+        let plot_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(right);
+        render_plot(f, app, plot, plot_chunks[0]);
+        right = plot_chunks[1];
+    }
+    if app.show_notes {
+        let right_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(right);
+        areas.history = right_chunks[0];
+        areas.history_offset = render_history(f, app, right_chunks[0]);
+
+        let title = if app.editing_notes { "Notes (editing, Esc to finish)" } else { "Notes" };
+        let mut notes_text = app.notes.clone();
+        if app.editing_notes {
+            notes_text.push('_');
+        }
+        let notes = Paragraph::new(notes_text)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .wrap(Wrap { trim: false })
+            .style(app.theme.notes.style());
+        f.render_widget(notes, right_chunks[1]);
+    } else {
+        areas.history = right;
+        areas.history_offset = render_history(f, app, right);
+    }
+
+    if app.show_help {
+        let mut help_text = vec![
+            "RPN Calculator Help".to_string(),
+            format!("Version {}", app.version),
+            String::new(),
+        ];
+        help_text.extend(app.registry.help_lines());
+        help_text.push(String::new());
+        help_text.push("Tab completes commands; arrows/PgUp/PgDn scroll".to_string());
+        help_text.push(
+            "In the input: ↑/↓ recall lines, ←/→/Home/End move, Ctrl-U clears".to_string(),
+        );
+        help_text.push("Press any other key to close".to_string());
+
+        let help_lines: Vec<Line> = help_text
+            .into_iter()
+            .map(|text| Line::from(Span::raw(text)))
+            .collect();
+
+        let help_paragraph = Paragraph::new(help_lines)
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .style(app.theme.help.style())
+            .scroll((app.help_scroll, 0));
+
+        let popup_area = centered_rect(60, 60, f.size());
+
+        // Clear background behind the modal
+        let clear_bg = Block::default().style(app.theme.help.style());
+        f.render_widget(clear_bg, popup_area);
+        
+        f.render_widget(Clear, popup_area);
+        f.render_widget(help_paragraph, popup_area);
+    }
+
+    if app.show_tape {
+        render_tape(f, app, f.size());
+    }
+
+    if let Some(popup) = &app.popup {
+        let lines: Vec<Line> = popup.lines.iter().map(|l| Line::from(l.as_str())).collect();
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(popup.title.as_str()))
+            .style(app.theme.help.style())
+            .scroll((popup.scroll, 0));
+        let popup_area = centered_rect(70, 60, f.size());
+        f.render_widget(Clear, popup_area);
+        f.render_widget(paragraph, popup_area);
+    }
+
+    if let Some(chart) = &app.chart {
+        let area = centered_rect(80, 70, f.size());
+        f.render_widget(Clear, area);
+        render_chart(f, app, chart, area);
+    }
+
+    if let Some(import) = &app.table_import {
+        let (values, skipped) = import.values();
+        let preview = values
+            .iter()
+            .take(8)
+            .map(|&v| format_number(v))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let import_text = vec![
+            Line::from(format!("Delimiter: {}  (Tab/d to change)", import.delimiter.name())),
+            Line::from(format!(
+                "Column: {} of {}  (←/→ to change)",
+                import.column + 1,
+                import.column_count()
+            )),
+            Line::from(""),
+            Line::from(format!("{} numbers, {} skipped", values.len(), skipped)),
+            Line::from(preview),
+            Line::from(""),
+            Line::from("Enter to push, Esc to cancel"),
+        ];
+        let import_paragraph = Paragraph::new(import_text)
+            .block(Block::default().borders(Borders::ALL).title("Import Pasted Data"))
+            .style(app.theme.help.style());
+        let popup_area = centered_rect(60, 40, f.size());
+        f.render_widget(Clear, popup_area);
+        f.render_widget(import_paragraph, popup_area);
+    }
+    areas
+}
+
+/// The soft-key labels, one slot per key, in the spirit of an HP-48's menu.
+fn render_softkeys(f: &mut Frame, app: &App, area: Rect) {
+    let slots = softkey_slots(app);
+    let width = area.width as usize / slots.len();
+    let spans: Vec<Span> = slots
+        .into_iter()
+        .flat_map(|action| {
+            let label = softkeys::label(action, width.saturating_sub(1));
+            let style =
+                if action.is_empty() { Style::default() } else { app.theme.softkey.style() };
+            [Span::styled(label, style), Span::raw(" ")]
+        })
+        .collect();
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+fn render_plot(f: &mut Frame, app: &App, plot: &Plot, area: Rect) {
+    let block = Block::default().borders(Borders::ALL).title(plot.title());
+    let points = plot.points(&app.stack);
+    let Some((x_bounds, y_bounds)) = plot::bounds(&points) else {
+        let empty = Paragraph::new("No numbers to plot").block(block);
+        f.render_widget(empty, area);
+        return;
+    };
+    let labels = |[lo, hi]: [f64; 2]| {
+        vec![Span::raw(format_number(lo)), Span::raw(format_number(hi))]
+    };
+    let dataset = Dataset::default()
+        .marker(symbols::Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(app.theme.plot.style())
+        .data(&points);
+    let chart = Chart::new(vec![dataset])
+        .block(block)
+        .x_axis(Axis::default().bounds(x_bounds).labels(labels(x_bounds)))
+        .y_axis(Axis::default().bounds(y_bounds).labels(labels(y_bounds)));
+    f.render_widget(chart, area);
+}
+
+/// Draws a `chart` popup, with the lowest and highest x and y as axis
+/// labels.
+fn render_chart(f: &mut Frame, app: &App, chart: &DataChart, area: Rect) {
+    let block = Block::default().borders(Borders::ALL).title(chart.title());
+    let Some((x_bounds, y_bounds)) = plot::bounds(&chart.points) else {
+        return;
+    };
+    let labels = |[lo, hi]: [f64; 2]| {
+        vec![Span::raw(format_number(lo)), Span::raw(format_number(hi))]
+    };
+    let (marker, graph_type) = match chart.style {
+        ChartStyle::Line => (symbols::Marker::Braille, GraphType::Line),
+        ChartStyle::Scatter => (symbols::Marker::Dot, GraphType::Scatter),
+    };
+    let dataset = Dataset::default()
+        .marker(marker)
+        .graph_type(graph_type)
+        .style(app.theme.plot.style())
+        .data(&chart.points);
+    let x_title = if chart.pairs { "x" } else { "entry" };
+    let widget = Chart::new(vec![dataset])
+        .block(block)
+        .style(app.theme.help.style())
+        .x_axis(Axis::default().title(x_title).bounds(x_bounds).labels(labels(x_bounds)))
+        .y_axis(Axis::default().title("y").bounds(y_bounds).labels(labels(y_bounds)));
+    f.render_widget(widget, area);
+}
+
+/// The input line, with each token colored by kind once there are several.
+fn input_line(app: &App) -> Line<'_> {
+    let pieces = highlight::highlight(app, &app.input);
+    if pieces.iter().filter(|(_, kind)| kind.is_some()).count() < 2 {
+        return Line::from(app.input.as_str());
+    }
+    let spans: Vec<Span> = pieces
+        .into_iter()
+        .map(|(text, kind)| {
+            let colors = match kind {
+                Some(TokenKind::Number) => app.theme.number,
+                Some(TokenKind::Command) => app.theme.command,
+                Some(TokenKind::Quoted) => app.theme.quoted,
+                Some(TokenKind::Unknown) => app.theme.unknown,
+                Some(TokenKind::Argument) | None => app.theme.input,
+            };
+            Span::styled(text, colors.style())
+        })
+        .collect();
+    Line::from(spans)
+}
+
+/// Draws the parked values, most recent at the bottom as on the stack; when
+/// there are more than fit, the oldest are left out.
+fn render_shelf(f: &mut Frame, app: &App, area: Rect) {
+    let visible = area.height.saturating_sub(2) as usize;
+    let skip = app.shelf.len().saturating_sub(visible);
+    let items: Vec<ListItem> = app.shelf[skip..]
+        .iter()
+        .map(|value| ListItem::new(format_value_with(value, app.number_format())))
+        .collect();
+    let title = format!("Shelf ({})", app.shelf.len());
+    let shelf = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .style(app.theme.shelf.style());
+    f.render_widget(shelf, area);
+}
+
+/// Draws the history pane and returns the index of the first entry shown,
+/// unless a search is filtering it.
+fn render_history(f: &mut Frame, app: &App, area: Rect) -> Option<usize> {
+    let visible = area.height.saturating_sub(2) as usize;
+    let (entries, title, mut state) = match &app.history_search {
+        Some(search) => (
+            app.history_matches(),
+            format!("History search: {}_", search.query),
+            ListState::default().with_selected(Some(search.selected)),
+        ),
+        None => {
+            let entries: Vec<_> = app.calc_history.iter().collect();
+            let scroll = app.history_scroll.min(entries.len().saturating_sub(visible));
+            let offset = entries.len().saturating_sub(visible + scroll);
+            let title = if scroll > 0 {
+                format!("History (↑{})", scroll)
+            } else {
+                "History".to_string()
+            };
+            (entries, title, ListState::default().with_offset(offset))
+        }
+    };
+
+    let history_items: Vec<ListItem> = entries
+        .iter()
+        .map(|entry| ListItem::new(Line::from(Span::raw(entry.text.as_str()))))
+        .collect();
+
+    let history = List::new(history_items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .style(app.theme.history.style())
+        .highlight_style(app.theme.selection.style());
+    f.render_stateful_widget(history, area, &mut state);
+    app.history_search.is_none().then_some(state.offset())
+}
+
+/// Draws the tape over the whole screen, formatting only the lines that fit.
+fn render_tape(f: &mut Frame, app: &App, area: Rect) {
+    let (height, len) = (area.height.saturating_sub(2) as usize, app.tape.len());
+    // Scrolled all the way back, the first page still fills the screen.
+    let end = (len - app.tape_scroll.min(len)).max(height.min(len));
+    let start = end.saturating_sub(height);
+    let lines: Vec<ListItem> = app
+        .tape
+        .lines(start..end, app.number_format())
+        .into_iter()
+        .map(ListItem::new)
+        .collect();
+    let title = format!("Tape: entries {}-{} of {}", start + 1, end, len);
+    let tape = List::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .style(app.theme.history.style());
+    f.render_widget(Clear, area);
+    f.render_widget(tape, area);
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let popup_area = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area)[1];
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_area)[1]
+}
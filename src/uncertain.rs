@@ -0,0 +1,90 @@
+//! Measurements with an uncertainty, such as `9.81±0.02`.
+//!
+//! Uncertainties are standard deviations of independent errors and are
+//! propagated to first order: they add in quadrature for sums and
+//! differences, relative uncertainties do so for products and quotients.
+
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+
+/// A central value and its (non-negative) uncertainty.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Uncertain {
+    pub value: f64,
+    pub error: f64,
+}
+
+impl Uncertain {
+    pub fn new(value: f64, error: f64) -> Uncertain {
+        Uncertain {
+            value,
+            error: error.abs(),
+        }
+    }
+
+    /// An exact number.
+    pub fn exact(value: f64) -> Uncertain {
+        Uncertain::new(value, 0.0)
+    }
+
+    /// Reads `value±error`, also written `value+-error`.
+    pub fn parse(text: &str) -> Option<Uncertain> {
+        let (value, error) = text.split_once('±').or_else(|| text.split_once("+-"))?;
+        let error: f64 = error.parse().ok()?;
+        if error < 0.0 {
+            return None;
+        }
+        Some(Uncertain::new(value.parse().ok()?, error))
+    }
+
+    pub fn is_finite(self) -> bool {
+        self.value.is_finite() && self.error.is_finite()
+    }
+
+    pub fn sqrt(self) -> Uncertain {
+        let root = self.value.sqrt();
+        Uncertain::new(root, self.error / (2.0 * root))
+    }
+}
+
+impl Add for Uncertain {
+    type Output = Uncertain;
+
+    fn add(self, other: Uncertain) -> Uncertain {
+        Uncertain::new(self.value + other.value, self.error.hypot(other.error))
+    }
+}
+
+impl Sub for Uncertain {
+    type Output = Uncertain;
+
+    fn sub(self, other: Uncertain) -> Uncertain {
+        Uncertain::new(self.value - other.value, self.error.hypot(other.error))
+    }
+}
+
+impl Mul for Uncertain {
+    type Output = Uncertain;
+
+    fn mul(self, other: Uncertain) -> Uncertain {
+        let error = (other.value * self.error).hypot(self.value * other.error);
+        Uncertain::new(self.value * other.value, error)
+    }
+}
+
+impl Div for Uncertain {
+    type Output = Uncertain;
+
+    fn div(self, other: Uncertain) -> Uncertain {
+        let quotient = self.value / other.value;
+        let error = (self.error / other.value).hypot(quotient * other.error / other.value);
+        Uncertain::new(quotient, error)
+    }
+}
+
+/// Full precision, in the form `Uncertain::parse` reads back.
+impl fmt::Display for Uncertain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}±{}", self.value, self.error)
+    }
+}
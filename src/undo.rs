@@ -0,0 +1,236 @@
+//! The undo journal. Each step records only the part of the stack an
+//! operation replaced, so pushing a number costs the same however deep the
+//! stack is, and long scripted sessions do not keep a copy of the stack per
+//! step. The few operations that change state beside the stack note it as
+//! it was before, as an `Aside`, and undo hands it back to be restored.
+
+use crate::{HistoryEntry, PairStats, Tvm, Value};
+
+/// State beside the stack that an operation changed, as it was before.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Aside {
+    /// The values put aside with `park`.
+    Shelf(Vec<Value>),
+    /// The numbers entered since the last `clear` or `subtotal`.
+    Entered(Vec<Value>),
+    /// How many numbers had been entered, for a push that entered another.
+    EnteredLen(usize),
+    /// The statistical registers; see `sadd`.
+    Pairs(PairStats),
+    /// The TVM registers; see `setn`.
+    Tvm(Tvm),
+    /// History entries that `bind` worked out, each as it was and as `bind`
+    /// left it, so that it can be found again.
+    Tape(Vec<(HistoryEntry, HistoryEntry)>),
+}
+
+/// How one operation changed the stack: it left the `kept` bottom values
+/// alone and replaced the ones above them, `removed`, with `added` new ones.
+/// A push keeps everything, removes nothing and adds one. `asides` is what
+/// it changed beside the stack, oldest first.
+#[derive(Clone, Debug, PartialEq)]
+struct Change {
+    kept: usize,
+    removed: Vec<Value>,
+    added: usize,
+    asides: Vec<Aside>,
+}
+
+impl Change {
+    /// Puts `stack` back as it was before the change, returning the state
+    /// beside it to restore, in the order to restore it.
+    fn revert(self, stack: &mut Vec<Value>) -> Vec<Aside> {
+        stack.truncate(self.kept);
+        stack.extend(self.removed);
+        self.asides.into_iter().rev().collect()
+    }
+}
+
+/// A change started by `Journal::begin`, recorded once the operation
+/// succeeds.
+#[must_use]
+pub struct Pending(Option<Change>);
+
+impl Pending {
+    /// Puts `stack` back as it was when the change began, for an operation
+    /// that cannot be kept; false if the journal is disabled and kept no
+    /// copy.
+    pub fn revert(self, stack: &mut Vec<Value>) -> bool {
+        match self.0 {
+            Some(change) => {
+                change.revert(stack);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The journal as it was when a macro, script or control-flow block
+/// started; see `Journal::open_scope`.
+#[must_use]
+pub struct Scope {
+    len: usize,
+    floor: usize,
+    enabled: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Journal {
+    changes: Vec<Change>,
+    /// State noted by the operation being recorded, for its step.
+    noted: Vec<Aside>,
+    /// Steps that `undo` may not reach, because they come before the block
+    /// being run.
+    floor: usize,
+    enabled: bool,
+}
+
+impl Default for Journal {
+    fn default() -> Journal {
+        Journal {
+            changes: Vec::new(),
+            noted: Vec::new(),
+            floor: 0,
+            enabled: true,
+        }
+    }
+}
+
+impl Journal {
+    /// A journal that records nothing, for batch runs that never undo.
+    pub fn disabled() -> Journal {
+        Journal {
+            enabled: false,
+            ..Journal::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Undo steps recorded.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Starts recording an operation that may replace the top `depth` values
+    /// of `stack`, or any of it with `None`.
+    pub fn begin(&self, stack: &[Value], depth: Option<usize>) -> Pending {
+        if !self.enabled {
+            return Pending(None);
+        }
+        let kept = depth.map_or(0, |depth| stack.len().saturating_sub(depth));
+        Pending(Some(Change {
+            kept,
+            removed: stack[kept..].to_vec(),
+            added: 0,
+            asides: Vec::new(),
+        }))
+    }
+
+    /// Notes state beside the stack, as it was before the operation being
+    /// recorded changed it, for its undo step.
+    pub fn note(&mut self, aside: Aside) {
+        if self.enabled {
+            self.noted.push(aside);
+        }
+    }
+
+    /// What the operation being recorded noted, for an operation that
+    /// failed and should leave that state as it was.
+    pub fn take_noted(&mut self) -> Vec<Aside> {
+        let mut noted = std::mem::take(&mut self.noted);
+        noted.reverse();
+        noted
+    }
+
+    /// Records `pending` as one undo step, `stack` being the result.
+    pub fn commit(&mut self, pending: Pending, stack: &[Value]) {
+        if let Some(mut change) = pending.0 {
+            debug_assert!(stack.len() >= change.kept, "operation reached below its operands");
+            change.added = stack.len().saturating_sub(change.kept);
+            change.asides = std::mem::take(&mut self.noted);
+            self.changes.push(change);
+        }
+    }
+
+    /// Records a push of one value onto a stack of `len` values.
+    pub fn pushed(&mut self, len: usize) {
+        if self.enabled {
+            self.changes.push(Change {
+                kept: len,
+                removed: Vec::new(),
+                added: 1,
+                asides: std::mem::take(&mut self.noted),
+            });
+        }
+    }
+
+    /// Reverts the most recent step, returning the state beside the stack
+    /// to restore in the order to restore it; `None` if there is no step.
+    pub fn undo(&mut self, stack: &mut Vec<Value>) -> Option<Vec<Aside>> {
+        if self.changes.len() <= self.floor {
+            return None;
+        }
+        self.changes.pop().map(|change| change.revert(stack))
+    }
+
+    /// Starts a block that undoes as one step and is reverted if it fails.
+    /// Its steps are recorded even when the journal is disabled, so that
+    /// the stack can be restored without copying it first.
+    pub fn open_scope(&mut self) -> Scope {
+        let scope = Scope {
+            len: self.changes.len(),
+            floor: self.floor,
+            enabled: self.enabled,
+        };
+        self.floor = scope.len;
+        self.enabled = true;
+        scope
+    }
+
+    /// Ends a block that succeeded, merging its steps into one. Only the
+    /// part of the stack they touched is copied.
+    pub fn close_scope(&mut self, scope: Scope, stack: &[Value]) {
+        if scope.enabled {
+            let steps = &self.changes[scope.len..];
+            let kept = steps.iter().map(|c| c.kept).min().unwrap_or(stack.len());
+            let mut tail = stack[kept.min(stack.len())..].to_vec();
+            let mut asides = Vec::new();
+            for mut change in self.changes.drain(scope.len..).rev() {
+                change.kept -= kept;
+                asides.extend(change.revert(&mut tail));
+            }
+            asides.reverse();
+            self.changes.push(Change {
+                kept,
+                removed: tail,
+                added: stack.len().saturating_sub(kept),
+                asides,
+            });
+        } else {
+            self.changes.truncate(scope.len);
+        }
+        self.floor = scope.floor;
+        self.enabled = scope.enabled;
+    }
+
+    /// Ends a block that failed, putting `stack` back as it was before and
+    /// returning the state beside it to restore, in the order to restore it.
+    pub fn abandon_scope(&mut self, scope: Scope, stack: &mut Vec<Value>) -> Vec<Aside> {
+        self.floor = scope.len;
+        let mut asides = self.take_noted();
+        while let Some(more) = self.undo(stack) {
+            asides.extend(more);
+        }
+        self.floor = scope.floor;
+        self.enabled = scope.enabled;
+        asides
+    }
+}
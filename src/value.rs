@@ -0,0 +1,832 @@
+//! Stack values: plain numbers, exact money amounts and fractions, measurements with an
+//! uncertainty, calendar dates, matrices with vectors as the one-row or one-column case, and
+//! placeholders with the calculations waiting on them.
+
+use std::fmt;
+
+use crate::{prefix, CalcError, Date, Decimal, Formula, Rational, Uncertain};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(f64),
+    /// An exact amount, entered or converted in money mode.
+    Decimal(Decimal),
+    /// An exact fraction, entered or converted in fraction mode.
+    Rational(Rational),
+    /// A measurement such as `9.81±0.02`.
+    Uncertain(Uncertain),
+    Date(Date),
+    Matrix(Matrix),
+    /// A placeholder such as `?x`, or a calculation depending on one.
+    Formula(Formula),
+}
+
+impl Value {
+    /// Reads a number, which may end in an SI prefix such as the `k` of
+    /// `4.7k`, a fraction such as `1/3`, a measurement such as
+    /// `9.81±0.02`, a date such as
+    /// `2024-03-01`, a bracketed matrix literal such as `[1 2; 3 4]` or a
+    /// placeholder such as `?x`.
+    pub fn parse(text: &str) -> Result<Value, String> {
+        if let Some(f) = Formula::parse_placeholder(text) {
+            Ok(Value::Formula(f))
+        } else if let Some(d) = Date::parse(text) {
+            Ok(Value::Date(d))
+        } else if text.starts_with('[') {
+            Matrix::parse(text).map(Value::Matrix)
+        } else if let Some(u) = Uncertain::parse(text) {
+            Ok(Value::Uncertain(u))
+        } else if text.contains('/') {
+            Rational::parse(text)
+                .map(Value::Rational)
+                .ok_or_else(|| format!("'{}' is not a fraction", text))
+        } else {
+            text.parse()
+                .ok()
+                .or_else(|| prefix::parse(text))
+                .map(Value::Number)
+                .ok_or_else(|| format!("'{}' is not a number", text))
+        }
+    }
+
+    /// The number, or the central value of a measurement.
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Decimal(d) => Some(d.to_f64()),
+            Value::Rational(r) => Some(r.to_f64()),
+            Value::Uncertain(u) => Some(u.value),
+            Value::Date(_) | Value::Matrix(_) | Value::Formula(_) => None,
+        }
+    }
+
+    /// True when the number, or every matrix entry, is finite.
+    pub fn is_finite(&self) -> bool {
+        match self {
+            Value::Number(n) => n.is_finite(),
+            Value::Decimal(_) | Value::Rational(_) | Value::Date(_) | Value::Formula(_) => true,
+            Value::Uncertain(u) => u.is_finite(),
+            Value::Matrix(m) => m.data.iter().all(|v| v.is_finite()),
+        }
+    }
+
+    /// True for placeholders and calculations waiting on one.
+    pub fn is_formula(&self) -> bool {
+        matches!(self, Value::Formula(_))
+    }
+
+    pub fn as_matrix(&self) -> Option<&Matrix> {
+        match self {
+            Value::Matrix(m) => Some(m),
+            _ => None,
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Value {
+        Value::Number(n)
+    }
+}
+
+impl From<Decimal> for Value {
+    fn from(d: Decimal) -> Value {
+        Value::Decimal(d)
+    }
+}
+
+impl From<Rational> for Value {
+    fn from(r: Rational) -> Value {
+        Value::Rational(r)
+    }
+}
+
+impl From<Uncertain> for Value {
+    fn from(u: Uncertain) -> Value {
+        Value::Uncertain(u)
+    }
+}
+
+impl From<Date> for Value {
+    fn from(d: Date) -> Value {
+        Value::Date(d)
+    }
+}
+
+impl From<Matrix> for Value {
+    fn from(m: Matrix) -> Value {
+        Value::Matrix(m)
+    }
+}
+
+impl PartialEq<f64> for Value {
+    fn eq(&self, other: &f64) -> bool {
+        self.as_number() == Some(*other)
+    }
+}
+
+/// Full precision, in the form `Value::parse` reads back; of formulas only
+/// placeholders can be read back.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Decimal(d) => write!(f, "{}", d),
+            Value::Rational(r) => write!(f, "{}", r),
+            Value::Uncertain(u) => write!(f, "{}", u),
+            Value::Date(d) => write!(f, "{}", d),
+            Value::Matrix(m) => write!(f, "{}", m.format_with(|v| v.to_string())),
+            Value::Formula(formula) => write!(f, "{}", formula.describe(&|v| v.to_string())),
+        }
+    }
+}
+
+/// Wraps plain numbers as stack values.
+pub fn numbers(values: &[f64]) -> Vec<Value> {
+    values.iter().map(|&v| Value::Number(v)).collect()
+}
+
+/// Writes `values` exactly, as words tagged with their kind and separated
+/// by spaces, for `decode_exact` to read back:
+///
+/// ```text
+/// num:0.1  dec:12.500000  frac:1/3  unc:9.81:0.02  date:19783
+/// mat:2:2:1,2,3,4  var:x  app:2:+ var:x num:3
+/// ```
+///
+/// Floating-point numbers are written in full, so they read back to the
+/// same bits. A formula waiting on a command is the command with its
+/// operand count, followed by the operands.
+pub fn encode_exact(values: &[Value]) -> String {
+    let mut words = Vec::new();
+    for value in values {
+        encode_value(value, &mut words);
+    }
+    words.join(" ")
+}
+
+/// The values written by `encode_exact`; `None` if any of the text is not
+/// one.
+pub fn decode_exact(text: &str) -> Option<Vec<Value>> {
+    let mut words = text.split(' ').filter(|w| !w.is_empty()).peekable();
+    let mut values = Vec::new();
+    while words.peek().is_some() {
+        values.push(decode_value(&mut words)?);
+    }
+    Some(values)
+}
+
+fn encode_value(value: &Value, words: &mut Vec<String>) {
+    match value {
+        Value::Number(n) => words.push(format!("num:{:?}", n)),
+        Value::Decimal(d) => words.push(format!("dec:{}", d.format(crate::decimal::PLACES))),
+        Value::Rational(r) => words.push(format!("frac:{}/{}", r.numer(), r.denom())),
+        Value::Uncertain(u) => words.push(format!("unc:{:?}:{:?}", u.value, u.error)),
+        Value::Date(d) => words.push(format!("date:{}", d.days())),
+        Value::Matrix(m) => {
+            let data: Vec<String> = m.data().iter().map(|x| format!("{:?}", x)).collect();
+            words.push(format!("mat:{}:{}:{}", m.rows(), m.cols(), data.join(",")));
+        }
+        Value::Formula(Formula::Placeholder(name)) => words.push(format!("var:{}", name)),
+        Value::Formula(Formula::Apply { op, args }) => {
+            words.push(format!("app:{}:{}", args.len(), op));
+            for arg in args {
+                encode_value(arg, words);
+            }
+        }
+    }
+}
+
+/// Reads the next value written by `encode_value`.
+fn decode_value<'a>(words: &mut impl Iterator<Item = &'a str>) -> Option<Value> {
+    let (tag, text) = words.next()?.split_once(':')?;
+    let value = match tag {
+        "num" => Value::Number(text.parse().ok()?),
+        "dec" => Value::Decimal(Decimal::parse(text)?),
+        "frac" => {
+            let (num, den) = text.split_once('/')?;
+            Value::Rational(Rational::new(num.parse().ok()?, den.parse().ok()?)?)
+        }
+        "unc" => {
+            let (value, error) = text.split_once(':')?;
+            Value::Uncertain(Uncertain {
+                value: value.parse().ok()?,
+                error: error.parse().ok()?,
+            })
+        }
+        "date" => Value::Date(Date::from_days(text.parse().ok()?)),
+        "mat" => {
+            let mut parts = text.splitn(3, ':');
+            let rows: usize = parts.next()?.parse().ok()?;
+            let cols: usize = parts.next()?.parse().ok()?;
+            let data = parts
+                .next()?
+                .split(',')
+                .filter(|x| !x.is_empty())
+                .map(|x| x.parse().ok())
+                .collect::<Option<Vec<f64>>>()?;
+            if data.len() != rows.checked_mul(cols)? {
+                return None;
+            }
+            Value::Matrix(Matrix::new(rows, cols, data))
+        }
+        "var" => Value::Formula(Formula::parse_placeholder(&format!("?{}", text))?),
+        "app" => {
+            let (count, op) = text.split_once(':')?;
+            let count: usize = count.parse().ok().filter(|_| !op.is_empty())?;
+            let args = (0..count)
+                .map(|_| decode_value(words))
+                .collect::<Option<Vec<Value>>>()?;
+            Value::Formula(Formula::Apply {
+                op: op.to_string(),
+                args,
+            })
+        }
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// A dense matrix stored row by row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>,
+}
+
+impl Matrix {
+    pub fn new(rows: usize, cols: usize, data: Vec<f64>) -> Matrix {
+        assert_eq!(
+            data.len(),
+            rows * cols,
+            "matrix data does not match its shape"
+        );
+        Matrix { rows, cols, data }
+    }
+
+    /// A one-row matrix.
+    pub fn vector(data: Vec<f64>) -> Matrix {
+        Matrix::new(1, data.len(), data)
+    }
+
+    pub fn identity(n: usize) -> Matrix {
+        let mut m = Matrix::new(n, n, vec![0.0; n * n]);
+        for i in 0..n {
+            m.data[i * n + i] = 1.0;
+        }
+        m
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn data(&self) -> &[f64] {
+        &self.data
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        self.data[row * self.cols + col]
+    }
+
+    pub fn row(&self, row: usize) -> &[f64] {
+        &self.data[row * self.cols..(row + 1) * self.cols]
+    }
+
+    pub fn is_vector(&self) -> bool {
+        self.rows == 1 || self.cols == 1
+    }
+
+    /// The shape as shown in messages, e.g. `2×3`.
+    pub fn shape(&self) -> String {
+        format!("{}×{}", self.rows, self.cols)
+    }
+
+    /// Reads `[1 2 3]`, `[1 2; 3 4]` or `[[1 2] [3 4]]`. Commas may separate
+    /// entries too.
+    pub fn parse(text: &str) -> Result<Matrix, String> {
+        let inner = text
+            .trim()
+            .strip_prefix('[')
+            .and_then(|t| t.strip_suffix(']'))
+            .ok_or_else(|| format!("'{}' is not a bracketed matrix", text))?;
+        let rows: Vec<&str> = if inner.contains('[') {
+            inner
+                .split(']')
+                .map(|r| r.trim().trim_start_matches(['[', ';', ',']).trim())
+                .filter(|r| !r.is_empty())
+                .collect()
+        } else {
+            inner.split(';').collect()
+        };
+        let mut data = Vec::new();
+        let mut cols = None;
+        for row in &rows {
+            let mut len = 0;
+            for entry in row.split([' ', ',', '[']).filter(|e| !e.is_empty()) {
+                data.push(
+                    entry
+                        .parse::<f64>()
+                        .ok()
+                        .or_else(|| prefix::parse(entry))
+                        .ok_or_else(|| format!("'{}' is not a number", entry))?,
+                );
+                len += 1;
+            }
+            if *cols.get_or_insert(len) != len {
+                return Err("All rows of a matrix must have the same length".to_string());
+            }
+        }
+        match cols {
+            Some(cols) if cols > 0 => Ok(Matrix::new(rows.len(), cols, data)),
+            _ => Err("A matrix needs at least one entry".to_string()),
+        }
+    }
+
+    /// Rows separated by `; `, entries by spaces.
+    pub fn format_with(&self, entry: impl Fn(f64) -> String) -> String {
+        let rows: Vec<String> = (0..self.rows)
+            .map(|r| {
+                self.row(r)
+                    .iter()
+                    .map(|&v| entry(v))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        format!("[{}]", rows.join("; "))
+    }
+
+    pub fn map(&self, f: impl Fn(f64) -> f64) -> Matrix {
+        Matrix::new(
+            self.rows,
+            self.cols,
+            self.data.iter().map(|&v| f(v)).collect(),
+        )
+    }
+
+    /// Combines entries pairwise; both matrices must have the same shape.
+    pub fn zip(&self, other: &Matrix, f: impl Fn(f64, f64) -> f64) -> Result<Matrix, String> {
+        if (self.rows, self.cols) != (other.rows, other.cols) {
+            return Err(format!(
+                "Shapes {} and {} differ",
+                self.shape(),
+                other.shape()
+            ));
+        }
+        let data = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(&a, &b)| f(a, b))
+            .collect();
+        Ok(Matrix::new(self.rows, self.cols, data))
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        let mut data = Vec::with_capacity(self.data.len());
+        for c in 0..self.cols {
+            for r in 0..self.rows {
+                data.push(self.get(r, c));
+            }
+        }
+        Matrix::new(self.cols, self.rows, data)
+    }
+
+    pub fn mul(&self, other: &Matrix) -> Result<Matrix, String> {
+        if self.cols != other.rows {
+            return Err(format!(
+                "Cannot multiply {} by {}",
+                self.shape(),
+                other.shape()
+            ));
+        }
+        let mut data = vec![0.0; self.rows * other.cols];
+        for r in 0..self.rows {
+            for c in 0..other.cols {
+                data[r * other.cols + c] = (0..self.cols)
+                    .map(|k| self.get(r, k) * other.get(k, c))
+                    .sum();
+            }
+        }
+        Ok(Matrix::new(self.rows, other.cols, data))
+    }
+
+    /// Euclidean norm of a vector, Frobenius norm of a matrix.
+    pub fn norm(&self) -> f64 {
+        self.data.iter().map(|v| v * v).sum::<f64>().sqrt()
+    }
+
+    pub fn det(&self) -> Result<f64, String> {
+        self.require_square("det")?;
+        let mut m = self.clone();
+        let n = self.rows;
+        let mut det = 1.0;
+        for col in 0..n {
+            let Some(pivot) = m.pivot_row(col) else {
+                return Ok(0.0);
+            };
+            if pivot != col {
+                m.swap_rows(pivot, col);
+                det = -det;
+            }
+            let p = m.get(col, col);
+            det *= p;
+            for r in col + 1..n {
+                let factor = m.get(r, col) / p;
+                m.sub_row(r, col, factor);
+            }
+        }
+        Ok(det)
+    }
+
+    /// Gauss-Jordan elimination with partial pivoting.
+    pub fn inverse(&self) -> Result<Matrix, String> {
+        self.require_square("inv")?;
+        let n = self.rows;
+        let mut m = self.clone();
+        let mut inv = Matrix::identity(n);
+        for col in 0..n {
+            let pivot = m.pivot_row(col).ok_or("Matrix is singular")?;
+            m.swap_rows(pivot, col);
+            inv.swap_rows(pivot, col);
+            let p = m.get(col, col);
+            for c in 0..n {
+                m.data[col * n + c] /= p;
+                inv.data[col * n + c] /= p;
+            }
+            for r in (0..n).filter(|&r| r != col) {
+                let factor = m.get(r, col);
+                m.sub_row(r, col, factor);
+                inv.sub_row(r, col, factor);
+            }
+        }
+        Ok(inv)
+    }
+
+    fn require_square(&self, op: &str) -> Result<(), String> {
+        if self.rows == self.cols {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} needs a square matrix, not {}",
+                op,
+                self.shape()
+            ))
+        }
+    }
+
+    /// The row at or below `col` with the largest entry in that column, if
+    /// that entry is not (numerically) zero.
+    fn pivot_row(&self, col: usize) -> Option<usize> {
+        let scale = self.data.iter().fold(0.0_f64, |m, v| m.max(v.abs()));
+        (col..self.rows)
+            .max_by(|&a, &b| self.get(a, col).abs().total_cmp(&self.get(b, col).abs()))
+            .filter(|&r| self.get(r, col).abs() > scale * 1e-12)
+    }
+
+    fn swap_rows(&mut self, a: usize, b: usize) {
+        for c in 0..self.cols {
+            self.data.swap(a * self.cols + c, b * self.cols + c);
+        }
+    }
+
+    /// Subtracts `factor` times row `src` from row `dst`.
+    fn sub_row(&mut self, dst: usize, src: usize, factor: f64) {
+        for c in 0..self.cols {
+            self.data[dst * self.cols + c] -= factor * self.data[src * self.cols + c];
+        }
+    }
+}
+
+fn domain(msg: String) -> CalcError {
+    CalcError::DomainError(msg)
+}
+
+fn scalar_or_elementwise(
+    a: &Value,
+    b: &Value,
+    op: &str,
+    f: fn(f64, f64) -> f64,
+) -> Result<Value, CalcError> {
+    let scalar = |v: &Value| {
+        v.as_number()
+            .ok_or_else(|| CalcError::wrong_type(op, "numbers or matrices"))
+    };
+    Ok(match (a, b) {
+        (Value::Matrix(a), Value::Matrix(b)) => a.zip(b, f).map_err(domain)?.into(),
+        (Value::Matrix(a), b) => {
+            let b = scalar(b)?;
+            a.map(|v| f(v, b)).into()
+        }
+        (a, Value::Matrix(b)) => {
+            let a = scalar(a)?;
+            b.map(|v| f(a, v)).into()
+        }
+        (a, b) => Value::Number(f(scalar(a)?, scalar(b)?)),
+    })
+}
+
+/// Both operands as decimals when at least one is a decimal and the other is
+/// a number that fits.
+fn decimals(a: &Value, b: &Value) -> Option<(Decimal, Decimal)> {
+    match (a, b) {
+        (Value::Decimal(x), Value::Decimal(y)) => Some((*x, *y)),
+        (Value::Decimal(x), Value::Number(y)) => Some((*x, Decimal::from_f64(*y)?)),
+        (Value::Number(x), Value::Decimal(y)) => Some((Decimal::from_f64(*x)?, *y)),
+        _ => None,
+    }
+}
+
+/// Both operands as fractions when at least one is a fraction and the other
+/// is a whole number.
+fn rationals(a: &Value, b: &Value) -> Option<(Rational, Rational)> {
+    let exact = |v: &Value| match v {
+        Value::Rational(r) => Some(*r),
+        Value::Number(n) => Rational::from_integer(*n),
+        _ => None,
+    };
+    match (a, b) {
+        (Value::Rational(_), _) | (_, Value::Rational(_)) => Some((exact(a)?, exact(b)?)),
+        _ => None,
+    }
+}
+
+/// Both operands as measurements when at least one is a measurement and the
+/// other is a number.
+fn uncertains(a: &Value, b: &Value) -> Option<(Uncertain, Uncertain)> {
+    let measure = |v: &Value| match v {
+        Value::Uncertain(u) => Some(*u),
+        Value::Matrix(_) => None,
+        v => v.as_number().map(Uncertain::exact),
+    };
+    match (a, b) {
+        (Value::Uncertain(_), _) | (_, Value::Uncertain(_)) => Some((measure(a)?, measure(b)?)),
+        _ => None,
+    }
+}
+
+fn exact(result: Result<Decimal, &'static str>) -> Result<Value, CalcError> {
+    result.map(Value::Decimal).map_err(CalcError::domain)
+}
+
+/// A whole number of days to move a date by.
+fn days(v: &Value, op: &str) -> Result<i64, CalcError> {
+    v.as_number()
+        .filter(|n| n.fract() == 0.0 && n.abs() < 1e9)
+        .map(|n| n as i64)
+        .ok_or_else(|| CalcError::wrong_type(op, "a whole number of days"))
+}
+
+/// Sum of numbers, a matrix and a number (entrywise), or two matrices of
+/// the same shape. A date plus a number of days is a date.
+pub fn add(a: &Value, b: &Value) -> Result<Value, CalcError> {
+    match (a, b) {
+        (Value::Date(_), Value::Date(_)) => return Err(CalcError::wrong_type("+", "one date")),
+        (Value::Date(d), n) | (n, Value::Date(d)) => return Ok(d.add_days(days(n, "+")?).into()),
+        _ => {}
+    }
+    if let Some((x, y)) = decimals(a, b) {
+        return exact(x.checked_add(y));
+    }
+    if let Some(sum) = rationals(a, b).and_then(|(x, y)| x.checked_add(y)) {
+        return Ok(sum.into());
+    }
+    if let Some((x, y)) = uncertains(a, b) {
+        return Ok((x + y).into());
+    }
+    scalar_or_elementwise(a, b, "+", |a, b| a + b)
+}
+
+/// Difference of numbers or matrices as for `add`. Dates less a number of
+/// days are dates; one date less another is the days between them.
+pub fn sub(a: &Value, b: &Value) -> Result<Value, CalcError> {
+    match (a, b) {
+        (Value::Date(x), Value::Date(y)) => return Ok(Value::Number((x.days() - y.days()) as f64)),
+        (Value::Date(d), n) => return Ok(d.add_days(-days(n, "-")?).into()),
+        (_, Value::Date(_)) => return Err(CalcError::wrong_type("-", "a date to subtract from")),
+        _ => {}
+    }
+    if let Some((x, y)) = decimals(a, b) {
+        return exact(x.checked_sub(y));
+    }
+    if let Some(difference) = rationals(a, b).and_then(|(x, y)| x.checked_sub(y)) {
+        return Ok(difference.into());
+    }
+    if let Some((x, y)) = uncertains(a, b) {
+        return Ok((x - y).into());
+    }
+    scalar_or_elementwise(a, b, "-", |a, b| a - b)
+}
+
+/// Scales by numbers; two matrices are multiplied as matrices.
+pub fn mul(a: &Value, b: &Value) -> Result<Value, CalcError> {
+    if let Some((x, y)) = decimals(a, b) {
+        return exact(x.checked_mul(y));
+    }
+    if let Some(product) = rationals(a, b).and_then(|(x, y)| x.checked_mul(y)) {
+        return Ok(product.into());
+    }
+    if let Some((x, y)) = uncertains(a, b) {
+        return Ok((x * y).into());
+    }
+    match (a, b) {
+        (Value::Matrix(a), Value::Matrix(b)) => Ok(a.mul(b).map_err(domain)?.into()),
+        _ => scalar_or_elementwise(a, b, "*", |a, b| a * b),
+    }
+}
+
+/// Division by a number; nothing can be divided by a matrix.
+pub fn div(a: &Value, b: &Value) -> Result<Value, CalcError> {
+    match b.as_number() {
+        None => Err(CalcError::wrong_type("/", "a number as divisor")),
+        Some(0.0) => Err(CalcError::DivisionByZero),
+        Some(_) => {
+            let quotient = rationals(a, b).and_then(|(x, y)| x.checked_div(y));
+            match (decimals(a, b), quotient, uncertains(a, b)) {
+                (Some((x, y)), _, _) => exact(x.checked_div(y)),
+                (_, Some(quotient), _) => Ok(quotient.into()),
+                (_, _, Some((x, y))) => Ok((x / y).into()),
+                _ => scalar_or_elementwise(a, b, "/", |a, b| a / b),
+            }
+        }
+    }
+}
+
+/// The number, money amount, fraction, measurement or matrix with its sign
+/// changed.
+pub fn neg(v: &Value) -> Result<Value, CalcError> {
+    Ok(match v {
+        Value::Number(n) => Value::Number(-n),
+        Value::Decimal(d) => return exact(Decimal::ZERO.checked_sub(*d)),
+        Value::Rational(r) => match r.checked_neg() {
+            Some(r) => r.into(),
+            None => Value::Number(-r.to_f64()),
+        },
+        Value::Uncertain(u) => Uncertain::new(-u.value, u.error).into(),
+        Value::Matrix(m) => m.map(|x| -x).into(),
+        _ => return Err(CalcError::wrong_type("chs", "a number or matrix")),
+    })
+}
+
+/// Applies a rounding function to a number, a money amount (which stays
+/// one), a fraction (which becomes a number) or every entry of a matrix.
+pub fn round_with(v: &Value, op: &str, f: impl Fn(f64) -> f64) -> Result<Value, CalcError> {
+    match v {
+        Value::Number(n) => Ok(Value::Number(f(*n))),
+        Value::Decimal(d) => Decimal::from_f64(f(d.to_f64()))
+            .map(Value::Decimal)
+            .ok_or_else(|| CalcError::domain("Money amount out of range")),
+        Value::Rational(r) => Ok(Value::Number(f(r.to_f64()))),
+        Value::Matrix(m) => Ok(m.map(f).into()),
+        _ => Err(CalcError::wrong_type(op, "a number or matrix")),
+    }
+}
+
+/// Rounds half away from zero to `places` decimal places, or to tens,
+/// hundreds, … for negative `places`. Works on the shortest decimal form of
+/// `x`, so 2.675 rounds to 2.68 as typed rather than down by its binary
+/// value.
+pub fn round_places(x: f64, places: i32) -> f64 {
+    if !x.is_finite() || x == 0.0 {
+        return x;
+    }
+    let sci = format!("{:e}", x.abs());
+    let (mantissa, exponent) = sci.split_once('e').expect("exponent notation");
+    let exponent: i32 = exponent.parse().expect("integer exponent");
+    let digits: Vec<u8> = mantissa
+        .bytes()
+        .filter(u8::is_ascii_digit)
+        .map(|b| b - b'0')
+        .collect();
+    // The first digit is worth 10^exponent, so this many are kept.
+    let keep = exponent + places + 1;
+    if keep < 0 {
+        return 0.0_f64.copysign(x);
+    }
+    let keep = keep as usize;
+    if keep >= digits.len() {
+        return x;
+    }
+    let mut kept = digits[..keep].iter().fold(0_u64, |n, &d| n * 10 + d as u64);
+    if digits[keep] >= 5 {
+        kept += 1;
+    }
+    let rounded: f64 = format!("{}e{}", kept, -places)
+        .parse()
+        .expect("a valid float");
+    rounded.copysign(x)
+}
+
+/// Square root of a number or measurement.
+pub fn sqrt(v: &Value) -> Result<Value, CalcError> {
+    match v {
+        Value::Uncertain(u) => Ok(u.sqrt().into()),
+        v => v
+            .as_number()
+            .map(|n| Value::Number(n.sqrt()))
+            .ok_or_else(|| CalcError::wrong_type("sqrt", "a number")),
+    }
+}
+
+fn vectors<'a>(
+    a: &'a Value,
+    b: &'a Value,
+    op: &str,
+) -> Result<(&'a Matrix, &'a Matrix), CalcError> {
+    match (a.as_matrix(), b.as_matrix()) {
+        (Some(a), Some(b)) if a.is_vector() && b.is_vector() => {
+            if a.data.len() != b.data.len() {
+                return Err(domain(format!(
+                    "{} needs vectors of the same length, not {} and {}",
+                    op,
+                    a.data.len(),
+                    b.data.len()
+                )));
+            }
+            Ok((a, b))
+        }
+        _ => Err(CalcError::wrong_type(op, "two vectors")),
+    }
+}
+
+pub fn dot(a: &Value, b: &Value) -> Result<Value, CalcError> {
+    let (a, b) = vectors(a, b, "dot")?;
+    Ok(Value::Number(
+        a.data.iter().zip(&b.data).map(|(x, y)| x * y).sum(),
+    ))
+}
+
+/// Cross product of two 3-vectors, shaped like `a`.
+pub fn cross(a: &Value, b: &Value) -> Result<Value, CalcError> {
+    let (m, n) = vectors(a, b, "cross")?;
+    let (u, v) = (&m.data, &n.data);
+    if u.len() != 3 {
+        return Err(domain("cross needs 3-element vectors".to_string()));
+    }
+    let data = vec![
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    Ok(Matrix::new(m.rows, m.cols, data).into())
+}
+
+fn matrix<'a>(v: &'a Value, op: &str) -> Result<&'a Matrix, CalcError> {
+    v.as_matrix()
+        .ok_or_else(|| CalcError::wrong_type(op, "a matrix or vector"))
+}
+
+pub fn norm(v: &Value) -> Result<Value, CalcError> {
+    Ok(Value::Number(matrix(v, "norm")?.norm()))
+}
+
+pub fn transpose(v: &Value) -> Result<Value, CalcError> {
+    Ok(matrix(v, "transpose")?.transpose().into())
+}
+
+pub fn det(v: &Value) -> Result<Value, CalcError> {
+    Ok(Value::Number(matrix(v, "det")?.det().map_err(domain)?))
+}
+
+pub fn inverse(v: &Value) -> Result<Value, CalcError> {
+    Ok(matrix(v, "inv")?.inverse().map_err(domain)?.into())
+}
+
+fn date(v: &Value, op: &str) -> Result<Date, CalcError> {
+    match v {
+        Value::Date(d) => Ok(*d),
+        _ => Err(CalcError::wrong_type(op, "a date")),
+    }
+}
+
+/// Days from date `a` to date `b`.
+pub fn ddays(a: &Value, b: &Value) -> Result<Value, CalcError> {
+    let (a, b) = (date(a, "ddays")?, date(b, "ddays")?);
+    Ok(Value::Number((b.days() - a.days()) as f64))
+}
+
+pub fn dadd(a: &Value, b: &Value) -> Result<Value, CalcError> {
+    Ok(date(a, "dadd")?.add_days(days(b, "dadd")?).into())
+}
+
+/// ISO day of the week, 1 for Monday through 7 for Sunday.
+pub fn weekday(v: &Value) -> Result<Value, CalcError> {
+    Ok(Value::Number(date(v, "dow")?.weekday() as f64))
+}
+
+pub fn to_unix(v: &Value) -> Result<Value, CalcError> {
+    Ok(Value::Number(date(v, "unix")?.unix() as f64))
+}
+
+pub fn from_unix(v: &Value) -> Result<Value, CalcError> {
+    v.as_number()
+        .and_then(Date::from_unix)
+        .map(Value::Date)
+        .ok_or_else(|| CalcError::wrong_type("fromunix", "a Unix timestamp"))
+}
@@ -0,0 +1,18 @@
+//! Independent stacks to switch between, one per problem being worked on.
+
+use crate::{Journal, Value};
+
+/// Workspaces `ws` and the F-keys can reach.
+pub const MAX_WORKSPACES: usize = 9;
+
+/// A workspace that is not current. The current one lives in the `App`
+/// itself, so that every operation works on `App::stack` as before.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Workspace {
+    pub stack: Vec<Value>,
+    pub journal: Journal,
+    pub mark: Option<f64>,
+    pub last_x: Option<Value>,
+    pub entered: Vec<Value>,
+    pub shelf: Vec<Value>,
+}
@@ -11,7 +11,7 @@ fn test_push_number() {
 #[test]
 fn test_addition() {
     let mut app = App::new();
-    app.stack = vec![3.0, 4.0];
+    app.stack = numbers(&[3.0, 4.0]);
     app.input = "+".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![7.0]);
@@ -20,7 +20,7 @@ fn test_addition() {
 #[test]
 fn test_subtraction() {
     let mut app = App::new();
-    app.stack = vec![10.0, 3.0];
+    app.stack = numbers(&[10.0, 3.0]);
     app.input = "-".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![7.0]);
@@ -29,7 +29,7 @@ fn test_subtraction() {
 #[test]
 fn test_multiplication() {
     let mut app = App::new();
-    app.stack = vec![3.0, 4.0];
+    app.stack = numbers(&[3.0, 4.0]);
     app.input = "*".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![12.0]);
@@ -38,7 +38,7 @@ fn test_multiplication() {
 #[test]
 fn test_division() {
     let mut app = App::new();
-    app.stack = vec![12.0, 3.0];
+    app.stack = numbers(&[12.0, 3.0]);
     app.input = "/".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![4.0]);
@@ -47,7 +47,7 @@ fn test_division() {
 #[test]
 fn test_division_by_zero() {
     let mut app = App::new();
-    app.stack = vec![5.0, 0.0];
+    app.stack = numbers(&[5.0, 0.0]);
     app.input = "/".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![5.0, 0.0]);
@@ -57,7 +57,7 @@ fn test_division_by_zero() {
 #[test]
 fn test_power() {
     let mut app = App::new();
-    app.stack = vec![2.0, 3.0];
+    app.stack = numbers(&[2.0, 3.0]);
     app.input = "^".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![8.0]);
@@ -66,7 +66,7 @@ fn test_power() {
 #[test]
 fn test_modulo() {
     let mut app = App::new();
-    app.stack = vec![10.0, 3.0];
+    app.stack = numbers(&[10.0, 3.0]);
     app.input = "%".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![1.0]);
@@ -75,7 +75,7 @@ fn test_modulo() {
 #[test]
 fn test_sqrt() {
     let mut app = App::new();
-    app.stack = vec![16.0];
+    app.stack = numbers(&[16.0]);
     app.input = "sqrt".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![4.0]);
@@ -84,7 +84,7 @@ fn test_sqrt() {
 #[test]
 fn test_reciprocal() {
     let mut app = App::new();
-    app.stack = vec![4.0];
+    app.stack = numbers(&[4.0]);
     app.input = "inv".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![0.25]);
@@ -93,7 +93,7 @@ fn test_reciprocal() {
 #[test]
 fn test_reciprocal_zero() {
     let mut app = App::new();
-    app.stack = vec![0.0];
+    app.stack = numbers(&[0.0]);
     app.input = "inv".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![0.0]);
@@ -103,7 +103,7 @@ fn test_reciprocal_zero() {
 #[test]
 fn test_factorial() {
     let mut app = App::new();
-    app.stack = vec![5.0];
+    app.stack = numbers(&[5.0]);
     app.input = "!".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![120.0]);
@@ -112,7 +112,7 @@ fn test_factorial() {
 #[test]
 fn test_factorial_negative() {
     let mut app = App::new();
-    app.stack = vec![-1.0];
+    app.stack = numbers(&[-1.0]);
     app.input = "!".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![-1.0]);
@@ -122,25 +122,25 @@ fn test_factorial_negative() {
 #[test]
 fn test_sin() {
     let mut app = App::new();
-    app.stack = vec![90.0];
+    app.stack = numbers(&[90.0]);
     app.input = "sin".to_string();
     app.execute_command();
-    assert!((app.stack[0] - 1.0).abs() < 1e-10);
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 1.0));
 }
 
 #[test]
 fn test_cos() {
     let mut app = App::new();
-    app.stack = vec![0.0];
+    app.stack = numbers(&[0.0]);
     app.input = "cos".to_string();
     app.execute_command();
-    assert!((app.stack[0] - 1.0).abs() < 1e-10);
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 1.0));
 }
 
 #[test]
 fn test_swap() {
     let mut app = App::new();
-    app.stack = vec![1.0, 2.0];
+    app.stack = numbers(&[1.0, 2.0]);
     app.input = "swap".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![2.0, 1.0]);
@@ -149,7 +149,7 @@ fn test_swap() {
 #[test]
 fn test_swap_insufficient() {
     let mut app = App::new();
-    app.stack = vec![1.0];
+    app.stack = numbers(&[1.0]);
     app.input = "swap".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![1.0]);
@@ -159,7 +159,7 @@ fn test_swap_insufficient() {
 #[test]
 fn test_drop() {
     let mut app = App::new();
-    app.stack = vec![1.0, 2.0, 3.0];
+    app.stack = numbers(&[1.0, 2.0, 3.0]);
     app.input = "drop".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![1.0, 2.0]);
@@ -170,24 +170,25 @@ fn test_drop_empty() {
     let mut app = App::new();
     app.input = "drop".to_string();
     app.execute_command();
-    assert_eq!(app.stack, vec![]);
+    assert!(app.stack.is_empty());
     assert!(app.message.contains("Stack is empty"));
 }
 
 #[test]
 fn test_clear() {
     let mut app = App::new();
-    app.stack = vec![1.0, 2.0, 3.0];
+    app.stack = numbers(&[1.0, 2.0, 3.0]);
     app.input = "clear".to_string();
     app.execute_command();
-    assert_eq!(app.stack, vec![]);
+    assert!(app.stack.is_empty());
 }
 
 #[test]
 fn test_undo() {
     let mut app = App::new();
-    app.stack = vec![1.0, 2.0];
-    app.history.push(vec![1.0]);
+    app.stack = numbers(&[1.0]);
+    app.input = "2".to_string();
+    app.execute_command();
     app.input = "undo".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![1.0]);
@@ -196,7 +197,7 @@ fn test_undo() {
 #[test]
 fn test_undo_empty_history() {
     let mut app = App::new();
-    app.stack = vec![1.0];
+    app.stack = numbers(&[1.0]);
     app.input = "undo".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![1.0]);
@@ -206,7 +207,7 @@ fn test_undo_empty_history() {
 #[test]
 fn test_binary_op_insufficient_stack() {
     let mut app = App::new();
-    app.stack = vec![1.0];
+    app.stack = numbers(&[1.0]);
     app.input = "+".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![1.0]);
@@ -218,7 +219,7 @@ fn test_unary_op_empty_stack() {
     let mut app = App::new();
     app.input = "sqrt".to_string();
     app.execute_command();
-    assert_eq!(app.stack, vec![]);
+    assert!(app.stack.is_empty());
     assert!(app.message.contains("Need 1 number"));
 }
 
@@ -233,34 +234,34 @@ fn test_unknown_command() {
 #[test]
 fn test_ln() {
     let mut app = App::new();
-    app.stack = vec![std::f64::consts::E];
+    app.stack = numbers(&[std::f64::consts::E]);
     app.input = "ln".to_string();
     app.execute_command();
-    assert!((app.stack[0] - 1.0).abs() < 1e-10);
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 1.0));
 }
 
 #[test]
 fn test_log() {
     let mut app = App::new();
-    app.stack = vec![100.0];
+    app.stack = numbers(&[100.0]);
     app.input = "log".to_string();
     app.execute_command();
-    assert!((app.stack[0] - 2.0).abs() < 1e-10);
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 2.0));
 }
 
 #[test]
 fn test_exp() {
     let mut app = App::new();
-    app.stack = vec![1.0];
+    app.stack = numbers(&[1.0]);
     app.input = "exp".to_string();
     app.execute_command();
-    assert!((app.stack[0] - std::f64::consts::E).abs() < 1e-10);
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), std::f64::consts::E));
 }
 
 #[test]
 fn test_10x() {
     let mut app = App::new();
-    app.stack = vec![2.0];
+    app.stack = numbers(&[2.0]);
     app.input = "10x".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![100.0]);
@@ -269,7 +270,7 @@ fn test_10x() {
 #[test]
 fn test_abs() {
     let mut app = App::new();
-    app.stack = vec![-5.0];
+    app.stack = numbers(&[-5.0]);
     app.input = "abs".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![5.0]);
@@ -278,25 +279,25 @@ fn test_abs() {
 #[test]
 fn test_cbrt() {
     let mut app = App::new();
-    app.stack = vec![8.0];
+    app.stack = numbers(&[8.0]);
     app.input = "cbrt".to_string();
     app.execute_command();
-    assert!((app.stack[0] - 2.0).abs() < 1e-10);
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 2.0));
 }
 
 #[test]
 fn test_root() {
     let mut app = App::new();
-    app.stack = vec![8.0, 3.0]; // 3rd root of 8
+    app.stack = numbers(&[8.0, 3.0]); // 3rd root of 8
     app.input = "root".to_string();
     app.execute_command();
-    assert!((app.stack[0] - 2.0).abs() < 1e-10);
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 2.0));
 }
 
 #[test]
 fn test_root_zero() {
     let mut app = App::new();
-    app.stack = vec![8.0, 0.0];
+    app.stack = numbers(&[8.0, 0.0]);
     app.input = "root".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![8.0, 0.0]);
@@ -306,9 +307,644 @@ fn test_root_zero() {
 #[test]
 fn test_root_insufficient_stack() {
     let mut app = App::new();
-    app.stack = vec![8.0];
+    app.stack = numbers(&[8.0]);
     app.input = "root".to_string();
     app.execute_command();
     assert_eq!(app.stack, vec![8.0]);
     assert!(app.message.contains("Need 2 numbers"));
-}
\ No newline at end of file
+}
+#[test]
+fn test_pow10_aliases() {
+    for name in ["pow10", "10^x", "alog"] {
+        let mut app = App::new();
+        app.stack = numbers(&[3.0]);
+        app.input = name.to_string();
+        app.execute_command();
+        assert_eq!(app.stack, vec![1000.0], "{}", name);
+    }
+}
+
+#[test]
+fn test_exp2() {
+    let mut app = App::new();
+    app.stack = numbers(&[10.0]);
+    app.input = "exp2".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![1024.0]);
+}
+
+#[test]
+fn test_pow2() {
+    let mut app = App::new();
+    app.stack = numbers(&[-3.0]);
+    app.input = "pow2".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![9.0]);
+}
+
+#[test]
+fn test_xroot() {
+    let mut app = App::new();
+    app.stack = numbers(&[27.0, 3.0]);
+    app.input = "xroot".to_string();
+    app.execute_command();
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 3.0));
+}
+
+#[test]
+fn test_radians_mode() {
+    let mut app = App::new();
+    app.input = "rad".to_string();
+    app.execute_command();
+    assert_eq!(app.angle_mode, AngleMode::Radians);
+    app.stack = numbers(&[std::f64::consts::FRAC_PI_2]);
+    app.input = "sin".to_string();
+    app.execute_command();
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 1.0));
+}
+
+#[test]
+fn test_atan2() {
+    let mut app = App::new();
+    app.stack = numbers(&[1.0, -1.0]);
+    app.input = "atan2".to_string();
+    app.execute_command();
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 135.0));
+}
+
+#[test]
+fn test_hyperbolic() {
+    let mut app = App::new();
+    app.stack = numbers(&[1.0]);
+    app.input = "sinh".to_string();
+    app.execute_command();
+    app.input = "asinh".to_string();
+    app.execute_command();
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 1.0));
+}
+
+#[test]
+fn test_acosh_domain() {
+    let mut app = App::new();
+    app.stack = numbers(&[0.5]);
+    app.input = "acosh".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![0.5]);
+    assert!(app.message.contains("x >= 1"));
+}
+
+#[test]
+fn test_atanh_domain() {
+    let mut app = App::new();
+    app.stack = numbers(&[1.0]);
+    app.input = "atanh".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![1.0]);
+    assert!(app.message.contains("-1 < x < 1"));
+}
+
+#[test]
+fn test_logb() {
+    let mut app = App::new();
+    app.stack = numbers(&[8.0, 2.0]);
+    app.input = "logb".to_string();
+    app.execute_command();
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 3.0));
+}
+
+#[test]
+fn test_logb_domain() {
+    let mut app = App::new();
+    app.stack = numbers(&[8.0, 1.0]);
+    app.input = "logb".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![8.0, 1.0]);
+    assert!(app.message.contains("logb needs"));
+}
+
+#[test]
+fn test_hypot() {
+    let mut app = App::new();
+    app.stack = numbers(&[3.0, 4.0]);
+    app.input = "hypot".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![5.0]);
+}
+
+#[test]
+fn test_gamma() {
+    let mut app = App::new();
+    app.stack = numbers(&[5.0]);
+    app.input = "gamma".to_string();
+    app.execute_command();
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 24.0));
+
+    app.stack = numbers(&[0.5]);
+    app.input = "gamma".to_string();
+    app.execute_command();
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), std::f64::consts::PI.sqrt()));
+}
+
+#[test]
+fn test_gamma_pole() {
+    let mut app = App::new();
+    app.stack = numbers(&[-2.0]);
+    app.input = "gamma".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![-2.0]);
+    assert!(app.message.contains("non-positive integers"));
+}
+
+#[test]
+fn test_lgamma() {
+    let mut app = App::new();
+    app.stack = numbers(&[101.0]);
+    app.input = "lgamma".to_string();
+    app.execute_command();
+    // ln(100!)
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 363.739_375_555_563_5));
+}
+
+#[test]
+fn test_mark_delta() {
+    let mut app = App::new();
+    app.stack = numbers(&[80.0]);
+    app.input = "mark 100".to_string();
+    app.execute_command();
+    assert_eq!(app.mark, Some(80.0));
+    assert_eq!(app.mark_status().unwrap(), "mark 80  Δ +20 (+25%)");
+    app.input = "drop drop 60".to_string();
+    app.execute_command();
+    assert_eq!(app.mark_status().unwrap(), "mark 80  Δ -20 (-25%)");
+}
+
+#[test]
+fn test_mark_zero_and_unmark() {
+    let mut app = App::new();
+    app.stack = numbers(&[0.0]);
+    app.input = "mark 5".to_string();
+    app.execute_command();
+    assert_eq!(app.mark_status().unwrap(), "mark 0  Δ +5");
+    app.input = "unmark".to_string();
+    app.execute_command();
+    assert_eq!(app.mark_status(), None);
+}
+
+#[test]
+fn test_mark_empty_stack() {
+    let mut app = App::new();
+    app.input = "mark".to_string();
+    app.execute_command();
+    assert_eq!(app.mark, None);
+    assert!(app.message.contains("Stack is empty"));
+}
+
+#[test]
+fn test_lastx_repeats_factor() {
+    let mut app = App::new();
+    app.stack = numbers(&[3.0]);
+    app.input = "1.07 *".to_string();
+    app.execute_command();
+    assert_eq!(app.last_x, Some(Value::Number(1.07)));
+    app.input = "lastx *".to_string();
+    app.execute_command();
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), 3.0 * 1.07 * 1.07));
+    assert_eq!(app.stack.len(), 1);
+}
+
+#[test]
+fn test_lastx_ignores_stack_operations() {
+    let mut app = App::new();
+    app.stack = numbers(&[16.0, 5.0]);
+    app.input = "swap sqrt drop lastx".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[5.0, 16.0]));
+}
+
+#[test]
+fn test_lastx_before_any_operation() {
+    let mut app = App::new();
+    app.input = "lastx".to_string();
+    app.execute_command();
+    assert!(app.stack.is_empty());
+    assert_eq!(app.message, "No operation has used an x yet");
+}
+
+#[test]
+fn test_sqrt_negative_rejected() {
+    let mut app = App::new();
+    app.stack = numbers(&[-1.0]);
+    app.input = "sqrt".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![-1.0]);
+    assert_eq!(app.message, "sqrt(-1) is undefined (x >= 0)");
+}
+
+#[test]
+fn test_ln_zero_rejected() {
+    let mut app = App::new();
+    app.stack = numbers(&[0.0]);
+    app.input = "ln".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![0.0]);
+    assert_eq!(app.message, "ln(0) is infinite");
+}
+
+#[test]
+fn test_asin_out_of_range_rejected() {
+    let mut app = App::new();
+    app.stack = numbers(&[2.0]);
+    app.input = "asin".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![2.0]);
+    assert!(app.message.contains("-1 <= x <= 1"));
+}
+
+#[test]
+fn test_overflow_rejected() {
+    let mut app = App::new();
+    app.stack = numbers(&[1e308, 10.0]);
+    app.input = "*".to_string();
+    app.execute_command();
+    assert_eq!(app.stack.len(), 2);
+    assert_eq!(app.message, "1.000000e308 * 10 is infinite");
+}
+
+#[test]
+fn test_allow_nan_propagates() {
+    let mut app = App::new();
+    app.allow_nan = true;
+    app.stack = numbers(&[-1.0]);
+    app.input = "sqrt".to_string();
+    app.execute_command();
+    assert!(app.stack[0].as_number().unwrap().is_nan());
+}
+
+#[test]
+fn test_chs() {
+    let mut app = App::new();
+    app.stack = numbers(&[5.0]);
+    app.input = "chs".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![-5.0]);
+    app.input = "[1 -2] neg".to_string();
+    app.execute_command();
+    assert_eq!(app.stack[1], Value::from(Matrix::parse("[-1 2]").unwrap()));
+    app.input = "2024-03-01 chs".to_string();
+    app.execute_command();
+    assert_eq!(app.stack.len(), 3);
+}
+
+#[test]
+fn test_negative_and_exponent_entry() {
+    let mut app = App::new();
+    assert!(app.sign_continues_number('-'));
+    assert!(!app.sign_continues_number('+'));
+    app.input = "1.5e".to_string();
+    assert!(app.sign_continues_number('-'));
+    assert!(app.sign_continues_number('+'));
+    app.input = "1.5".to_string();
+    assert!(!app.sign_continues_number('-'));
+    app.input = "e".to_string();
+    assert!(!app.sign_continues_number('-'));
+
+    app.input = "2 1.5e-3".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![2.0, 0.0015]);
+}
+
+#[test]
+fn test_enter_replaces_its_copy() {
+    let mut app = App::new();
+    app.eval_line("3 enter 4 +").unwrap();
+    assert_eq!(app.stack, vec![7.0]);
+    app.eval_line("enter *").unwrap();
+    assert_eq!(app.stack, vec![49.0]);
+    // Any other operation in between lifts the stack again.
+    app.eval_line("enter drop 2").unwrap();
+    assert_eq!(app.stack, vec![49.0, 2.0]);
+    app.eval_line("enter 5").unwrap();
+    app.eval_line("undo").unwrap();
+    assert_eq!(app.stack, vec![49.0, 2.0, 2.0]);
+    assert!(App::new().eval_line("enter").is_err());
+}
+
+#[test]
+fn test_classic_entry() {
+    let (config, warnings) = Config::parse("entry = \"classic\"\n");
+    assert!(warnings.is_empty());
+    assert_eq!(config.entry, EntryMode::Classic);
+    assert!(config.to_string().contains("entry = classic\n"));
+    assert_eq!(Config::parse("entry = rpl\n").1.len(), 1);
+    let mut app = App::new();
+    config.apply(&mut app);
+    // `-` on an empty line subtracts; `_` makes numbers negative.
+    assert!(!app.sign_continues_number('-'));
+    app.input = "1.5e".to_string();
+    assert!(app.sign_continues_number('-'));
+}
+
+#[test]
+fn test_change_input_sign() {
+    let mut app = App::new();
+    app.input = "3 12".to_string();
+    app.change_input_sign();
+    assert_eq!(app.input, "3 -12");
+    app.change_input_sign();
+    assert_eq!(app.input, "3 12");
+    app.input = "-1.5e".to_string();
+    app.change_input_sign();
+    assert_eq!(app.input, "-1.5e-");
+    app.input = "1.5e+3".to_string();
+    app.change_input_sign();
+    assert_eq!(app.input, "1.5e-3");
+    app.change_input_sign();
+    assert_eq!(app.input, "1.5e3");
+    app.input = "sin".to_string();
+    app.change_input_sign();
+    assert_eq!(app.input, "sin");
+}
+
+#[test]
+fn test_rounding() {
+    let cases = [
+        ("2.5 round", 3.0),
+        ("-2.5 round", -3.0),
+        ("-2.5 floor", -3.0),
+        ("2.1 ceil", 3.0),
+        ("-2.7 trunc", -2.0),
+        ("-2.7 ip", -2.0),
+        ("3.25 frac", 0.25),
+        ("12.34567 rnd 3", 12.346),
+        ("2.675 rnd 2", 2.68),
+        ("-1.005 rnd 2", -1.01),
+        ("1234 rnd -2", 1200.0),
+        ("0.004 rnd 2", 0.0),
+        ("9.995 rnd 2", 10.0),
+    ];
+    for (line, expected) in cases {
+        let mut app = App::new();
+        app.eval_line(line).unwrap();
+        assert_eq!(app.stack, vec![expected], "{}", line);
+    }
+}
+
+#[test]
+fn test_rounding_undo_and_errors() {
+    let mut app = App::new();
+    app.eval_line("2.7 floor").unwrap();
+    assert_eq!(app.stack, vec![2.0]);
+    app.eval_line("undo").unwrap();
+    assert_eq!(app.stack, vec![2.7]);
+    assert!(app.eval_line("rnd 1.5").is_err());
+    assert!(app.eval_line("rnd 16").is_err());
+    assert_eq!(app.stack, vec![2.7]);
+    app.eval_line("[1.4 -1.6] round").unwrap();
+    assert_eq!(app.stack[1], Value::from(Matrix::parse("[1 -2]").unwrap()));
+}
+
+#[test]
+fn test_factorial_range() {
+    let mut app = App::new();
+    app.eval_line("20 !").unwrap();
+    assert_eq!(app.stack, vec![2_432_902_008_176_640_000.0]);
+    assert!(app.eval_line("171 !").is_err());
+    assert!(app.eval_line("1e100 !").is_err());
+    assert_eq!(app.stack[2], 1e100);
+}
+
+#[test]
+fn test_selftest_passes() {
+    let mut app = App::new();
+    app.stack = numbers(&[7.0]);
+    app.input = "selftest".to_string();
+    app.execute_command();
+    assert!(app.message.starts_with("Self-test: all"), "{}", app.message);
+    assert!(app.popup.is_none());
+    assert_eq!(app.stack, vec![7.0]);
+}
+
+#[test]
+fn test_pick_copies_entry_to_top() {
+    let mut app = App::new();
+    app.stack = numbers(&[1.0, 2.0, 3.0]);
+    app.pick(0).unwrap();
+    assert_eq!(app.stack, vec![1.0, 2.0, 3.0, 1.0]);
+    assert!(app.pick(9).is_err());
+    app.undo().unwrap();
+    assert_eq!(app.stack, vec![1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn test_move_stack_entry() {
+    let mut app = App::new();
+    app.stack = numbers(&[1.0, 2.0, 3.0]);
+    app.move_stack_entry(2, 0).unwrap();
+    assert_eq!(app.stack, vec![3.0, 1.0, 2.0]);
+    app.move_stack_entry(0, 2).unwrap();
+    assert_eq!(app.stack, vec![1.0, 2.0, 3.0]);
+    assert!(app.move_stack_entry(0, 3).is_err());
+    app.undo().unwrap();
+    assert_eq!(app.stack, vec![3.0, 1.0, 2.0]);
+}
+
+#[test]
+fn test_pick_and_move_levels() {
+    let mut app = App::new();
+    app.eval_line("1 2 3 pick 3").unwrap();
+    assert_eq!(app.stack, vec![1.0, 2.0, 3.0, 1.0]);
+    app.eval_line("move 4 1").unwrap();
+    assert_eq!(app.stack, vec![2.0, 3.0, 1.0, 1.0]);
+    app.eval_line("move 1 3").unwrap();
+    assert_eq!(app.stack, vec![2.0, 1.0, 3.0, 1.0]);
+    let err = app.eval_line("pick 5").unwrap_err();
+    assert_eq!(err.to_string(), "No stack level 5; the stack holds 4");
+    assert!(app.eval_line("pick x").is_err());
+    app.eval_line("undo").unwrap();
+    assert_eq!(app.stack, vec![2.0, 3.0, 1.0, 1.0]);
+}
+
+#[test]
+fn test_push_exact_values() {
+    let mut app = App::new();
+    app.eval_line("money push \"num:0.1 frac:1/3 date:0\"").unwrap();
+    assert_eq!(app.stack[0], Value::Number(0.1));
+    assert_eq!(app.stack[1], Value::Rational(Rational::new(1, 3).unwrap()));
+    assert_eq!(app.stack[2], Value::Date(Date::from_ymd(1970, 1, 1).unwrap()));
+    assert!(app.eval_line("push \"num:x\"").is_err());
+    app.eval_line("undo").unwrap();
+    assert!(app.stack.is_empty());
+}
+
+#[test]
+fn test_park_and_unpark() {
+    let mut app = App::new();
+    app.eval_line("1 2 park 3 park").unwrap();
+    assert_eq!(app.stack, vec![1.0]);
+    assert_eq!(app.shelf, vec![2.0, 3.0]);
+    app.eval_line("10 * unpark").unwrap();
+    assert_eq!(app.stack, vec![10.0, 3.0]);
+    assert_eq!(app.shelf, vec![2.0]);
+
+    app.eval_line("unpark").unwrap();
+    let err = app.eval_line("unpark").unwrap_err();
+    assert_eq!(err.to_string(), "Nothing is parked");
+    assert!(app.eval_line("clear park").is_err());
+}
+
+#[test]
+fn test_park_and_unpark_undo() {
+    let mut app = App::new();
+    app.eval_line("1 2 park").unwrap();
+    app.eval_line("undo").unwrap();
+    assert_eq!(app.stack, vec![1.0, 2.0]);
+    assert!(app.shelf.is_empty());
+
+    app.eval_line("park unpark").unwrap();
+    app.eval_line("undo").unwrap();
+    assert_eq!(app.stack, vec![1.0]);
+    assert_eq!(app.shelf, vec![2.0]);
+
+    // A macro undoes as one step, shelf and all.
+    app.eval_line("def stash \"park park\" 5 stash").unwrap();
+    assert_eq!(app.shelf, vec![2.0, 5.0, 1.0]);
+    app.eval_line("undo").unwrap();
+    assert_eq!(app.stack, vec![1.0, 5.0]);
+    assert_eq!(app.shelf, vec![2.0]);
+    // So does a macro that fails part way.
+    assert!(app.eval_line("def spill \"unpark unpark\" spill").is_err());
+    assert_eq!(app.stack, vec![1.0, 5.0]);
+    assert_eq!(app.shelf, vec![2.0]);
+}
+
+#[test]
+fn test_input_preview() {
+    let mut app = App::new();
+    app.eval_line("3 4").unwrap();
+    app.input = "5".to_string();
+    app.update_preview();
+    assert_eq!(app.preview, None);
+    app.input = "+".to_string();
+    app.update_preview();
+    assert_eq!(app.preview.as_deref(), Some("→ 7"));
+    app.input = "+ 0 /".to_string();
+    app.update_preview();
+    assert_eq!(app.preview.as_deref(), Some("→ Division by zero"));
+    app.input = "copy".to_string();
+    app.update_preview();
+    assert_eq!(app.preview.as_deref(), Some("→ copy is not previewed"));
+    assert_eq!(app.stack, [Value::Number(3.0), Value::Number(4.0)]);
+}
+
+#[test]
+fn test_slow_commands_are_not_previewed() {
+    let mut app = App::new();
+    app.eval_line("def square \"dup *\" 0 3").unwrap();
+    for (input, name) in [
+        ("integ square", "integ"),
+        ("solve square", "solve"),
+        ("deriv square", "deriv"),
+        ("plotf \"x sq\" 0 1 1000", "plotf"),
+        ("test square", "test"),
+        ("runtests", "runtests"),
+    ] {
+        app.input = input.to_string();
+        app.update_preview();
+        let expected = format!("→ {} is not previewed", name);
+        assert_eq!(app.preview.as_deref(), Some(expected.as_str()), "{}", input);
+    }
+    assert!(app.plot.is_none());
+}
+
+#[test]
+fn test_input_cursor_editing() {
+    let mut app = App::new();
+    app.input = "1 3 +".to_string();
+    app.cursor_home();
+    app.move_cursor(true);
+    app.move_cursor(true);
+    assert_eq!(app.cursor_position(), 2);
+    app.insert_input('2');
+    app.insert_input(' ');
+    assert_eq!(app.input, "1 2 3 +");
+    app.delete_input_back();
+    app.delete_input_back();
+    assert_eq!(app.input, "1 3 +");
+    assert_eq!(app.cursor_position(), 2);
+    app.cursor_end();
+    assert!(app.cursor_at_end());
+    app.move_cursor(true);
+    assert!(app.cursor_at_end());
+    app.move_cursor(false);
+    assert_eq!(app.cursor_position(), 4);
+    app.execute_command();
+    assert_eq!(app.stack, [Value::Number(4.0)]);
+    assert!(app.cursor_at_end());
+}
+
+#[test]
+fn test_input_history_recall() {
+    let mut app = App::new();
+    for line in ["1", "2", "2", "+"] {
+        app.input = line.to_string();
+        app.execute_command();
+    }
+    assert_eq!(app.input_history, ["1", "2", "+"]);
+    app.input = "draft".to_string();
+    app.recall_input(false);
+    assert_eq!(app.input, "+");
+    app.recall_input(false);
+    app.recall_input(false);
+    app.recall_input(false);
+    assert_eq!(app.input, "1");
+    app.recall_input(true);
+    assert_eq!(app.input, "2");
+    app.recall_input(true);
+    app.recall_input(true);
+    assert_eq!(app.input, "draft");
+    app.recall_input(true);
+    assert_eq!(app.input, "draft");
+    app.clear_input();
+    assert_eq!(app.input, "");
+}
+
+#[test]
+fn test_submit_line() {
+    let mut app = App::new();
+    assert!(app.submit_line("1 2 +".to_string()).is_ok());
+    assert_eq!(app.stack, vec![3.0]);
+    assert!(app.submit_line("foo".to_string()).is_err());
+    assert_eq!(app.input_history, ["1 2 +", "foo"]);
+    assert_eq!(app.last_error.as_ref().map(|(line, _)| line.as_str()), Some("foo"));
+}
+
+#[test]
+fn test_stack_depth_limit() {
+    let mut app = App::new();
+    app.max_depth = 3;
+    app.eval_line("1 2 depth").unwrap();
+    assert_eq!(app.stack, vec![1.0, 2.0, 2.0]);
+    assert_eq!(app.eval_line("4"), Err(CalcError::StackFull(3)));
+    assert_eq!(app.eval_line("dup"), Err(CalcError::StackFull(3)));
+    assert!(app.push_text_numbers("5").is_err());
+    assert_eq!(app.stack, vec![1.0, 2.0, 2.0]);
+    // Operations that do not grow the stack still work.
+    app.eval_line("+").unwrap();
+    assert_eq!(app.stack, vec![1.0, 4.0]);
+    app.eval_line("undo").unwrap();
+    assert_eq!(app.stack, vec![1.0, 2.0, 2.0]);
+    // A loop that overflows puts the stack back.
+    app.eval_line("clear 0").unwrap();
+    assert!(app.eval_line("5 times dup loop").is_err());
+    assert_eq!(app.stack, vec![0.0, 5.0]);
+}
+
+#[test]
+fn test_stack_depth_limit_without_journal() {
+    let mut app = App::new();
+    app.journal = Journal::disabled();
+    app.max_depth = 2;
+    app.eval_line("1 2").unwrap();
+    assert_eq!(app.eval_line("dup"), Err(CalcError::StackFull(2)));
+    assert_eq!(app.stack, vec![1.0, 2.0]);
+}
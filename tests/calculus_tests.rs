@@ -0,0 +1,55 @@
+use rpncalc::calculus::{derivative, integrate};
+use rpncalc::*;
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-9 * b.abs().max(1.0)
+}
+
+#[test]
+fn test_integrate() {
+    assert!(close(integrate(|x| Ok(x * x), 0.0, 3.0).unwrap(), 9.0));
+    let bell = integrate(|x| Ok((-x * x).exp()), -10.0, 10.0).unwrap();
+    assert!(close(bell, std::f64::consts::PI.sqrt()));
+    // Reversed bounds change the sign.
+    assert!(close(integrate(Ok, 2.0, 0.0).unwrap(), -2.0));
+}
+
+#[test]
+fn test_integrate_errors() {
+    let err = integrate(|x| Ok(1.0 / x), 0.0, 1.0).unwrap_err();
+    assert_eq!(err.to_string(), "f is not finite at 0");
+    let half = |x: f64| if x < 0.5 { Err(CalcError::DivisionByZero) } else { Ok(x) };
+    assert_eq!(integrate(half, 0.0, 1.0), Err(CalcError::DivisionByZero));
+}
+
+#[test]
+fn test_derivative() {
+    assert!(close(derivative(|x| Ok(x * x * x), 2.0).unwrap(), 12.0));
+    assert!(close(derivative(|x| Ok(x.sin()), 1.0).unwrap(), 1f64.cos()));
+    // Steps shrink to stay where f is defined.
+    let sqrt = |x: f64| if x < 0.0 { Err(CalcError::domain("negative")) } else { Ok(x.sqrt()) };
+    assert!(close(derivative(sqrt, 0.001).unwrap(), 0.5 / 0.001f64.sqrt()));
+}
+
+#[test]
+fn test_integ_and_deriv_commands() {
+    let mut app = App::new();
+    app.eval_line("0 3 integ \"dup *\"").unwrap();
+    assert!(close(app.stack[0].as_number().unwrap(), 9.0));
+    app.eval_line("drop 2 deriv \"dup dup * *\"").unwrap();
+    assert!(close(app.stack[0].as_number().unwrap(), 12.0));
+    assert_eq!(app.calc_history.len(), 2);
+    assert!(app.eval_line("0 1 integ nope").is_err());
+    assert_eq!(app.stack.len(), 3);
+}
+
+#[test]
+fn test_integ_counts_steps_per_evaluation() {
+    // Each evaluation runs over a thousand steps, and the integral needs
+    // about a thousand evaluations: well within the limit each, but not in
+    // all.
+    let mut app = App::new();
+    app.eval_line("def slowroot \"0 600 times 1 + loop drop sqrt\"").unwrap();
+    app.eval_line("0 1 integ slowroot").unwrap();
+    assert!(close(app.stack[0].as_number().unwrap(), 2.0 / 3.0));
+}
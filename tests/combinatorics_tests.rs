@@ -0,0 +1,68 @@
+use rpncalc::*;
+
+#[test]
+fn test_permutations_and_combinations() {
+    assert_eq!(combinatorics::permutations(5.0, 2.0), Ok(20.0));
+    assert_eq!(combinatorics::permutations(5.0, 0.0), Ok(1.0));
+    assert_eq!(combinatorics::combinations(52.0, 5.0), Ok(2_598_960.0));
+    assert_eq!(combinatorics::combinations(10.0, 10.0), Ok(1.0));
+    assert_eq!(combinatorics::combinations(0.0, 0.0), Ok(1.0));
+    // Well past 20!, where a factorial-based formula loses precision.
+    assert_eq!(combinatorics::combinations(100.0, 3.0), Ok(161_700.0));
+    assert_eq!(combinatorics::combinations(60.0, 30.0), Ok(118_264_581_564_861_424.0));
+    assert_eq!(combinatorics::combinations(1e6, 2.0), Ok(499_999_500_000.0));
+}
+
+#[test]
+fn test_counting_overflows_to_infinity() {
+    assert_eq!(combinatorics::permutations(500.0, 200.0), Ok(f64::INFINITY));
+    assert_eq!(combinatorics::combinations(5000.0, 2500.0), Ok(f64::INFINITY));
+    let c = combinatorics::combinations(1000.0, 500.0).unwrap();
+    assert!(Tolerance::default().close(c.log10(), 299.431_827_151_863_7));
+}
+
+#[test]
+fn test_counting_rejects_bad_operands() {
+    assert!(combinatorics::permutations(2.0, 3.0).is_err());
+    assert!(combinatorics::combinations(5.5, 2.0).is_err());
+    assert!(combinatorics::combinations(5.0, -1.0).is_err());
+    assert!(combinatorics::fibonacci(2.5).is_err());
+    assert!(combinatorics::gcd(1.5, 3.0).is_err());
+    assert!(combinatorics::lcm(1e300, 3.0).is_err());
+}
+
+#[test]
+fn test_gcd_and_lcm() {
+    assert_eq!(combinatorics::gcd(48.0, 18.0), Ok(6.0));
+    assert_eq!(combinatorics::gcd(-48.0, 18.0), Ok(6.0));
+    assert_eq!(combinatorics::gcd(0.0, 0.0), Ok(0.0));
+    assert_eq!(combinatorics::lcm(4.0, 6.0), Ok(12.0));
+    assert_eq!(combinatorics::lcm(0.0, 6.0), Ok(0.0));
+}
+
+#[test]
+fn test_fibonacci() {
+    let fib = |n| combinatorics::fibonacci(n).unwrap();
+    assert_eq!(fib(0.0), 0.0);
+    assert_eq!(fib(1.0), 1.0);
+    assert_eq!(fib(10.0), 55.0);
+    assert_eq!(fib(90.0), 2_880_067_194_370_816_120.0);
+    assert!(fib(185.0).is_finite());
+    assert!(fib(1476.0).is_finite());
+    assert_eq!(fib(1477.0), f64::INFINITY);
+}
+
+#[test]
+fn test_combinatorics_commands() {
+    let mut app = App::new();
+    app.eval_line("5 2 nPr 52 5 ncr 48 18 gcd 10 fib").unwrap();
+    assert_eq!(app.stack, vec![20.0, 2_598_960.0, 6.0, 55.0]);
+
+    let err = app.eval_line("2.5 2 nCr").unwrap_err();
+    assert_eq!(err.to_string(), "nCr needs whole numbers with 0 <= r <= n");
+    assert_eq!(app.stack.len(), 6);
+
+    // 171 items taken 171 at a time is too large for a number.
+    assert!(app.eval_line("clear 171 171 nPr").is_err());
+    assert_eq!(app.stack, vec![171.0, 171.0]);
+}
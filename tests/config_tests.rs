@@ -0,0 +1,61 @@
+use rpncalc::*;
+
+#[test]
+fn test_parse_config() {
+    let (config, warnings) = Config::parse("# IEEE please\n\nallow_nan = yes\nfreq_bars = off\nlow_power = on\n");
+    assert!(config.allow_nan);
+    assert!(!config.freq_bars);
+    assert!(config.low_power);
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn test_config_warnings() {
+    let (config, warnings) = Config::parse("allow_nan = maybe\ncolour = blue\nnonsense\n");
+    assert_eq!(config, Config::default());
+    assert_eq!(warnings.len(), 3);
+    assert!(warnings[0].starts_with("line 1:"));
+    assert!(warnings[1].contains("unknown setting 'colour'"));
+}
+
+#[test]
+fn test_missing_config_is_default() {
+    let path = std::env::temp_dir().join("rpncalc-no-such-dir/config");
+    let (config, warnings) = Config::load(&path).unwrap();
+    assert_eq!(config, Config::default());
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn test_config_round_trip() {
+    let mut app = App::new();
+    app.allow_nan = true;
+    app.tolerance.relative = 1e-6;
+    app.solver.max_iterations = 250;
+    app.max_depth = 500;
+    app.low_power = true;
+    app.export.decimal_comma = true;
+    app.export.delimiter = Some('\t');
+    app.export.quoting = Quoting::All;
+    app.export.width = 12;
+    app.theme = Theme::named("light").unwrap();
+    app.theme.input = theme::Colors::parse("#ff8800 on 236").unwrap();
+    let config = Config::of(&app);
+    let (parsed, warnings) = Config::parse(&config.to_string());
+    assert!(warnings.is_empty());
+    assert_eq!(parsed, config);
+}
+
+#[test]
+fn test_max_depth_setting() {
+    let (config, warnings) = Config::parse("max_depth = 50\n");
+    assert!(warnings.is_empty());
+    let mut app = App::new();
+    config.apply(&mut app);
+    assert_eq!(app.max_depth, 50);
+    for value in ["0", "-1", "many", "100000000"] {
+        let (config, warnings) = Config::parse(&format!("max_depth = {}", value));
+        assert_eq!(config.max_depth, DEFAULT_MAX_DEPTH);
+        assert_eq!(warnings.len(), 1, "{}", value);
+    }
+}
@@ -0,0 +1,80 @@
+use rpncalc::*;
+
+fn date(text: &str) -> Value {
+    Value::Date(Date::parse(text).unwrap())
+}
+
+#[test]
+fn test_parse_and_display_dates() {
+    assert_eq!(Date::parse("1970-01-01"), Some(Date::from_days(0)));
+    assert_eq!(Date::parse("2024-02-29").unwrap().to_string(), "2024-02-29");
+    assert_eq!(Date::parse("2023-02-29"), None);
+    assert_eq!(Date::parse("2024-13-01"), None);
+    assert_eq!(Date::parse("24-03-01"), None);
+    assert_eq!(Value::parse("2000-03-01"), Ok(date("2000-03-01")));
+}
+
+#[test]
+fn test_calendar_round_trip() {
+    for days in [-719_468, -1, 0, 59, 365, 11_016, 19_783, 2_932_896] {
+        let d = Date::from_days(days);
+        let (y, m, day) = d.ymd();
+        assert_eq!(Date::from_ymd(y, m, day), Some(d), "{}", d);
+    }
+}
+
+#[test]
+fn test_ddays_and_dadd() {
+    let mut app = App::new();
+    app.input = "2024-01-01 2024-03-01 ddays".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[60.0]));
+
+    app.input = "2024-02-20 10 dadd".to_string();
+    app.execute_command();
+    assert_eq!(app.stack[1], date("2024-03-01"));
+}
+
+#[test]
+fn test_date_plus_and_minus() {
+    let mut app = App::new();
+    app.input = "2023-12-25 7 + 2024-01-31 swap -".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[30.0]));
+    app.input = "2024-03-01 1 -".to_string();
+    app.execute_command();
+    assert_eq!(app.stack[1], date("2024-02-29"));
+    assert!(app.eval_line("2024-03-01 2024-03-02 +").is_err());
+    assert!(app.eval_line("2024-03-01 0.5 +").is_err());
+}
+
+#[test]
+fn test_day_of_week() {
+    let mut app = App::new();
+    app.input = "2024-03-01 dow".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[5.0]));
+    assert_eq!(Date::parse("2024-03-03").unwrap().weekday_name(), "Sunday");
+}
+
+#[test]
+fn test_unix_timestamps() {
+    let mut app = App::new();
+    app.input = "2024-03-01 unix".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[1_709_251_200.0]));
+    app.input = "3600 + fromunix".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![date("2024-03-01")]);
+    app.input = "-1 fromunix".to_string();
+    app.execute_command();
+    assert_eq!(app.stack[1], date("1969-12-31"));
+}
+
+#[test]
+fn test_dates_are_not_numbers() {
+    let mut app = App::new();
+    app.stack = vec![date("2024-03-01")];
+    assert!(matches!(app.eval_line("sqrt"), Err(CalcError::WrongType { .. })));
+    assert!(matches!(app.eval_line("5 dow"), Err(CalcError::WrongType { .. })));
+}
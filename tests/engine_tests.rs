@@ -0,0 +1,89 @@
+use rpncalc::*;
+
+#[test]
+fn test_execute_lines_and_tokens() {
+    let mut engine = Engine::new();
+    engine.execute("2").unwrap();
+    engine.execute("3").unwrap();
+    let outcome = engine.execute("*").unwrap();
+    assert_eq!(outcome.message(), "2 * 3 = 6");
+    engine.execute("explain sin").unwrap();
+    assert_eq!(engine.stack(), [Value::Number(6.0)]);
+    assert_eq!(engine.history().len(), 1);
+}
+
+#[test]
+fn test_errors_leave_stack() {
+    let mut engine = Engine::new();
+    engine.push(4.0).unwrap();
+    assert!(matches!(
+        engine.execute("nope"),
+        Err(CalcError::UnknownCommand(_))
+    ));
+    assert_eq!(engine.execute("+"), Err(CalcError::underflow("+", 2)));
+    assert_eq!(engine.pop(), Some(Value::Number(4.0)));
+    assert_eq!(engine.pop(), None);
+}
+
+#[test]
+fn test_push_and_pop_undo_and_respect_max_depth() {
+    let (config, _) = Config::parse("max_depth = 2\n");
+    let mut engine = Engine::with_config(&config);
+    engine.push(1.0).unwrap();
+    engine.push(2.0).unwrap();
+    assert_eq!(engine.push(3.0), Err(CalcError::StackFull(2)));
+    assert_eq!(engine.stack(), [Value::Number(1.0), Value::Number(2.0)]);
+
+    assert_eq!(engine.pop(), Some(Value::Number(2.0)));
+    engine.execute("undo").unwrap();
+    assert_eq!(engine.stack(), [Value::Number(1.0), Value::Number(2.0)]);
+    engine.execute("undo").unwrap();
+    assert_eq!(engine.stack(), [Value::Number(1.0)]);
+
+    // After enter, a pushed value replaces the copy as a typed one does,
+    // unless a pop came between.
+    engine.execute("enter").unwrap();
+    engine.push(5.0).unwrap();
+    assert_eq!(engine.stack(), [Value::Number(1.0), Value::Number(5.0)]);
+    engine.execute("drop enter").unwrap();
+    engine.pop();
+    engine.push(6.0).unwrap();
+    assert_eq!(engine.stack(), [Value::Number(1.0), Value::Number(6.0)]);
+}
+
+#[test]
+fn test_settings_and_modes() {
+    let (config, _) = Config::parse("allow_nan = true\n");
+    let mut engine = Engine::with_config(&config);
+    engine.execute("-1 sqrt").unwrap();
+    assert!(engine.stack()[0].as_number().unwrap().is_nan());
+
+    engine.set_angle_mode(AngleMode::Radians);
+    engine.execute("pi 2 / sin").unwrap();
+    assert_eq!(engine.stack()[1], Value::Number(1.0));
+    assert_eq!(engine.angle_mode(), AngleMode::Radians);
+}
+
+#[test]
+fn test_register_command() {
+    let mut engine = Engine::new();
+    engine.register(Command::new("answer", 0, "Custom", "Push 42", |app| {
+        app.stack.push(Value::Number(42.0));
+        Ok(OpOutcome::info("42"))
+    }));
+    engine.execute("answer").unwrap();
+    assert_eq!(engine.stack(), [Value::Number(42.0)]);
+    assert!(engine.commands().lookup("answer").is_some());
+}
+
+#[test]
+fn test_dry_run_changes_nothing() {
+    let mut engine = Engine::new();
+    engine.execute("3 4").unwrap();
+    assert_eq!(engine.dry_run("+ 2 *").unwrap(), [Value::Number(14.0)]);
+    assert_eq!(engine.stack(), [Value::Number(3.0), Value::Number(4.0)]);
+    assert!(engine.history().is_empty());
+    assert!(engine.dry_run("export csv out.csv").is_err());
+    assert!(engine.dry_run("1 100000 times 1 + loop").is_err());
+    assert!(engine.dry_run("1 1000 times 1 + loop").is_ok());
+}
@@ -0,0 +1,61 @@
+use rpncalc::*;
+
+#[test]
+fn test_eval_line_returns_calculation() {
+    let mut app = App::new();
+    let outcome = app.eval_line("2 3 +").unwrap();
+    assert_eq!(
+        outcome,
+        OpOutcome::Calc {
+            text: "2 + 3 = 5".to_string(),
+            result: Value::Number(5.0)
+        }
+    );
+    assert_eq!(app.calc_history.len(), 1);
+}
+
+#[test]
+fn test_eval_line_stops_at_first_error() {
+    let mut app = App::new();
+    let err = app.eval_line("1 0 / 5").unwrap_err();
+    assert_eq!(err, CalcError::DivisionByZero);
+    assert_eq!(app.stack, vec![1.0, 0.0]);
+}
+
+#[test]
+fn test_underflow_error() {
+    let mut app = App::new();
+    app.stack = numbers(&[1.0]);
+    let err = app.eval_line("+").unwrap_err();
+    assert_eq!(
+        err,
+        CalcError::StackUnderflow {
+            op: "+".to_string(),
+            needed: 2
+        }
+    );
+    assert_eq!(err.to_string(), "Need 2 numbers for +");
+}
+
+#[test]
+fn test_failed_command_leaves_no_undo_snapshot() {
+    let mut app = App::new();
+    app.eval_line("4").unwrap();
+    assert!(app.eval_line("0 inv").is_err());
+    app.eval_line("undo").unwrap();
+    assert_eq!(app.stack, vec![4.0]);
+}
+
+#[test]
+fn test_unknown_command_error() {
+    let mut app = App::new();
+    let err = app.eval_line("frob").unwrap_err();
+    assert_eq!(err, CalcError::UnknownCommand("frob".to_string()));
+}
+
+#[test]
+fn test_usage_error() {
+    let mut app = App::new();
+    let err = app.eval_line("explain").unwrap_err();
+    assert!(matches!(err, CalcError::Usage(_)));
+}
@@ -0,0 +1,78 @@
+use rpncalc::*;
+
+fn sample() -> App {
+    let mut app = App::new();
+    app.eval_line("1.5 2 + 0.25").unwrap();
+    app
+}
+
+#[test]
+fn test_export_csv() {
+    let app = sample();
+    let csv = app.export.render(export::Format::Csv, &app);
+    assert_eq!(
+        csv,
+        "section,entry,value\nstack,0,3.5\nstack,1,0.25\ntape,1.5 + 2 = 3.5,3.5\n"
+    );
+}
+
+#[test]
+fn test_export_decimal_comma() {
+    let mut app = sample();
+    app.export.decimal_comma = true;
+    let csv = app.export.render(export::Format::Csv, &app);
+    assert_eq!(csv.lines().nth(1), Some("stack;0;3,5"));
+
+    app.export.delimiter = Some(',');
+    let csv = app.export.render(export::Format::Csv, &app);
+    assert_eq!(csv.lines().nth(1), Some("stack,0,\"3,5\""));
+
+    app.export.quoting = Quoting::All;
+    let csv = app.export.render(export::Format::Csv, &app);
+    assert_eq!(csv.lines().nth(2), Some("\"stack\",\"1\",\"0,25\""));
+}
+
+#[test]
+fn test_export_fixed_width() {
+    let mut app = sample();
+    app.export.width = 6;
+    let text = app.export.render(export::Format::Fixed, &app);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "section entry          value");
+    assert_eq!(lines[1], "stack   0                3.5");
+    assert_eq!(lines[3], "tape    1.5 + 2 = 3.5    3.5");
+}
+
+#[test]
+fn test_export_command() {
+    let path = std::env::temp_dir().join(format!("rpncalc-export-{}.csv", std::process::id()));
+    let mut app = sample();
+    let outcome = app.eval_line(&format!("export csv {}", path.display())).unwrap();
+    assert!(outcome.message().starts_with("Exported 2 stack values and 1 calculation"));
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with("section,entry,value\n"));
+    std::fs::remove_file(&path).unwrap();
+
+    let err = app.eval_line("export xml out.xml").unwrap_err();
+    assert!(err.to_string().contains("Unknown export format 'xml'"));
+    assert_eq!(app.stack.len(), 2);
+
+    let err = app.eval_line("export csv /nonexistent-dir/out.csv").unwrap_err();
+    assert!(err.to_string().starts_with("Could not write /nonexistent-dir/out.csv"));
+}
+
+#[test]
+fn test_export_json() {
+    let mut app = sample();
+    app.eval_line("2024-03-01 money 1.1").unwrap();
+    let json = app.export.render(export::Format::Json, &app);
+    assert_eq!(
+        json,
+        "{\n  \"stack\": [3.5, 0.25, \"2024-03-01\", 1.1],\n  \"history\": [\n    \
+         {\"text\": \"1.5 + 2 = 3.5\", \"result\": 3.5}\n  ]\n}\n"
+    );
+
+    let empty = App::new();
+    let json = empty.export.render(export::Format::Json, &empty);
+    assert_eq!(json, "{\n  \"stack\": [],\n  \"history\": []\n}\n");
+}
@@ -0,0 +1,164 @@
+use rpncalc::finance::{irr, npv};
+use rpncalc::*;
+
+#[test]
+fn test_pct_keeps_base() {
+    let mut app = App::new();
+    app.eval_line("200 15 pct +").unwrap();
+    assert_eq!(app.stack, vec![230.0]);
+}
+
+#[test]
+fn test_pctchg() {
+    let mut app = App::new();
+    app.eval_line("80 100 pctchg").unwrap();
+    assert_eq!(app.stack, vec![25.0]);
+}
+
+#[test]
+fn test_pctchg_zero_base() {
+    let mut app = App::new();
+    let err = app.eval_line("0 100 pctchg").unwrap_err();
+    assert_eq!(app.stack, vec![0.0, 100.0]);
+    assert!(err.to_string().contains("non-zero base"));
+}
+
+#[test]
+fn test_markup() {
+    let mut app = App::new();
+    app.eval_line("40 25 markup").unwrap();
+    assert_eq!(app.stack, vec![50.0]);
+}
+
+#[test]
+fn test_mortgage_payment() {
+    // 30 years monthly at 6%/yr on 200,000
+    let mut app = App::new();
+    app.eval_line("360 setn 0.5 seti 200000 setpv 0 setfv pmt").unwrap();
+    assert!((app.stack[0].as_number().unwrap() - -1199.101050304).abs() < 1e-6);
+    assert_eq!(app.stack[0], app.tvm.pmt);
+}
+
+#[test]
+fn test_undo_restores_tvm_registers() {
+    let mut app = App::new();
+    app.eval_line("5 setn undo").unwrap();
+    assert_eq!(app.stack, vec![5.0]);
+    assert_eq!(app.tvm, Tvm::default());
+    app.eval_line("setn 10 seti -100 setpmt 0 setfv pv undo").unwrap();
+    assert!(app.stack.is_empty());
+    assert_eq!(app.tvm.pv, 0.0);
+    assert_eq!(app.tvm.n, 5.0);
+}
+
+#[test]
+fn test_future_value() {
+    let mut app = App::new();
+    app.tvm = Tvm {
+        n: 10.0,
+        i: 5.0,
+        pv: -1000.0,
+        pmt: 0.0,
+        fv: 0.0,
+    };
+    app.eval_line("fv").unwrap();
+    assert!((app.stack[0].as_number().unwrap() - 1628.894626777442).abs() < 1e-9);
+}
+
+#[test]
+fn test_pv_zero_rate() {
+    let tvm = Tvm {
+        n: 12.0,
+        i: 0.0,
+        pv: 0.0,
+        pmt: -100.0,
+        fv: 0.0,
+    };
+    assert_eq!(tvm.solve(TvmRegister::Pv), Ok(1200.0));
+}
+
+#[test]
+fn test_pmt_needs_periods() {
+    let tvm = Tvm::default();
+    assert!(tvm.solve(TvmRegister::Pmt).is_err());
+}
+
+#[test]
+fn test_npv_command() {
+    let mut app = App::new();
+    app.tvm.i = 10.0;
+    app.eval_line("-100 60 60 3 npv").unwrap();
+    assert_eq!(app.stack.len(), 1);
+    assert!((app.stack[0].as_number().unwrap() - 4.132231404958).abs() < 1e-9);
+}
+
+#[test]
+fn test_npv_needs_flows() {
+    let mut app = App::new();
+    let err = app.eval_line("-100 3 npv").unwrap_err();
+    assert_eq!(app.stack, vec![-100.0, 3.0]);
+    assert!(err.to_string().contains("Need 4 numbers for npv"));
+}
+
+#[test]
+fn test_irr() {
+    let rate = irr(&[-100.0, 60.0, 60.0]).unwrap();
+    assert!((rate - 13.066238629).abs() < 1e-6);
+    assert!(npv(rate, &[-100.0, 60.0, 60.0]).abs() < 1e-9);
+}
+
+#[test]
+fn test_irr_needs_sign_change() {
+    let mut app = App::new();
+    let err = app.eval_line("100 60 2 irr").unwrap_err();
+    assert_eq!(app.stack, vec![100.0, 60.0, 2.0]);
+    assert!(err.to_string().contains("positive and negative"));
+}
+
+#[test]
+fn test_subtotal() {
+    let mut app = App::new();
+    app.eval_line("12.5 drop 3.25 2 * subtotal").unwrap();
+    assert_eq!(app.stack, vec![6.5, 17.75]);
+    assert_eq!(app.calc_history.last().unwrap().text, "subtotal of 3 entries = 17.75");
+
+    // A new run starts after each subtotal and each clear.
+    app.eval_line("1 subtotal").unwrap();
+    assert_eq!(app.stack.last(), Some(&Value::from(1.0)));
+    app.eval_line("5 clear 2 3 subtotal").unwrap();
+    assert_eq!(app.stack, vec![2.0, 3.0, 5.0]);
+    assert!(app.eval_line("subtotal").is_err());
+}
+
+#[test]
+fn test_subtotal_undo() {
+    let mut app = App::new();
+    app.eval_line("1 2 3 subtotal").unwrap();
+    app.eval_line("undo").unwrap();
+    assert_eq!(app.stack, vec![1.0, 2.0, 3.0]);
+    app.eval_line("subtotal").unwrap();
+    assert_eq!(app.stack, vec![1.0, 2.0, 3.0, 6.0]);
+
+    // Undoing a clear brings back its run too.
+    app.eval_line("4 clear undo subtotal").unwrap();
+    assert_eq!(app.stack.last(), Some(&Value::from(4.0)));
+
+    // An undone number is not counted.
+    app.eval_line("clear 1 2").unwrap();
+    app.eval_line("undo subtotal").unwrap();
+    assert_eq!(app.stack, vec![1.0, 1.0]);
+    app.eval_line("clear 1 2 +").unwrap();
+    app.eval_line("undo undo subtotal").unwrap();
+    assert_eq!(app.stack, vec![1.0, 1.0]);
+}
+
+#[test]
+fn test_subtotal_money_and_workspaces() {
+    let mut app = App::new();
+    app.eval_line("money 0.1 0.2 drop drop").unwrap();
+    app.switch_workspace(1).unwrap();
+    app.eval_line("7").unwrap();
+    app.switch_workspace(0).unwrap();
+    app.eval_line("subtotal").unwrap();
+    assert_eq!(format_value(app.stack.last().unwrap()), "0.30");
+}
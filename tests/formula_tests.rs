@@ -0,0 +1,87 @@
+use rpncalc::*;
+
+fn placeholder(name: &str) -> Value {
+    Value::Formula(Formula::Placeholder(name.to_string()))
+}
+
+#[test]
+fn test_parse_placeholder() {
+    assert_eq!(Value::parse("?rate"), Ok(placeholder("rate")));
+    assert_eq!(Formula::parse_placeholder("?1x"), None);
+    assert_eq!(Formula::parse_placeholder("?"), None);
+}
+
+#[test]
+fn test_calculation_waits_on_placeholder() {
+    let mut app = App::new();
+    app.input = "?x 3 + 2 *".to_string();
+    app.execute_command();
+    assert_eq!(app.stack.len(), 1);
+    assert!(app.stack[0].is_formula());
+    assert_eq!(format_value(&app.stack[0]), "(?x + 3) * 2");
+    let tape: Vec<&str> = app.calc_history.iter().map(|e| e.text.as_str()).collect();
+    assert_eq!(tape, vec!["?x + 3 = ?", "(?x + 3) * 2 = ?"]);
+}
+
+#[test]
+fn test_only_calculations_wait_on_placeholder() {
+    let mut app = App::new();
+    for line in ["?x mark", "?x 2 sadd", "1 ?x 2 npv", "?x predict"] {
+        let err = app.eval_line(&format!("clear {}", line)).unwrap_err();
+        assert!(matches!(err, CalcError::WrongType { .. }), "{}: {:?}", line, err);
+    }
+    assert_eq!(app.mark, None);
+    assert!(app.pairs.is_empty());
+}
+
+#[test]
+fn test_bind_recomputes_stack_and_tape() {
+    let mut app = App::new();
+    app.input = "10 ?x 3 + 2 * sqrt".to_string();
+    app.execute_command();
+    app.input = "bind x 5".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[10.0, 4.0]));
+    let tape: Vec<&str> = app.calc_history.iter().map(|e| e.text.as_str()).collect();
+    assert_eq!(tape, vec!["?x + 3 = 8", "(?x + 3) * 2 = 16", "sqrt((?x + 3) * 2) = 4"]);
+    assert_eq!(app.message, "Bound ?x = 5; recomputed 3 tape entries");
+
+    app.input = "undo".to_string();
+    app.execute_command();
+    assert!(app.stack[1].is_formula());
+    let tape: Vec<&str> = app.calc_history.iter().map(|e| e.text.as_str()).collect();
+    assert_eq!(tape, vec!["?x + 3 = ?", "(?x + 3) * 2 = ?", "sqrt((?x + 3) * 2) = ?"]);
+    app.eval_line("bind x 47").unwrap();
+    assert_eq!(app.stack, numbers(&[10.0, 10.0]));
+    assert_eq!(app.calc_history[2].text, "sqrt((?x + 3) * 2) = 10");
+}
+
+#[test]
+fn test_partial_binding() {
+    let mut app = App::new();
+    app.input = "?w ?h * bind w 3".to_string();
+    app.execute_command();
+    assert_eq!(format_value(&app.stack[0]), "3 * ?h");
+    app.input = "bind h 4".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[12.0]));
+}
+
+#[test]
+fn test_failed_bind_keeps_stack() {
+    let mut app = App::new();
+    app.input = "?x sqrt".to_string();
+    app.execute_command();
+    let before = app.stack.clone();
+    assert!(matches!(app.eval_line("bind x -4"), Err(CalcError::DomainError(_))));
+    assert_eq!(app.stack, before);
+    assert!(matches!(app.eval_line("bind y 2"), Err(CalcError::InvalidArgument(_))));
+}
+
+#[test]
+fn test_stack_operations_move_placeholders() {
+    let mut app = App::new();
+    app.input = "?a 1 swap".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![Value::Number(1.0), placeholder("a")]);
+}
@@ -0,0 +1,72 @@
+use rpncalc::*;
+
+fn fraction(num: i64, den: i64) -> Value {
+    Value::Rational(Rational::new(num, den).unwrap())
+}
+
+#[test]
+fn test_rational_arithmetic() {
+    let third = Rational::new(2, -6).unwrap();
+    assert_eq!((third.numer(), third.denom()), (-1, 3));
+    let half = Rational::new(1, 2).unwrap();
+    assert_eq!(half.checked_add(third), Rational::new(1, 6));
+    assert_eq!(half.checked_div(third), Rational::new(-3, 2));
+    assert_eq!(half.checked_div(Rational::new(0, 1).unwrap()), None);
+    let huge = Rational::new(i64::MAX, 1).unwrap();
+    assert_eq!(huge.checked_mul(huge), None);
+    assert_eq!(third.to_string(), "-1/3");
+    assert_eq!(Rational::new(4, 2).unwrap().to_string(), "2");
+}
+
+#[test]
+fn test_rational_parse_and_approximation() {
+    assert_eq!(Rational::parse("-1.25"), Rational::new(-5, 4));
+    assert_eq!(Rational::parse("3/9"), Rational::new(1, 3));
+    assert_eq!(Rational::parse("1/0"), None);
+    assert_eq!(Rational::parse("1e3"), None);
+    assert_eq!(Rational::from_f64(0.1 + 0.2), Rational::new(3, 10));
+    assert_eq!(Rational::from_f64(-1.0 / 3.0), Rational::new(-1, 3));
+    assert_eq!(Rational::from_f64(std::f64::consts::PI), None);
+    assert_eq!(Value::parse("22/7"), Ok(fraction(22, 7)));
+}
+
+#[test]
+fn test_frac_mode_keeps_fractions_exact() {
+    let mut app = App::new();
+    app.eval_line("fraction 1 3 /").unwrap();
+    assert_eq!(app.stack, [fraction(1, 3)]);
+    assert_eq!(format_value(&app.stack[0]), "1/3");
+    app.eval_line("1 6 / + 0.25 *").unwrap();
+    assert_eq!(app.stack, [fraction(1, 8)]);
+    assert_eq!(app.calc_history.last().unwrap().text, "1/2 * 1/4 = 1/8");
+
+    // Anything but + - * / leaves exact arithmetic.
+    app.eval_line("sqrt").unwrap();
+    assert!(matches!(app.stack[0], Value::Number(_)));
+    app.eval_line("dup * tofrac").unwrap();
+    assert_eq!(app.stack, [fraction(1, 8)]);
+    app.eval_line("tofloat").unwrap();
+    assert_eq!(app.stack, [Value::Number(0.125)]);
+}
+
+#[test]
+fn test_frac_mode_switches() {
+    let mut app = App::new();
+    app.eval_line("0.5 3 pi fraction").unwrap();
+    assert_eq!(app.stack[..2], [fraction(1, 2), fraction(3, 1)]);
+    assert!(matches!(app.stack[2], Value::Number(_)));
+    assert!(app.eval_line("tofrac").is_err());
+
+    app.eval_line("money").unwrap();
+    assert!(!app.frac);
+    assert_eq!(app.stack[0], Value::Decimal(Decimal::parse("0.5").unwrap()));
+    app.eval_line("clear fraction 2/3 float").unwrap();
+    assert!(!app.frac && !app.money);
+    assert_eq!(app.stack, [Value::Number(2.0 / 3.0)]);
+
+    // Typed fractions are exact even outside fraction mode.
+    app.eval_line("clear 1/3 1/6 +").unwrap();
+    assert_eq!(app.stack, [fraction(1, 2)]);
+    app.eval_line("0.1 +").unwrap();
+    assert_eq!(app.stack, [Value::Number(0.6)]);
+}
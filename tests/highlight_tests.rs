@@ -0,0 +1,57 @@
+use rpncalc::highlight::{highlight, TokenKind};
+use rpncalc::*;
+
+fn kinds(app: &App, line: &str) -> Vec<(String, TokenKind)> {
+    highlight(app, line)
+        .into_iter()
+        .filter_map(|(text, kind)| Some((text.to_string(), kind?)))
+        .collect()
+}
+
+#[test]
+fn test_highlight_kinds() {
+    let app = App::new();
+    let kinds = kinds(&app, "2 [1 2; 3 4] det sqrtt rnd 2 plotf \"x dup *\" 0 1 10 if");
+    let expected = [
+        ("2", TokenKind::Number),
+        ("[1 2; 3 4]", TokenKind::Number),
+        ("det", TokenKind::Command),
+        ("sqrtt", TokenKind::Unknown),
+        ("rnd", TokenKind::Command),
+        ("2", TokenKind::Argument),
+        ("plotf", TokenKind::Command),
+        ("\"x dup *\"", TokenKind::Quoted),
+        ("0", TokenKind::Argument),
+        ("1", TokenKind::Argument),
+        ("10", TokenKind::Argument),
+        ("if", TokenKind::Command),
+    ];
+    let expected: Vec<(String, TokenKind)> =
+        expected.iter().map(|(t, k)| (t.to_string(), *k)).collect();
+    assert_eq!(kinds, expected);
+}
+
+#[test]
+fn test_highlight_keeps_whitespace() {
+    let mut app = App::new();
+    app.macros = MacroLibrary::parse("def ftoc = 32 - 5 * 9 /").0;
+    let pieces = highlight(&app, " 212  ftoc ");
+    assert_eq!(
+        pieces,
+        vec![
+            (" ", None),
+            ("212", Some(TokenKind::Number)),
+            ("  ", None),
+            ("ftoc", Some(TokenKind::Command)),
+            (" ", None),
+        ]
+    );
+    let text: String = pieces.iter().map(|(t, _)| *t).collect();
+    assert_eq!(text, " 212  ftoc ");
+}
+
+#[test]
+fn test_highlight_unfinished_input() {
+    let app = App::new();
+    assert_eq!(highlight(&app, "1 [2 3"), vec![("1 [2 3", None)]);
+}
@@ -0,0 +1,116 @@
+use rpncalc::history::{self, HISTORY_LIMIT};
+use rpncalc::*;
+use std::fs;
+
+#[test]
+fn test_history_keeps_more_than_ten_entries() {
+    let mut app = App::new();
+    for _ in 0..15 {
+        app.eval_line("1 1 +").unwrap();
+    }
+    assert_eq!(app.calc_history.len(), 15);
+}
+
+#[test]
+fn test_history_entry_parse() {
+    let entry = HistoryEntry::parse("3 + 4 = 7").unwrap();
+    assert_eq!(entry.result, 7.0);
+    assert_eq!(HistoryEntry::parse("PMT = -1.199101e3").unwrap().result, -1199.101);
+    assert!(HistoryEntry::parse("not a calculation").is_none());
+}
+
+#[test]
+fn test_history_log_round_trip() {
+    let dir = std::env::temp_dir().join(format!("rpncalc-history-{}", std::process::id()));
+    let path = dir.join("history.log");
+
+    let mut app = App::new();
+    app.history_file = Some(path.clone());
+    app.eval_line("3 4 + 2 *").unwrap();
+
+    // Timings are not logged.
+    let entries = history::load(&path, HISTORY_LIMIT).unwrap();
+    let untimed: Vec<HistoryEntry> = app
+        .calc_history
+        .iter()
+        .map(|e| HistoryEntry::new(e.text.clone(), e.result.clone()))
+        .collect();
+    assert_eq!(entries, untimed);
+
+    let last = history::load(&path, 1).unwrap();
+    assert_eq!(last.len(), 1);
+    assert_eq!(last[0].result, 14.0);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_search_filters_and_repushes() {
+    let mut app = App::new();
+    app.eval_line("3 4 + 16 sqrt 2 3 ^ clear").unwrap();
+    app.start_history_search().unwrap();
+    app.update_history_query(|q| q.push_str("SQRT"));
+    assert_eq!(app.history_matches().len(), 1);
+    app.push_selected_history();
+    assert_eq!(app.stack, vec![4.0]);
+    assert!(app.history_search.is_none());
+}
+
+#[test]
+fn test_search_selection_defaults_to_newest() {
+    let mut app = App::new();
+    app.eval_line("1 2 + 3 4 + clear").unwrap();
+    app.start_history_search().unwrap();
+    app.push_selected_history();
+    assert_eq!(app.stack, vec![7.0]);
+
+    app.start_history_search().unwrap();
+    app.move_history_selection(-5);
+    app.push_selected_history();
+    assert_eq!(app.stack, vec![7.0, 3.0]);
+}
+
+#[test]
+fn test_search_without_matches() {
+    let mut app = App::new();
+    app.eval_line("1 2 +").unwrap();
+    app.start_history_search().unwrap();
+    app.update_history_query(|q| q.push_str("zzz"));
+    app.push_selected_history();
+    assert_eq!(app.stack, vec![3.0]);
+    assert!(app.message.contains("No matching"));
+}
+
+#[test]
+fn test_scroll_history_is_clamped() {
+    let mut app = App::new();
+    app.eval_line("1 2 + 3 +").unwrap();
+    app.scroll_history(10);
+    assert_eq!(app.history_scroll, 1);
+    app.scroll_history(-10);
+    assert_eq!(app.history_scroll, 0);
+}
+
+#[test]
+fn test_push_history_entry() {
+    let mut app = App::new();
+    app.eval_line("1 2 + 3 4 + clear").unwrap();
+    app.push_history_entry(0);
+    assert_eq!(app.stack, vec![3.0]);
+    app.push_history_entry(9);
+    assert_eq!(app.stack, vec![3.0]);
+    app.undo().unwrap();
+    assert!(app.stack.is_empty());
+}
+
+#[test]
+fn test_history_limit() {
+    let mut app = App::new();
+    app.history_limit = 3;
+    for n in 1..=5 {
+        app.eval_line(&format!("{} 1 +", n)).unwrap();
+    }
+    let texts: Vec<&str> = app.calc_history.iter().map(|e| e.text.as_str()).collect();
+    assert_eq!(texts, ["3 + 1 = 4", "4 + 1 = 5", "5 + 1 = 6"]);
+    assert_eq!(app.tape.len(), 15);
+}
@@ -0,0 +1,115 @@
+use rpncalc::*;
+
+#[test]
+fn test_single_token_paste_goes_to_input() {
+    let mut app = App::new();
+    app.paste("3.14\n");
+    assert_eq!(app.input, "3.14");
+    assert!(app.table_import.is_none());
+}
+
+#[test]
+fn test_multiline_paste_opens_import() {
+    let mut app = App::new();
+    app.paste("1\n2\n3\n");
+    assert!(app.table_import.is_some());
+    app.confirm_import();
+    assert_eq!(app.stack, vec![1.0, 2.0, 3.0]);
+    assert!(app.table_import.is_none());
+}
+
+#[test]
+fn test_csv_column_selection_skips_header() {
+    let mut app = App::new();
+    app.paste("name,price\napple,1.5\npear,2.25\n");
+    let import = app.table_import.as_mut().unwrap();
+    assert_eq!(import.delimiter, Delimiter::Comma);
+    assert_eq!(import.column_count(), 2);
+    import.next_column();
+    app.confirm_import();
+    assert_eq!(app.stack, vec![1.5, 2.25]);
+    assert!(app.message.contains("1 skipped"));
+}
+
+#[test]
+fn test_import_is_undoable() {
+    let mut app = App::new();
+    app.stack = numbers(&[9.0]);
+    app.paste("1\t2\n3\t4\n");
+    app.confirm_import();
+    assert_eq!(app.stack, vec![9.0, 1.0, 3.0]);
+    app.input = "undo".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![9.0]);
+}
+
+#[test]
+fn test_cancel_import() {
+    let mut app = App::new();
+    app.paste("1 2\n3 4\n");
+    app.cancel_import();
+    assert!(app.table_import.is_none());
+    assert!(app.stack.is_empty());
+}
+
+#[test]
+fn test_cycle_delimiter() {
+    let mut import = TableImport::new("1;2\n3;4\n");
+    assert_eq!(import.delimiter, Delimiter::Semicolon);
+    import.cycle_delimiter();
+    assert_eq!(import.delimiter, Delimiter::Whitespace);
+    assert_eq!(import.column_count(), 1);
+}
+
+#[test]
+fn test_push_text_numbers() {
+    let mut app = App::new();
+    let outcome = app.push_text_numbers("1 2.5\n-3\tfoo\n").unwrap();
+    assert_eq!(app.stack, vec![1.0, 2.5, -3.0]);
+    assert!(outcome.message().contains("1 skipped"));
+}
+
+#[test]
+fn test_push_text_without_numbers() {
+    let mut app = App::new();
+    let err = app.push_text_numbers("hello world").unwrap_err();
+    assert!(app.stack.is_empty());
+    assert!(err.to_string().contains("No numbers"));
+}
+
+#[test]
+fn test_copy_empty_stack() {
+    let mut app = App::new();
+    app.input = "copy".to_string();
+    app.execute_command();
+    assert!(app.message.contains("Stack is empty"));
+}
+
+#[test]
+fn test_import_file() {
+    let dir = std::env::temp_dir().join(format!("rpncalc-import-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let column = dir.join("column.csv");
+    std::fs::write(&column, "price\n1.5\n2.25\n").unwrap();
+    let mut app = App::new();
+    let outcome = app.eval_line(&format!("import {}", column.display())).unwrap();
+    assert_eq!(app.stack, vec![1.5, 2.25]);
+    assert_eq!(outcome.message(), "Imported 2 values (1 skipped)");
+    app.undo().unwrap();
+    assert!(app.stack.is_empty());
+
+    // A file written by export has several columns, so one has to be picked.
+    let exported = dir.join("export.csv");
+    app.export.decimal_comma = true;
+    app.eval_line(&format!("1.5 export csv {}", exported.display())).unwrap();
+    let err = app.eval_line(&format!("clear import {}", exported.display())).unwrap_err();
+    assert!(err.to_string().contains("has 3 columns"), "{}", err);
+    assert!(app.table_import.is_none());
+    app.eval_line(&format!("importcol {} 3", exported.display())).unwrap();
+    assert_eq!(app.stack, vec![1.5]);
+    assert!(app.eval_line(&format!("importcol {} 4", exported.display())).is_err());
+
+    let err = app.eval_line(&format!("import {}", dir.join("missing.csv").display()));
+    assert!(err.unwrap_err().to_string().starts_with("Could not read"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
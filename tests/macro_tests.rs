@@ -0,0 +1,94 @@
+use rpncalc::*;
+
+const LIBRARY: &str = "\
+# Fahrenheit to Celsius
+def ftoc = 32 - 5 * 9 /
+def sq = 2 ^
+def hyp = sq swap sq + sqrt
+test ftoc: 212 ftoc expect 100
+test ftoc: -40 ftoc expect -40
+test hyp: 3 4 hyp expect 5
+";
+
+fn app_with(text: &str) -> App {
+    let mut app = App::new();
+    app.macros = MacroLibrary::parse(text).0;
+    app
+}
+
+#[test]
+fn test_parse_macros() {
+    let (library, warnings) = MacroLibrary::parse(LIBRARY);
+    assert!(warnings.is_empty());
+    assert_eq!(library.macros.len(), 3);
+    assert_eq!(library.get("ftoc").unwrap().body, "32 - 5 * 9 /");
+    assert_eq!(library.tests.len(), 3);
+    assert_eq!(library.tests[0].input, "212 ftoc");
+    assert_eq!(library.tests[0].expect, numbers(&[100.0]));
+    assert_eq!(library.tests[0].line, 5);
+}
+
+#[test]
+fn test_macro_warnings() {
+    let (library, warnings) = MacroLibrary::parse("def = 1\ntest sq: 2 sq\nfoo\ndef ok = 1 +\n");
+    assert_eq!(library.macros.len(), 1);
+    assert_eq!(warnings.len(), 3);
+    assert!(warnings[1].starts_with("line 2:"));
+}
+
+#[test]
+fn test_run_macro() {
+    let mut app = app_with(LIBRARY);
+    app.input = "3 4 hyp".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[5.0]));
+}
+
+#[test]
+fn test_macro_undoes_as_one_step() {
+    let mut app = app_with(LIBRARY);
+    app.stack = numbers(&[3.0, 4.0]);
+    app.input = "hyp".to_string();
+    app.execute_command();
+    app.input = "undo".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[3.0, 4.0]));
+}
+
+#[test]
+fn test_failed_macro_restores_stack() {
+    let mut app = app_with(LIBRARY);
+    app.stack = numbers(&[3.0]);
+    app.input = "hyp".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[3.0]));
+}
+
+#[test]
+fn test_recursive_macro_is_stopped() {
+    let mut app = app_with("def loop = 1 + loop\n");
+    app.stack = numbers(&[0.0]);
+    let result = app.eval_line("loop");
+    assert!(matches!(result, Err(CalcError::InvalidArgument(_))));
+    assert_eq!(app.stack, numbers(&[0.0]));
+}
+
+#[test]
+fn test_runtests_reports() {
+    let mut app = app_with(&format!("{}test sq: 3 sq expect 10\n", LIBRARY));
+    app.stack = numbers(&[7.0]);
+    let outcome = app.eval_line("runtests").unwrap();
+    assert_eq!(outcome.message(), "Macro tests: 3 passed, 1 failed");
+    assert_eq!(app.stack, numbers(&[7.0]));
+    let popup = app.popup.as_ref().unwrap();
+    assert!(popup.lines.iter().any(|l| l == "FAIL line 8: 3 sq"));
+    assert!(popup.lines.iter().any(|l| l.contains("expected 10, got 9")));
+}
+
+#[test]
+fn test_single_macro_tests() {
+    let mut app = app_with(LIBRARY);
+    let outcome = app.eval_line("test ftoc").unwrap();
+    assert_eq!(outcome.message(), "Macro tests: 2 passed, 0 failed");
+    assert!(app.eval_line("test nosuch").is_err());
+}
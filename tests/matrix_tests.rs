@@ -0,0 +1,122 @@
+use rpncalc::*;
+
+fn matrix(text: &str) -> Value {
+    Matrix::parse(text).unwrap().into()
+}
+
+fn assert_close(value: &Value, expected: &[f64]) {
+    let m = value.as_matrix().expect("a matrix");
+    assert_eq!(m.data().len(), expected.len());
+    for (a, b) in m.data().iter().zip(expected) {
+        assert!(Tolerance::default().close(*a, *b), "{} != {}", a, b);
+    }
+}
+
+#[test]
+fn test_matrix_literals() {
+    let a = Matrix::parse("[1 2; 3 4]").unwrap();
+    assert_eq!((a.rows(), a.cols()), (2, 2));
+    assert_eq!(Matrix::parse("[[1 2] [3 4]]").unwrap(), a);
+    assert_eq!(Matrix::parse("[1, 2, 3]").unwrap().cols(), 3);
+    assert!(Matrix::parse("[1 2; 3]").is_err());
+    assert!(Matrix::parse("[]").is_err());
+}
+
+#[test]
+fn test_push_literal() {
+    let mut app = App::new();
+    app.eval_line("[1 2 3] [-1 0.5; 2 3]").unwrap();
+    assert_eq!(app.stack, vec![matrix("[1 2 3]"), matrix("[-1 0.5; 2 3]")]);
+}
+
+#[test]
+fn test_unclosed_literal() {
+    let mut app = App::new();
+    let err = app.eval_line("[1 2").unwrap_err();
+    assert!(app.stack.is_empty());
+    assert!(err.to_string().contains("Unclosed"));
+}
+
+#[test]
+fn test_vec() {
+    let mut app = App::new();
+    app.eval_line("1 2 3 3 vec").unwrap();
+    assert_eq!(app.stack, vec![matrix("[1 2 3]")]);
+}
+
+#[test]
+fn test_dot_cross_norm() {
+    let mut app = App::new();
+    app.eval_line("[1 2 3] [4 5 6] dot").unwrap();
+    assert_eq!(app.stack, vec![32.0]);
+    app.eval_line("clear [1 0 0] [0 1 0] cross").unwrap();
+    assert_eq!(app.stack, vec![matrix("[0 0 1]")]);
+    app.eval_line("clear [3 4] norm").unwrap();
+    assert_eq!(app.stack, vec![5.0]);
+}
+
+#[test]
+fn test_transpose_and_det() {
+    let mut app = App::new();
+    app.eval_line("[1 2 3; 4 5 6] trn").unwrap();
+    assert_eq!(app.stack, vec![matrix("[1 4; 2 5; 3 6]")]);
+    app.eval_line("clear [1 2; 3 4] det").unwrap();
+    assert!(Tolerance::default().close(app.stack[0].as_number().unwrap(), -2.0));
+}
+
+#[test]
+fn test_matrix_inverse() {
+    let mut app = App::new();
+    app.eval_line("[4 7; 2 6] inv").unwrap();
+    assert_close(&app.stack[0], &[0.6, -0.7, -0.2, 0.4]);
+    let err = app.eval_line("clear [1 2; 2 4] inv").unwrap_err();
+    assert_eq!(app.stack, vec![matrix("[1 2; 2 4]")]);
+    assert!(err.to_string().contains("singular"));
+}
+
+#[test]
+fn test_matrix_arithmetic() {
+    let mut app = App::new();
+    app.eval_line("[1 2; 3 4] [5 6; 7 8] *").unwrap();
+    assert_eq!(app.stack, vec![matrix("[19 22; 43 50]")]);
+    app.eval_line("2 *").unwrap();
+    assert_eq!(app.stack, vec![matrix("[38 44; 86 100]")]);
+    app.eval_line("[1 1; 1 1] -").unwrap();
+    assert_eq!(app.stack, vec![matrix("[37 43; 85 99]")]);
+    let err = app.eval_line("[1 2 3] +").unwrap_err();
+    assert!(err.to_string().contains("differ"));
+}
+
+#[test]
+fn test_matrix_product_shapes() {
+    let mut app = App::new();
+    let err = app.eval_line("[1 2 3] [1 2 3] *").unwrap_err();
+    assert_eq!(app.stack.len(), 2);
+    assert!(err.to_string().contains("Cannot multiply 1×3 by 1×3"));
+}
+
+#[test]
+fn test_number_ops_reject_matrices() {
+    let mut app = App::new();
+    let err = app.eval_line("[1 2] sin").unwrap_err();
+    assert_eq!(app.stack, vec![matrix("[1 2]")]);
+    assert_eq!(err.to_string(), "sin needs numbers");
+}
+
+#[test]
+fn test_matrix_history_text() {
+    let mut app = App::new();
+    app.eval_line("[1 2] [3 4] +").unwrap();
+    let entry = app.calc_history.last().unwrap();
+    assert_eq!(entry.text, "[1 2] + [3 4] = [4 6]");
+    assert_eq!(HistoryEntry::parse(&entry.text).unwrap().result, matrix("[4 6]"));
+}
+
+#[test]
+fn test_matrix_display_lines() {
+    assert_eq!(
+        format_value_lines(&matrix("[1 -20; 300 4]")),
+        vec!["[   1  -20 ]", "[ 300    4 ]"]
+    );
+    assert_eq!(format_value_lines(&matrix("[1 2 3]")), vec!["[1 2 3]"]);
+}
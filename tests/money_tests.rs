@@ -0,0 +1,81 @@
+use rpncalc::*;
+
+fn dec(text: &str) -> Decimal {
+    Decimal::parse(text).unwrap()
+}
+
+#[test]
+fn test_decimal_parse_and_format() {
+    assert_eq!(dec("12.5").format(2), "12.50");
+    assert_eq!(dec("-0.005").format(2), "-0.01");
+    assert_eq!(dec("0.004999").format(2), "0.00");
+    assert_eq!(dec("1.23456789").to_string(), "1.234568");
+    assert_eq!(dec(".5").to_string(), "0.5");
+    assert_eq!(dec("1e3").format(0), "1000");
+    assert!(Decimal::parse("12a").is_none());
+    assert!(Decimal::parse(".").is_none());
+}
+
+#[test]
+fn test_decimal_arithmetic() {
+    assert_eq!(dec("0.1").checked_add(dec("0.2")).unwrap(), dec("0.3"));
+    assert_eq!(dec("19.99").checked_mul(dec("0.075")).unwrap(), dec("1.49925"));
+    assert_eq!(dec("10").checked_div(dec("3")).unwrap().to_string(), "3.333333");
+    assert_eq!(dec("-2").checked_div(dec("3")).unwrap().to_string(), "-0.666667");
+}
+
+#[test]
+fn test_money_mode_sums_exactly() {
+    let mut app = App::new();
+    let outcome = app.eval_line("money 0.1 0.2 +").unwrap();
+    assert_eq!(app.stack, vec![Value::Decimal(dec("0.3"))]);
+    assert_eq!(outcome.message(), "0.10 + 0.20 = 0.30");
+}
+
+#[test]
+fn test_money_mode_keeps_guard_digits() {
+    let mut app = App::new();
+    app.eval_line("money 100 3 / 3 *").unwrap();
+    assert_eq!(app.stack, vec![Value::Decimal(dec("99.999999"))]);
+    assert_eq!(format_value(&app.stack[0]), "100.00");
+}
+
+#[test]
+fn test_money_pct() {
+    let mut app = App::new();
+    let outcome = app.eval_line("money 19.99 7.5 pct").unwrap();
+    assert_eq!(app.stack[1], Value::Decimal(dec("1.49925")));
+    assert_eq!(outcome.message(), "7.50% of 19.99 = 1.50");
+}
+
+#[test]
+fn test_money_and_float_convert_stack() {
+    let mut app = App::new();
+    app.stack = numbers(&[2.5]);
+    app.eval_line("money").unwrap();
+    assert!(app.money);
+    assert_eq!(app.stack, vec![Value::Decimal(dec("2.5"))]);
+    app.eval_line("float").unwrap();
+    assert!(!app.money);
+    assert_eq!(app.stack, vec![Value::Number(2.5)]);
+}
+
+#[test]
+fn test_money_division_by_zero() {
+    let mut app = App::new();
+    let err = app.eval_line("money 5 0 /").unwrap_err();
+    assert_eq!(app.stack.len(), 2);
+    assert_eq!(err, CalcError::DivisionByZero);
+}
+
+#[test]
+fn test_money_rounding_stays_exact() {
+    let mut app = App::new();
+    app.eval_line("money 100 3 / 3 * rnd 2").unwrap();
+    assert_eq!(app.stack, vec![Value::Decimal(dec("100"))]);
+    app.eval_line("2.675 rnd 2 1.99 floor").unwrap();
+    assert_eq!(
+        app.stack[1..],
+        [Value::Decimal(dec("2.68")), Value::Decimal(dec("1"))]
+    );
+}
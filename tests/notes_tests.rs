@@ -0,0 +1,39 @@
+use rpncalc::*;
+use std::fs;
+
+#[test]
+fn test_toggle_notes() {
+    let mut app = App::new();
+    app.input = "notes".to_string();
+    app.execute_command();
+    assert!(app.show_notes);
+    assert!(app.editing_notes);
+    app.input = "notes".to_string();
+    app.execute_command();
+    assert!(!app.show_notes);
+    assert!(!app.editing_notes);
+}
+
+#[test]
+fn test_notes_round_trip() {
+    let dir = std::env::temp_dir().join(format!("rpncalc-notes-{}", std::process::id()));
+    let path = dir.join("notes.txt");
+
+    let mut app = App::new();
+    app.notes = "g = 9.81\nassume no drag".to_string();
+    app.save_notes(&path).unwrap();
+
+    let mut restored = App::new();
+    restored.load_notes(&path).unwrap();
+    assert_eq!(restored.notes, app.notes);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_load_missing_notes_is_empty() {
+    let mut app = App::new();
+    let path = std::env::temp_dir().join("rpncalc-does-not-exist/notes.txt");
+    app.load_notes(&path).unwrap();
+    assert!(app.notes.is_empty());
+}
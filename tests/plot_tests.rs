@@ -0,0 +1,110 @@
+use rpncalc::*;
+
+#[test]
+fn test_sample_points() {
+    assert_eq!(plot::sample_points(0.0, 1.0, 5), vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+}
+
+#[test]
+fn test_bounds() {
+    assert_eq!(plot::bounds(&[]), None);
+    assert_eq!(
+        plot::bounds(&[(0.0, 3.0), (1.0, -2.0), (2.0, 5.0)]),
+        Some(([0.0, 2.0], [-2.0, 5.0]))
+    );
+    assert_eq!(plot::bounds(&[(4.0, 7.0)]), Some(([3.0, 5.0], [6.0, 8.0])));
+}
+
+#[test]
+fn test_plot_toggles_stack_pane() {
+    let mut app = App::new();
+    app.stack = numbers(&[3.0, 1.0, 4.0]);
+    app.input = "plot".to_string();
+    app.execute_command();
+    assert_eq!(app.plot, Some(Plot::Stack));
+    let points = app.plot.as_ref().unwrap().points(&app.stack);
+    assert_eq!(points, vec![(0.0, 3.0), (1.0, 1.0), (2.0, 4.0)]);
+
+    app.input = "plot".to_string();
+    app.execute_command();
+    assert_eq!(app.plot, None);
+    assert_eq!(app.stack.len(), 3);
+}
+
+#[test]
+fn test_plotf_samples_expression() {
+    let mut app = App::new();
+    app.stack = numbers(&[42.0]);
+    app.input = "plotf \"x dup *\" 0 10 11".to_string();
+    app.execute_command();
+    let Some(Plot::Function { expr, points }) = &app.plot else {
+        panic!("no function plot: {}", app.message);
+    };
+    assert_eq!(expr, "x dup *");
+    assert_eq!(points.len(), 11);
+    assert_eq!(points[3], (3.0, 9.0));
+    assert_eq!(app.stack, numbers(&[42.0]));
+}
+
+#[test]
+fn test_plotf_macro_skips_failures() {
+    let mut app = App::new();
+    app.macros = MacroLibrary::parse("def half = 2 / sqrt\n").0;
+    app.input = "plotf half -2 2 5".to_string();
+    app.execute_command();
+    let Some(Plot::Function { points, .. }) = &app.plot else {
+        panic!("no function plot: {}", app.message);
+    };
+    assert_eq!(points.len(), 3);
+    assert_eq!(app.message, "Plotted half at 3 of 5 points from -2 to 2");
+}
+
+#[test]
+fn test_plotf_errors() {
+    let mut app = App::new();
+    assert!(matches!(
+        app.eval_line("plotf \"x sqrt\" -4 -1 10"),
+        Err(CalcError::DomainError(_))
+    ));
+    assert!(app.eval_line("plotf x 0 1 1").is_err());
+    assert!(matches!(
+        app.eval_line("plotf \"x dup * 0 1 10"),
+        Err(CalcError::InvalidArgument(_))
+    ));
+    assert_eq!(app.plot, None);
+}
+
+#[test]
+fn test_chart_points() {
+    let stack = numbers(&[1.0, 10.0, 2.0, 20.0]);
+    assert_eq!(
+        plot::chart_points(&stack, false).unwrap(),
+        vec![(0.0, 1.0), (1.0, 10.0), (2.0, 2.0), (3.0, 20.0)]
+    );
+    assert_eq!(
+        plot::chart_points(&stack, true).unwrap(),
+        vec![(1.0, 10.0), (2.0, 20.0)]
+    );
+    assert!(plot::chart_points(&stack[..3], true).is_err());
+    assert_eq!(plot::chart_points(&[], false), Err(CalcError::EmptyStack));
+    assert!(plot::chart_points(&numbers(&[1.0, f64::NAN]), false).is_err());
+}
+
+#[test]
+fn test_chart_opens_snapshot() {
+    let mut app = App::new();
+    app.stack = numbers(&[3.0, 1.0, 4.0]);
+    app.input = "chart scatter".to_string();
+    app.execute_command();
+    let chart = app.chart.clone().unwrap();
+    assert_eq!(chart.style, ChartStyle::Scatter);
+    assert!(!chart.pairs);
+    assert_eq!(chart.title(), "Chart: 3 points, y min 1 max 4 (any key to close)");
+    assert_eq!(app.stack.len(), 3);
+
+    app.eval_line("drop").unwrap();
+    assert_eq!(app.chart.as_ref().unwrap().points.len(), 3);
+    assert!(app.eval_line("chart bars").is_err());
+    assert!(app.eval_line("chartxy line").is_ok());
+    assert!(app.chart.as_ref().unwrap().pairs);
+}
@@ -0,0 +1,47 @@
+use rpncalc::*;
+
+#[test]
+fn test_parse_prefixed_numbers() {
+    assert_eq!(prefix::parse("4.7k"), Some(4700.0));
+    assert_eq!(prefix::parse("100n"), Some(1e-7));
+    assert_eq!(prefix::parse("-2.2M"), Some(-2.2e6));
+    assert_eq!(prefix::parse("3.3u"), prefix::parse("3.3µ"));
+    assert_eq!(prefix::parse(".5m"), Some(0.0005));
+    for text in ["k", "1.2.3k", "1e3k", "infk", "4.7", "4.7E", "4.7x", "1 k"] {
+        assert_eq!(prefix::parse(text), None, "{}", text);
+    }
+}
+
+#[test]
+fn test_format_prefixed_numbers() {
+    assert_eq!(prefix::format(0.0000047), "4.7 µ");
+    assert_eq!(prefix::format(470000.0), "470 k");
+    assert_eq!(prefix::format(-0.015), "-15 m");
+    assert_eq!(prefix::format(2.2e6), "2.2 M");
+    assert_eq!(prefix::format(1.0 / 3.0 * 1e4), "3.33333333333 k");
+    assert_eq!(prefix::format(999.9999999999999), "1 k");
+    // Without a prefix to use, numbers are shown as usual.
+    assert_eq!(prefix::format(12.5), "12.5");
+    assert_eq!(prefix::format(0.0), "0");
+    assert_eq!(prefix::format(3e20), format_number(3e20));
+    assert_eq!(prefix::format(f64::INFINITY), "inf");
+}
+
+#[test]
+fn test_prefixed_entry_and_display() {
+    let mut app = App::new();
+    app.eval_line("4.7k 100n * [1k 2m]").unwrap();
+    assert_eq!(app.stack[0], Value::Number(4.7e-4));
+    assert_eq!(format_value(&app.stack[1]), "[1000 0.002]");
+    app.eval_line("engpre").unwrap();
+    let number = app.number_format();
+    assert_eq!(format_value_with(&app.stack[0], number), "470 µ");
+    assert_eq!(format_value_with(&app.stack[1], number), "[1 k 2 m]");
+    assert_eq!(
+        format_stack_columns_with(&numbers(&[4700.0, 1e-7]), number),
+        ["  4.7 k", "100 n"]
+    );
+    app.eval_line("engpre").unwrap();
+    assert_eq!(format_value_with(&app.stack[0], app.number_format()), "0.00047");
+    assert!(app.eval_line("def 1k \"1000\"").is_err());
+}
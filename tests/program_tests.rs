@@ -0,0 +1,92 @@
+use rpncalc::program::{self, Node};
+use rpncalc::*;
+
+fn eval(input: &str) -> Vec<Value> {
+    let mut app = App::new();
+    app.eval_line(input).unwrap();
+    app.stack
+}
+
+#[test]
+fn test_parse_program() {
+    let nodes = program::parse(&["1", "if", "2", "else", "3", "times", "loop", "then"]).unwrap();
+    assert_eq!(
+        nodes,
+        vec![
+            Node::Token("1"),
+            Node::If {
+                then: vec![Node::Token("2")],
+                otherwise: vec![Node::Token("3"), Node::Times(vec![])],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_unbalanced() {
+    for input in [&["if", "1"][..], &["1", "then"], &["times", "if", "loop"], &["if", "else", "loop"]] {
+        assert!(program::parse(input).is_err(), "{:?}", input);
+    }
+}
+
+#[test]
+fn test_comparisons() {
+    assert_eq!(eval("2 3 < 2 3 > 3 3 <= 2 3 >= 0.5 0.25 2 * =="), numbers(&[1.0, 0.0, 1.0, 0.0, 1.0]));
+}
+
+#[test]
+fn test_if_then_else() {
+    assert_eq!(eval("-4 dup 0 < if chs then"), numbers(&[4.0]));
+    assert_eq!(eval("4 dup 0 < if chs then"), numbers(&[4.0]));
+    assert_eq!(eval("7 dup 2 % 0 == if 2 / else 3 * 1 + then"), numbers(&[22.0]));
+    assert_eq!(eval("1 if 1 if 5 then then"), numbers(&[5.0]));
+}
+
+#[test]
+fn test_times_loop() {
+    assert_eq!(eval("1 10 times 2 * loop"), numbers(&[1024.0]));
+    assert_eq!(eval("1 0 times 2 * loop"), numbers(&[1.0]));
+    // Newton's method for the square root of 2: x := x/2 + 1/x.
+    let root = eval("1 6 times dup 2 / swap inv + loop");
+    assert!(Tolerance::default().close(root[0].as_number().unwrap(), 2f64.sqrt()));
+}
+
+#[test]
+fn test_control_flow_undoes_as_one_step() {
+    let mut app = App::new();
+    app.eval_line("1 3 times 2 * loop").unwrap();
+    app.undo().unwrap();
+    assert_eq!(app.stack, numbers(&[1.0, 3.0]));
+}
+
+#[test]
+fn test_control_flow_errors_restore_stack() {
+    let mut app = App::new();
+    app.eval_line("1 2").unwrap();
+    // The count was pushed before the loop ran, so it stays, as any value
+    // before a failing command does.
+    assert!(app.eval_line("3 times 0 / loop").is_err());
+    assert_eq!(app.stack, numbers(&[1.0, 2.0, 3.0]));
+    assert!(app.eval_line("1.5 times loop").is_err());
+    assert!(app.eval_line("if").is_err());
+}
+
+#[test]
+fn test_step_limit() {
+    let mut app = App::new();
+    let err = app.eval_line("1e9 times loop").unwrap_err();
+    assert!(err.to_string().contains("Stopped after"));
+    assert_eq!(app.stack, numbers(&[1e9]));
+    // The limit is per line.
+    app.eval_line("drop 1 100 times 1 + loop").unwrap();
+    assert_eq!(app.stack, numbers(&[101.0]));
+}
+
+#[test]
+fn test_macros_with_control_flow() {
+    let mut app = App::new();
+    app.macros = MacroLibrary::parse("def abs2 = dup 0 < if chs then\ndef if = 1\n").0;
+    assert_eq!(app.macros.macros.len(), 1);
+    app.eval_line("-3 abs2").unwrap();
+    assert_eq!(app.stack, numbers(&[3.0]));
+}
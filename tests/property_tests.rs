@@ -0,0 +1,72 @@
+use proptest::prelude::*;
+use rpncalc::*;
+
+/// Literals of every kind of value, including awkward ones.
+const LITERALS: [&str; 17] = [
+    "0", "-0", "0.5", "-2.5", "1e20", "1e308", "-1e-320", "4294967296", "1/3", "2.5k",
+    "2024-03-01", "9.81±0.02", "?x", "[1 2; 3 4]", "[1 2 3]", "rnd 2", "seed 7",
+];
+
+/// Commands that take no inline arguments and touch no files, clipboard or
+/// screen, so any of them may run in any order.
+fn commands() -> Vec<&'static str> {
+    let app = App::new();
+    app.registry
+        .commands()
+        .iter()
+        .filter(|c| c.previewable && c.params.is_empty())
+        .map(|c| c.name)
+        .collect()
+}
+
+fn token() -> impl Strategy<Value = String> {
+    prop_oneof![
+        3 => (-1000i32..1000).prop_map(|n| n.to_string()),
+        1 => prop::sample::select(&LITERALS[..]).prop_map(str::to_string),
+        4 => prop::sample::select(commands()).prop_map(str::to_string),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn test_any_input_is_answered(lines in prop::collection::vec("\\PC{0,24}", 0..8)) {
+        let mut app = App::new();
+        for line in lines {
+            let _ = app.eval_line(&line);
+        }
+    }
+
+    #[test]
+    fn test_tokens_keep_invariants(tokens in prop::collection::vec(token(), 0..40)) {
+        let mut app = App::new();
+        for token in &tokens {
+            let before = app.stack.clone();
+            let cmd = app.registry.lookup(token).cloned();
+            let result = app.eval_line(token);
+            if result.is_err() {
+                prop_assert_eq!(&app.stack, &before, "{} failed but changed the stack", token);
+                continue;
+            }
+            let Some(cmd) = cmd else {
+                continue;
+            };
+            if !cmd.whole_stack && cmd.undoable {
+                let expected = before.len() - cmd.arity + cmd.produces;
+                prop_assert_eq!(app.stack.len(), expected, "{} left the wrong depth", token);
+            }
+            if cmd.undoable && app.stack != before {
+                app.eval_line("undo").unwrap();
+                prop_assert_eq!(&app.stack, &before, "undo after {} did not restore", token);
+            }
+        }
+    }
+
+    #[test]
+    fn test_replay_is_deterministic(tokens in prop::collection::vec(token(), 0..20)) {
+        let line = format!("seed 42 {}", tokens.join(" "));
+        let (mut first, mut second) = (Engine::new(), Engine::new());
+        let results = (first.execute(&line).is_ok(), second.execute(&line).is_ok());
+        prop_assert_eq!(results.0, results.1);
+        prop_assert_eq!(first.checksum(), second.checksum());
+    }
+}
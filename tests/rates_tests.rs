@@ -0,0 +1,87 @@
+use rpncalc::*;
+
+const RATES: &str = "\
+# Taken from the ECB
+base = \"EUR\"
+updated = \"2024-03-01\"
+
+[rates]
+USD = 1.25
+gbp = 0.8   # lower case is fine
+";
+
+fn app_with_rates() -> App {
+    let mut app = App::new();
+    app.rates = RateTable::parse(RATES).0;
+    app
+}
+
+#[test]
+fn test_parse_rates() {
+    let (table, warnings) = RateTable::parse(RATES);
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    assert_eq!(table.base, "EUR");
+    assert_eq!(table.updated.as_deref(), Some("2024-03-01"));
+    assert_eq!(table.rate("GBP"), Some(0.8));
+    assert_eq!(table.rate("eur"), Some(1.0));
+}
+
+#[test]
+fn test_parse_warnings() {
+    let (table, warnings) = RateTable::parse("base = EUR\n[rates]\nUSD = -1\nDOLLARS = 1\n");
+    assert!(table.is_empty());
+    assert_eq!(warnings.len(), 3);
+    assert!(warnings[0].starts_with("line 1: expected a quoted string"));
+}
+
+#[test]
+fn test_convert() {
+    let table = RateTable::parse(RATES).0;
+    assert_eq!(table.convert(100.0, "usd", "eur"), Ok(80.0));
+    assert_eq!(table.convert(125.0, "USD", "GBP"), Ok(80.0));
+    assert_eq!(table.convert(1.0, "USD", "XYZ"), Err("No exchange rate for 'XYZ'".to_string()));
+    assert!(RateTable::default().convert(1.0, "USD", "EUR").is_err());
+}
+
+#[test]
+fn test_round_trip() {
+    let table = RateTable::parse(RATES).0;
+    let (parsed, warnings) = RateTable::parse(&table.to_string());
+    assert!(warnings.is_empty());
+    assert_eq!(parsed, table);
+}
+
+#[test]
+fn test_from_json() {
+    let json = r#"{"base": "usd", "date": "2024-03-04", "rates": {"EUR": 0.92, "JPY": 150.1}}"#;
+    let table = RateTable::from_json(json, "2024-03-05").unwrap();
+    assert_eq!(table.base, "USD");
+    assert_eq!(table.updated.as_deref(), Some("2024-03-04"));
+    assert_eq!(table.rate("JPY"), Some(150.1));
+    let json = r#"{"base_code":"USD","conversion_rates":{"EUR":0.92}}"#;
+    let table = RateTable::from_json(json, "2024-03-05").unwrap();
+    assert_eq!(table.updated.as_deref(), Some("2024-03-05"));
+    assert!(RateTable::from_json("{\"rates\": {}}", "2024-03-05").is_err());
+}
+
+#[test]
+fn test_convertccy_command() {
+    let mut app = app_with_rates();
+    app.eval_line("100 convertccy usd eur").unwrap();
+    assert_eq!(app.stack, vec![80.0]);
+    assert_eq!(app.calc_history[0].text, "100 USD in EUR = 80");
+    assert!(app.eval_line("convertccy usd xyz").is_err());
+    app.eval_line("clear money 12.50 convertccy eur usd").unwrap();
+    assert_eq!(app.stack, vec![Value::Decimal(Decimal::parse("15.625").unwrap())]);
+}
+
+#[test]
+fn test_rates_popup() {
+    let mut app = App::new();
+    assert!(app.eval_line("rates").is_err());
+    let mut app = app_with_rates();
+    app.eval_line("rates").unwrap();
+    let popup = app.popup.unwrap();
+    assert_eq!(popup.lines[0], "1 EUR buys, as of 2024-03-01:");
+    assert_eq!(popup.lines[2], "USD  1.25");
+}
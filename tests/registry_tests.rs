@@ -0,0 +1,200 @@
+use rpncalc::*;
+
+#[test]
+fn test_lookup_by_alias() {
+    let registry = CommandRegistry::with_builtins();
+    assert_eq!(registry.lookup("clr").unwrap().name, "clear");
+    assert_eq!(registry.lookup("xroot").unwrap().name, "root");
+    assert!(registry.lookup("nope").is_none());
+}
+
+#[test]
+fn test_register_custom_command() {
+    let mut app = App::new();
+    app.registry
+        .register(Command::new("double", 1, "Custom", "Double x", |app| {
+            app.unary_op(|a| a * 2.0, "double")
+        }));
+    app.stack = numbers(&[21.0]);
+    app.input = "double".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![42.0]);
+}
+
+#[test]
+fn test_register_replaces_existing() {
+    let mut registry = CommandRegistry::with_builtins();
+    let count = registry.commands().len();
+    registry.register(Command::new("+", 2, "Arithmetic", "Add", |app| {
+        app.binary_op(|a, b| a + b + 1.0, "+")
+    }));
+    assert_eq!(registry.commands().len(), count);
+}
+
+#[test]
+fn test_help_lists_every_command() {
+    let registry = CommandRegistry::with_builtins();
+    let help = registry.help_lines().join("\n");
+    for cmd in registry.commands() {
+        assert!(help.contains(cmd.name), "help is missing {}", cmd.name);
+    }
+}
+
+#[test]
+fn test_complete() {
+    let registry = CommandRegistry::with_builtins();
+    assert_eq!(registry.complete("sq"), vec!["sq", "sqrt"]);
+    assert_eq!(registry.complete("und"), vec!["undo"]);
+}
+
+#[test]
+fn test_complete_input_unique() {
+    let mut app = App::new();
+    app.input = "und".to_string();
+    app.complete_input();
+    assert_eq!(app.input, "undo");
+}
+
+#[test]
+fn test_complete_input_common_prefix() {
+    let mut app = App::new();
+    app.input = "a".to_string();
+    app.complete_input();
+    assert!(app.message.contains("abs"));
+    assert!(app.message.contains("acos"));
+}
+
+#[test]
+fn test_single_char_uses_registry() {
+    let mut app = App::new();
+    app.stack = numbers(&[6.0]);
+    app.input = "7".to_string();
+    app.execute_single_char('*');
+    assert_eq!(app.stack, vec![42.0]);
+    assert!(app.input.is_empty());
+}
+
+#[test]
+fn test_multiple_tokens_on_one_line() {
+    let mut app = App::new();
+    app.input = "5 4 + 3 *".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![27.0]);
+}
+
+#[test]
+fn test_line_stops_at_unknown_command() {
+    let mut app = App::new();
+    app.input = "1 bogus 2".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, vec![1.0]);
+    assert!(app.message.contains("'bogus'"));
+}
+
+#[test]
+fn test_usage() {
+    let registry = CommandRegistry::with_builtins();
+    assert_eq!(registry.lookup("logb").unwrap().usage(), "y x logb");
+    assert_eq!(registry.lookup("sqrt").unwrap().usage(), "x sqrt");
+    assert_eq!(registry.lookup("explain").unwrap().usage(), "explain <command>");
+}
+
+#[test]
+fn test_explain() {
+    let mut app = App::new();
+    app.input = "explain acosh".to_string();
+    app.execute_command();
+    let popup = app.popup.expect("explain opens a popup");
+    let text = popup.lines.join("\n");
+    assert!(text.contains("consumes 1, produces 1"));
+    assert!(text.contains("x >= 1"));
+    assert!(text.contains("2 acosh  →  1.3169"));
+}
+
+#[test]
+fn test_explain_shows_current_angle_mode() {
+    let mut app = App::new();
+    app.input = "rad explain sin".to_string();
+    app.execute_command();
+    let text = app.popup.unwrap().lines.join("\n");
+    assert!(text.contains("angle mode (currently RAD)"));
+    assert!(app.stack.is_empty());
+}
+
+#[test]
+fn test_explain_needs_argument() {
+    let mut app = App::new();
+    app.input = "explain".to_string();
+    app.execute_command();
+    assert!(app.popup.is_none());
+    assert!(app.message.contains("Usage: explain <command>"));
+}
+
+#[test]
+fn test_explain_unknown() {
+    let mut app = App::new();
+    app.input = "explain frobnicate".to_string();
+    app.execute_command();
+    assert!(app.popup.is_none());
+    assert!(app.message.contains("Unknown command"));
+}
+
+#[test]
+fn test_every_example_runs_cleanly() {
+    let registry = CommandRegistry::with_builtins();
+    for cmd in registry.commands() {
+        if let Some(example) = cmd.example {
+            let mut app = App::new();
+            app.input = example.to_string();
+            app.execute_command();
+            assert!(!app.stack.is_empty(), "{} example left an empty stack", cmd.name);
+            assert!(
+                app.stack.iter().all(|v| v.is_finite()),
+                "{} example produced {:?}",
+                cmd.name,
+                app.stack
+            );
+        }
+    }
+}
+
+#[test]
+fn test_complete_last_word() {
+    let mut app = App::new();
+    app.input = "explain acos".to_string();
+    app.complete_input();
+    assert_eq!(app.input, "explain acos");
+    app.input = "explain hyp".to_string();
+    app.complete_input();
+    assert_eq!(app.input, "explain hypot");
+}
+
+#[test]
+fn test_reference_covers_every_command() {
+    let app = App::new();
+    let markdown = reference::render(&app, reference::Format::Markdown, "1.0", "Usage: rpncalc");
+    let man = reference::render(&app, reference::Format::Man, "1.0", "Usage: rpncalc");
+    for cmd in app.registry.commands() {
+        assert!(markdown.contains(&format!("### `{}`", cmd.name)), "{} missing", cmd.name);
+        assert!(man.contains(&format!(".B {}\n", cmd.usage().replace('-', "\\-"))));
+    }
+    assert!(markdown.contains("- Example: `3 4 +` → `7`"));
+    assert!(man.starts_with(".TH RPNCALC 1"));
+}
+
+#[test]
+fn test_reference_formats() {
+    assert_eq!(reference::Format::from_name("md"), Some(reference::Format::Markdown));
+    assert_eq!(reference::Format::from_name("man"), Some(reference::Format::Man));
+    assert_eq!(reference::Format::from_name("pdf"), None);
+}
+
+#[test]
+fn test_completions_include_macros() {
+    let mut app = App::new();
+    app.eval_line("def hypo \"dup *\"").unwrap();
+    let names = app.completions("hyp");
+    assert!(names.contains(&"hypot".to_string()));
+    assert!(names.contains(&"hypo".to_string()));
+    assert!(app.completions("").len() > 100);
+}
@@ -0,0 +1,161 @@
+use rpncalc::*;
+use std::fs;
+
+#[test]
+fn test_replay_sessions() {
+    let log = "1 2\n# session 2024-03-01\nseed 7\n# date 2024-03-01\n3 4 +\n\n# a note\nrand *\n\
+               # session 2024-03-02\n";
+    let sessions = replay::sessions(log);
+    assert_eq!(sessions.len(), 3);
+    assert_eq!(sessions[0], [(1, "1 2")]);
+    let lines = [(3, "seed 7"), (4, "# date 2024-03-01"), (5, "3 4 +"), (8, "rand *")];
+    assert_eq!(sessions[1], lines);
+    assert!(sessions[2].is_empty());
+}
+
+#[test]
+fn test_replay_log_round_trip() {
+    let path = std::env::temp_dir().join(format!("rpncalc-replay-{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut app = App::new();
+    app.start_replay_log(path.clone());
+    assert_eq!(app.replay_file.as_ref(), Some(&path));
+    app.submit_line("3 4 + rand *".to_string()).unwrap();
+    assert!(app.submit_line("1 0 /".to_string()).is_err());
+    app.eval_line("undo").unwrap();
+
+    let text = fs::read_to_string(&path).unwrap();
+    let sessions = replay::sessions(&text);
+    assert_eq!(sessions.len(), 1);
+    let mut replayed = App::new();
+    let errors = replay::run(&mut replayed, &sessions[0]);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, 5);
+    assert_eq!(replayed.stack, app.stack);
+    assert_eq!(replayed.checksum(), app.checksum());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_replay_log_covers_keys_and_mouse() {
+    let path = std::env::temp_dir().join(format!("rpncalc-replay-ui-{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut app = App::new();
+    // History from earlier sessions, which the replay does not have.
+    let sum = Value::Number(0.1 + 0.2);
+    app.calc_history.push(HistoryEntry::new("0.1 + 0.2 = 0.3".to_string(), sum));
+    app.start_replay_log(path.clone());
+    app.eval_line("money 5").unwrap();
+    app.push_history_entry(0);
+    app.paste("1.5\n2.5\n");
+    app.confirm_import();
+    app.pick(1).unwrap();
+    app.move_stack_entry(0, 3).unwrap();
+    app.eval_line("ws 2").unwrap();
+    app.eval_line("7").unwrap();
+    app.eval_line("ws 1").unwrap();
+
+    let text = fs::read_to_string(&path).unwrap();
+    let mut replayed = App::new();
+    let errors = replay::run(&mut replayed, &replay::sessions(&text)[0]);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(replayed.stack, app.stack);
+    assert_eq!(replayed.checksum(), app.checksum());
+    assert_eq!(replayed.stack[0], Value::Number(0.1 + 0.2));
+    replayed.eval_line("ws 2").unwrap();
+    assert_eq!(replayed.stack, vec![7.0]);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_replay_does_not_touch_files() {
+    let dir = std::env::temp_dir().join(format!("rpncalc-replay-files-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (path, data, out) = (dir.join("replay.log"), dir.join("data.csv"), dir.join("out.csv"));
+    fs::write(&data, "1.5\n2.5\n").unwrap();
+    let mut app = App::new();
+    app.start_replay_log(path.clone());
+    app.eval_line(&format!("import {} 7 +", data.display())).unwrap();
+    app.eval_line(&format!("export csv {}", out.display())).unwrap();
+    let missing = format!("import {}", dir.join("missing.csv").display());
+    let err = app.eval_line(&missing).unwrap_err();
+
+    // The files have changed or gone by the time of the replay.
+    fs::write(&data, "9\n").unwrap();
+    fs::remove_file(&out).unwrap();
+    let text = fs::read_to_string(&path).unwrap();
+    let mut replayed = App::new();
+    let errors = replay::run(&mut replayed, &replay::sessions(&text)[0]);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].1.to_string(), err.to_string());
+    assert_eq!(replayed.stack, vec![1.5, 9.5]);
+    assert_eq!(replayed.checksum(), app.checksum());
+    assert!(!out.exists());
+    replayed.eval_line("undo undo undo").unwrap();
+    assert!(replayed.stack.is_empty());
+
+    // A script is not run again, since it may have changed.
+    let lines = [(1, "run beam.rpn")];
+    let errors = replay::run(&mut replayed, &lines);
+    assert!(errors[0].1.to_string().contains("does not run beam.rpn"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_replay_gives_today_the_logged_date() {
+    let path = std::env::temp_dir().join(format!("rpncalc-replay-date-{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut app = App::new();
+    app.fixed_date = Date::parse("2024-02-28");
+    app.start_replay_log(path.clone());
+    app.eval_line("today").unwrap();
+    app.eval_line("1 dadd").unwrap();
+    // The session runs past midnight.
+    app.fixed_date = Date::parse("2024-02-29");
+    app.eval_line("today").unwrap();
+
+    let text = fs::read_to_string(&path).unwrap();
+    assert_eq!(text.matches(replay::DATE_MARK).count(), 2);
+    let mut replayed = App::new();
+    assert!(replay::run(&mut replayed, &replay::sessions(&text)[0]).is_empty());
+    let leap_day = Value::Date(Date::from_ymd(2024, 2, 29).unwrap());
+    assert_eq!(replayed.stack, [leap_day.clone(), leap_day]);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_replay_log_keeps_recent_sessions() {
+    let path = std::env::temp_dir().join(format!("rpncalc-replay-keep-{}.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    for seed in 0..replay::KEEP_SESSIONS as u64 + 5 {
+        replay::start(&path, "2024-03-01", seed).unwrap();
+        replay::append(&path, &format!("{} dup", seed)).unwrap();
+    }
+    let text = fs::read_to_string(&path).unwrap();
+    let sessions = replay::sessions(&text);
+    assert_eq!(sessions.len(), replay::KEEP_SESSIONS);
+    assert_eq!(sessions[0][0].1, "seed 5");
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_checksum_follows_state() {
+    // The random numbers are part of the state.
+    let (mut engine, mut other) = (Engine::new(), Engine::new());
+    engine.execute("seed 1").unwrap();
+    other.execute("seed 1").unwrap();
+    let empty = engine.checksum();
+    assert_eq!(empty, other.checksum());
+    engine.execute("3 4 +").unwrap();
+    let seven = engine.checksum();
+    assert_ne!(seven, empty);
+    // The history, undo journal and tape are left out.
+    other.execute("1 2 + clear 3 4 +").unwrap();
+    assert_eq!(other.checksum(), seven);
+    // Undo brings the stack back, but not last x.
+    engine.execute("undo").unwrap();
+    assert_ne!(engine.checksum(), empty);
+    engine.execute("3 4 + rad").unwrap();
+    assert_ne!(engine.checksum(), seven);
+    assert_eq!(replay::fingerprint(b""), 0xcbf2_9ce4_8422_2325);
+}
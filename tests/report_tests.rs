@@ -0,0 +1,41 @@
+use rpncalc::*;
+
+#[test]
+fn test_last_error_is_recorded() {
+    let mut app = App::new();
+    assert!(app.submit_line("1 0 /".to_string()).is_err());
+    assert_eq!(
+        app.last_error,
+        Some(("1 0 /".to_string(), CalcError::DivisionByZero))
+    );
+    app.submit_line("2 +".to_string()).unwrap();
+    assert_eq!(app.last_error.as_ref().unwrap().1, CalcError::DivisionByZero);
+}
+
+#[test]
+fn test_bundle_contents() {
+    let mut app = App::new();
+    app.allow_nan = true;
+    assert!(app.submit_line("3 4 + foo".to_string()).is_err());
+    let text = report::bundle(&app);
+    assert!(text.contains(&format!("Version:  {}", app.version)));
+    assert!(text.contains("[settings]\nallow_nan = true\n"));
+    assert!(text.contains("[stack]\n7\n"));
+    assert!(text.contains("input: 3 4 + foo\nerror: Unknown command 'foo'"));
+    assert!(text.contains("[tape]\n3 + 4 = 7\n"));
+}
+
+#[test]
+fn test_report_is_saved() {
+    let dir = std::env::temp_dir().join(format!("rpncalc-reports-{}", std::process::id()));
+    let mut app = App::new();
+    let err = app.eval_line("report").unwrap_err();
+    assert!(err.to_string().contains("No data directory"));
+
+    app.report_dir = Some(dir.clone());
+    let outcome = app.eval_line("report").unwrap();
+    assert!(outcome.message().starts_with("Saved bug report to"), "{}", outcome.message());
+    let file = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+    assert!(std::fs::read_to_string(&file).unwrap().starts_with("rpncalc bug report"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
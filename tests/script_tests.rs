@@ -0,0 +1,80 @@
+use rpncalc::*;
+
+#[test]
+fn test_strip_comment() {
+    assert_eq!(script::strip_comment("  2 3 +  # sum"), "2 3 +");
+    assert_eq!(script::strip_comment("# only a comment"), "");
+}
+
+#[test]
+fn test_run_script() {
+    let mut app = App::new();
+    let text = "# Beam load\n2000 3\n\n4 ^ *   # w L^4\n8 /\n";
+    script::run(&mut app, "beam.rpn", text).unwrap();
+    assert_eq!(app.stack, numbers(&[20250.0]));
+}
+
+#[test]
+fn test_script_error_names_line() {
+    let mut app = App::new();
+    let err = script::run(&mut app, "calc.rpn", "1 2 +\n# note\nfrob\n4\n").unwrap_err();
+    assert_eq!(
+        err,
+        CalcError::Script {
+            file: "calc.rpn".to_string(),
+            line: 3,
+            error: Box::new(CalcError::UnknownCommand("frob".to_string())),
+        }
+    );
+    assert!(err.to_string().starts_with("calc.rpn:3: Unknown command 'frob'"));
+}
+
+#[test]
+fn test_run_command() {
+    let path = std::env::temp_dir().join(format!("rpncalc-run-{}.rpn", std::process::id()));
+    std::fs::write(&path, "3 4 # sides\nswap 2 ^ swap 2 ^ + sqrt\n").unwrap();
+    let mut app = App::new();
+    app.stack = numbers(&[1.0]);
+    app.input = format!("run {}", path.display());
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[1.0, 5.0]));
+
+    app.input = "undo".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[1.0]));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_failed_run_restores_stack() {
+    let path = std::env::temp_dir().join(format!("rpncalc-fail-{}.rpn", std::process::id()));
+    std::fs::write(&path, "5\n+ +\n").unwrap();
+    let mut app = App::new();
+    app.stack = numbers(&[1.0]);
+    let err = app.eval_line(&format!("run {}", path.display())).unwrap_err();
+    assert!(matches!(err, CalcError::Script { line: 2, .. }));
+    assert_eq!(app.stack, numbers(&[1.0]));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_missing_script() {
+    let mut app = App::new();
+    let result = app.eval_line("run /nonexistent/calc.rpn");
+    assert!(matches!(result, Err(CalcError::InvalidArgument(_))));
+}
+
+#[test]
+fn test_stack_columns_align_decimal_points() {
+    let stack = vec![
+        Value::Number(1.0),
+        Value::Number(22.5),
+        Value::Number(-3.125),
+        Matrix::parse("[1 2; 3 4]").unwrap().into(),
+    ];
+    assert_eq!(
+        format_stack_columns(&stack),
+        [" 1", "22.5", "-3.125", "[ 1  2 ]", "[ 3  4 ]"]
+    );
+    assert!(format_stack_columns(&[]).is_empty());
+}
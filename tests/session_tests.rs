@@ -0,0 +1,137 @@
+#![cfg(unix)]
+
+use rpncalc::session::{Client, Role, Server, Snapshot};
+use rpncalc::*;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+fn socket_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rpncalc-{}-{}.sock", name, std::process::id()))
+}
+
+// Polls the server until the client sees a snapshot, giving up after a second.
+fn sync(server: &mut Server, app: &mut App, client: &mut Client) -> Option<Snapshot> {
+    for _ in 0..100 {
+        server.poll(app);
+        if let Some(snapshot) = client.receive().unwrap() {
+            return Some(snapshot);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    None
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    let snapshot = Snapshot {
+        stack: vec![
+            Value::Number(1.5),
+            Matrix::parse("[1 2; 3 0.1]").unwrap().into(),
+            Value::Number(1e300),
+        ],
+        angle_mode: AngleMode::Radians,
+        message: "2 + 3 = 5".to_string(),
+    };
+    assert_eq!(Snapshot::decode(&snapshot.encode()), Some(snapshot));
+}
+
+#[test]
+fn test_snapshot_round_trips_every_kind_of_value() {
+    let formula = Value::Formula(Formula::Apply {
+        op: "+".to_string(),
+        args: vec![
+            Value::parse("?x").unwrap(),
+            Value::Formula(Formula::Apply {
+                op: "sqrt".to_string(),
+                args: vec![Value::Number(2.0)],
+            }),
+        ],
+    });
+    let snapshot = Snapshot {
+        stack: vec![
+            Value::Number(0.1 + 0.2),
+            Value::Number(-0.0),
+            Value::Number(f64::NEG_INFINITY),
+            Decimal::parse("-1234.567891").unwrap().into(),
+            Rational::new(-22, 7).unwrap().into(),
+            Uncertain::new(9.81, 0.02).into(),
+            Date::parse("2024-03-01").unwrap().into(),
+            Matrix::parse("[1 2 3; 4 5 6]").unwrap().into(),
+            Matrix::vector(Vec::new()).into(),
+            formula,
+            Value::parse("?y").unwrap(),
+        ],
+        angle_mode: AngleMode::Gradians,
+        message: "tab\there".to_string(),
+    };
+    let decoded = Snapshot::decode(&snapshot.encode()).unwrap();
+    assert_eq!(decoded.stack, snapshot.stack);
+    assert_eq!(decoded.message, "tab here");
+    assert!(matches!(decoded.stack[1], Value::Number(z) if z.is_sign_negative()));
+    assert_eq!(Snapshot::decode("radians\tnum:1 app:2:+ num:2\t"), None);
+}
+
+#[test]
+fn test_view_receives_state() {
+    let path = socket_path("view");
+    let mut server = Server::bind(&path).unwrap();
+    let mut app = App::new();
+    app.stack = numbers(&[7.0]);
+    let mut client = Client::attach(&path, Role::View).unwrap();
+    let snapshot = sync(&mut server, &mut app, &mut client).unwrap();
+    assert_eq!(snapshot.stack, vec![7.0]);
+    assert_eq!(server.attached(), 1);
+    assert!(client.send("drop").is_err());
+}
+
+#[test]
+fn test_control_evaluates_lines() {
+    let path = socket_path("control");
+    let mut server = Server::bind(&path).unwrap();
+    let mut app = App::new();
+    let mut client = Client::attach(&path, Role::Control).unwrap();
+    sync(&mut server, &mut app, &mut client).unwrap();
+    client.send("2 3 +").unwrap();
+    let snapshot = sync(&mut server, &mut app, &mut client).unwrap();
+    assert_eq!(app.stack, vec![5.0]);
+    assert_eq!(snapshot.stack, vec![5.0]);
+    assert_eq!(snapshot.message, "2 + 3 = 5");
+}
+
+#[test]
+fn test_money_stays_exact_when_attached() {
+    let path = socket_path("money");
+    let mut server = Server::bind(&path).unwrap();
+    let mut app = App::new();
+    let mut client = Client::attach(&path, Role::Control).unwrap();
+    sync(&mut server, &mut app, &mut client).unwrap();
+    client.send("money 0.1 0.2 + 1000000 3 /").unwrap();
+    let snapshot = sync(&mut server, &mut app, &mut client).unwrap();
+    assert_eq!(snapshot.stack, app.stack);
+    assert_eq!(snapshot.stack[0], Value::Decimal(Decimal::parse("0.3").unwrap()));
+    // The guard digits beyond the cent come across too.
+    assert_eq!(snapshot.stack[1], Value::Decimal(Decimal::parse("333333.333333").unwrap()));
+    assert_eq!(format_value(&snapshot.stack[0]), "0.30");
+}
+
+#[test]
+fn test_socket_removed_on_drop() {
+    let path = socket_path("drop");
+    let server = Server::bind(&path).unwrap();
+    assert!(path.exists());
+    drop(server);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_poll_reports_changes() {
+    let path = socket_path("changes");
+    let mut server = Server::bind(&path).unwrap();
+    let mut app = App::new();
+    server.poll(&mut app);
+    assert!(!server.poll(&mut app));
+    app.stack = numbers(&[1.0]);
+    assert!(server.poll(&mut app));
+    assert!(!server.poll(&mut app));
+}
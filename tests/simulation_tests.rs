@@ -0,0 +1,82 @@
+use rpncalc::*;
+
+#[test]
+fn test_rng_is_reproducible() {
+    let (mut a, mut b) = (Rng::new(7), Rng::new(7));
+    let first: Vec<u64> = (0..5).map(|_| a.next_u64()).collect();
+    let second: Vec<u64> = (0..5).map(|_| b.next_u64()).collect();
+    assert_eq!(first, second);
+    assert_ne!(Rng::new(8).next_u64(), first[0]);
+}
+
+#[test]
+fn test_uniform_and_normal() {
+    let mut rng = Rng::new(1);
+    let uniform: Vec<f64> = (0..10_000).map(|_| rng.uniform()).collect();
+    assert!(uniform.iter().all(|u| (0.0..1.0).contains(u)));
+    assert!((stats::mean(&uniform) - 0.5).abs() < 0.02);
+    let normal: Vec<f64> = (0..10_000).map(|_| rng.normal()).collect();
+    assert!(stats::mean(&normal).abs() < 0.05);
+    assert!((stats::std_dev(&normal) - 1.0).abs() < 0.05);
+}
+
+#[test]
+fn test_seed_makes_rand_repeatable() {
+    let mut app = App::new();
+    app.input = "seed 42 rand rand seed 42 rand".to_string();
+    app.execute_command();
+    assert_eq!(app.stack.len(), 3);
+    assert_eq!(app.stack[0], app.stack[2]);
+    assert_ne!(app.stack[0], app.stack[1]);
+}
+
+#[test]
+fn test_simulate_collects_results() {
+    let mut app = App::new();
+    app.macros = MacroLibrary::parse("def roll = rand 6 * 1 + \n").0;
+    app.stack = numbers(&[100.0]);
+    app.input = "seed 3 simulate 50 roll".to_string();
+    app.execute_command();
+    assert_eq!(app.stack.len(), 51);
+    assert_eq!(app.stack[0], 100.0);
+    let rolls: Vec<f64> = app.stack[1..].iter().map(|v| v.as_number().unwrap()).collect();
+    assert!(rolls.iter().all(|r| (1.0..7.0).contains(r)));
+    assert!(app.message.starts_with("Simulated roll 50 times: mean"));
+
+    let mut again = App::new();
+    again.macros = app.macros.clone();
+    again.stack = numbers(&[100.0]);
+    again.input = "seed 3 simulate 50 roll".to_string();
+    again.execute_command();
+    assert_eq!(again.stack, app.stack);
+
+    app.input = "undo".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[100.0]));
+}
+
+#[test]
+fn test_simulate_uses_stack_and_quoted_input() {
+    let mut app = App::new();
+    app.stack = numbers(&[10.0, 2.0]);
+    app.input = "simulate 3 \"*\"".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[10.0, 2.0, 20.0, 20.0, 20.0]));
+    assert_eq!(app.message, "Simulated * 3 times: mean 20, sd 0");
+    assert!(app.calc_history.is_empty());
+}
+
+#[test]
+fn test_simulate_errors() {
+    let mut app = App::new();
+    app.stack = numbers(&[1.0]);
+    assert!(matches!(
+        app.eval_line("simulate 5 +"),
+        Err(CalcError::StackUnderflow { .. })
+    ));
+    assert!(matches!(
+        app.eval_line("simulate 0 rand"),
+        Err(CalcError::InvalidArgument(_))
+    ));
+    assert_eq!(app.stack, numbers(&[1.0]));
+}
@@ -0,0 +1,71 @@
+use rpncalc::softkeys::{self, SoftKeys};
+use rpncalc::*;
+
+#[test]
+fn test_default_softkeys() {
+    let keys = SoftKeys::default();
+    assert_eq!(keys.pages(), 2);
+    assert_eq!(keys.page(0).len(), softkeys::PAGE_SIZE);
+    assert_eq!(keys.action(0, 0), Some("swap"));
+    assert_eq!(keys.action(1, 7), Some("sq"));
+    assert_eq!(keys.action(2, 0), None);
+    assert!(keys.changes().is_empty());
+}
+
+#[test]
+fn test_bind_softkeys() {
+    let mut keys = SoftKeys::default();
+    keys.bind(3, "cube").unwrap();
+    keys.bind(17, "2 /").unwrap();
+    assert_eq!(keys.pages(), 3);
+    assert_eq!(keys.action(2, 0), Some("2 /"));
+    assert_eq!(keys.changes(), [(3, "cube"), (17, "2 /")]);
+    // Unbinding the only key on the last page drops the page.
+    keys.bind(17, "").unwrap();
+    assert_eq!(keys.pages(), 2);
+    keys.bind(8, "").unwrap();
+    assert_eq!(keys.action(0, 7), None);
+    assert!(keys.bind(0, "dup").is_err());
+    assert!(keys.bind(softkeys::MAX_KEYS + 1, "dup").is_err());
+}
+
+#[test]
+fn test_softkey_labels() {
+    assert_eq!(softkeys::label("swap", 8), "  swap  ");
+    assert_eq!(softkeys::label("runtests", 4), "runt");
+    assert_eq!(softkeys::label("", 3), "   ");
+}
+
+#[test]
+fn test_press_softkeys() {
+    let mut app = App::new();
+    app.eval_line("9 4").unwrap();
+    app.press_softkey(0).unwrap();
+    assert_eq!(app.stack, [Value::Number(4.0), Value::Number(9.0)]);
+    app.next_softkey_page().unwrap();
+    app.press_softkey(7).unwrap();
+    assert_eq!(app.stack, [Value::Number(4.0), Value::Number(81.0)]);
+    // Back to the first page after the last.
+    app.next_softkey_page().unwrap();
+    assert_eq!(app.softkey_page, 0);
+    app.softkeys.bind(2, "").unwrap();
+    let err = app.press_softkey(1).unwrap_err();
+    assert!(err.to_string().contains("F2"));
+}
+
+#[test]
+fn test_softkey_config() {
+    let (config, warnings) =
+        Config::parse("softkeys = off\nsoftkey.3 = dup *\nsoftkey.f4 = cube\nsoftkey.99 = x\n");
+    assert_eq!(warnings.len(), 2);
+    assert!(!config.show_softkeys);
+    assert_eq!(config.softkeys.action(0, 2), Some("dup *"));
+    let (parsed, warnings) = Config::parse(&config.to_string());
+    assert!(warnings.is_empty());
+    assert_eq!(parsed, config);
+    let mut app = App::new();
+    config.apply(&mut app);
+    app.eval_line("3").unwrap();
+    app.press_softkey(2).unwrap();
+    assert_eq!(app.stack, [Value::Number(9.0)]);
+}
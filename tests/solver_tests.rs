@@ -0,0 +1,71 @@
+use rpncalc::solver::{self, Method, SolverSettings};
+use rpncalc::*;
+
+fn solve(f: impl Fn(f64) -> f64, guess: f64) -> Result<solver::Root, String> {
+    solver::solve(|x| Some(f(x)), guess, &SolverSettings::default())
+}
+
+#[test]
+fn test_newton_finds_root() {
+    let root = solve(|x| x * x - 2.0, 1.0).unwrap();
+    assert!((root.x - 2f64.sqrt()).abs() < 1e-12);
+    assert_eq!(root.method, Method::Newton);
+}
+
+#[test]
+fn test_newton_steps_around_domain() {
+    let root = solver::solve(|x| (x > 0.0).then(|| x.ln()), 5.0, &SolverSettings::default());
+    assert_eq!(root.unwrap().x, 1.0);
+}
+
+#[test]
+fn test_bisection_fallback() {
+    // Newton's method cycles between ±1 on this one.
+    let f = |x: f64| x.signum() * x.abs().sqrt();
+    let root = solve(f, 1.0).unwrap();
+    assert!(root.x.abs() < 1e-9);
+    assert_eq!(root.method, Method::Bisection);
+}
+
+#[test]
+fn test_no_root() {
+    let err = solve(|x| x * x + 1.0, 2.0).unwrap_err();
+    assert!(err.contains("no root found near 2"), "{}", err);
+    let err = solve(|x| 1.0 / x, 2.0).unwrap_err();
+    assert!(err.contains("no root there"), "{}", err);
+}
+
+#[test]
+fn test_iteration_limit() {
+    let settings = SolverSettings {
+        tolerance: 1e-12,
+        max_iterations: 2,
+    };
+    let err = solver::solve(|x| Some(x.signum() * x.abs().sqrt()), 1.0, &settings).unwrap_err();
+    assert!(err.contains("did not converge"), "{}", err);
+}
+
+#[test]
+fn test_solve_command() {
+    let mut app = App::new();
+    app.eval_line("def f \"dup dup * * 2 -\" 1 solve f").unwrap();
+    let x = app.stack[0].as_number().unwrap();
+    assert!((x - 2f64.cbrt()).abs() < 1e-12);
+    app.undo().unwrap();
+    assert_eq!(app.stack, vec![1.0]);
+    assert!(app.eval_line("2 solve \"dup * 1 +\"").is_err());
+    assert_eq!(app.stack, vec![1.0, 2.0]);
+    // An error at the guess is the macro's own.
+    assert_eq!(
+        app.eval_line("-3 solve sqrt"),
+        Err(CalcError::domain("sqrt(-3) is undefined (x >= 0)"))
+    );
+}
+
+#[test]
+fn test_def_command() {
+    let mut app = App::new();
+    app.eval_line("def square \"dup *\" 3 square").unwrap();
+    assert_eq!(app.stack, vec![9.0]);
+    assert!(app.eval_line("def dup \"1\"").is_err());
+}
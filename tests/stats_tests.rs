@@ -0,0 +1,142 @@
+use rpncalc::*;
+
+#[test]
+fn test_frequencies() {
+    let table = stats::frequencies(&[3.0, 1.0, 3.0, 0.30000000000000004, 0.3], 6);
+    assert_eq!(table, vec![(0.3, 2), (1.0, 1), (3.0, 2)]);
+    assert_eq!(stats::modes(&table), (vec![0.3, 3.0], 2));
+}
+
+#[test]
+fn test_freq() {
+    let mut app = App::new();
+    app.stack = numbers(&[2.0, 1.0, 2.0, 5.0, 2.0]);
+    app.input = "freq".to_string();
+    app.execute_command();
+    assert!(app.stack.is_empty());
+    assert_eq!(app.message, "5 values, 3 distinct, mode 2 (3×)");
+    let popup = app.popup.as_ref().unwrap();
+    assert_eq!(popup.lines[0], "value  count");
+    assert_eq!(popup.lines[2], "    2      3  ██████████████████████████████");
+    assert_eq!(popup.lines[3], "    5      1  ██████████");
+
+    app.input = "undo".to_string();
+    app.execute_command();
+    assert_eq!(app.stack.len(), 5);
+}
+
+#[test]
+fn test_freq_without_bars() {
+    let mut app = App::new();
+    app.freq_bars = false;
+    app.stack = numbers(&[1.5, 2.5]);
+    app.input = "freq".to_string();
+    app.execute_command();
+    assert_eq!(app.message, "2 values, all distinct");
+    assert_eq!(app.popup.as_ref().unwrap().lines[1], "  1.5      1");
+}
+
+#[test]
+fn test_freq_empty_stack() {
+    let mut app = App::new();
+    assert_eq!(app.eval_line("freq"), Err(CalcError::EmptyStack));
+}
+
+#[test]
+fn test_quartiles() {
+    assert_eq!(stats::quartiles(&[7.0, 1.0, 3.0, 5.0, 9.0]), (3.0, 7.0));
+    assert_eq!(stats::quartiles(&[1.0, 2.0, 3.0, 4.0]), (1.75, 3.25));
+}
+
+#[test]
+fn test_outliers_std_dev() {
+    let mut app = App::new();
+    app.stack = numbers(&[10.0, 11.0, 9.0, 10.0, 30.0, 10.0]);
+    app.input = "outliers 1.5".to_string();
+    app.execute_command();
+    assert_eq!(app.outliers, vec![4]);
+    assert_eq!(app.stack.len(), 6);
+    assert!(app.message.starts_with("1 outlier outside"));
+
+    app.input = "1".to_string();
+    app.execute_command();
+    assert!(app.outliers.is_empty());
+}
+
+#[test]
+fn test_outliers_iqr() {
+    let mut app = App::new();
+    app.stack = numbers(&[-40.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+    app.input = "outliers 1.5iqr".to_string();
+    app.execute_command();
+    assert_eq!(app.outliers, vec![0]);
+    assert_eq!(app.message, "1 outlier outside -4 to 12 (1.5 IQR)");
+}
+
+#[test]
+fn test_outliers_bad_k() {
+    let mut app = App::new();
+    app.stack = numbers(&[1.0, 2.0, 3.0]);
+    let result = app.eval_line("outliers lots");
+    assert!(matches!(result, Err(CalcError::InvalidArgument(_))));
+    assert!(app.outliers.is_empty());
+}
+
+#[test]
+fn test_pair_regression() {
+    let mut pairs = PairStats::default();
+    for (x, y) in [(1.0, 3.0), (2.0, 5.0), (3.0, 7.0)] {
+        pairs.add(x, y);
+    }
+    assert_eq!(pairs.linreg(), Ok((2.0, 1.0)));
+    assert_eq!(pairs.corr(), Ok(1.0));
+    assert_eq!(pairs.predict(10.0), Ok(21.0));
+    pairs.add(4.0, 0.0);
+    pairs.remove(4.0, 0.0).unwrap();
+    assert_eq!(pairs.linreg(), Ok((2.0, 1.0)));
+}
+
+#[test]
+fn test_pair_regression_errors() {
+    let mut pairs = PairStats::default();
+    assert!(pairs.remove(1.0, 1.0).is_err());
+    pairs.add(1.0, 1.0);
+    assert_eq!(pairs.linreg(), Err("Regression needs at least 2 pairs"));
+    pairs.add(1.0, 2.0);
+    assert_eq!(pairs.linreg(), Err("Regression needs x values that differ"));
+    let mut flat = PairStats::default();
+    flat.add(1.0, 5.0);
+    flat.add(2.0, 5.0);
+    assert_eq!(flat.linreg(), Ok((0.0, 5.0)));
+    assert_eq!(flat.corr(), Err("Correlation needs y values that differ"));
+}
+
+#[test]
+fn test_pair_commands() {
+    let mut app = App::new();
+    app.eval_line("1 3 Σ+ 2 5 sadd").unwrap();
+    assert_eq!(app.stack, vec![1.0, 2.0]);
+    app.eval_line("clear linreg").unwrap();
+    assert_eq!(app.stack, vec![2.0, 1.0]);
+    app.eval_line("clear 10 predict corr").unwrap();
+    assert_eq!(app.stack, vec![21.0, 1.0]);
+    app.eval_line("2 5 Σ-").unwrap();
+    assert_eq!(app.pairs.n, 1);
+    assert_eq!(app.eval_line("sreg").unwrap(), OpOutcome::info("n=1 Σx=1 Σy=3 Σx²=1 Σy²=9 Σxy=3"));
+    app.eval_line("sclear").unwrap();
+    assert!(app.pairs.is_empty());
+}
+
+#[test]
+fn test_undo_restores_pair_registers() {
+    let mut app = App::new();
+    app.eval_line("1 2 sadd").unwrap();
+    app.eval_line("undo").unwrap();
+    assert_eq!(app.stack, vec![1.0, 2.0]);
+    assert!(app.pairs.is_empty());
+    app.eval_line("sadd 3 4 sadd").unwrap();
+    app.eval_line("sclear undo").unwrap();
+    assert_eq!(app.pairs.n, 2);
+    app.eval_line("3 4 Σ- undo").unwrap();
+    assert_eq!(app.pairs.n, 2);
+}
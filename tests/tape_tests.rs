@@ -0,0 +1,68 @@
+use rpncalc::*;
+use std::fs;
+
+#[test]
+fn test_tape_records_entries_and_results() {
+    let mut app = App::new();
+    app.eval_line("12.5 3.25 + 2 * rnd 1").unwrap();
+    app.eval_line("def half \"2 /\"").unwrap();
+    app.eval_line("half 1 2 times 1 + loop").unwrap();
+    let lines = app.tape.lines(0..app.tape.len(), format_number);
+    assert_eq!(
+        lines,
+        [
+            "12.5",
+            " 3.25",
+            "15.75  +",
+            " 2",
+            "31.5   *",
+            "31.5   rnd 1",
+            "15.75  half",
+            " 1",
+            " 2",
+            " 3     times",
+        ]
+    );
+    // Definitions, views and settings are not operations on the tape.
+    app.eval_line("deg depth").unwrap();
+    assert_eq!(app.tape.len(), 11);
+}
+
+#[test]
+fn test_tape_lines_range() {
+    let mut app = App::new();
+    app.eval_line("1 2 drop clear").unwrap();
+    assert_eq!(app.tape.lines(2..10, format_number), ["1  drop", "   clear"]);
+    assert!(app.tape.lines(5..9, format_number).is_empty());
+}
+
+#[test]
+fn test_failed_operations_stay_off_tape() {
+    let mut app = App::new();
+    assert!(app.eval_line("1 0 /").is_err());
+    assert_eq!(app.tape.len(), 2);
+    assert!(app.eval_line("tape").is_ok());
+    assert!(app.show_tape);
+    app.scroll_tape(100);
+    assert_eq!(app.tape_scroll, 1);
+    assert!(App::new().open_tape().is_err());
+}
+
+#[test]
+fn test_save_tape() {
+    let path = std::env::temp_dir().join(format!("rpncalc-tape-{}.txt", std::process::id()));
+    let mut app = App::new();
+    app.eval_line("4.7k 2 *").unwrap();
+    app.eval_line(&format!("savetape {}", path.display())).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "4700\n   2\n9400  *\n");
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_describe_tape_entries() {
+    let mut app = App::new();
+    app.eval_line("3 4 + dup drop").unwrap();
+    let described: Vec<String> =
+        app.tape.entries().iter().map(|e| e.describe(format_number)).collect();
+    assert_eq!(described, ["pushed 3", "pushed 4", "3 + 4 = 7", "dup", "drop"]);
+}
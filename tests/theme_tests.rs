@@ -0,0 +1,57 @@
+use ratatui::style::{Color, Style};
+use rpncalc::theme::{self, Colors};
+use rpncalc::*;
+
+#[test]
+fn test_parse_colors() {
+    assert_eq!(Colors::parse("yellow"), Some(Colors { fg: Color::Yellow, bg: Color::Reset }));
+    assert_eq!(
+        Colors::parse("light blue on #002b36"),
+        Some(Colors { fg: Color::LightBlue, bg: Color::Rgb(0x00, 0x2b, 0x36) })
+    );
+    assert_eq!(Colors::parse("236").map(|c| c.fg), Some(Color::Indexed(236)));
+    assert_eq!(Colors::parse("chartreuse"), None);
+    assert_eq!(Colors::parse("red on"), None);
+}
+
+#[test]
+fn test_colors_round_trip_and_style() {
+    for text in ["Yellow", "#FF8800 on 236", "Black on White"] {
+        assert_eq!(Colors::parse(text).unwrap().to_string(), text);
+    }
+    // The terminal's own colors are left alone rather than reset.
+    assert_eq!(Colors::parse("green").unwrap().style(), Style::default().fg(Color::Green));
+}
+
+#[test]
+fn test_builtin_themes() {
+    for name in theme::NAMES {
+        let theme = Theme::named(name).unwrap();
+        assert_eq!(theme.name, name);
+        assert!(theme.changes().is_empty());
+    }
+    assert_eq!(Theme::default(), Theme::named("dark").unwrap());
+    assert!(Theme::named("neon").is_none());
+}
+
+#[test]
+fn test_theme_command() {
+    let mut app = App::new();
+    app.eval_line("theme solarized").unwrap();
+    assert_eq!(app.theme.name, "solarized");
+    let err = app.eval_line("theme neon").unwrap_err();
+    assert!(err.to_string().contains("dark, light, solarized, mono"));
+    assert_eq!(app.theme.name, "solarized");
+}
+
+#[test]
+fn test_theme_config() {
+    let (config, warnings) =
+        Config::parse("theme = mono\ncolor.input = yellow\ncolor.help = red on blue\ncolor.sidebar = red\n");
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("'sidebar'"));
+    assert_eq!(config.theme.name, "mono");
+    assert_eq!(config.theme.input.fg, Color::Yellow);
+    assert_eq!(config.theme.changes().len(), 2);
+    assert!(config.to_string().contains("theme = mono\ncolor.input = Yellow\ncolor.help = Red on Blue\n"));
+}
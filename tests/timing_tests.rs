@@ -0,0 +1,41 @@
+use rpncalc::timing::{self, Timings};
+use rpncalc::*;
+use std::time::Duration;
+
+#[test]
+fn test_slowest_first_and_bounded() {
+    let mut timings = Timings::default();
+    for (name, ms) in [("a", 3), ("b", 9), ("c", 1), ("d", 9)] {
+        timings.record(name, Duration::from_millis(ms));
+    }
+    let names: Vec<&str> = timings.slowest(3).iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, ["d", "b", "a"]);
+
+    for _ in 0..timing::MAX_TIMINGS {
+        timings.record("x", Duration::ZERO);
+    }
+    assert_eq!(timings.len(), timing::MAX_TIMINGS);
+    assert_eq!(timings.slowest(1)[0].name, "x");
+}
+
+#[test]
+fn test_format_elapsed() {
+    assert_eq!(timing::format_elapsed(Duration::from_micros(12_345)), "12.345 ms");
+}
+
+#[test]
+fn test_operations_are_timed() {
+    let mut app = App::new();
+    assert!(app.eval_line("slowops").is_err());
+    app.eval_line("3 4 + dup").unwrap();
+    assert_eq!(app.timings.len(), 3);
+    assert!(app.calc_history[0].elapsed.is_some());
+    app.eval_line("slowops").unwrap();
+    let popup = app.popup.as_ref().unwrap();
+    assert_eq!(popup.lines.len(), 3);
+    assert!(popup.lines.iter().any(|l| l.ends_with("  +")));
+
+    let mut engine = Engine::new();
+    engine.execute("2 sqrt").unwrap();
+    assert_eq!(engine.timings().slowest(5)[0].name, "sqrt");
+}
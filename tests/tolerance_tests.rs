@@ -0,0 +1,48 @@
+use rpncalc::*;
+
+#[test]
+fn test_close() {
+    let tolerance = Tolerance::default();
+    assert!(tolerance.close(0.1 + 0.2, 0.3));
+    assert!(tolerance.close(1e20, 1e20 + 1e5));
+    assert!(tolerance.close(1e-13, 0.0));
+    assert!(!tolerance.close(1.0, 1.001));
+    assert!(!tolerance.close(1e-9, 0.0));
+}
+
+#[test]
+fn test_approx_equal() {
+    let mut app = App::new();
+    app.eval_line("0.1 0.2 + 0.3 ~=").unwrap();
+    assert_eq!(app.stack, numbers(&[1.0]));
+    app.eval_line("1 1.001 ~=").unwrap();
+    assert_eq!(app.stack, numbers(&[1.0, 0.0]));
+    app.eval_line("[1 2] [1 2.0000000000001] ~=").unwrap();
+    assert_eq!(app.stack, numbers(&[1.0, 0.0, 1.0]));
+
+    app.stack.clear();
+    assert!(matches!(
+        app.eval_line("1 ~="),
+        Err(CalcError::StackUnderflow { .. })
+    ));
+}
+
+#[test]
+fn test_custom_tolerance() {
+    let mut app = App::new();
+    app.tolerance.relative = 0.01;
+    app.eval_line("1 1.001 ~=").unwrap();
+    assert_eq!(app.stack, numbers(&[1.0]));
+}
+
+#[test]
+fn test_tolerance_settings() {
+    let (config, warnings) = Config::parse("tolerance = 1e-6\nabs_tolerance = 0\n");
+    assert!(warnings.is_empty());
+    assert_eq!(config.tolerance.relative, 1e-6);
+    assert_eq!(config.tolerance.absolute, 0.0);
+
+    let (config, warnings) = Config::parse("tolerance = -1\n");
+    assert_eq!(config, Config::default());
+    assert_eq!(warnings.len(), 1);
+}
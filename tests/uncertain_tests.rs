@@ -0,0 +1,76 @@
+use rpncalc::*;
+
+fn assert_close(value: &Value, expected: f64, error: f64) {
+    let tolerance = Tolerance::default();
+    let Value::Uncertain(u) = value else {
+        panic!("{:?} is not a measurement", value);
+    };
+    assert!(tolerance.close(u.value, expected), "{} != {}", u.value, expected);
+    assert!(tolerance.close(u.error, error), "±{} != ±{}", u.error, error);
+}
+
+#[test]
+fn test_parse_uncertain() {
+    assert_eq!(Uncertain::parse("9.81±0.02"), Some(Uncertain::new(9.81, 0.02)));
+    assert_eq!(Uncertain::parse("-3+-0.5"), Some(Uncertain::new(-3.0, 0.5)));
+    assert_eq!(Uncertain::parse("3±-1"), None);
+    assert_eq!(Uncertain::parse("3±"), None);
+    assert_eq!(Value::parse("1.5±0.1"), Ok(Value::Uncertain(Uncertain::new(1.5, 0.1))));
+}
+
+#[test]
+fn test_display_uncertain() {
+    let value = Value::from(Uncertain::new(9.81, 0.02));
+    assert_eq!(format_value(&value), "9.81 ± 0.02");
+    assert_eq!(Value::parse(&value.to_string()), Ok(value));
+}
+
+#[test]
+fn test_sum_adds_in_quadrature() {
+    let mut app = App::new();
+    app.input = "10±0.3 5±0.4 +".to_string();
+    app.execute_command();
+    assert_close(&app.stack[0], 15.0, 0.5);
+
+    app.input = "2 -".to_string();
+    app.execute_command();
+    assert_close(&app.stack[0], 13.0, 0.5);
+}
+
+#[test]
+fn test_product_and_quotient() {
+    let mut app = App::new();
+    app.input = "10±0.3 4±0.16 *".to_string();
+    app.execute_command();
+    // Relative errors 3% and 4% give 5%.
+    assert_close(&app.stack[0], 40.0, 2.0);
+
+    app.input = "10±0.3 4±0.16 /".to_string();
+    app.execute_command();
+    assert_close(&app.stack[1], 2.5, 0.125);
+}
+
+#[test]
+fn test_sqrt_uncertain() {
+    let mut app = App::new();
+    app.input = "16±0.8 sqrt".to_string();
+    app.execute_command();
+    assert_close(&app.stack[0], 4.0, 0.1);
+}
+
+#[test]
+fn test_uncertain_sqrt_domain() {
+    let mut app = App::new();
+    app.stack = vec![Uncertain::new(-4.0, 0.1).into()];
+    let result = app.eval_line("sqrt");
+    assert!(matches!(result, Err(CalcError::DomainError(_))));
+    assert_eq!(app.stack.len(), 1);
+}
+
+#[test]
+fn test_other_functions_use_central_value() {
+    let mut app = App::new();
+    app.input = "100±1 log".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[2.0]));
+}
@@ -0,0 +1,89 @@
+use rpncalc::highlight::{self, TokenKind};
+use rpncalc::*;
+
+/// Values left below each example, which undo must not disturb.
+const SENTINELS: &str = "7 8 9";
+
+/// Splits an example into the steps it runs: a value, or a command with its
+/// inline arguments.
+fn steps(app: &App, example: &str) -> Vec<String> {
+    let mut steps: Vec<String> = Vec::new();
+    for (token, kind) in highlight::highlight(app, example) {
+        match (kind, steps.last_mut()) {
+            (None, _) => {}
+            (Some(TokenKind::Argument | TokenKind::Quoted), Some(step)) => {
+                step.push(' ');
+                step.push_str(token);
+            }
+            _ => steps.push(token.to_string()),
+        }
+    }
+    steps
+}
+
+#[test]
+fn test_undo_reverses_every_example_step() {
+    let registry = CommandRegistry::with_builtins();
+    for cmd in registry.commands().iter().filter(|c| c.undoable) {
+        let Some(example) = cmd.example else {
+            continue;
+        };
+        let mut app = App::new();
+        app.eval_line(SENTINELS).unwrap();
+        let mut states = vec![app.stack.clone()];
+        for step in steps(&app, example) {
+            app.eval_line(&step).unwrap();
+            states.push(app.stack.clone());
+        }
+        states.pop();
+        while let Some(state) = states.pop() {
+            app.eval_line("undo").unwrap();
+            assert_eq!(app.stack, state, "undoing {} in '{}'", cmd.name, example);
+        }
+    }
+}
+
+#[test]
+fn test_macro_undoes_as_one_step() {
+    let mut app = App::new();
+    app.macros = MacroLibrary::parse("def sq = dup *").0;
+    app.eval_line("1 2 3 4").unwrap();
+    app.eval_line("sq sq").unwrap();
+    assert_eq!(app.stack, numbers(&[1.0, 2.0, 3.0, 256.0]));
+    app.undo().unwrap();
+    assert_eq!(app.stack, numbers(&[1.0, 2.0, 3.0, 16.0]));
+    app.undo().unwrap();
+    assert_eq!(app.stack, numbers(&[1.0, 2.0, 3.0, 4.0]));
+}
+
+#[test]
+fn test_failed_block_restores_deep_stack() {
+    let mut app = App::new();
+    app.eval_line("1 2 3").unwrap();
+    assert!(app.eval_line("1 times + + 0 / loop").is_err());
+    assert_eq!(app.stack, numbers(&[1.0, 2.0, 3.0, 1.0]));
+}
+
+#[test]
+fn test_journal_keeps_only_operands() {
+    let mut app = App::new();
+    for i in 0..10_000 {
+        app.eval_line(&i.to_string()).unwrap();
+    }
+    app.eval_line("+").unwrap();
+    app.undo().unwrap();
+    assert_eq!(app.stack.len(), 10_000);
+    assert_eq!(app.journal.len(), 10_000);
+}
+
+#[test]
+fn test_disabled_journal() {
+    let mut app = App::new();
+    app.journal = Journal::disabled();
+    app.eval_line("1 2 +").unwrap();
+    assert!(app.journal.is_empty());
+    assert!(app.undo().is_err());
+    // Blocks still restore the stack when they fail.
+    assert!(app.eval_line("1 times 0 / loop").is_err());
+    assert_eq!(app.stack, numbers(&[3.0, 1.0]));
+}
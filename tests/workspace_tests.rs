@@ -0,0 +1,60 @@
+use rpncalc::*;
+
+#[test]
+fn test_workspaces_keep_separate_stacks() {
+    let mut app = App::new();
+    app.input = "1 2 +".to_string();
+    app.execute_command();
+    app.input = "ws 2".to_string();
+    app.execute_command();
+    assert!(app.stack.is_empty());
+    assert_eq!(app.workspace, 1);
+    assert_eq!(app.workspaces.len(), 2);
+
+    app.input = "10 20".to_string();
+    app.execute_command();
+    app.input = "ws 1".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[3.0]));
+    assert_eq!(app.message, "Workspace 1 (1 on the stack)");
+
+    app.switch_workspace(1).unwrap();
+    assert_eq!(app.stack, numbers(&[10.0, 20.0]));
+}
+
+#[test]
+fn test_workspaces_have_own_undo() {
+    let mut app = App::new();
+    app.input = "5 sqrt".to_string();
+    app.execute_command();
+    app.input = "ws 3 7".to_string();
+    app.execute_command();
+    app.input = "undo".to_string();
+    app.execute_command();
+    assert!(app.stack.is_empty());
+    app.input = "undo".to_string();
+    app.execute_command();
+    assert_eq!(app.message, "Nothing to undo");
+
+    app.input = "ws 1 undo".to_string();
+    app.execute_command();
+    assert_eq!(app.stack, numbers(&[5.0]));
+}
+
+#[test]
+fn test_workspace_range() {
+    let mut app = App::new();
+    assert!(matches!(app.eval_line("ws 0"), Err(CalcError::InvalidArgument(_))));
+    assert!(matches!(app.eval_line("ws 10"), Err(CalcError::InvalidArgument(_))));
+    assert_eq!(app.workspaces.len(), 1);
+}
+
+#[test]
+fn test_shelf_belongs_to_workspace() {
+    let mut app = App::new();
+    app.eval_line("5 park").unwrap();
+    app.switch_workspace(1).unwrap();
+    assert!(app.shelf.is_empty());
+    app.switch_workspace(0).unwrap();
+    assert_eq!(app.shelf, vec![5.0]);
+}